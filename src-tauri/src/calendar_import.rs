@@ -0,0 +1,1249 @@
+//! iCalendar (`.ics`) import.
+//!
+//! Every VEVENT occurrence becomes a memory whose graph is built from the event itself, with no
+//! model call: an Event entity, a normalized Time entity (`happened_on`), the LOCATION as a
+//! Location (`located_at`), and the organizer and attendees as Person entities with their
+//! address (`organized` / `participated_in`). Recurring events are expanded (RRULE, RDATE,
+//! EXDATE and RECURRENCE-ID overrides) up to a cutoff date. Each memory records its UID and
+//! instance in the frontmatter, so re-importing a calendar only adds new occurrences.
+//!
+//! Times are kept as wall-clock time in the zone of the event's DTSTART (its `TZID`, UTC for
+//! `Z` values, or the local zone for floating times), so recurrences follow that zone's clock.
+//! Values written in another zone (e.g. a `Z` UNTIL or EXDATE) are converted into it, and the
+//! memory's `created` is converted to UTC.
+
+use crate::email_import::Mailbox;
+use crate::file_manager::{list_memory_files, read_memory};
+use crate::library_crypto::LibraryKey;
+use crate::ollama::{ExtractedEntity, ExtractedRelation};
+use chrono::{
+    DateTime, Datelike, Duration, Local, Months, NaiveDate, NaiveDateTime, NaiveTime, TimeZone,
+    Utc, Weekday,
+};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value as YamlValue};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// Recurrences are expanded this far ahead when the request sets no cutoff.
+pub const DEFAULT_EXPANSION_DAYS: i64 = 365;
+/// Upper bound on the occurrences of one recurring event.
+const MAX_OCCURRENCES: usize = 1000;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CalendarImportRequest {
+    pub path: String,
+    /// Last date (`YYYY-MM-DD`) recurring events are expanded to; defaults to a year from now.
+    pub expand_until: Option<String>,
+    /// Parse and expand only; nothing is saved.
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalendarEventSummary {
+    pub title: String,
+    pub start: String,
+    pub location: Option<String>,
+    pub attendees: Vec<String>,
+    pub recurring: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalendarImportError {
+    pub title: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CalendarImportReport {
+    pub dry_run: bool,
+    /// VEVENT components in the file.
+    pub events: usize,
+    /// Occurrences after recurrence expansion.
+    pub occurrences: usize,
+    /// Occurrences skipped because their UID/instance was imported before.
+    pub duplicates: usize,
+    pub imported: Vec<CalendarEventSummary>,
+    pub saved: usize,
+    pub failed: usize,
+    pub errors: Vec<CalendarImportError>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EventTime {
+    pub at: NaiveDateTime,
+    /// `VALUE=DATE`: the event spans whole days.
+    pub all_day: bool,
+}
+
+impl EventTime {
+    fn render(&self) -> String {
+        if self.all_day {
+            self.at.format("%Y-%m-%d").to_string()
+        } else {
+            self.at.format("%Y-%m-%d %H:%M").to_string()
+        }
+    }
+}
+
+/// The zone a DATE-TIME value is written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventZone {
+    /// A `Z` suffix.
+    Utc,
+    /// A `TZID` parameter naming an IANA zone.
+    Named(Tz),
+    /// No zone: the local time of whoever reads the calendar.
+    Floating,
+}
+
+impl EventZone {
+    fn of(property: &Property) -> EventZone {
+        if property.value.trim().ends_with('Z') {
+            return EventZone::Utc;
+        }
+        let Some(tzid) = property.param("TZID") else {
+            return EventZone::Floating;
+        };
+        // Some exporters prefix the IANA name with a path, e.g. `/mozilla.org/.../Europe/Berlin`.
+        let name = tzid.trim().trim_matches('"');
+        let parsed = name.parse::<Tz>().ok().or_else(|| {
+            let parts: Vec<&str> = name.split('/').collect();
+            (1..parts.len()).find_map(|i| parts[i..].join("/").parse::<Tz>().ok())
+        });
+        match parsed {
+            Some(tz) => EventZone::Named(tz),
+            None => {
+                println!(
+                    "⚠️ [import_calendar] Unknown TZID '{}'; reading it as local time",
+                    tzid
+                );
+                EventZone::Floating
+            }
+        }
+    }
+
+    /// Resolve a wall-clock time in this zone. A time skipped by a DST change moves an hour on.
+    pub fn to_utc(self, at: NaiveDateTime) -> DateTime<Utc> {
+        fn resolve<Z: TimeZone>(zone: &Z, at: NaiveDateTime) -> Option<DateTime<Utc>> {
+            zone.from_local_datetime(&at)
+                .earliest()
+                .or_else(|| {
+                    zone.from_local_datetime(&(at + Duration::hours(1)))
+                        .earliest()
+                })
+                .map(|t| t.with_timezone(&Utc))
+        }
+        match self {
+            EventZone::Utc => None,
+            EventZone::Named(tz) => resolve(&tz, at),
+            EventZone::Floating => resolve(&Local, at),
+        }
+        .unwrap_or_else(|| DateTime::<Utc>::from_naive_utc_and_offset(at, Utc))
+    }
+
+    /// Wall-clock time in this zone of a UTC instant.
+    fn wall_clock(self, at: DateTime<Utc>) -> NaiveDateTime {
+        match self {
+            EventZone::Utc => at.naive_utc(),
+            EventZone::Named(tz) => at.with_timezone(&tz).naive_local(),
+            EventZone::Floating => at.with_timezone(&Local).naive_local(),
+        }
+    }
+
+    /// Move a wall-clock time written in `self` into `target`'s wall clock.
+    fn convert(self, at: NaiveDateTime, target: EventZone) -> NaiveDateTime {
+        if self == target {
+            at
+        } else {
+            target.wall_clock(self.to_utc(at))
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Attendee {
+    pub mailbox: Mailbox,
+    /// `PARTSTAT`, e.g. `ACCEPTED` or `DECLINED`.
+    pub status: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Rule {
+    freq: String,
+    interval: u32,
+    count: Option<usize>,
+    until: Option<NaiveDateTime>,
+    by_day: Vec<(Option<i32>, Weekday)>,
+    by_month_day: Vec<i32>,
+    by_month: Vec<u32>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CalendarEvent {
+    pub uid: String,
+    pub summary: String,
+    pub description: Option<String>,
+    pub location: Option<String>,
+    pub start: EventTime,
+    pub duration: Duration,
+    pub tzid: Option<String>,
+    /// Zone of `start`; every other time of the event is converted into it.
+    pub zone: EventZone,
+    pub status: Option<String>,
+    pub organizer: Option<Mailbox>,
+    pub attendees: Vec<Attendee>,
+    pub recurrence_id: Option<EventTime>,
+    rule: Option<Rule>,
+    rdates: Vec<EventTime>,
+    exdates: Vec<EventTime>,
+}
+
+/// One dated instance of an event.
+#[derive(Debug, Clone)]
+pub struct EventOccurrence {
+    pub event: CalendarEvent,
+    /// Set for instances of recurring events; identifies the instance within the UID.
+    pub instance: Option<String>,
+}
+
+// ---------------------------------------------------------------------------
+// Content lines
+// ---------------------------------------------------------------------------
+
+/// A content line; vCard files use the same syntax.
+pub(crate) struct Property {
+    pub(crate) name: String,
+    pub(crate) params: HashMap<String, String>,
+    pub(crate) value: String,
+}
+
+impl Property {
+    pub(crate) fn param(&self, name: &str) -> Option<&str> {
+        self.params.get(name).map(String::as_str)
+    }
+}
+
+/// Join folded lines (continuations start with a space or tab).
+pub(crate) fn unfold(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.split('\n') {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ if line.is_empty() => {}
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+/// `NAME;PARAM=value;PARAM="quoted:value":VALUE`
+pub(crate) fn parse_property(line: &str) -> Option<Property> {
+    let mut in_quotes = false;
+    let mut split_at = None;
+    let mut segments = Vec::new();
+    let mut segment_start = 0;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            ';' if !in_quotes => {
+                segments.push(&line[segment_start..i]);
+                segment_start = i + 1;
+            }
+            ':' if !in_quotes => {
+                split_at = Some(i);
+                break;
+            }
+            _ => {}
+        }
+    }
+    let split_at = split_at?;
+    segments.push(&line[segment_start..split_at]);
+    let name = segments.first()?.trim().to_ascii_uppercase();
+    let params = segments[1..]
+        .iter()
+        .filter_map(|p| p.split_once('='))
+        .map(|(k, v)| {
+            (
+                k.trim().to_ascii_uppercase(),
+                v.trim().trim_matches('"').to_string(),
+            )
+        })
+        .collect();
+    Some(Property {
+        name,
+        params,
+        value: line[split_at + 1..].to_string(),
+    })
+}
+
+/// Undo TEXT escaping (`\n`, `\,`, `\;`, `\\`).
+pub(crate) fn unescape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => out.push('\n'),
+            Some(other) => out.push(other),
+            None => {}
+        }
+    }
+    out.trim().to_string()
+}
+
+fn parse_time(value: &str, date_only: bool) -> Option<EventTime> {
+    let value = value.trim().trim_end_matches('Z');
+    if date_only || value.len() == 8 {
+        let date = NaiveDate::parse_from_str(value.get(..8)?, "%Y%m%d").ok()?;
+        return Some(EventTime {
+            at: date.and_time(NaiveTime::MIN),
+            all_day: true,
+        });
+    }
+    let at = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S")
+        .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M"))
+        .ok()?;
+    Some(EventTime { at, all_day: false })
+}
+
+/// Parse a value written in `from` as wall-clock time in `zone`. Dates have no zone.
+fn time_in(value: &str, date_only: bool, from: EventZone, zone: EventZone) -> Option<EventTime> {
+    let mut time = parse_time(value, date_only)?;
+    if !time.all_day {
+        time.at = from.convert(time.at, zone);
+    }
+    Some(time)
+}
+
+fn property_time(property: &Property, zone: EventZone) -> Option<EventTime> {
+    let date_only = property.param("VALUE") == Some("DATE");
+    time_in(&property.value, date_only, EventZone::of(property), zone)
+}
+
+/// Comma-separated date lists (EXDATE, RDATE).
+fn property_times(property: &Property, zone: EventZone) -> Vec<EventTime> {
+    let date_only = property.param("VALUE") == Some("DATE");
+    let from = EventZone::of(property);
+    property
+        .value
+        .split(',')
+        .filter_map(|v| {
+            // In a list only each value itself says whether it is UTC.
+            let from = if v.trim().ends_with('Z') {
+                EventZone::Utc
+            } else {
+                from
+            };
+            time_in(v, date_only, from, zone)
+        })
+        .collect()
+}
+
+/// ISO 8601 durations such as `PT1H30M`, `P1D` or `P2W`.
+fn parse_duration(value: &str) -> Option<Duration> {
+    let (negative, value) = match value.trim().strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, value.trim().trim_start_matches('+')),
+    };
+    let value = value.strip_prefix('P')?;
+    let mut total = Duration::zero();
+    let mut number = String::new();
+    let mut in_time = false;
+    for c in value.chars() {
+        match c {
+            'T' => in_time = true,
+            '0'..='9' => number.push(c),
+            unit => {
+                let n: i64 = number.parse().ok()?;
+                number.clear();
+                // Out-of-range amounts make the whole value invalid rather than panicking.
+                let part = match (unit, in_time) {
+                    ('W', false) => Duration::try_weeks(n),
+                    ('D', false) => Duration::try_days(n),
+                    ('H', true) => Duration::try_hours(n),
+                    ('M', true) => Duration::try_minutes(n),
+                    ('S', true) => Duration::try_seconds(n),
+                    _ => None,
+                }?;
+                total = total.checked_add(&part)?;
+            }
+        }
+    }
+    Some(if negative { -total } else { total })
+}
+
+fn parse_weekday(code: &str) -> Option<Weekday> {
+    Some(match code {
+        "MO" => Weekday::Mon,
+        "TU" => Weekday::Tue,
+        "WE" => Weekday::Wed,
+        "TH" => Weekday::Thu,
+        "FR" => Weekday::Fri,
+        "SA" => Weekday::Sat,
+        "SU" => Weekday::Sun,
+        _ => return None,
+    })
+}
+
+fn parse_rule(value: &str, zone: EventZone) -> Option<Rule> {
+    let mut rule = Rule {
+        interval: 1,
+        ..Default::default()
+    };
+    for part in value.split(';') {
+        let Some((key, value)) = part.split_once('=') else {
+            continue;
+        };
+        match key.trim().to_ascii_uppercase().as_str() {
+            "FREQ" => rule.freq = value.trim().to_ascii_uppercase(),
+            "INTERVAL" => rule.interval = value.trim().parse().unwrap_or(1).max(1),
+            "COUNT" => rule.count = value.trim().parse().ok(),
+            "UNTIL" => {
+                let from = if value.trim().ends_with('Z') {
+                    EventZone::Utc
+                } else {
+                    zone
+                };
+                rule.until = time_in(value, false, from, zone).map(|t| t.at)
+            }
+            "BYDAY" => {
+                rule.by_day = value
+                    .split(',')
+                    .filter_map(|day| {
+                        let day = day.trim().to_ascii_uppercase();
+                        let (ordinal, code) = day.split_at(day.len().checked_sub(2)?);
+                        let ordinal = (!ordinal.is_empty())
+                            .then(|| ordinal.trim_start_matches('+').parse().ok())
+                            .flatten();
+                        Some((ordinal, parse_weekday(code)?))
+                    })
+                    .collect()
+            }
+            "BYMONTHDAY" => {
+                rule.by_month_day = value
+                    .split(',')
+                    .filter_map(|d| d.trim().parse().ok())
+                    .collect()
+            }
+            "BYMONTH" => {
+                rule.by_month = value
+                    .split(',')
+                    .filter_map(|m| m.trim().parse().ok())
+                    .collect()
+            }
+            _ => {}
+        }
+    }
+    matches!(
+        rule.freq.as_str(),
+        "DAILY" | "WEEKLY" | "MONTHLY" | "YEARLY"
+    )
+    .then_some(rule)
+}
+
+fn parse_mailbox(property: &Property) -> Option<Mailbox> {
+    let value = property.value.trim();
+    let address = value
+        .get(..7)
+        .filter(|scheme| scheme.eq_ignore_ascii_case("mailto:"))
+        .map_or(value, |_| &value[7..])
+        .trim()
+        .to_string();
+    let name = property
+        .param("CN")
+        .map(|n| n.trim().to_string())
+        .filter(|n| !n.is_empty() && *n != address);
+    (!address.is_empty() || name.is_some()).then(|| Mailbox {
+        address: if address.is_empty() {
+            name.clone().unwrap_or_default()
+        } else {
+            address
+        },
+        name,
+    })
+}
+
+fn parse_event(properties: &[Property]) -> Option<CalendarEvent> {
+    let get = |name: &str| properties.iter().find(|p| p.name == name);
+    let start_property = get("DTSTART")?;
+    let zone = EventZone::of(start_property);
+    let start = property_time(start_property, zone)?;
+    let duration = match (
+        get("DTEND").and_then(|p| property_time(p, zone)),
+        get("DURATION"),
+    ) {
+        (Some(end), _) => end.at - start.at,
+        (None, Some(duration)) => parse_duration(&duration.value).unwrap_or_else(Duration::zero),
+        (None, None) if start.all_day => Duration::days(1),
+        (None, None) => Duration::zero(),
+    };
+    let summary = get("SUMMARY")
+        .map(|p| unescape(&p.value))
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| "Untitled event".to_string());
+    let uid = get("UID")
+        .map(|p| p.value.trim().to_string())
+        .filter(|u| !u.is_empty())
+        .unwrap_or_else(|| format!("{}@{}", summary, start.render()));
+    Some(CalendarEvent {
+        uid,
+        summary,
+        description: get("DESCRIPTION")
+            .map(|p| unescape(&p.value))
+            .filter(|d| !d.is_empty()),
+        location: get("LOCATION")
+            .map(|p| unescape(&p.value))
+            .filter(|l| !l.is_empty()),
+        start,
+        duration: duration.max(Duration::zero()),
+        tzid: match start_property.param("TZID") {
+            Some(tzid) => Some(tzid.to_string()),
+            None if start_property.value.trim().ends_with('Z') => Some("UTC".to_string()),
+            None => None,
+        },
+        zone,
+        status: get("STATUS").map(|p| p.value.trim().to_ascii_uppercase()),
+        organizer: get("ORGANIZER").and_then(parse_mailbox),
+        attendees: properties
+            .iter()
+            .filter(|p| p.name == "ATTENDEE")
+            .filter_map(|p| {
+                Some(Attendee {
+                    mailbox: parse_mailbox(p)?,
+                    status: p.param("PARTSTAT").map(|s| s.to_ascii_uppercase()),
+                })
+            })
+            .collect(),
+        recurrence_id: get("RECURRENCE-ID").and_then(|p| property_time(p, zone)),
+        rule: get("RRULE").and_then(|p| parse_rule(&p.value, zone)),
+        rdates: properties
+            .iter()
+            .filter(|p| p.name == "RDATE")
+            .flat_map(|p| property_times(p, zone))
+            .collect(),
+        exdates: properties
+            .iter()
+            .filter(|p| p.name == "EXDATE")
+            .flat_map(|p| property_times(p, zone))
+            .collect(),
+    })
+}
+
+/// Every VEVENT of an `.ics` file (cancelled events are left out).
+pub fn read_calendar(path: &Path) -> Result<Vec<CalendarEvent>, String> {
+    let bytes = std::fs::read(path).map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+    let text = String::from_utf8_lossy(&bytes);
+    if !text
+        .trim_start_matches('\u{feff}')
+        .trim_start()
+        .starts_with("BEGIN:VCALENDAR")
+    {
+        return Err(format!("{:?} is not an iCalendar file.", path));
+    }
+    let mut events = Vec::new();
+    let mut current: Option<Vec<Property>> = None;
+    // Nested components (VALARM) inside an event are skipped.
+    let mut nested = 0usize;
+    for line in unfold(&text) {
+        let Some(property) = parse_property(&line) else {
+            continue;
+        };
+        let value = property.value.trim().to_ascii_uppercase();
+        match (property.name.as_str(), current.as_mut()) {
+            ("BEGIN", None) if value == "VEVENT" => current = Some(Vec::new()),
+            ("BEGIN", Some(_)) => nested += 1,
+            ("END", Some(_)) if nested > 0 => nested -= 1,
+            ("END", Some(_)) if value == "VEVENT" => {
+                let properties = current.take().unwrap_or_default();
+                match parse_event(&properties) {
+                    Some(event) if event.status.as_deref() != Some("CANCELLED") => {
+                        events.push(event)
+                    }
+                    Some(_) => {}
+                    None => println!("⚠️ [import_calendar] Skipping a VEVENT without DTSTART"),
+                }
+            }
+            (_, Some(properties)) if nested == 0 => properties.push(property),
+            _ => {}
+        }
+    }
+    Ok(events)
+}
+
+// ---------------------------------------------------------------------------
+// Recurrence expansion
+// ---------------------------------------------------------------------------
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    NaiveDate::from_ymd_opt(year, month, 1)
+        .and_then(|first| Some((first.checked_add_months(Months::new(1))? - first).num_days()))
+        .map_or(31, |days| days as u32)
+}
+
+/// BYMONTHDAY days of one month; negative values count back from the month's end.
+fn month_day_dates(rule: &Rule, year: i32, month: u32) -> Vec<NaiveDate> {
+    let length = days_in_month(year, month) as i32;
+    rule.by_month_day
+        .iter()
+        .map(|&day| if day < 0 { length + day + 1 } else { day })
+        .filter(|day| (1..=length).contains(day))
+        .filter_map(|day| NaiveDate::from_ymd_opt(year, month, day as u32))
+        .collect()
+}
+
+/// Days of `span` (a month or a year) matching BYDAY. `2MO` is the span's second Monday and
+/// `-1FR` its last Friday.
+fn weekday_dates(rule: &Rule, span: &[NaiveDate]) -> Vec<NaiveDate> {
+    let mut days = Vec::new();
+    for &(ordinal, weekday) in &rule.by_day {
+        let matching: Vec<NaiveDate> = span
+            .iter()
+            .copied()
+            .filter(|d| d.weekday() == weekday)
+            .collect();
+        match ordinal {
+            None => days.extend(matching),
+            Some(n) if n > 0 => days.extend(matching.get(n as usize - 1)),
+            Some(n) => days.extend(
+                matching
+                    .len()
+                    .checked_sub(n.unsigned_abs() as usize)
+                    .map(|i| matching[i]),
+            ),
+        }
+    }
+    days
+}
+
+/// Days of one month matching BYMONTHDAY / BYDAY, or the start's day of month by default.
+fn month_days(rule: &Rule, year: i32, month: u32, default_day: u32) -> Vec<NaiveDate> {
+    let length = days_in_month(year, month);
+    let span: Vec<NaiveDate> = (1..=length)
+        .filter_map(|day| NaiveDate::from_ymd_opt(year, month, day))
+        .collect();
+    let mut days = month_day_dates(rule, year, month);
+    days.extend(weekday_dates(rule, &span));
+    if rule.by_month_day.is_empty() && rule.by_day.is_empty() {
+        days.extend(NaiveDate::from_ymd_opt(year, month, default_day));
+    }
+    days.sort();
+    days.dedup();
+    days
+}
+
+/// Days of a YEARLY rule without BYMONTH: BYMONTHDAY applies to every month and BYDAY to the
+/// whole year (`20MO` is the year's twentieth Monday), as RFC 5545 expands them.
+fn year_days(rule: &Rule, year: i32) -> Vec<NaiveDate> {
+    let Some(first) = NaiveDate::from_ymd_opt(year, 1, 1) else {
+        return vec![];
+    };
+    let span: Vec<NaiveDate> = first.iter_days().take_while(|d| d.year() == year).collect();
+    let mut days: Vec<NaiveDate> = (1..=12)
+        .flat_map(|month| month_day_dates(rule, year, month))
+        .collect();
+    days.extend(weekday_dates(rule, &span));
+    days.sort();
+    days.dedup();
+    days
+}
+
+/// Candidate dates of the `period`-th interval of a rule, unfiltered by COUNT/UNTIL. Periods
+/// past the end of the calendar have no dates.
+fn period_dates(rule: &Rule, start: NaiveDate, period: u32) -> Vec<NaiveDate> {
+    let Some(step) = period.checked_mul(rule.interval) else {
+        return vec![];
+    };
+    match rule.freq.as_str() {
+        "DAILY" => {
+            let Some(day) = start.checked_add_signed(Duration::days(step as i64)) else {
+                return vec![];
+            };
+            let weekday_ok =
+                rule.by_day.is_empty() || rule.by_day.iter().any(|(_, w)| *w == day.weekday());
+            let month_ok = rule.by_month.is_empty() || rule.by_month.contains(&day.month());
+            if weekday_ok && month_ok {
+                vec![day]
+            } else {
+                vec![]
+            }
+        }
+        "WEEKLY" => {
+            let Some(week_start) = start
+                .checked_sub_signed(Duration::days(start.weekday().num_days_from_monday() as i64))
+                .and_then(|monday| monday.checked_add_signed(Duration::weeks(step as i64)))
+            else {
+                return vec![];
+            };
+            if rule.by_day.is_empty() {
+                return week_start
+                    .checked_add_signed(Duration::days(
+                        start.weekday().num_days_from_monday() as i64
+                    ))
+                    .into_iter()
+                    .collect();
+            }
+            let mut days: Vec<NaiveDate> = rule
+                .by_day
+                .iter()
+                .filter_map(|(_, w)| {
+                    week_start.checked_add_signed(Duration::days(w.num_days_from_monday() as i64))
+                })
+                .collect();
+            days.sort();
+            days.dedup();
+            days
+        }
+        "MONTHLY" => {
+            let Some(month) = start
+                .with_day(1)
+                .and_then(|d| d.checked_add_months(Months::new(step)))
+            else {
+                return vec![];
+            };
+            if !rule.by_month.is_empty() && !rule.by_month.contains(&month.month()) {
+                return vec![];
+            }
+            month_days(rule, month.year(), month.month(), start.day())
+        }
+        "YEARLY" => {
+            let Some(year) = i32::try_from(step)
+                .ok()
+                .and_then(|step| start.year().checked_add(step))
+            else {
+                return vec![];
+            };
+            if rule.by_month.is_empty() {
+                if rule.by_month_day.is_empty() && rule.by_day.is_empty() {
+                    return month_days(rule, year, start.month(), start.day());
+                }
+                return year_days(rule, year);
+            }
+            let mut months = rule.by_month.clone();
+            months.sort();
+            months.dedup();
+            months
+                .into_iter()
+                .filter(|m| (1..=12).contains(m))
+                .flat_map(|m| month_days(rule, year, m, start.day()))
+                .collect()
+        }
+        _ => vec![],
+    }
+}
+
+fn instance_key(time: &EventTime) -> String {
+    if time.all_day {
+        time.at.format("%Y%m%d").to_string()
+    } else {
+        time.at.format("%Y%m%dT%H%M%S").to_string()
+    }
+}
+
+/// RRULE instances from `start` up to `cutoff` (inclusive), appended to `starts`.
+fn rule_starts(rule: &Rule, start: EventTime, cutoff: NaiveDate, starts: &mut Vec<EventTime>) {
+    let mut emitted = 0usize;
+    'periods: for period in 0..10_000u32 {
+        let dates = period_dates(rule, start.at.date(), period);
+        if dates.first().is_some_and(|d| *d > cutoff) && period > 0 {
+            break;
+        }
+        for date in dates {
+            let at = date.and_time(start.at.time());
+            if at < start.at {
+                continue;
+            }
+            if date > cutoff
+                || rule.until.is_some_and(|until| at > until)
+                || rule.count.is_some_and(|count| emitted >= count)
+                || starts.len() >= MAX_OCCURRENCES
+            {
+                break 'periods;
+            }
+            emitted += 1;
+            starts.push(EventTime {
+                at,
+                all_day: start.all_day,
+            });
+        }
+    }
+}
+
+/// Start times of a recurring event up to `cutoff` (inclusive), EXDATEs removed. Without an
+/// RRULE the series is DTSTART plus the RDATEs.
+fn recurrence_starts(
+    event: &CalendarEvent,
+    rule: Option<&Rule>,
+    cutoff: NaiveDate,
+) -> Vec<EventTime> {
+    let mut starts = Vec::new();
+    match rule {
+        Some(rule) => rule_starts(rule, event.start, cutoff, &mut starts),
+        None => starts.push(event.start),
+    }
+    for rdate in &event.rdates {
+        if rdate.at.date() <= cutoff && !starts.iter().any(|s| s.at == rdate.at) {
+            starts.push(*rdate);
+        }
+    }
+    starts.retain(|s| {
+        !event
+            .exdates
+            .iter()
+            .any(|x| x.at == s.at || (x.all_day && x.at.date() == s.at.date()))
+    });
+    starts.sort_by_key(|s| s.at);
+    starts
+}
+
+/// Expand events into occurrences. Recurring events produce one occurrence per instance up to
+/// `cutoff`; instances overridden by a RECURRENCE-ID event are replaced by that event.
+pub fn expand_occurrences(events: &[CalendarEvent], cutoff: NaiveDate) -> Vec<EventOccurrence> {
+    let overrides: HashSet<(String, String)> = events
+        .iter()
+        .filter_map(|e| Some((e.uid.clone(), instance_key(e.recurrence_id.as_ref()?))))
+        .collect();
+    let mut occurrences = Vec::new();
+    for event in events {
+        if let Some(recurrence_id) = &event.recurrence_id {
+            occurrences.push(EventOccurrence {
+                event: event.clone(),
+                instance: Some(instance_key(recurrence_id)),
+            });
+            continue;
+        }
+        if event.rule.is_none() && event.rdates.is_empty() {
+            occurrences.push(EventOccurrence {
+                event: event.clone(),
+                instance: None,
+            });
+            continue;
+        }
+        for start in recurrence_starts(event, event.rule.as_ref(), cutoff) {
+            let key = instance_key(&start);
+            if overrides.contains(&(event.uid.clone(), key.clone())) {
+                continue;
+            }
+            let mut instance = event.clone();
+            instance.start = start;
+            occurrences.push(EventOccurrence {
+                event: instance,
+                instance: Some(key),
+            });
+        }
+    }
+    occurrences.sort_by_key(|o| o.event.start.at);
+    occurrences
+}
+
+// ---------------------------------------------------------------------------
+// Memories
+// ---------------------------------------------------------------------------
+
+impl EventOccurrence {
+    /// Dedupe key stored as `ics_instance`: the UID, plus the instance for recurring events.
+    pub fn key(&self) -> String {
+        match &self.instance {
+            Some(instance) => format!("{}#{}", self.event.uid, instance),
+            None => self.event.uid.clone(),
+        }
+    }
+
+    fn end(&self) -> EventTime {
+        EventTime {
+            at: self
+                .event
+                .start
+                .at
+                .checked_add_signed(self.event.duration)
+                .unwrap_or(NaiveDateTime::MAX),
+            all_day: self.event.start.all_day,
+        }
+    }
+
+    /// Name of the Event entity; the date keeps repeated and recurring events apart.
+    pub fn entity_name(&self) -> String {
+        format!(
+            "{} ({})",
+            self.event.summary,
+            self.event.start.at.format("%Y-%m-%d")
+        )
+    }
+
+    /// `created` of the memory: the start of the occurrence in UTC. All-day events start at
+    /// local midnight.
+    pub fn created(&self) -> String {
+        let start = &self.event.start;
+        let zone = if start.all_day {
+            EventZone::Floating
+        } else {
+            self.event.zone
+        };
+        zone.to_utc(start.at)
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string()
+    }
+
+    /// Organizer and attendees who didn't decline.
+    pub fn people(&self) -> Vec<&Mailbox> {
+        let mut seen = HashSet::new();
+        self.event
+            .organizer
+            .iter()
+            .chain(
+                self.event
+                    .attendees
+                    .iter()
+                    .filter(|a| a.status.as_deref() != Some("DECLINED"))
+                    .map(|a| &a.mailbox),
+            )
+            .filter(|m| seen.insert(m.address.to_lowercase()))
+            .collect()
+    }
+
+    /// The Time entity for the occurrence's day(s), already normalized.
+    fn time_entity(&self) -> ExtractedEntity {
+        let start = self.event.start.at.date();
+        // All-day end dates are exclusive.
+        let last = match self.event.start.all_day {
+            true => (self.end().at - Duration::days(1)).date().max(start),
+            false => self.end().at.date(),
+        };
+        let mut attributes = serde_json::json!({
+            "normalized_date": start.format("%Y-%m-%d").to_string(),
+            "normalized_by": "ics",
+        });
+        let name = if last > start {
+            attributes["normalized_end_date"] = last.format("%Y-%m-%d").to_string().into();
+            format!("{} ~ {}", start.format("%Y-%m-%d"), last.format("%Y-%m-%d"))
+        } else {
+            start.format("%Y-%m-%d").to_string()
+        };
+        ExtractedEntity {
+            entity_type: "Time".to_string(),
+            name,
+            attributes: Some(attributes),
+        }
+    }
+
+    /// Entities and relations of the occurrence. `person_name` maps an address to the name of
+    /// the Person entity it resolved to.
+    pub fn graph(
+        &self,
+        person_name: impl Fn(&Mailbox) -> String,
+    ) -> (Vec<ExtractedEntity>, Vec<ExtractedRelation>) {
+        let event = &self.event;
+        let event_name = self.entity_name();
+        let mut attributes = serde_json::json!({
+            "start": event.start.render(),
+            "end": self.end().render(),
+            "uid": event.uid,
+        });
+        if let Some(tzid) = &event.tzid {
+            attributes["timezone"] = tzid.clone().into();
+        }
+        if let Some(status) = &event.status {
+            attributes["status"] = status.to_lowercase().into();
+        }
+        if event.rule.is_some() || self.instance.is_some() {
+            attributes["recurring"] = true.into();
+        }
+        let mut entities = vec![ExtractedEntity {
+            entity_type: "Event".to_string(),
+            name: event_name.clone(),
+            attributes: Some(attributes),
+        }];
+        let relation = |from: &str, to: &str, kind: &str| ExtractedRelation {
+            from: from.to_string(),
+            to: to.to_string(),
+            relation: kind.to_string(),
+        };
+        let time = self.time_entity();
+        let mut relations = vec![relation(&event_name, &time.name, "happened_on")];
+        entities.push(time);
+        if let Some(location) = &event.location {
+            let name = location
+                .lines()
+                .next()
+                .unwrap_or(location)
+                .trim()
+                .to_string();
+            relations.push(relation(&event_name, &name, "located_at"));
+            entities.push(ExtractedEntity {
+                entity_type: "Location".to_string(),
+                name,
+                attributes: None,
+            });
+        }
+        for mailbox in self.people() {
+            let name = person_name(mailbox);
+            let is_organizer = event
+                .organizer
+                .as_ref()
+                .is_some_and(|o| o.address.eq_ignore_ascii_case(&mailbox.address));
+            let kind = if is_organizer {
+                "organized"
+            } else {
+                "participated_in"
+            };
+            relations.push(relation(&name, &event_name, kind));
+            entities.push(ExtractedEntity {
+                entity_type: "Person".to_string(),
+                attributes: mailbox
+                    .address
+                    .contains('@')
+                    .then(|| serde_json::json!({ "email": mailbox.address })),
+                name,
+            });
+        }
+        (entities, relations)
+    }
+
+    /// Readable body of the memory.
+    pub fn content(&self) -> String {
+        let event = &self.event;
+        let end = self.end();
+        let when = match (event.start.all_day, end.at.date() == event.start.at.date()) {
+            (true, _) if event.duration <= Duration::days(1) => event.start.render(),
+            (true, _) => format!(
+                "{} – {}",
+                event.start.render(),
+                (end.at - Duration::days(1)).format("%Y-%m-%d")
+            ),
+            (false, true) => format!("{} – {}", event.start.render(), end.at.format("%H:%M")),
+            (false, false) => format!("{} – {}", event.start.render(), end.render()),
+        };
+        let mut content = format!("{}\n\nWhen: {}", event.summary, when);
+        if let Some(tzid) = &event.tzid {
+            content.push_str(&format!(" ({})", tzid));
+        }
+        if let Some(location) = &event.location {
+            content.push_str(&format!("\nWhere: {}", location.replace('\n', ", ")));
+        }
+        if let Some(organizer) = &event.organizer {
+            content.push_str(&format!("\nOrganizer: {}", organizer.label()));
+        }
+        if !event.attendees.is_empty() {
+            let attendees: Vec<String> = event
+                .attendees
+                .iter()
+                .map(|a| match a.status.as_deref() {
+                    Some("DECLINED") => format!("{} (declined)", a.mailbox.label()),
+                    Some("TENTATIVE") => format!("{} (tentative)", a.mailbox.label()),
+                    _ => a.mailbox.label().to_string(),
+                })
+                .collect();
+            content.push_str(&format!("\nAttendees: {}", attendees.join(", ")));
+        }
+        if let Some(description) = &event.description {
+            content.push_str("\n\n");
+            content.push_str(description);
+        }
+        content
+    }
+
+    pub fn frontmatter(&self) -> Mapping {
+        let mut map = Mapping::new();
+        map.insert("ics_uid".into(), YamlValue::String(self.event.uid.clone()));
+        map.insert("ics_instance".into(), YamlValue::String(self.key()));
+        map
+    }
+
+    pub fn summary(&self) -> CalendarEventSummary {
+        CalendarEventSummary {
+            title: self.event.summary.clone(),
+            start: self.event.start.render(),
+            location: self.event.location.clone(),
+            attendees: self
+                .event
+                .attendees
+                .iter()
+                .map(|a| a.mailbox.label().to_string())
+                .collect(),
+            recurring: self.instance.is_some(),
+        }
+    }
+}
+
+/// `ics_instance` keys recorded in the frontmatter of the library's memory files.
+pub fn imported_event_keys(
+    memories_dir: &Path,
+    key: Option<&LibraryKey>,
+) -> Result<HashSet<String>, String> {
+    let mut keys = HashSet::new();
+    for path in list_memory_files(memories_dir)? {
+        let Ok(record) = read_memory(&path, key) else {
+            continue;
+        };
+        if let Some(key) = record
+            .frontmatter
+            .raw
+            .get("ics_instance")
+            .and_then(|v| v.as_str())
+        {
+            keys.insert(key.to_string());
+        }
+    }
+    Ok(keys)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Instance keys of one event built from `lines`, expanded up to `cutoff`.
+    fn instances(lines: &[&str], cutoff: &str) -> Vec<String> {
+        let properties: Vec<Property> = lines.iter().filter_map(|l| parse_property(l)).collect();
+        let event = parse_event(&properties).expect("event has a DTSTART");
+        let cutoff = NaiveDate::parse_from_str(cutoff, "%Y-%m-%d").unwrap();
+        expand_occurrences(&[event], cutoff)
+            .into_iter()
+            .map(|o| o.instance.unwrap_or_default())
+            .collect()
+    }
+
+    #[test]
+    fn rule_expansion() {
+        // 2024-01-01 is a Monday.
+        let cases: &[(&str, &[&str], &[&str])] = &[
+            (
+                "daily count",
+                &["DTSTART;VALUE=DATE:20240101", "RRULE:FREQ=DAILY;COUNT=3"],
+                &["20240101", "20240102", "20240103"],
+            ),
+            (
+                "daily interval until",
+                &[
+                    "DTSTART;VALUE=DATE:20240101",
+                    "RRULE:FREQ=DAILY;INTERVAL=2;UNTIL=20240107T000000",
+                ],
+                &["20240101", "20240103", "20240105", "20240107"],
+            ),
+            (
+                "daily byday",
+                &[
+                    "DTSTART;VALUE=DATE:20240101",
+                    "RRULE:FREQ=DAILY;BYDAY=SA,SU;COUNT=4",
+                ],
+                &["20240106", "20240107", "20240113", "20240114"],
+            ),
+            (
+                "weekly byday",
+                &[
+                    "DTSTART;VALUE=DATE:20240101",
+                    "RRULE:FREQ=WEEKLY;BYDAY=WE,MO;COUNT=4",
+                ],
+                &["20240101", "20240103", "20240108", "20240110"],
+            ),
+            (
+                "weekly interval until",
+                &[
+                    "DTSTART;VALUE=DATE:20240101",
+                    "RRULE:FREQ=WEEKLY;INTERVAL=2;BYDAY=FR;UNTIL=20240201T000000",
+                ],
+                &["20240105", "20240119"],
+            ),
+            (
+                "weekly exdate in another zone",
+                &[
+                    "DTSTART;TZID=Europe/Berlin:20240101T090000",
+                    "RRULE:FREQ=WEEKLY;COUNT=3",
+                    "EXDATE:20240108T080000Z",
+                ],
+                &["20240101T090000", "20240115T090000"],
+            ),
+            (
+                "monthly last day",
+                &[
+                    "DTSTART;VALUE=DATE:20240131",
+                    "RRULE:FREQ=MONTHLY;BYMONTHDAY=-1;COUNT=3",
+                ],
+                &["20240131", "20240229", "20240331"],
+            ),
+            (
+                "monthly second tuesday",
+                &[
+                    "DTSTART;VALUE=DATE:20240101",
+                    "RRULE:FREQ=MONTHLY;BYDAY=2TU;COUNT=3",
+                ],
+                &["20240109", "20240213", "20240312"],
+            ),
+            (
+                "monthly skips short months",
+                &["DTSTART;VALUE=DATE:20240131", "RRULE:FREQ=MONTHLY;COUNT=3"],
+                &["20240131", "20240331", "20240531"],
+            ),
+            (
+                "yearly leap day",
+                &["DTSTART;VALUE=DATE:20240229", "RRULE:FREQ=YEARLY;COUNT=3"],
+                &["20240229", "20280229", "20320229"],
+            ),
+            (
+                "yearly bymonth last sunday",
+                &[
+                    "DTSTART;VALUE=DATE:20240101",
+                    "RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=-1SU;COUNT=2",
+                ],
+                &["20240331", "20250330"],
+            ),
+            (
+                "yearly byday ordinal counts through the year",
+                &[
+                    "DTSTART;VALUE=DATE:20240101",
+                    "RRULE:FREQ=YEARLY;BYDAY=20MO;COUNT=2",
+                ],
+                &["20240513", "20250519"],
+            ),
+            (
+                "yearly byday without bymonth crosses months",
+                &[
+                    "DTSTART;VALUE=DATE:20241220",
+                    "RRULE:FREQ=YEARLY;BYDAY=FR;COUNT=3",
+                ],
+                &["20241220", "20241227", "20250103"],
+            ),
+            (
+                "yearly bymonthday without bymonth",
+                &[
+                    "DTSTART;VALUE=DATE:20241101",
+                    "RRULE:FREQ=YEARLY;BYMONTHDAY=1;COUNT=3",
+                ],
+                &["20241101", "20241201", "20250101"],
+            ),
+            (
+                "cutoff ends an open series",
+                &["DTSTART;VALUE=DATE:20341230", "RRULE:FREQ=DAILY"],
+                &["20341230", "20341231"],
+            ),
+        ];
+        for (name, lines, expected) in cases {
+            assert_eq!(instances(lines, "2034-12-31"), *expected, "{}", name);
+        }
+    }
+
+    #[test]
+    fn rule_expansion_survives_huge_intervals() {
+        let lines = [
+            "DTSTART;VALUE=DATE:20240101",
+            "RRULE:FREQ=YEARLY;INTERVAL=4000000000;BYDAY=MO",
+        ];
+        assert_eq!(instances(&lines, "2024-01-10"), ["20240101", "20240108"]);
+    }
+
+    #[test]
+    fn parse_duration_rejects_out_of_range_values() {
+        assert_eq!(
+            parse_duration("P1W2DT3H"),
+            Some(Duration::days(9) + Duration::hours(3))
+        );
+        assert_eq!(parse_duration("-PT15M"), Some(-Duration::minutes(15)));
+        assert_eq!(parse_duration("P9223372036854775807W"), None);
+        assert_eq!(parse_duration("P15250284452471W"), None);
+    }
+}
@@ -0,0 +1,787 @@
+//! Chat-log importers: Telegram JSON (`result.json`), WhatsApp TXT and WeChat CSV/HTML dumps.
+//!
+//! Exports are parsed into messages, grouped into conversations by day or by thread (one chat),
+//! and rendered as plain-text memories. Senders become the conversation's participants, which
+//! the caller seeds as Person entities (except the exporting user) when it feeds the memories
+//! through the save pipeline.
+
+use crate::graph_import::parse_csv;
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// Conversations longer than this are split into several memories.
+const MAX_CONVERSATION_CHARS: usize = 6000;
+/// Sender name for the exporting user when the dump only flags outgoing messages.
+const SELF_SENDER: &str = "Me";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChatFormat {
+    Telegram,
+    WhatsApp,
+    WeChatCsv,
+    WeChatHtml,
+}
+
+impl ChatFormat {
+    pub fn parse(format: &str) -> Result<Self, String> {
+        match format.trim().to_lowercase().as_str() {
+            "telegram" => Ok(ChatFormat::Telegram),
+            "whatsapp" => Ok(ChatFormat::WhatsApp),
+            "wechat_csv" | "wechat-csv" => Ok(ChatFormat::WeChatCsv),
+            "wechat_html" | "wechat-html" => Ok(ChatFormat::WeChatHtml),
+            other => Err(format!("Unknown chat export format: {}", other)),
+        }
+    }
+
+    /// Guess the format from the file extension.
+    pub fn detect(path: &Path) -> Result<Self, String> {
+        let extension = path
+            .extension()
+            .and_then(|x| x.to_str())
+            .unwrap_or_default()
+            .to_lowercase();
+        match extension.as_str() {
+            "json" => Ok(ChatFormat::Telegram),
+            "txt" => Ok(ChatFormat::WhatsApp),
+            "csv" => Ok(ChatFormat::WeChatCsv),
+            "html" | "htm" => Ok(ChatFormat::WeChatHtml),
+            _ => Err(format!(
+                "Can't tell the chat export format of {:?}; choose one explicitly.",
+                path
+            )),
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ChatFormat::Telegram => "Telegram",
+            ChatFormat::WhatsApp => "WhatsApp",
+            ChatFormat::WeChatCsv | ChatFormat::WeChatHtml => "WeChat",
+        }
+    }
+
+    /// Tag added to imported memories.
+    pub fn tag(self) -> &'static str {
+        match self {
+            ChatFormat::Telegram => "telegram",
+            ChatFormat::WhatsApp => "whatsapp",
+            ChatFormat::WeChatCsv | ChatFormat::WeChatHtml => "wechat",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChatSplit {
+    /// One memory per chat per day.
+    Day,
+    /// One memory per chat.
+    Thread,
+}
+
+impl ChatSplit {
+    pub fn parse(split: &str) -> Result<Self, String> {
+        match split.trim().to_lowercase().as_str() {
+            "" | "day" => Ok(ChatSplit::Day),
+            "thread" | "chat" => Ok(ChatSplit::Thread),
+            other => Err(format!(
+                "Unknown split mode: {} (expected day or thread)",
+                other
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChatImportRequest {
+    pub path: String,
+    /// `telegram`, `whatsapp`, `wechat_csv` or `wechat_html`; guessed from the extension.
+    pub format: Option<String>,
+    /// `day` (default) or `thread`.
+    pub split_by: Option<String>,
+    /// Conversations saved between progress reports.
+    pub batch_size: Option<usize>,
+    /// Parse and group only; nothing is saved.
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatConversationSummary {
+    pub title: String,
+    pub created: String,
+    pub participants: Vec<String>,
+    pub message_count: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatImportError {
+    pub title: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChatImportReport {
+    pub format: String,
+    pub dry_run: bool,
+    pub messages: usize,
+    pub conversations: Vec<ChatConversationSummary>,
+    pub saved: usize,
+    pub failed: usize,
+    pub errors: Vec<ChatImportError>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ChatMessage {
+    pub thread: String,
+    pub sender: String,
+    /// Local time, as shown in the export.
+    pub sent_at: NaiveDateTime,
+    pub text: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct ChatConversation {
+    pub title: String,
+    /// First message time in UTC, `YYYY-MM-DD HH:MM:SS` (the memory's `created`).
+    pub created: String,
+    pub participants: Vec<String>,
+    pub message_count: usize,
+    pub content: String,
+}
+
+impl ChatConversation {
+    pub fn summary(&self) -> ChatConversationSummary {
+        ChatConversationSummary {
+            title: self.title.clone(),
+            created: self.created.clone(),
+            participants: self.participants.clone(),
+            message_count: self.message_count,
+        }
+    }
+
+    /// Participants to seed as Person entities. The exporting user's placeholder name is left
+    /// out, or one "Me" node would link every imported conversation together.
+    pub fn seed_participants(&self) -> impl Iterator<Item = &String> {
+        self.participants.iter().filter(|name| *name != SELF_SENDER)
+    }
+}
+
+pub fn read_chat_log(path: &Path, format: ChatFormat) -> Result<Vec<ChatMessage>, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+    let text = text.strip_prefix('\u{feff}').unwrap_or(&text);
+    let thread = default_thread_name(path);
+    let messages = match format {
+        ChatFormat::Telegram => parse_telegram(text)?,
+        ChatFormat::WhatsApp => parse_whatsapp(text, &thread),
+        ChatFormat::WeChatCsv => parse_wechat_csv(text, &thread)?,
+        ChatFormat::WeChatHtml => parse_wechat_text(&html_to_text(text), &thread),
+    };
+    if messages.is_empty() {
+        return Err(format!(
+            "No messages found in {:?}; is it a {} export?",
+            path,
+            format.label()
+        ));
+    }
+    Ok(messages)
+}
+
+/// Chat name from the file name, minus the prefix WhatsApp puts in front of it.
+fn default_thread_name(path: &Path) -> String {
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let name = ["WhatsApp Chat with ", "WhatsApp Chat - "]
+        .iter()
+        .find_map(|prefix| stem.strip_prefix(prefix))
+        .unwrap_or(&stem)
+        .trim();
+    if name.is_empty() {
+        "chat".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+fn local_from_unix(seconds: i64) -> Option<NaiveDateTime> {
+    Local
+        .timestamp_opt(seconds, 0)
+        .single()
+        .map(|t| t.naive_local())
+}
+
+/// `2023-01-31 10:00[:00]`, `2023/01/31 10:00[:00]`, `2023-01-31T10:00:00` or Unix seconds.
+fn parse_datetime(text: &str) -> Option<NaiveDateTime> {
+    let text = text.trim();
+    if !text.is_empty() && text.chars().all(|c| c.is_ascii_digit()) {
+        return text.parse().ok().and_then(local_from_unix);
+    }
+    [
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%d %H:%M",
+        "%Y/%m/%d %H:%M:%S",
+        "%Y/%m/%d %H:%M",
+        "%Y-%m-%dT%H:%M:%S",
+    ]
+    .iter()
+    .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+}
+
+// ---------------------------------------------------------------------------
+// Telegram
+// ---------------------------------------------------------------------------
+
+/// Message text is either a string or a list of strings and `{type, text}` entities.
+fn telegram_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Array(parts) => parts
+            .iter()
+            .map(|part| match part {
+                Value::String(s) => s.as_str(),
+                other => other
+                    .get("text")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default(),
+            })
+            .collect(),
+        _ => String::new(),
+    }
+}
+
+fn telegram_media(message: &Value) -> Option<String> {
+    if message.get("photo").is_some() {
+        return Some("[photo]".to_string());
+    }
+    if let Some(media) = message.get("media_type").and_then(Value::as_str) {
+        let emoji = message
+            .get("sticker_emoji")
+            .and_then(Value::as_str)
+            .map(|e| format!(" {}", e))
+            .unwrap_or_default();
+        return Some(format!("[{}{}]", media.replace('_', " "), emoji));
+    }
+    message.get("file").map(|_| "[file]".to_string())
+}
+
+/// Either a full export (`chats.list`) or a single chat (`messages`).
+fn parse_telegram(text: &str) -> Result<Vec<ChatMessage>, String> {
+    let root: Value =
+        serde_json::from_str(text).map_err(|e| format!("Invalid Telegram export: {}", e))?;
+    let chats: Vec<&Value> = match root.pointer("/chats/list").and_then(Value::as_array) {
+        Some(list) => list.iter().collect(),
+        None if root.get("messages").is_some() => vec![&root],
+        None => return Err("Not a Telegram export: no chats or messages.".to_string()),
+    };
+
+    let mut messages = Vec::new();
+    for chat in chats {
+        let thread = chat
+            .get("name")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("Telegram chat {}", chat.get("id").unwrap_or(&Value::Null)));
+        for message in chat
+            .get("messages")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            if message.get("type").and_then(Value::as_str) != Some("message") {
+                continue;
+            }
+            let sent_at = message
+                .get("date_unixtime")
+                .and_then(Value::as_str)
+                .and_then(|t| t.parse().ok())
+                .and_then(local_from_unix)
+                .or_else(|| {
+                    message
+                        .get("date")
+                        .and_then(Value::as_str)
+                        .and_then(parse_datetime)
+                });
+            let Some(sent_at) = sent_at else {
+                continue;
+            };
+            let mut text = telegram_text(message.get("text").unwrap_or(&Value::Null));
+            if let Some(media) = telegram_media(message) {
+                text = if text.trim().is_empty() {
+                    media
+                } else {
+                    format!("{} {}", media, text)
+                };
+            }
+            if text.trim().is_empty() {
+                continue;
+            }
+            let sender = ["from", "from_id"]
+                .iter()
+                .find_map(|k| message.get(*k).and_then(Value::as_str))
+                .unwrap_or("Unknown");
+            messages.push(ChatMessage {
+                thread: thread.clone(),
+                sender: sender.to_string(),
+                sent_at,
+                text,
+            });
+        }
+    }
+    Ok(messages)
+}
+
+// ---------------------------------------------------------------------------
+// WhatsApp
+// ---------------------------------------------------------------------------
+
+struct WhatsAppHeader<'a> {
+    date: [u32; 3],
+    time: NaiveTime,
+    rest: &'a str,
+}
+
+fn parse_whatsapp_time(text: &str) -> Option<NaiveTime> {
+    let normalized = text
+        .replace(['\u{202f}', '\u{a0}'], " ")
+        .replace('.', "")
+        .to_lowercase();
+    let (clock, meridiem) = match normalized.trim() {
+        t if t.ends_with("am") => (t.trim_end_matches("am").trim(), Some(false)),
+        t if t.ends_with("pm") => (t.trim_end_matches("pm").trim(), Some(true)),
+        t => (t, None),
+    };
+    let parts: Vec<u32> = clock
+        .split(':')
+        .map(|p| p.trim().parse().ok())
+        .collect::<Option<_>>()?;
+    let (mut hour, minute, second) = match parts.as_slice() {
+        [h, m] => (*h, *m, 0),
+        [h, m, s] => (*h, *m, *s),
+        _ => return None,
+    };
+    match meridiem {
+        Some(true) if hour < 12 => hour += 12,
+        Some(false) if hour == 12 => hour = 0,
+        _ => {}
+    }
+    NaiveTime::from_hms_opt(hour, minute, second)
+}
+
+/// `31/12/2023, 22:15 - Alice: hi` (Android) or `[31/12/2023, 22:15:03] Alice: hi` (iOS).
+fn parse_whatsapp_header(line: &str) -> Option<WhatsAppHeader<'_>> {
+    let line = line.trim_start_matches(['\u{200e}', '\u{200f}']);
+    let (stamp, rest) = if let Some(inner) = line.strip_prefix('[') {
+        let (stamp, rest) = inner.split_once(']')?;
+        (stamp, rest.trim_start())
+    } else {
+        let (stamp, rest) = line.split_once(" - ")?;
+        (stamp, rest)
+    };
+    let (date, time) = stamp.split_once(", ").or_else(|| stamp.split_once(' '))?;
+    let date: Vec<u32> = date
+        .split(['/', '.', '-'])
+        .map(|p| p.trim().parse().ok())
+        .collect::<Option<_>>()?;
+    let date: [u32; 3] = date.try_into().ok()?;
+    Some(WhatsAppHeader {
+        date,
+        time: parse_whatsapp_time(time)?,
+        rest,
+    })
+}
+
+/// Resolve WhatsApp's locale-dependent date order from all headers in the file.
+fn whatsapp_date_resolver(headers: &[[u32; 3]]) -> impl Fn([u32; 3]) -> Option<NaiveDate> {
+    let year_first = headers.iter().any(|d| d[0] > 31);
+    let month_first =
+        !year_first && !headers.iter().any(|d| d[0] > 12) && headers.iter().any(|d| d[1] > 12);
+    move |d: [u32; 3]| {
+        let (year, month, day) = if year_first {
+            (d[0], d[1], d[2])
+        } else if month_first {
+            (d[2], d[0], d[1])
+        } else {
+            (d[2], d[1], d[0])
+        };
+        let year = if year < 100 { year + 2000 } else { year };
+        NaiveDate::from_ymd_opt(year as i32, month, day)
+    }
+}
+
+fn parse_whatsapp(text: &str, thread: &str) -> Vec<ChatMessage> {
+    let headers: Vec<[u32; 3]> = text
+        .lines()
+        .filter_map(parse_whatsapp_header)
+        .map(|h| h.date)
+        .collect();
+    let resolve_date = whatsapp_date_resolver(&headers);
+
+    let mut messages: Vec<ChatMessage> = Vec::new();
+    // Continuation lines belong to the last message, unless a system notice came in between.
+    let mut continuing = false;
+    for line in text.lines() {
+        match parse_whatsapp_header(line) {
+            Some(header) => {
+                let sent_at = resolve_date(header.date).map(|d| d.and_time(header.time));
+                continuing = match (sent_at, header.rest.split_once(": ")) {
+                    (Some(sent_at), Some((sender, body))) => {
+                        messages.push(ChatMessage {
+                            thread: thread.to_string(),
+                            sender: sender.trim().to_string(),
+                            sent_at,
+                            text: body.to_string(),
+                        });
+                        true
+                    }
+                    _ => false,
+                };
+            }
+            None if continuing => {
+                if let Some(last) = messages.last_mut() {
+                    last.text.push('\n');
+                    last.text.push_str(line);
+                }
+            }
+            None => {}
+        }
+    }
+    messages
+}
+
+// ---------------------------------------------------------------------------
+// WeChat
+// ---------------------------------------------------------------------------
+
+fn find_column(header: &[String], candidates: &[&str]) -> Option<usize> {
+    candidates.iter().find_map(|candidate| {
+        header
+            .iter()
+            .position(|h| h.trim().eq_ignore_ascii_case(candidate))
+    })
+}
+
+/// CSV dumps from the usual WeChat export tools (`StrTime`/`CreateTime`, `NickName`/`Sender`,
+/// `StrContent`, optional `Type` and `IsSender`).
+fn parse_wechat_csv(text: &str, thread: &str) -> Result<Vec<ChatMessage>, String> {
+    let mut rows = parse_csv(text).into_iter();
+    let header = rows.next().ok_or("The CSV file is empty.")?;
+    let time_column = find_column(
+        &header,
+        &["StrTime", "CreateTime", "time", "datetime", "date", "时间"],
+    )
+    .ok_or("The CSV file has no time column (StrTime, CreateTime, time…).")?;
+    let content_column = find_column(
+        &header,
+        &[
+            "StrContent",
+            "content",
+            "message",
+            "msg",
+            "text",
+            "内容",
+            "消息",
+        ],
+    )
+    .ok_or("The CSV file has no message column (StrContent, content, message…).")?;
+    let sender_columns: Vec<usize> = [
+        "Remark",
+        "NickName",
+        "Sender",
+        "sender",
+        "from",
+        "talker",
+        "发送人",
+        "昵称",
+    ]
+    .iter()
+    .filter_map(|c| find_column(&header, &[c]))
+    .collect();
+    let thread_column = find_column(&header, &["TalkerName", "chat", "conversation", "会话"]);
+    let type_column = find_column(&header, &["Type"]);
+    let is_sender_column = find_column(&header, &["IsSender"]);
+
+    let mut messages = Vec::new();
+    for row in rows {
+        let field = |i: usize| row.get(i).map(|s| s.trim()).unwrap_or_default();
+        let text = match type_column.map(field) {
+            None | Some("1") => field(content_column).to_string(),
+            Some("3") => "[image]".to_string(),
+            Some("34") => "[voice message]".to_string(),
+            Some("43") => "[video]".to_string(),
+            Some("47") => "[sticker]".to_string(),
+            // System notices, calls, mini-programs, ...
+            Some(_) => continue,
+        };
+        let Some(sent_at) = parse_datetime(field(time_column)) else {
+            continue;
+        };
+        if text.is_empty() {
+            continue;
+        }
+        let sender = sender_columns
+            .iter()
+            .map(|&i| field(i))
+            .find(|s| !s.is_empty())
+            .map(str::to_string)
+            .or_else(|| (is_sender_column.map(field) == Some("1")).then(|| SELF_SENDER.into()))
+            .unwrap_or_else(|| "Unknown".to_string());
+        let thread = thread_column
+            .map(field)
+            .filter(|t| !t.is_empty())
+            .unwrap_or(thread);
+        messages.push(ChatMessage {
+            thread: thread.to_string(),
+            sender,
+            sent_at,
+            text,
+        });
+    }
+    Ok(messages)
+}
+
+fn decode_entities(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let decoded = rest.find(';').filter(|&end| end <= 10).and_then(|end| {
+            let entity = &rest[1..end];
+            let c = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some(' '),
+                _ => entity
+                    .strip_prefix("#x")
+                    .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                    .or_else(|| entity.strip_prefix('#').and_then(|n| n.parse().ok()))
+                    .and_then(char::from_u32),
+            }?;
+            Some((c, end))
+        });
+        match decoded {
+            Some((c, end)) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Text content of an HTML page, one line per block element.
+pub fn html_to_text(html: &str) -> String {
+    let mut out = String::new();
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        out.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('>') else {
+            rest = "";
+            break;
+        };
+        let tag = rest[start + 1..start + end].trim().to_lowercase();
+        rest = &rest[start + end + 1..];
+        let name: String = tag
+            .trim_start_matches('/')
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect();
+        if !tag.starts_with('/') && (name == "script" || name == "style") {
+            let close = format!("</{}", name);
+            // ASCII lowercasing keeps byte offsets, so the index is valid in `rest`.
+            rest = match rest.to_ascii_lowercase().find(&close) {
+                Some(i) => &rest[i..],
+                None => "",
+            };
+            continue;
+        }
+        match name.as_str() {
+            "br" | "p" | "div" | "tr" | "li" | "h1" | "h2" | "h3" | "h4" => out.push('\n'),
+            "td" | "span" => out.push(' '),
+            _ => {}
+        }
+    }
+    out.push_str(rest);
+    decode_entities(&out)
+}
+
+/// Find a timestamp at the start or end of a line: returns it and the remaining text.
+fn split_timestamp(line: &str) -> Option<(NaiveDateTime, &str)> {
+    for len in [19, 16] {
+        if line.len() >= len && line.is_char_boundary(line.len() - len) {
+            let (head, tail) = line.split_at(line.len() - len);
+            if let Some(t) = parse_datetime(tail) {
+                return Some((t, head.trim()));
+            }
+        }
+        if line.len() >= len && line.is_char_boundary(len) {
+            let (head, tail) = line.split_at(len);
+            if let Some(t) = parse_datetime(head) {
+                return Some((t, tail.trim()));
+            }
+        }
+    }
+    None
+}
+
+/// `Name 2023-01-31 10:00:00` (or `2023-01-31 10:00:00 Name`) header lines, each followed by
+/// the message's lines — the layout of WeChat's own chat-history text and most HTML dumps.
+fn parse_wechat_text(text: &str, thread: &str) -> Vec<ChatMessage> {
+    let mut messages: Vec<ChatMessage> = Vec::new();
+    for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        match split_timestamp(line) {
+            Some((sent_at, sender)) if !sender.is_empty() => messages.push(ChatMessage {
+                thread: thread.to_string(),
+                sender: sender.trim_end_matches([':', '：']).to_string(),
+                sent_at,
+                text: String::new(),
+            }),
+            _ => {
+                if let Some(last) = messages.last_mut() {
+                    if !last.text.is_empty() {
+                        last.text.push('\n');
+                    }
+                    last.text.push_str(line);
+                }
+            }
+        }
+    }
+    messages.retain(|m| !m.text.trim().is_empty());
+    messages
+}
+
+// ---------------------------------------------------------------------------
+// Grouping
+// ---------------------------------------------------------------------------
+
+fn utc_timestamp(local: NaiveDateTime) -> String {
+    let utc = match Local.from_local_datetime(&local).earliest() {
+        Some(t) => t.with_timezone(&Utc),
+        None => DateTime::<Utc>::from_naive_utc_and_offset(local, Utc),
+    };
+    utc.format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+fn render_message(message: &ChatMessage, split: ChatSplit) -> String {
+    let stamp = match split {
+        ChatSplit::Day => message.sent_at.format("%H:%M"),
+        ChatSplit::Thread => message.sent_at.format("%Y-%m-%d %H:%M"),
+    };
+    let text = message.text.trim().replace('\n', "\n  ");
+    format!("[{}] {}: {}\n", stamp, message.sender, text)
+}
+
+fn build_conversation(
+    format: ChatFormat,
+    split: ChatSplit,
+    messages: &[&ChatMessage],
+    part: Option<usize>,
+) -> ChatConversation {
+    let first = messages[0];
+    let last = messages[messages.len() - 1];
+    let span = match split {
+        ChatSplit::Day => first.sent_at.format("%Y-%m-%d").to_string(),
+        ChatSplit::Thread if first.sent_at.date() == last.sent_at.date() => {
+            first.sent_at.format("%Y-%m-%d").to_string()
+        }
+        ChatSplit::Thread => format!(
+            "{} to {}",
+            first.sent_at.format("%Y-%m-%d"),
+            last.sent_at.format("%Y-%m-%d")
+        ),
+    };
+    let mut title = format!("{} chat \"{}\" — {}", format.label(), first.thread, span);
+    if let Some(part) = part {
+        title.push_str(&format!(" (part {})", part));
+    }
+    let mut participants: Vec<String> = Vec::new();
+    for message in messages {
+        if !participants.contains(&message.sender) {
+            participants.push(message.sender.clone());
+        }
+    }
+    let mut content = format!("{}\nParticipants: {}\n\n", title, participants.join(", "));
+    for message in messages {
+        content.push_str(&render_message(message, split));
+    }
+    ChatConversation {
+        title,
+        created: utc_timestamp(first.sent_at),
+        participants,
+        message_count: messages.len(),
+        content: content.trim_end().to_string(),
+    }
+}
+
+/// Group messages into conversations, oldest first, splitting ones that are too long.
+pub fn group_conversations(
+    messages: &[ChatMessage],
+    format: ChatFormat,
+    split: ChatSplit,
+) -> Vec<ChatConversation> {
+    let mut groups: HashMap<(String, Option<NaiveDate>), Vec<&ChatMessage>> = HashMap::new();
+    for message in messages {
+        let day = (split == ChatSplit::Day).then(|| message.sent_at.date());
+        groups
+            .entry((message.thread.clone(), day))
+            .or_default()
+            .push(message);
+    }
+
+    let mut conversations = Vec::new();
+    for mut group in groups.into_values() {
+        group.sort_by_key(|m| m.sent_at);
+        let mut parts: Vec<Vec<&ChatMessage>> = vec![Vec::new()];
+        let mut length = 0;
+        for message in group {
+            let message_length = render_message(message, split).chars().count();
+            let current = parts.last_mut().expect("parts is never empty");
+            if !current.is_empty() && length + message_length > MAX_CONVERSATION_CHARS {
+                parts.push(Vec::new());
+                length = 0;
+            }
+            length += message_length;
+            parts
+                .last_mut()
+                .expect("parts is never empty")
+                .push(message);
+        }
+        let numbered = parts.len() > 1;
+        for (i, part) in parts.iter().enumerate() {
+            conversations.push(build_conversation(
+                format,
+                split,
+                part,
+                numbered.then_some(i + 1),
+            ));
+        }
+    }
+    conversations.sort_by(|a, b| a.created.cmp(&b.created).then(a.title.cmp(&b.title)));
+    conversations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn html_to_text_skips_non_ascii_script_and_style_bodies() {
+        // Lowercasing 'ẞ' or the Kelvin sign changes their byte length.
+        let html =
+            "<p>前</p><script>var s = 'ẞ中';</script><STYLE>p::after { content: '\u{212A}中' }</STYLE>后";
+        assert_eq!(html_to_text(html), "\n前\n后");
+    }
+}
@@ -1,11 +1,15 @@
 //! SQLite database module: entities, relations, memories, and their join tables.
 
 use chrono::Utc;
-use rusqlite::{params, Connection, Result as SqliteResult};
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
 use serde::{Deserialize, Serialize};
 use std::path::Path;
 use std::sync::Mutex;
 
+/// Version of the schema `init_schema` produces, stored in `PRAGMA user_version`.
+/// 1: original tables; 2: `source` on entities and relations.
+pub const SCHEMA_VERSION: i64 = 2;
+
 /// Global database connection managed via Tauri State.
 pub struct DbState(pub Mutex<Option<Connection>>);
 
@@ -40,17 +44,38 @@ pub struct Memory {
 }
 
 /// Initialize the database: open (or create) the DB file and run schema migrations.
-pub fn init_db(db_path: &Path) -> SqliteResult<Connection> {
+/// `key` is the SQLCipher key of an encrypted library.
+pub fn init_db(db_path: &Path, key: Option<&str>) -> SqliteResult<Connection> {
     if let Some(parent) = db_path.parent() {
         std::fs::create_dir_all(parent).map_err(|e| {
             rusqlite::Error::ToSqlConversionFailure(Box::new(e))
         })?;
     }
-    let conn = Connection::open(db_path)?;
+    let conn = open_db(db_path, key)?;
     init_schema(&conn)?;
     Ok(conn)
 }
 
+/// Open a DB file without touching the schema, unlocking it first when `key` is set.
+pub fn open_db(db_path: &Path, key: Option<&str>) -> SqliteResult<Connection> {
+    let conn = Connection::open(db_path)?;
+    if let Some(key) = key {
+        conn.pragma_update(None, "key", key)?;
+    }
+    Ok(conn)
+}
+
+/// Write an encrypted copy of the database to `target` (SQLCipher's `sqlcipher_export`).
+pub fn export_encrypted_copy(conn: &Connection, target: &Path, key: &str) -> SqliteResult<()> {
+    conn.execute(
+        "ATTACH DATABASE ?1 AS encrypted KEY ?2",
+        params![target.to_string_lossy(), key],
+    )?;
+    let exported = conn.query_row("SELECT sqlcipher_export('encrypted')", [], |_| Ok(()));
+    conn.execute("DETACH DATABASE encrypted", [])?;
+    exported
+}
+
 fn init_schema(conn: &Connection) -> SqliteResult<()> {
     conn.execute_batch(
         r#"
@@ -111,6 +136,41 @@ fn init_schema(conn: &Connection) -> SqliteResult<()> {
         CREATE INDEX IF NOT EXISTS idx_memory_entities_entity ON memory_entities(entity_id);
         "#,
     )?;
+    // Where rows came from when they were imported rather than extracted (see `graph_import`).
+    add_column_if_missing(conn, "entities", "source", "TEXT")?;
+    add_column_if_missing(conn, "relations", "source", "TEXT")?;
+    conn.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+    Ok(())
+}
+
+/// Schema version recorded in the database; 0 for databases created before versioning.
+pub fn schema_version(conn: &Connection) -> SqliteResult<i64> {
+    conn.pragma_query_value(None, "user_version", |row| row.get(0))
+}
+
+/// Flush a WAL into the main file so the file alone is a complete copy. No-op otherwise.
+pub fn checkpoint(conn: &Connection) -> SqliteResult<()> {
+    conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))
+}
+
+fn add_column_if_missing(
+    conn: &Connection,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> SqliteResult<()> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let exists = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<SqliteResult<Vec<_>>>()?
+        .iter()
+        .any(|name| name == column);
+    if !exists {
+        conn.execute(
+            &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+            [],
+        )?;
+    }
     Ok(())
 }
 
@@ -199,6 +259,22 @@ pub fn add_entity_alias(conn: &Connection, entity_id: i64, alias: &str) -> Sqlit
     Ok(())
 }
 
+/// Record where an entity was imported from. An existing source is kept.
+pub fn set_entity_source(conn: &Connection, entity_id: i64, source: &str) -> SqliteResult<()> {
+    conn.execute(
+        "UPDATE entities SET source = COALESCE(source, ?2) WHERE id = ?1",
+        params![entity_id, source],
+    )?;
+    Ok(())
+}
+
+/// Imported entities with their source.
+pub fn list_entity_sources(conn: &Connection) -> SqliteResult<Vec<(i64, String)>> {
+    let mut stmt = conn.prepare("SELECT id, source FROM entities WHERE source IS NOT NULL")?;
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    rows.collect()
+}
+
 /// Look up an entity ID by exact name or alias.
 pub fn find_entity_id_by_name_or_alias(conn: &Connection, name: &str) -> SqliteResult<Option<i64>> {
     // Try exact name match first
@@ -305,6 +381,72 @@ pub fn upsert_relation(
     Ok(())
 }
 
+/// Record where a relation was imported from. An existing source is kept.
+pub fn set_relation_source(
+    conn: &Connection,
+    from_entity_id: i64,
+    to_entity_id: i64,
+    relation_type: &str,
+    source: &str,
+) -> SqliteResult<()> {
+    conn.execute(
+        r#"UPDATE relations SET source = COALESCE(source, ?4)
+           WHERE from_entity_id = ?1 AND to_entity_id = ?2 AND relation_type = ?3"#,
+        params![from_entity_id, to_entity_id, relation_type, source],
+    )?;
+    Ok(())
+}
+
+/// Imported relations with their source.
+pub fn list_relation_sources(conn: &Connection) -> SqliteResult<Vec<(i64, String)>> {
+    let mut stmt = conn.prepare("SELECT id, source FROM relations WHERE source IS NOT NULL")?;
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    rows.collect()
+}
+
+/// Insert a relation with a known strength, keeping the larger strength on conflict.
+pub fn restore_relation(
+    conn: &Connection,
+    from_entity_id: i64,
+    to_entity_id: i64,
+    relation_type: &str,
+    strength: i32,
+) -> SqliteResult<()> {
+    conn.execute(
+        r#"
+        INSERT INTO relations (from_entity_id, to_entity_id, relation_type, strength)
+        VALUES (?1, ?2, ?3, ?4)
+        ON CONFLICT(from_entity_id, to_entity_id, relation_type) DO UPDATE SET
+            strength = MAX(strength, excluded.strength)
+        "#,
+        params![from_entity_id, to_entity_id, relation_type, strength],
+    )?;
+    Ok(())
+}
+
+/// Strength of a relation, or `None` when it does not exist.
+pub fn get_relation_strength(
+    conn: &Connection,
+    from_entity_id: i64,
+    to_entity_id: i64,
+    relation_type: &str,
+) -> SqliteResult<Option<i32>> {
+    conn.query_row(
+        "SELECT strength FROM relations
+         WHERE from_entity_id = ?1 AND to_entity_id = ?2 AND relation_type = ?3",
+        params![from_entity_id, to_entity_id, relation_type],
+        |row| row.get(0),
+    )
+    .optional()
+}
+
+/// List every `(entity_id, alias)` pair.
+pub fn list_entity_aliases(conn: &Connection) -> SqliteResult<Vec<(i64, String)>> {
+    let mut stmt = conn.prepare("SELECT entity_id, alias FROM entity_aliases")?;
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    rows.collect()
+}
+
 pub fn list_relations(conn: &Connection) -> SqliteResult<Vec<Relation>> {
     let mut stmt = conn.prepare(
         "SELECT id, from_entity_id, to_entity_id, relation_type, strength, created_at FROM relations",
@@ -335,6 +477,21 @@ pub fn insert_memory(
     Ok(conn.last_insert_rowid())
 }
 
+/// Insert a memory with an explicit creation time (used when rebuilding from Markdown files).
+pub fn insert_memory_at(
+    conn: &Connection,
+    content: &str,
+    md_file_path: Option<&str>,
+    tags: Option<&str>,
+    created_at: &str,
+) -> SqliteResult<i64> {
+    conn.execute(
+        "INSERT INTO memories (content, md_file_path, tags, created_at) VALUES (?1, ?2, ?3, ?4)",
+        params![content, md_file_path, tags, created_at],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
 pub fn link_memory_entity(conn: &Connection, memory_id: i64, entity_id: i64) -> SqliteResult<()> {
     conn.execute(
         "INSERT OR IGNORE INTO memory_entities (memory_id, entity_id) VALUES (?1, ?2)",
@@ -343,6 +500,20 @@ pub fn link_memory_entity(conn: &Connection, memory_id: i64, entity_id: i64) ->
     Ok(())
 }
 
+/// IDs of the entities a memory is linked to.
+pub fn get_entity_ids_for_memory(conn: &Connection, memory_id: i64) -> SqliteResult<Vec<i64>> {
+    let mut stmt = conn.prepare("SELECT entity_id FROM memory_entities WHERE memory_id = ?1")?;
+    let rows = stmt.query_map(params![memory_id], |row| row.get(0))?;
+    rows.collect()
+}
+
+/// List every `(memory_id, entity_id)` association.
+pub fn list_memory_entity_links(conn: &Connection) -> SqliteResult<Vec<(i64, i64)>> {
+    let mut stmt = conn.prepare("SELECT memory_id, entity_id FROM memory_entities")?;
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    rows.collect()
+}
+
 pub fn get_memory_by_id(conn: &Connection, id: i64) -> SqliteResult<Memory> {
     conn.query_row(
         "SELECT id, content, md_file_path, created_at, tags FROM memories WHERE id = ?1",
@@ -359,6 +530,32 @@ pub fn get_memory_by_id(conn: &Connection, id: i64) -> SqliteResult<Memory> {
     )
 }
 
+/// Look up the memory backed by a given Markdown file.
+pub fn get_memory_by_md_path(conn: &Connection, md_file_path: &str) -> SqliteResult<Option<Memory>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, content, md_file_path, created_at, tags FROM memories WHERE md_file_path = ?1",
+    )?;
+    let mut rows = stmt.query(params![md_file_path])?;
+    if let Some(row) = rows.next()? {
+        return Ok(Some(Memory {
+            id: row.get(0)?,
+            content: row.get(1)?,
+            md_file_path: row.get(2)?,
+            created_at: row.get(3)?,
+            tags: row.get(4)?,
+        }));
+    }
+    Ok(None)
+}
+
+pub fn update_memory_file_path(conn: &Connection, id: i64, md_file_path: &str) -> SqliteResult<()> {
+    conn.execute(
+        "UPDATE memories SET md_file_path = ?1 WHERE id = ?2",
+        params![md_file_path, id],
+    )?;
+    Ok(())
+}
+
 pub fn list_memories(conn: &Connection) -> SqliteResult<Vec<Memory>> {
     let mut stmt = conn.prepare(
         "SELECT id, content, md_file_path, created_at, tags FROM memories ORDER BY created_at DESC",
@@ -394,7 +591,7 @@ pub fn delete_memory(conn: &Connection, id: i64) -> SqliteResult<()> {
 
     // Re-process graph integrity after memory deletion:
     // 1) Remove relations that point to entities no longer referenced by any memory.
-    // 2) Remove entities that are no longer referenced by any memory.
+    // 2) Remove entities that are no longer referenced by any memory (imported ones stay).
     // 3) Final pass for dangling relations.
     prune_orphan_entities_and_relations(conn)?;
 
@@ -408,19 +605,21 @@ pub fn prune_orphan_entities_and_relations(conn: &Connection) -> SqliteResult<()
                     SELECT e.id
                     FROM entities e
                     LEFT JOIN memory_entities me ON me.entity_id = e.id
-                    WHERE me.entity_id IS NULL
+                    WHERE me.entity_id IS NULL AND e.source IS NULL
                 )
               OR to_entity_id IN (
                     SELECT e.id
                     FROM entities e
                     LEFT JOIN memory_entities me ON me.entity_id = e.id
-                    WHERE me.entity_id IS NULL
+                    WHERE me.entity_id IS NULL AND e.source IS NULL
                 )"#,
         [],
     )?;
 
     conn.execute(
-        "DELETE FROM entities WHERE id NOT IN (SELECT DISTINCT entity_id FROM memory_entities)",
+        r#"DELETE FROM entities
+           WHERE source IS NULL
+             AND id NOT IN (SELECT DISTINCT entity_id FROM memory_entities)"#,
         [],
     )?;
 
@@ -467,9 +666,11 @@ pub fn cleanup_database(conn: &Connection) -> SqliteResult<()> {
         [],
     )?;
 
-    // 4. Remove orphaned entities (not referenced by any memory)
+    // 4. Remove orphaned entities (not referenced by any memory, and not imported)
     conn.execute(
-        "DELETE FROM entities WHERE id NOT IN (SELECT DISTINCT entity_id FROM memory_entities)",
+        r#"DELETE FROM entities
+           WHERE source IS NULL
+             AND id NOT IN (SELECT DISTINCT entity_id FROM memory_entities)"#,
         [],
     )?;
 
@@ -0,0 +1,758 @@
+//! Long-document ingestion: `.txt`, `.md`, `.html` and `.epub` files are read into plain text
+//! with their headings kept, split into chunks that fit a token budget (breaking at section
+//! and paragraph boundaries before sentences), extracted chunk by chunk and reconciled into a
+//! single set of entities and relations.
+//!
+//! The document is saved as one parent memory holding the outline and the reconciled graph,
+//! plus one memory per chunk. All of them carry the same `document_id` in their frontmatter,
+//! and chunks record their position (`chunk` of `chunks`), so a document can be walked from
+//! any of its memories.
+
+use crate::chat_import::html_to_text;
+use crate::file_manager::{list_memory_files, read_memory};
+use crate::library_crypto::LibraryKey;
+use crate::llm_provider::estimate_tokens;
+use crate::ollama::{ExtractedData, ExtractedEntity, ExtractedRelation};
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value as YamlValue};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use zip::ZipArchive;
+
+/// Default chunk budget, small enough for the context window of small local models once the
+/// extraction prompt is added.
+pub const DEFAULT_CHUNK_TOKENS: usize = 1500;
+const MIN_CHUNK_TOKENS: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentFormat {
+    Text,
+    Markdown,
+    Html,
+    Epub,
+}
+
+impl DocumentFormat {
+    pub fn detect(path: &Path) -> Result<Self, String> {
+        let ext = path
+            .extension()
+            .and_then(|x| x.to_str())
+            .unwrap_or_default()
+            .to_lowercase();
+        match ext.as_str() {
+            "txt" | "text" => Ok(Self::Text),
+            "md" | "markdown" => Ok(Self::Markdown),
+            "html" | "htm" | "xhtml" => Ok(Self::Html),
+            "epub" => Ok(Self::Epub),
+            _ => Err(format!(
+                "Unsupported document type '{}'; use .txt, .md, .html or .epub.",
+                ext
+            )),
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Text => "txt",
+            Self::Markdown => "md",
+            Self::Html => "html",
+            Self::Epub => "epub",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DocumentImportRequest {
+    pub path: String,
+    /// Token budget per chunk; defaults to `DEFAULT_CHUNK_TOKENS`.
+    pub max_chunk_tokens: Option<usize>,
+    /// Tags added to every memory of the document, besides `document`.
+    pub tags: Option<Vec<String>>,
+    /// Read and chunk only; nothing is extracted or saved.
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentChunkSummary {
+    pub index: usize,
+    pub section: Option<String>,
+    pub tokens: usize,
+    pub chars: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentChunkError {
+    pub index: usize,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DocumentImportReport {
+    pub document_id: String,
+    pub title: String,
+    pub format: String,
+    pub dry_run: bool,
+    pub chunks: Vec<DocumentChunkSummary>,
+    /// Chunks whose extraction failed; they are saved without entities.
+    pub errors: Vec<DocumentChunkError>,
+    pub entities: usize,
+    pub relations: usize,
+    pub memory_id: Option<i64>,
+    pub chunk_memory_ids: Vec<i64>,
+}
+
+/// One memory of a document, as listed by `document_parts`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentPart {
+    /// `None` for the parent memory, 1-based position for chunks.
+    pub chunk: Option<usize>,
+    pub section: Option<String>,
+    /// From the file's frontmatter; `None` until the memory is indexed.
+    pub memory_id: Option<i64>,
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Clone)]
+pub struct Document {
+    pub id: String,
+    pub title: String,
+    pub format: DocumentFormat,
+    pub file_name: String,
+    /// Paragraphs separated by blank lines, headings as Markdown `#` lines.
+    pub text: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct DocumentChunk {
+    pub index: usize,
+    /// Heading of the section the chunk starts in.
+    pub section: Option<String>,
+    pub text: String,
+    pub tokens: usize,
+}
+
+impl DocumentChunk {
+    pub fn summary(&self) -> DocumentChunkSummary {
+        DocumentChunkSummary {
+            index: self.index,
+            section: self.section.clone(),
+            tokens: self.tokens,
+            chars: self.text.chars().count(),
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Reading
+// ---------------------------------------------------------------------------
+
+/// Turn HTML into blank-line separated paragraphs, with `h1`–`h6` as Markdown headings.
+fn html_to_document_text(html: &str) -> String {
+    // Only the body: the head's <title> and metadata are not part of the text.
+    let html = html
+        .to_ascii_lowercase()
+        .find("<body")
+        .map_or(html, |start| &html[start..]);
+    let mut marked = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        marked.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('>')
+            .map_or(rest.len(), |e| start + e + 1);
+        let tag = &rest[start..end];
+        let lower = tag.to_ascii_lowercase();
+        let level = lower
+            .strip_prefix("<h")
+            .and_then(|t| t.chars().next())
+            .and_then(|c| c.to_digit(10))
+            .filter(|l| (1..=6).contains(l));
+        match level {
+            Some(level) => {
+                marked.push_str("<p>");
+                marked.push_str(&"#".repeat(level as usize));
+                marked.push(' ');
+            }
+            None if lower.starts_with("</h") => marked.push_str("<p>"),
+            None => marked.push_str(tag),
+        }
+        rest = &rest[end..];
+    }
+    marked.push_str(rest);
+    html_to_text(&marked)
+        .lines()
+        .map(|l| l.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|l| !l.trim_start_matches('#').trim().is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+fn html_title(html: &str) -> Option<String> {
+    let lower = html.to_ascii_lowercase();
+    let start = lower.find("<title")?;
+    let start = start + lower[start..].find('>')? + 1;
+    let end = start + lower[start..].find("</title")?;
+    let title = html_to_text(&html[start..end]).trim().to_string();
+    (!title.is_empty()).then_some(title)
+}
+
+/// Value of `name="…"` (or single-quoted) inside a tag.
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let lower = tag.to_ascii_lowercase();
+    let mut from = 0;
+    while let Some(i) = lower[from..].find(name) {
+        let at = from + i;
+        from = at + name.len();
+        let preceded = at == 0 || lower.as_bytes()[at - 1].is_ascii_whitespace();
+        let rest = lower[from..].trim_start();
+        if !preceded || !rest.starts_with('=') {
+            continue;
+        }
+        let value_start = tag.len() - rest.len() + 1;
+        let value = tag[value_start..].trim_start();
+        let quote = value.chars().next()?;
+        if quote != '"' && quote != '\'' {
+            return None;
+        }
+        let end = value[1..].find(quote)?;
+        return Some(value[1..1 + end].to_string());
+    }
+    None
+}
+
+/// Start tags named `name` (namespace prefixes ignored), e.g. every `<item …>` of a manifest.
+fn tags_named<'a>(xml: &'a str, name: &str) -> Vec<&'a str> {
+    let mut tags = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find('<') {
+        let Some(end) = rest[start..].find('>') else {
+            break;
+        };
+        let tag = &rest[start..start + end + 1];
+        let tag_name: String = tag[1..]
+            .chars()
+            .take_while(|c| !c.is_whitespace() && *c != '>' && *c != '/')
+            .collect();
+        let local = tag_name.rsplit(':').next().unwrap_or_default();
+        if local.eq_ignore_ascii_case(name) {
+            tags.push(tag);
+        }
+        rest = &rest[start + end + 1..];
+    }
+    tags
+}
+
+fn zip_text(archive: &mut ZipArchive<File>, name: &str) -> Result<String, String> {
+    let mut entry = archive
+        .by_name(name)
+        .map_err(|_| format!("The EPUB is missing '{}'.", name))?;
+    let mut bytes = Vec::new();
+    entry
+        .read_to_end(&mut bytes)
+        .map_err(|e| format!("Failed to read '{}': {}", name, e))?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Resolve `href` (percent-encoded, relative to the package file) to a zip entry name.
+fn resolve_href(base_dir: &str, href: &str) -> String {
+    let href = href.split('#').next().unwrap_or_default();
+    let mut decoded = Vec::with_capacity(href.len());
+    let bytes = href.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    let mut parts: Vec<String> = base_dir
+        .split('/')
+        .filter(|p| !p.is_empty())
+        .map(str::to_string)
+        .collect();
+    for part in String::from_utf8_lossy(&decoded).split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part.to_string()),
+        }
+    }
+    parts.join("/")
+}
+
+/// Title and text of an EPUB, chapters in spine order.
+fn read_epub(path: &Path) -> Result<(Option<String>, String), String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open {:?}: {}", path, e))?;
+    let mut archive =
+        ZipArchive::new(file).map_err(|e| format!("{:?} is not an EPUB: {}", path, e))?;
+    let container = zip_text(&mut archive, "META-INF/container.xml")?;
+    let package_path = tags_named(&container, "rootfile")
+        .into_iter()
+        .find_map(|tag| attribute(tag, "full-path"))
+        .ok_or("The EPUB's container.xml names no package file.")?;
+    let package = zip_text(&mut archive, &package_path)?;
+    let base_dir = package_path.rsplit_once('/').map_or("", |(dir, _)| dir);
+
+    let title = package
+        .find("<dc:title")
+        .and_then(|start| {
+            let open_end = start + package[start..].find('>')? + 1;
+            let close = open_end + package[open_end..].find("</dc:title")?;
+            Some(html_to_text(&package[open_end..close]).trim().to_string())
+        })
+        .filter(|t| !t.is_empty());
+    let manifest: HashMap<String, String> = tags_named(&package, "item")
+        .into_iter()
+        .filter_map(|tag| Some((attribute(tag, "id")?, attribute(tag, "href")?)))
+        .collect();
+    let mut chapters = Vec::new();
+    for idref in tags_named(&package, "itemref")
+        .into_iter()
+        .filter(|tag| attribute(tag, "linear").as_deref() != Some("no"))
+        .filter_map(|tag| attribute(tag, "idref"))
+    {
+        let Some(href) = manifest.get(&idref) else {
+            continue;
+        };
+        let html = zip_text(&mut archive, &resolve_href(base_dir, href))?;
+        let text = html_to_document_text(&html);
+        if !text.trim().is_empty() {
+            chapters.push(text);
+        }
+    }
+    if chapters.is_empty() {
+        return Err("The EPUB has no readable chapters.".to_string());
+    }
+    Ok((title, chapters.join("\n\n")))
+}
+
+/// Plain text or Markdown with paragraphs separated by blank lines. Text files that never
+/// leave a blank line get one paragraph per line.
+fn normalize_plain_text(text: &str) -> String {
+    let text = text.replace("\r\n", "\n").replace('\r', "\n");
+    if text.contains("\n\n") {
+        text
+    } else {
+        text.lines().collect::<Vec<_>>().join("\n\n")
+    }
+}
+
+fn first_heading(text: &str) -> Option<String> {
+    text.lines()
+        .find_map(heading_of)
+        .map(|(_, title)| title.to_string())
+}
+
+/// Read a document and identify it by the SHA-256 of its text.
+pub fn read_document(path: &Path) -> Result<Document, String> {
+    let format = DocumentFormat::detect(path)?;
+    let read_text = || {
+        let bytes = fs::read(path).map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+        Ok::<_, String>(String::from_utf8_lossy(&bytes).into_owned())
+    };
+    let (title, text) = match format {
+        DocumentFormat::Text | DocumentFormat::Markdown => {
+            let text = normalize_plain_text(&read_text()?);
+            (first_heading(&text), text)
+        }
+        DocumentFormat::Html => {
+            let html = read_text()?;
+            let text = html_to_document_text(&html);
+            (html_title(&html).or_else(|| first_heading(&text)), text)
+        }
+        DocumentFormat::Epub => read_epub(path)?,
+    };
+    if text.trim().is_empty() {
+        return Err(format!("{:?} has no text to import.", path));
+    }
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let title = title.unwrap_or_else(|| {
+        path.file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "Document".to_string())
+    });
+    let digest = format!("{:x}", Sha256::digest(text.as_bytes()));
+    Ok(Document {
+        id: digest[..16].to_string(),
+        title,
+        format,
+        file_name,
+        text,
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Chunking
+// ---------------------------------------------------------------------------
+
+/// `(level, title)` of a Markdown ATX heading line.
+fn heading_of(line: &str) -> Option<(usize, &str)> {
+    let trimmed = line.trim_start();
+    let level = trimmed.chars().take_while(|c| *c == '#').count();
+    let title = trimmed[level..]
+        .strip_prefix(' ')?
+        .trim()
+        .trim_end_matches('#')
+        .trim();
+    ((1..=6).contains(&level) && !title.is_empty()).then_some((level, title))
+}
+
+/// Split an oversized paragraph at sentence ends, then (for text without any) at spaces or
+/// characters, so that every piece fits `budget`.
+fn split_paragraph(paragraph: &str, budget: usize) -> Vec<String> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    let chars: Vec<(usize, char)> = paragraph.char_indices().collect();
+    for (i, &(at, c)) in chars.iter().enumerate() {
+        let next = chars.get(i + 1).map(|&(_, n)| n);
+        let ends = matches!(c, '。' | '！' | '？' | '；')
+            || (matches!(c, '.' | '!' | '?' | ';') && next.is_none_or(char::is_whitespace));
+        if ends {
+            let end = at + c.len_utf8();
+            sentences.push(&paragraph[start..end]);
+            start = end;
+        }
+    }
+    if start < paragraph.len() {
+        sentences.push(&paragraph[start..]);
+    }
+
+    let mut pieces: Vec<String> = Vec::new();
+    let mut current = String::new();
+    for sentence in sentences {
+        let sentence = sentence.trim();
+        if sentence.is_empty() {
+            continue;
+        }
+        if !current.is_empty() && estimate_tokens(&current) + estimate_tokens(sentence) > budget {
+            pieces.push(std::mem::take(&mut current));
+        }
+        if estimate_tokens(sentence) > budget {
+            // A run-on "sentence": cut it by words, or by characters when it has no spaces.
+            let mut words: Vec<String> = sentence.split(' ').map(str::to_string).collect();
+            if words.len() == 1 {
+                words = sentence.chars().map(String::from).collect();
+            }
+            let joiner = if sentence.contains(' ') { " " } else { "" };
+            for word in words {
+                if !current.is_empty()
+                    && estimate_tokens(&current) + estimate_tokens(&word) > budget
+                {
+                    pieces.push(std::mem::take(&mut current));
+                }
+                if !current.is_empty() {
+                    current.push_str(joiner);
+                }
+                current.push_str(&word);
+            }
+            continue;
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(sentence);
+    }
+    if !current.is_empty() {
+        pieces.push(current);
+    }
+    pieces
+}
+
+/// Split document text into chunks of at most `budget` tokens. Paragraphs are kept whole when
+/// they fit, and a new section starts a new chunk once the current one is half full. A chunk
+/// that starts mid-section repeats the section heading so it reads on its own.
+pub fn chunk_document(text: &str, budget: usize) -> Vec<DocumentChunk> {
+    let budget = budget.max(MIN_CHUNK_TOKENS);
+    let mut chunks: Vec<DocumentChunk> = Vec::new();
+    let mut section: Option<String> = None;
+    let mut current: Vec<String> = Vec::new();
+    let mut current_tokens = 0;
+    let mut current_section: Option<String> = None;
+
+    let mut flush = |current: &mut Vec<String>, tokens: &mut usize, section: Option<String>| {
+        if current.is_empty() {
+            return;
+        }
+        let body = current.join("\n\n");
+        let text = match (&section, current.first().and_then(|p| heading_of(p))) {
+            (Some(title), None) => format!("## {}\n\n{}", title, body),
+            _ => body,
+        };
+        chunks.push(DocumentChunk {
+            index: chunks.len() + 1,
+            section,
+            tokens: estimate_tokens(&text),
+            text,
+        });
+        current.clear();
+        *tokens = 0;
+    };
+
+    // Blank lines inside fenced code blocks don't end a paragraph.
+    let mut paragraphs: Vec<String> = Vec::new();
+    let mut in_fence = false;
+    let mut buffer = String::new();
+    for line in text.lines() {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+        }
+        if line.trim().is_empty() && !in_fence {
+            if !buffer.trim().is_empty() {
+                paragraphs.push(std::mem::take(&mut buffer));
+            }
+            buffer.clear();
+            continue;
+        }
+        // A heading line is its own paragraph even without blank lines around it.
+        if !in_fence && heading_of(line).is_some() {
+            if !buffer.trim().is_empty() {
+                paragraphs.push(std::mem::take(&mut buffer));
+            }
+            paragraphs.push(line.trim().to_string());
+            buffer.clear();
+            continue;
+        }
+        if !buffer.is_empty() {
+            buffer.push('\n');
+        }
+        buffer.push_str(line);
+    }
+    if !buffer.trim().is_empty() {
+        paragraphs.push(buffer);
+    }
+
+    for paragraph in paragraphs {
+        let paragraph = paragraph.trim_end().to_string();
+        if let Some((_, title)) = heading_of(&paragraph) {
+            let title = title.to_string();
+            if current_tokens * 2 >= budget {
+                flush(&mut current, &mut current_tokens, current_section.take());
+            }
+            section = Some(title);
+        }
+        // Room for the `## section` line repeated at the top of a continuation chunk.
+        let reserve = section.as_deref().map_or(0, |s| estimate_tokens(s) + 3);
+        let piece_budget = budget.saturating_sub(reserve).max(MIN_CHUNK_TOKENS / 2);
+        let pieces = if estimate_tokens(&paragraph) > piece_budget {
+            split_paragraph(&paragraph, piece_budget)
+        } else {
+            vec![paragraph]
+        };
+        for piece in pieces {
+            let tokens = estimate_tokens(&piece);
+            if !current.is_empty() && current_tokens + tokens > budget {
+                flush(&mut current, &mut current_tokens, current_section.take());
+            }
+            if current.is_empty() {
+                current_section = section.clone();
+                if heading_of(&piece).is_none() {
+                    current_tokens = reserve;
+                }
+            }
+            current_tokens += tokens;
+            current.push(piece);
+        }
+    }
+    flush(&mut current, &mut current_tokens, current_section.take());
+    chunks
+}
+
+// ---------------------------------------------------------------------------
+// Reconciling chunk extractions
+// ---------------------------------------------------------------------------
+
+/// The chunks' extractions merged into one graph.
+#[derive(Debug, Clone, Default)]
+pub struct ReconciledGraph {
+    pub entities: Vec<ExtractedEntity>,
+    pub relations: Vec<ExtractedRelation>,
+    /// Entities mentioned by each chunk, under their reconciled names.
+    pub chunk_entities: Vec<Vec<ExtractedEntity>>,
+}
+
+/// Merge per-chunk extractions: entities with the same name (case-insensitive) become one,
+/// keeping the first spelling and type and the union of their attributes; relations are
+/// renamed to match and deduplicated. Chunks that failed to extract are `None`.
+pub fn reconcile_extractions(extractions: &[Option<ExtractedData>]) -> ReconciledGraph {
+    let mut graph = ReconciledGraph::default();
+    let mut index_of: HashMap<String, usize> = HashMap::new();
+    let mut seen_relations = std::collections::HashSet::new();
+    for extraction in extractions {
+        let mut mentioned: Vec<usize> = Vec::new();
+        if let Some(data) = extraction {
+            for entity in &data.entities {
+                let key = entity.name.trim().to_lowercase();
+                if key.is_empty() {
+                    continue;
+                }
+                let index = *index_of.entry(key).or_insert_with(|| {
+                    graph.entities.push(ExtractedEntity {
+                        entity_type: entity.entity_type.clone(),
+                        name: entity.name.trim().to_string(),
+                        attributes: None,
+                    });
+                    graph.entities.len() - 1
+                });
+                if let Some(serde_json::Value::Object(extra)) = &entity.attributes {
+                    let merged = graph.entities[index]
+                        .attributes
+                        .get_or_insert_with(|| serde_json::Value::Object(Default::default()));
+                    if let serde_json::Value::Object(merged) = merged {
+                        for (key, value) in extra {
+                            merged.entry(key.clone()).or_insert_with(|| value.clone());
+                        }
+                    }
+                }
+                if !mentioned.contains(&index) {
+                    mentioned.push(index);
+                }
+            }
+            for relation in &data.relations {
+                let from = index_of.get(&relation.from.trim().to_lowercase());
+                let to = index_of.get(&relation.to.trim().to_lowercase());
+                let (Some(&from), Some(&to)) = (from, to) else {
+                    continue;
+                };
+                let kind = relation.relation.trim();
+                if kind.is_empty() || !seen_relations.insert((from, to, kind.to_lowercase())) {
+                    continue;
+                }
+                graph.relations.push(ExtractedRelation {
+                    from: graph.entities[from].name.clone(),
+                    to: graph.entities[to].name.clone(),
+                    relation: kind.to_string(),
+                });
+            }
+        }
+        graph.chunk_entities.push(
+            mentioned
+                .into_iter()
+                .map(|i| graph.entities[i].clone())
+                .collect(),
+        );
+    }
+    graph
+}
+
+// ---------------------------------------------------------------------------
+// Memories of a document
+// ---------------------------------------------------------------------------
+
+/// Content of the parent memory: title, origin and the list of sections.
+pub fn outline_content(document: &Document, chunks: &[DocumentChunk]) -> String {
+    let mut content = format!(
+        "# {}\n\nImported from {} in {} parts.\n",
+        document.title,
+        document.file_name,
+        chunks.len()
+    );
+    for chunk in chunks {
+        let label = chunk
+            .section
+            .clone()
+            .unwrap_or_else(|| format!("Part {}", chunk.index));
+        content.push_str(&format!("\n{}. {}", chunk.index, label));
+    }
+    content
+}
+
+/// Content of a chunk memory; the first line names the document and the chunk's position.
+pub fn chunk_content(document: &Document, chunk: &DocumentChunk, total: usize) -> String {
+    format!(
+        "{} ({}/{})\n\n{}",
+        document.title, chunk.index, total, chunk.text
+    )
+}
+
+/// Frontmatter keys linking a memory to its document: the parent gets the title and file name,
+/// chunks their position and section.
+pub fn document_frontmatter(
+    document: &Document,
+    chunk: Option<&DocumentChunk>,
+    total: usize,
+) -> Mapping {
+    let mut map = Mapping::new();
+    map.insert("document_id".into(), YamlValue::String(document.id.clone()));
+    match chunk {
+        None => {
+            map.insert(
+                "document_title".into(),
+                YamlValue::String(document.title.clone()),
+            );
+            map.insert(
+                "document_file".into(),
+                YamlValue::String(document.file_name.clone()),
+            );
+        }
+        Some(chunk) => {
+            map.insert(
+                "chunk".into(),
+                YamlValue::Number((chunk.index as u64).into()),
+            );
+            if let Some(section) = &chunk.section {
+                map.insert("section".into(), YamlValue::String(section.clone()));
+            }
+        }
+    }
+    map.insert("chunks".into(), YamlValue::Number((total as u64).into()));
+    map
+}
+
+/// `document_id` in a memory file's frontmatter, if it belongs to a document.
+pub fn document_id_of(path: &Path, key: Option<&LibraryKey>) -> Option<String> {
+    let record = read_memory(path, key).ok()?;
+    let id = record.frontmatter.raw.get("document_id")?.as_str()?;
+    Some(id.to_string())
+}
+
+/// Memory files of a document: the parent first, then chunks in order.
+pub fn document_parts(
+    memories_dir: &Path,
+    document_id: &str,
+    key: Option<&LibraryKey>,
+) -> Result<Vec<DocumentPart>, String> {
+    let mut parts = Vec::new();
+    for path in list_memory_files(memories_dir)? {
+        let Ok(record) = read_memory(&path, key) else {
+            continue;
+        };
+        let raw = &record.frontmatter.raw;
+        if raw.get("document_id").and_then(|v| v.as_str()) != Some(document_id) {
+            continue;
+        }
+        parts.push(DocumentPart {
+            chunk: raw
+                .get("chunk")
+                .and_then(|v| v.as_u64())
+                .map(|c| c as usize),
+            section: raw
+                .get("section")
+                .or_else(|| raw.get("document_title"))
+                .and_then(|v| v.as_str())
+                .map(str::to_string),
+            memory_id: record.frontmatter.id,
+            path,
+        });
+    }
+    parts.sort_by_key(|p| p.chunk.unwrap_or(0));
+    Ok(parts)
+}
@@ -0,0 +1,795 @@
+//! Email ingestion from mbox files and directories of `.eml` files.
+//!
+//! Messages are parsed with a small MIME reader (multipart bodies, base64 / quoted-printable,
+//! RFC 2047 headers, legacy charsets) and turned into one memory per message or per thread.
+//! Message-IDs are recorded in each memory's frontmatter, and messages whose ID is already
+//! there are skipped, so importing the same mailbox twice adds nothing.
+
+use crate::chat_import::html_to_text;
+use crate::database::{
+    add_entity_alias, find_entity_id_by_name_or_alias, get_entity_by_id, upsert_entity,
+};
+use crate::file_manager::{list_memory_files, read_memory};
+use crate::library_crypto::LibraryKey;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use encoding_rs::{Encoding, UTF_8};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value as YamlValue};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use walkdir::WalkDir;
+
+/// Longer bodies are cut off; the memory keeps the head of the message.
+const MAX_BODY_CHARS: usize = 6000;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EmailImportRequest {
+    /// An mbox file, a single `.eml` file or a directory searched for `.eml` files.
+    pub path: String,
+    /// `message` (default) or `thread`.
+    pub split_by: Option<String>,
+    /// Memories saved between progress reports.
+    pub batch_size: Option<usize>,
+    /// Parse and group only; nothing is saved.
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailMemorySummary {
+    pub subject: String,
+    pub created: Option<String>,
+    pub participants: Vec<String>,
+    pub message_count: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailImportError {
+    pub subject: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EmailImportReport {
+    pub dry_run: bool,
+    pub messages: usize,
+    /// Messages skipped because their Message-ID was imported before.
+    pub duplicates: usize,
+    pub memories: Vec<EmailMemorySummary>,
+    pub saved: usize,
+    pub failed: usize,
+    pub errors: Vec<EmailImportError>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mailbox {
+    pub name: Option<String>,
+    pub address: String,
+}
+
+impl Mailbox {
+    /// Display name, falling back to the address.
+    pub fn label(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.address)
+    }
+
+    fn render(&self) -> String {
+        match &self.name {
+            Some(name) => format!("{} <{}>", name, self.address),
+            None => self.address.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EmailMessage {
+    pub message_id: String,
+    pub in_reply_to: Option<String>,
+    pub references: Vec<String>,
+    pub subject: String,
+    pub from: Vec<Mailbox>,
+    pub to: Vec<Mailbox>,
+    pub cc: Vec<Mailbox>,
+    pub date: Option<DateTime<Utc>>,
+    pub body: String,
+}
+
+/// One memory to save: a message, or a thread of messages.
+#[derive(Debug, Clone)]
+pub struct EmailMemory {
+    pub subject: String,
+    /// `YYYY-MM-DD HH:MM:SS` UTC of the first message, when it has a date.
+    pub created: Option<String>,
+    pub message_ids: Vec<String>,
+    pub from: Vec<Mailbox>,
+    pub to: Vec<Mailbox>,
+    pub cc: Vec<Mailbox>,
+    pub content: String,
+}
+
+impl EmailMemory {
+    /// Everyone on the messages, senders first, without repeats.
+    pub fn correspondents(&self) -> Vec<&Mailbox> {
+        let mut seen = HashSet::new();
+        self.from
+            .iter()
+            .chain(&self.to)
+            .chain(&self.cc)
+            .filter(|m| seen.insert(m.address.to_lowercase()))
+            .collect()
+    }
+
+    pub fn summary(&self) -> EmailMemorySummary {
+        EmailMemorySummary {
+            subject: self.subject.clone(),
+            created: self.created.clone(),
+            participants: self
+                .correspondents()
+                .iter()
+                .map(|m| m.label().to_string())
+                .collect(),
+            message_count: self.message_ids.len(),
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Reading mailboxes
+// ---------------------------------------------------------------------------
+
+/// Split an mbox into raw messages. `From ` lines after a blank line start a message;
+/// `>From ` escapes (mboxrd) lose one `>`.
+fn split_mbox(data: &[u8]) -> Vec<Vec<u8>> {
+    let mut messages = Vec::new();
+    let mut current: Option<Vec<u8>> = None;
+    let mut previous_blank = true;
+    for line in data.split_inclusive(|b| *b == b'\n') {
+        if previous_blank && line.starts_with(b"From ") {
+            if let Some(message) = current.take() {
+                messages.push(message);
+            }
+            current = Some(Vec::new());
+            previous_blank = false;
+            continue;
+        }
+        previous_blank = line.iter().all(|b| b.is_ascii_whitespace());
+        let Some(message) = current.as_mut() else {
+            continue;
+        };
+        let quoted = line.iter().take_while(|b| **b == b'>').count();
+        if quoted > 0 && line[quoted..].starts_with(b"From ") {
+            message.extend_from_slice(&line[1..]);
+        } else {
+            message.extend_from_slice(line);
+        }
+    }
+    messages.extend(current);
+    messages
+}
+
+/// Raw messages from an mbox file, a `.eml` file or a directory of `.eml` files.
+fn read_raw_messages(path: &Path) -> Result<Vec<Vec<u8>>, String> {
+    if path.is_dir() {
+        let mut messages = Vec::new();
+        for entry in WalkDir::new(path).sort_by_file_name() {
+            let entry = entry.map_err(|e| e.to_string())?;
+            let is_eml = entry
+                .path()
+                .extension()
+                .and_then(|x| x.to_str())
+                .is_some_and(|x| x.eq_ignore_ascii_case("eml"));
+            if entry.file_type().is_file() && is_eml {
+                messages.push(
+                    fs::read(entry.path())
+                        .map_err(|e| format!("Failed to read {:?}: {}", entry.path(), e))?,
+                );
+            }
+        }
+        return Ok(messages);
+    }
+    let data = fs::read(path).map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+    if data.starts_with(b"From ") {
+        Ok(split_mbox(&data))
+    } else {
+        Ok(vec![data])
+    }
+}
+
+// ---------------------------------------------------------------------------
+// MIME
+// ---------------------------------------------------------------------------
+
+struct Part<'a> {
+    headers: Vec<(String, String)>,
+    body: &'a [u8],
+}
+
+impl Part<'_> {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Lower-cased MIME type and its parameters.
+    fn content_type(&self) -> (String, HashMap<String, String>) {
+        let value = self.header("Content-Type").unwrap_or("text/plain");
+        let mut pieces = value.split(';');
+        let mime = pieces.next().unwrap_or_default().trim().to_lowercase();
+        let params = pieces
+            .filter_map(|p| p.split_once('='))
+            .map(|(k, v)| {
+                (
+                    k.trim().to_lowercase(),
+                    v.trim().trim_matches('"').to_string(),
+                )
+            })
+            .collect();
+        (mime, params)
+    }
+}
+
+fn parse_part(raw: &[u8]) -> Part<'_> {
+    let (head, body) = match find_subslice(raw, b"\r\n\r\n") {
+        Some(i) => (&raw[..i], &raw[i + 4..]),
+        None => match find_subslice(raw, b"\n\n") {
+            Some(i) => (&raw[..i], &raw[i + 2..]),
+            None => (raw, &raw[raw.len()..]),
+        },
+    };
+    let head = String::from_utf8_lossy(head);
+    let mut headers: Vec<(String, String)> = Vec::new();
+    for line in head.lines() {
+        if line.starts_with([' ', '\t']) {
+            if let Some((_, value)) = headers.last_mut() {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+        } else if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    Part { headers, body }
+}
+
+fn find_subslice(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+pub(crate) fn decode_charset(bytes: &[u8], charset: Option<&str>) -> String {
+    let encoding = charset
+        .and_then(|c| Encoding::for_label(c.trim().as_bytes()))
+        .unwrap_or(UTF_8);
+    encoding.decode(bytes).0.into_owned()
+}
+
+pub(crate) fn decode_quoted_printable(data: &[u8], header: bool) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut i = 0;
+    while i < data.len() {
+        match data[i] {
+            b'=' if data[i + 1..].starts_with(b"\r\n") => i += 3,
+            b'=' if data[i + 1..].starts_with(b"\n") => i += 2,
+            b'=' if i + 2 < data.len() => {
+                let hex = std::str::from_utf8(&data[i + 1..i + 3]).ok();
+                match hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                    Some(byte) => {
+                        out.push(byte);
+                        i += 3;
+                    }
+                    None => {
+                        out.push(b'=');
+                        i += 1;
+                    }
+                }
+            }
+            b'_' if header => {
+                out.push(b' ');
+                i += 1;
+            }
+            byte => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+fn decode_transfer(body: &[u8], encoding: Option<&str>) -> Vec<u8> {
+    match encoding.map(|e| e.trim().to_lowercase()).as_deref() {
+        Some("base64") => {
+            let compact: Vec<u8> = body
+                .iter()
+                .copied()
+                .filter(|b| !b.is_ascii_whitespace())
+                .collect();
+            BASE64.decode(compact).unwrap_or_default()
+        }
+        Some("quoted-printable") => decode_quoted_printable(body, false),
+        _ => body.to_vec(),
+    }
+}
+
+/// Decode RFC 2047 encoded words (`=?utf-8?B?...?=`); whitespace between them is dropped.
+pub fn decode_header(value: &str) -> String {
+    let mut out = String::new();
+    let mut rest = value;
+    let mut pending_space = String::new();
+    let mut after_word = false;
+    while !rest.is_empty() {
+        let decoded = rest.strip_prefix("=?").and_then(|word| {
+            let (charset, word) = word.split_once('?')?;
+            let (encoding, word) = word.split_once('?')?;
+            let end = word.find("?=")?;
+            let text = &word[..end];
+            let bytes = match encoding.to_ascii_lowercase().as_str() {
+                "b" => BASE64.decode(text).ok()?,
+                "q" => decode_quoted_printable(text.as_bytes(), true),
+                _ => return None,
+            };
+            let consumed = 2 + charset.len() + 1 + encoding.len() + 1 + end + 2;
+            // Language suffix (RFC 2231): `utf-8*en`.
+            let charset = charset.split('*').next().unwrap_or(charset);
+            Some((decode_charset(&bytes, Some(charset)), consumed))
+        });
+        match decoded {
+            Some((text, consumed)) => {
+                if !after_word {
+                    out.push_str(&pending_space);
+                }
+                pending_space.clear();
+                out.push_str(&text);
+                rest = &rest[consumed.min(rest.len())..];
+                after_word = true;
+            }
+            None => {
+                let c = rest.chars().next().expect("rest is not empty");
+                if c.is_whitespace() {
+                    pending_space.push(c);
+                } else {
+                    out.push_str(&pending_space);
+                    pending_space.clear();
+                    out.push(c);
+                    after_word = false;
+                }
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    out.push_str(&pending_space);
+    out.trim().to_string()
+}
+
+/// Readable text of a part: the plain-text alternative when there is one, otherwise HTML
+/// converted to text. Attachments are skipped.
+fn part_text(part: &Part) -> Option<String> {
+    let is_attachment = part
+        .header("Content-Disposition")
+        .is_some_and(|d| d.trim().to_lowercase().starts_with("attachment"));
+    if is_attachment {
+        return None;
+    }
+    let (mime, params) = part.content_type();
+    if let Some(boundary) = mime
+        .starts_with("multipart/")
+        .then(|| params.get("boundary"))
+        .flatten()
+    {
+        let children: Vec<Part> = split_multipart(part.body, boundary)
+            .into_iter()
+            .map(parse_part)
+            .collect();
+        if mime == "multipart/alternative" {
+            let plain = children
+                .iter()
+                .find(|c| c.content_type().0 == "text/plain")
+                .and_then(part_text);
+            return plain.or_else(|| children.iter().find_map(part_text));
+        }
+        let texts: Vec<String> = children.iter().filter_map(part_text).collect();
+        return (!texts.is_empty()).then(|| texts.join("\n\n"));
+    }
+    let decode = || {
+        let bytes = decode_transfer(part.body, part.header("Content-Transfer-Encoding"));
+        decode_charset(&bytes, params.get("charset").map(String::as_str))
+    };
+    match mime.as_str() {
+        "text/plain" => Some(decode()),
+        "text/html" => Some(html_to_text(&decode())),
+        "message/rfc822" => part_text(&parse_part(part.body)),
+        _ => None,
+    }
+}
+
+fn split_multipart<'a>(body: &'a [u8], boundary: &str) -> Vec<&'a [u8]> {
+    let delimiter = format!("--{}", boundary);
+    let mut parts = Vec::new();
+    let mut current: Option<usize> = None;
+    let mut offset = 0;
+    for line in body.split_inclusive(|b| *b == b'\n') {
+        let trimmed = line.trim_ascii_end();
+        if trimmed.starts_with(delimiter.as_bytes()) {
+            if let Some(start) = current {
+                parts.push(&body[start..offset]);
+            }
+            if trimmed == format!("{}--", delimiter).as_bytes() {
+                return parts;
+            }
+            current = Some(offset + line.len());
+        }
+        offset += line.len();
+    }
+    if let Some(start) = current {
+        parts.push(&body[start..]);
+    }
+    parts
+}
+
+/// Split an address list on commas outside quotes and angle brackets.
+fn parse_mailboxes(value: &str) -> Vec<Mailbox> {
+    let mut items = Vec::new();
+    let (mut current, mut quoted, mut angle) = (String::new(), false, false);
+    for c in value.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            '<' if !quoted => angle = true,
+            '>' if !quoted => angle = false,
+            ',' | ';' if !quoted && !angle => {
+                items.push(std::mem::take(&mut current));
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    items.push(current);
+
+    items
+        .iter()
+        .filter_map(|item| {
+            let item = item.trim();
+            let (name, address) = match (item.rfind('<'), item.rfind('>')) {
+                (Some(open), Some(close)) if open < close => {
+                    (&item[..open], item[open + 1..close].trim())
+                }
+                _ => ("", item),
+            };
+            if !address.contains('@') {
+                return None;
+            }
+            let name = decode_header(name.trim().trim_matches('"').trim());
+            Some(Mailbox {
+                name: (!name.is_empty() && name != address).then_some(name),
+                address: address.to_string(),
+            })
+        })
+        .collect()
+}
+
+fn parse_message_ids(value: &str) -> Vec<String> {
+    value
+        .split('<')
+        .filter_map(|piece| piece.split_once('>').map(|(id, _)| id.trim().to_string()))
+        .filter(|id| !id.is_empty())
+        .collect()
+}
+
+fn parse_date(value: &str) -> Option<DateTime<Utc>> {
+    // Drop comments such as `(UTC)` or `(CEST)` that RFC 2822 parsing rejects.
+    let mut cleaned = String::new();
+    let mut depth = 0;
+    for c in value.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth > 0 => depth -= 1,
+            c if depth == 0 => cleaned.push(c),
+            _ => {}
+        }
+    }
+    DateTime::parse_from_rfc2822(cleaned.trim())
+        .ok()
+        .map(|d| d.with_timezone(&Utc))
+}
+
+fn truncate_body(body: &str) -> String {
+    // Quoted replies repeat earlier messages; drop them along with trailing blank lines.
+    let kept: Vec<&str> = body
+        .lines()
+        .map(str::trim_end)
+        .filter(|l| !l.starts_with('>'))
+        .collect();
+    let text = kept.join("\n");
+    let text = text.trim();
+    if text.chars().count() <= MAX_BODY_CHARS {
+        return text.to_string();
+    }
+    let cut: String = text.chars().take(MAX_BODY_CHARS).collect();
+    format!("{}\n[…]", cut.trim_end())
+}
+
+fn parse_message(raw: &[u8]) -> EmailMessage {
+    let part = parse_part(raw);
+    let header = |name: &str| part.header(name).map(decode_header).unwrap_or_default();
+    let addresses = |name: &str| part.header(name).map(parse_mailboxes).unwrap_or_default();
+    let subject = header("Subject");
+    let from = addresses("From");
+    let date = part.header("Date").and_then(parse_date);
+    let message_id = part
+        .header("Message-ID")
+        .and_then(|v| parse_message_ids(v).into_iter().next())
+        .unwrap_or_else(|| {
+            // No Message-ID: derive a stable one from what identifies the message.
+            let mut hasher = Sha256::new();
+            hasher.update(part.header("From").unwrap_or_default());
+            hasher.update(part.header("Date").unwrap_or_default());
+            hasher.update(&subject);
+            hasher.update(part.body);
+            format!("generated-{:x}@kraph", hasher.finalize())
+        });
+    EmailMessage {
+        message_id,
+        in_reply_to: part
+            .header("In-Reply-To")
+            .and_then(|v| parse_message_ids(v).into_iter().next()),
+        references: part
+            .header("References")
+            .map(parse_message_ids)
+            .unwrap_or_default(),
+        subject: if subject.is_empty() {
+            "(no subject)".to_string()
+        } else {
+            subject
+        },
+        from,
+        to: addresses("To"),
+        cc: addresses("Cc"),
+        date,
+        body: truncate_body(&part_text(&part).unwrap_or_default()),
+    }
+}
+
+/// Parse every message at `path`, oldest first.
+pub fn read_messages(path: &Path) -> Result<Vec<EmailMessage>, String> {
+    let mut messages: Vec<EmailMessage> = read_raw_messages(path)?
+        .iter()
+        .map(|raw| parse_message(raw))
+        .collect();
+    if messages.is_empty() {
+        return Err(format!("No email messages found in {:?}.", path));
+    }
+    messages.sort_by_key(|m| m.date);
+    Ok(messages)
+}
+
+/// Make sure a correspondent exists as a Person with their address: looked up by address
+/// (kept as an alias) or display name, created otherwise. Returns the entity's name.
+pub fn precreate_correspondent(conn: &Connection, mailbox: &Mailbox) -> Result<String, String> {
+    let mut existing =
+        find_entity_id_by_name_or_alias(conn, &mailbox.address).map_err(|e| e.to_string())?;
+    if existing.is_none() {
+        if let Some(name) = &mailbox.name {
+            existing = find_entity_id_by_name_or_alias(conn, name).map_err(|e| e.to_string())?;
+        }
+    }
+    let (id, name) = match existing {
+        Some(id) => {
+            let entity = get_entity_by_id(conn, id).map_err(|e| e.to_string())?;
+            let mut attributes = entity
+                .attributes
+                .as_deref()
+                .and_then(|a| serde_json::from_str::<serde_json::Value>(a).ok())
+                .and_then(|v| v.as_object().cloned())
+                .unwrap_or_default();
+            if entity.entity_type == "Person" && !attributes.contains_key("email") {
+                attributes.insert("email".to_string(), mailbox.address.clone().into());
+                let attributes = serde_json::Value::Object(attributes).to_string();
+                upsert_entity(conn, &entity.entity_type, &entity.name, Some(&attributes))
+                    .map_err(|e| e.to_string())?;
+            }
+            (id, entity.name)
+        }
+        None => {
+            let attributes = serde_json::json!({ "email": mailbox.address }).to_string();
+            let id = upsert_entity(conn, "Person", mailbox.label(), Some(&attributes))
+                .map_err(|e| e.to_string())?;
+            (id, mailbox.label().to_string())
+        }
+    };
+    if name != mailbox.address {
+        add_entity_alias(conn, id, &mailbox.address).map_err(|e| e.to_string())?;
+    }
+    Ok(name)
+}
+
+// ---------------------------------------------------------------------------
+// Dedupe and grouping
+// ---------------------------------------------------------------------------
+
+/// Message-IDs recorded in the frontmatter of the library's memory files.
+pub fn imported_message_ids(
+    memories_dir: &Path,
+    key: Option<&LibraryKey>,
+) -> Result<HashSet<String>, String> {
+    let mut ids = HashSet::new();
+    for path in list_memory_files(memories_dir)? {
+        let Ok(record) = read_memory(&path, key) else {
+            continue;
+        };
+        let raw = &record.frontmatter.raw;
+        if let Some(id) = raw.get("message_id").and_then(|v| v.as_str()) {
+            ids.insert(id.to_string());
+        }
+        for id in raw
+            .get("message_ids")
+            .and_then(|v| v.as_sequence())
+            .into_iter()
+            .flatten()
+            .filter_map(|v| v.as_str())
+        {
+            ids.insert(id.to_string());
+        }
+    }
+    Ok(ids)
+}
+
+fn push_unique(target: &mut Vec<Mailbox>, mailboxes: &[Mailbox]) {
+    for mailbox in mailboxes {
+        if !target
+            .iter()
+            .any(|m| m.address.eq_ignore_ascii_case(&mailbox.address))
+        {
+            target.push(mailbox.clone());
+        }
+    }
+}
+
+fn render_addresses(mailboxes: &[Mailbox]) -> String {
+    mailboxes
+        .iter()
+        .map(Mailbox::render)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn format_date(date: Option<DateTime<Utc>>) -> Option<String> {
+    date.map(|d| d.format("%Y-%m-%d %H:%M:%S").to_string())
+}
+
+fn message_memory(message: &EmailMessage) -> EmailMemory {
+    let mut content = format!(
+        "{}\n\nFrom: {}\n",
+        message.subject,
+        render_addresses(&message.from)
+    );
+    if !message.to.is_empty() {
+        content.push_str(&format!("To: {}\n", render_addresses(&message.to)));
+    }
+    if !message.cc.is_empty() {
+        content.push_str(&format!("Cc: {}\n", render_addresses(&message.cc)));
+    }
+    if let Some(date) = format_date(message.date) {
+        content.push_str(&format!("Date: {} UTC\n", date));
+    }
+    content.push('\n');
+    content.push_str(&message.body);
+    EmailMemory {
+        subject: message.subject.clone(),
+        created: format_date(message.date),
+        message_ids: vec![message.message_id.clone()],
+        from: message.from.clone(),
+        to: message.to.clone(),
+        cc: message.cc.clone(),
+        content: content.trim_end().to_string(),
+    }
+}
+
+fn thread_memory(messages: &[&EmailMessage]) -> EmailMemory {
+    let subject = messages[0].subject.clone();
+    let (mut from, mut to, mut cc) = (Vec::new(), Vec::new(), Vec::new());
+    let mut content = format!("{}\n", subject);
+    for message in messages {
+        push_unique(&mut from, &message.from);
+        push_unique(&mut to, &message.to);
+        push_unique(&mut cc, &message.cc);
+        let sender = message
+            .from
+            .first()
+            .map(|m| m.label().to_string())
+            .unwrap_or_else(|| "Unknown sender".to_string());
+        let date = format_date(message.date)
+            .map(|d| format!(" on {} UTC", d))
+            .unwrap_or_default();
+        content.push_str(&format!("\n{}{} wrote:\n{}\n", sender, date, message.body));
+    }
+    EmailMemory {
+        subject,
+        created: format_date(messages[0].date),
+        message_ids: messages.iter().map(|m| m.message_id.clone()).collect(),
+        from,
+        to,
+        cc,
+        content: content.trim_end().to_string(),
+    }
+}
+
+impl EmailMemory {
+    /// Frontmatter keys describing the message(s): sender, recipients, subject and IDs.
+    pub fn frontmatter(&self) -> Mapping {
+        let list = |mailboxes: &[Mailbox]| {
+            YamlValue::Sequence(
+                mailboxes
+                    .iter()
+                    .map(|m| YamlValue::String(m.render()))
+                    .collect(),
+            )
+        };
+        let mut map = Mapping::new();
+        let from = match self.from.as_slice() {
+            [single] => YamlValue::String(single.render()),
+            many => list(many),
+        };
+        map.insert("from".into(), from);
+        if !self.to.is_empty() {
+            map.insert("to".into(), list(&self.to));
+        }
+        if !self.cc.is_empty() {
+            map.insert("cc".into(), list(&self.cc));
+        }
+        map.insert("subject".into(), YamlValue::String(self.subject.clone()));
+        match self.message_ids.as_slice() {
+            [single] => map.insert("message_id".into(), YamlValue::String(single.clone())),
+            many => map.insert(
+                "message_ids".into(),
+                YamlValue::Sequence(many.iter().cloned().map(YamlValue::String).collect()),
+            ),
+        };
+        map
+    }
+}
+
+/// One memory per message, or per thread (following References / In-Reply-To) when
+/// `by_thread` is set.
+pub fn group_messages(messages: &[EmailMessage], by_thread: bool) -> Vec<EmailMemory> {
+    if !by_thread {
+        return messages.iter().map(message_memory).collect();
+    }
+    let mut thread_of: HashMap<&str, usize> = HashMap::new();
+    let mut threads: Vec<Vec<&EmailMessage>> = Vec::new();
+    for message in messages {
+        let parent = message
+            .references
+            .iter()
+            .chain(&message.in_reply_to)
+            .find_map(|id| thread_of.get(id.as_str()).copied());
+        let index = parent.unwrap_or_else(|| {
+            threads.push(Vec::new());
+            threads.len() - 1
+        });
+        threads[index].push(message);
+        thread_of.insert(&message.message_id, index);
+        for id in &message.references {
+            thread_of.entry(id).or_insert(index);
+        }
+    }
+    threads.iter().map(|t| thread_memory(t)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_header_skips_language_tagged_words_whole() {
+        assert_eq!(
+            decode_header("=?utf-8*en?Q?Caf=C3=A9?= =?UTF-8*de-CH?B?R3LDvGV6aQ==?= bar"),
+            "CaféGrüezi bar"
+        );
+    }
+}
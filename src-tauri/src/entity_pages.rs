@@ -0,0 +1,325 @@
+//! Generated entity pages: one Markdown file per entity in the library's `entities/` folder,
+//! with its type, aliases, attributes, relations as wiki-links and a list of backlinked memories.
+//!
+//! Pages are rewritten incrementally. `.index.json` in the folder records which file belongs to
+//! which entity and that entity's neighbours, so pages of deleted or merged entities can be
+//! removed and the pages that linked to them refreshed.
+
+use crate::database::{
+    get_memories_for_entity, list_entities, list_entity_aliases, list_relations, Entity, Memory,
+    Relation,
+};
+use crate::file_manager::replace_file_contents;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const INDEX_FILE: &str = ".index.json";
+
+/// Characters of a memory's first line used as its link label.
+const MEMORY_LABEL_CHARS: usize = 60;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct PageIndexEntry {
+    file: String,
+    neighbours: Vec<i64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct PageIndex {
+    pages: BTreeMap<i64, PageIndexEntry>,
+}
+
+pub fn entity_pages_dir(library_dir: &Path) -> PathBuf {
+    library_dir.join("entities")
+}
+
+fn load_index(dir: &Path) -> PageIndex {
+    fs::read_to_string(dir.join(INDEX_FILE))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn save_index(dir: &Path, index: &PageIndex) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(index).map_err(|e| e.to_string())?;
+    replace_file_contents(&dir.join(INDEX_FILE), &content, None)
+}
+
+fn page_stem(name: &str) -> String {
+    let cleaned: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' | '#' | '^' | '[' | ']' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let cleaned = cleaned.trim().trim_matches('.').trim();
+    if cleaned.is_empty() {
+        "untitled".to_string()
+    } else {
+        cleaned.to_string()
+    }
+}
+
+/// Give every entity a page file name. The oldest entity keeps the plain name;
+/// later entities whose names clash get their ID appended, e.g. `Paris (42)`.
+fn allocate_page_stems(entities: &[Entity]) -> HashMap<i64, String> {
+    let mut sorted: Vec<&Entity> = entities.iter().collect();
+    sorted.sort_by_key(|e| e.id);
+    let mut taken: HashSet<String> = HashSet::new();
+    let mut stems = HashMap::new();
+    for e in sorted {
+        let base = page_stem(&e.name);
+        let stem = if taken.insert(base.to_lowercase()) {
+            base
+        } else {
+            format!("{} ({})", base, e.id)
+        };
+        stems.insert(e.id, stem);
+    }
+    stems
+}
+
+fn entity_link(stems: &HashMap<i64, String>, entity: &Entity) -> String {
+    match stems.get(&entity.id) {
+        Some(stem) if *stem == entity.name => format!("[[{}]]", stem),
+        Some(stem) => format!("[[{}|{}]]", stem, entity.name),
+        None => entity.name.clone(),
+    }
+}
+
+/// Wiki-link to a memory file, by its path relative to the library folder.
+fn memory_link(library_dir: &Path, memory: &Memory) -> String {
+    let label: String = memory
+        .content
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty())
+        .unwrap_or("untitled")
+        .chars()
+        .take(MEMORY_LABEL_CHARS)
+        .collect::<String>()
+        .replace(['[', ']', '|'], " ");
+    match memory.md_file_path.as_deref().map(Path::new) {
+        Some(path) => {
+            let relative = path.strip_prefix(library_dir).unwrap_or(path);
+            let target = relative
+                .with_extension("")
+                .to_string_lossy()
+                .replace('\\', "/");
+            format!("[[{}|{}]]", target, label.trim())
+        }
+        None => label.trim().to_string(),
+    }
+}
+
+fn attribute_text(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+struct GraphView<'a> {
+    library_dir: &'a Path,
+    by_id: HashMap<i64, &'a Entity>,
+    stems: HashMap<i64, String>,
+    aliases: HashMap<i64, Vec<String>>,
+    relations: HashMap<i64, Vec<&'a Relation>>,
+}
+
+impl GraphView<'_> {
+    fn neighbours(&self, id: i64) -> Vec<i64> {
+        let mut out: Vec<i64> = self
+            .relations
+            .get(&id)
+            .into_iter()
+            .flatten()
+            .map(|r| {
+                if r.from_entity_id == id {
+                    r.to_entity_id
+                } else {
+                    r.from_entity_id
+                }
+            })
+            .filter(|other| *other != id)
+            .collect();
+        out.sort_unstable();
+        out.dedup();
+        out
+    }
+
+    fn render_page(&self, conn: &Connection, entity: &Entity) -> Result<String, String> {
+        let aliases = self.aliases.get(&entity.id).cloned().unwrap_or_default();
+
+        let mut fm = Mapping::new();
+        fm.insert(Value::from("kraph_entity_id"), Value::from(entity.id));
+        fm.insert(Value::from("type"), Value::from(entity.entity_type.clone()));
+        if !aliases.is_empty() {
+            fm.insert(
+                Value::from("aliases"),
+                Value::Sequence(aliases.iter().cloned().map(Value::from).collect()),
+            );
+        }
+        let yaml = serde_yaml::to_string(&fm).map_err(|e| e.to_string())?;
+
+        let mut out = format!("---\n{}---\n\n# {}\n\n", yaml, entity.name);
+        out.push_str(&format!("- **Type:** {}\n", entity.entity_type));
+        if !aliases.is_empty() {
+            out.push_str(&format!("- **Aliases:** {}\n", aliases.join(", ")));
+        }
+
+        let attributes = entity
+            .attributes
+            .as_deref()
+            .and_then(|a| serde_json::from_str::<serde_json::Value>(a).ok())
+            .and_then(|v| v.as_object().cloned())
+            .filter(|m| !m.is_empty());
+        if let Some(attributes) = attributes {
+            out.push_str("\n## Attributes\n\n");
+            for (key, value) in &attributes {
+                out.push_str(&format!("- **{}:** {}\n", key, attribute_text(value)));
+            }
+        }
+
+        let mut lines: Vec<String> = self
+            .relations
+            .get(&entity.id)
+            .into_iter()
+            .flatten()
+            .filter_map(|r| {
+                let from = self.by_id.get(&r.from_entity_id)?;
+                let to = self.by_id.get(&r.to_entity_id)?;
+                Some(if r.from_entity_id == entity.id {
+                    format!("- {} → {}", r.relation_type, entity_link(&self.stems, to))
+                } else {
+                    format!("- {} → {}", entity_link(&self.stems, from), r.relation_type)
+                })
+            })
+            .collect();
+        lines.sort();
+        lines.dedup();
+        if !lines.is_empty() {
+            out.push_str("\n## Relations\n\n");
+            out.push_str(&lines.join("\n"));
+            out.push('\n');
+        }
+
+        let memories = get_memories_for_entity(conn, entity.id).map_err(|e| e.to_string())?;
+        if !memories.is_empty() {
+            out.push_str("\n## Memories\n\n");
+            for memory in &memories {
+                let date = memory.created_at.split(' ').next().unwrap_or_default();
+                out.push_str(&format!(
+                    "- {} — {}\n",
+                    date,
+                    memory_link(self.library_dir, memory)
+                ));
+            }
+        }
+        Ok(out)
+    }
+}
+
+/// Bring `entities/` in line with the database.
+///
+/// `changed` lists entities whose memories, relations or aliases may have changed; their pages
+/// and those of their neighbours are rewritten. `None` regenerates every page. Pages of entities
+/// that no longer exist are always removed. Returns the number of pages written.
+pub fn update_entity_pages(
+    conn: &Connection,
+    library_dir: &Path,
+    changed: Option<&[i64]>,
+) -> Result<usize, String> {
+    let dir = entity_pages_dir(library_dir);
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create entities directory: {e}"))?;
+
+    let entities = list_entities(conn).map_err(|e| e.to_string())?;
+    let relations = list_relations(conn).map_err(|e| e.to_string())?;
+    let mut aliases: HashMap<i64, Vec<String>> = HashMap::new();
+    for (entity_id, alias) in list_entity_aliases(conn).map_err(|e| e.to_string())? {
+        aliases.entry(entity_id).or_default().push(alias);
+    }
+    for list in aliases.values_mut() {
+        list.sort();
+    }
+    let mut by_relation: HashMap<i64, Vec<&Relation>> = HashMap::new();
+    for r in &relations {
+        by_relation.entry(r.from_entity_id).or_default().push(r);
+        if r.to_entity_id != r.from_entity_id {
+            by_relation.entry(r.to_entity_id).or_default().push(r);
+        }
+    }
+    let view = GraphView {
+        library_dir,
+        by_id: entities.iter().map(|e| (e.id, e)).collect(),
+        stems: allocate_page_stems(&entities),
+        aliases,
+        relations: by_relation,
+    };
+
+    let mut index = load_index(&dir);
+    let mut affected: HashSet<i64> = match changed {
+        None => view.by_id.keys().copied().collect(),
+        Some(ids) => ids
+            .iter()
+            .flat_map(|id| std::iter::once(*id).chain(view.neighbours(*id)))
+            .collect(),
+    };
+
+    // Entities that were deleted or merged away: drop their pages and refresh former neighbours.
+    let gone: Vec<i64> = index
+        .pages
+        .keys()
+        .filter(|id| !view.by_id.contains_key(id))
+        .copied()
+        .collect();
+    for id in gone {
+        if let Some(entry) = index.pages.remove(&id) {
+            let _ = fs::remove_file(dir.join(&entry.file));
+            affected.extend(entry.neighbours);
+        }
+    }
+    // Entities whose page file name changed need their links updated everywhere they appear.
+    for (id, entry) in &index.pages {
+        let expected = view.stems.get(id).map(|s| format!("{}.md", s));
+        if expected.as_deref() != Some(entry.file.as_str()) {
+            affected.insert(*id);
+            affected.extend(entry.neighbours.iter().copied());
+        }
+    }
+
+    let mut written = 0;
+    for id in affected {
+        let Some(entity) = view.by_id.get(&id) else {
+            continue;
+        };
+        let file = format!("{}.md", view.stems[&id]);
+        let content = view.render_page(conn, entity)?;
+        let path = dir.join(&file);
+        if fs::read_to_string(&path).ok().as_deref() != Some(content.as_str()) {
+            replace_file_contents(&path, &content, None)?;
+            written += 1;
+        }
+        let previous = index.pages.insert(
+            id,
+            PageIndexEntry {
+                file: file.clone(),
+                neighbours: view.neighbours(id),
+            },
+        );
+        if let Some(previous) = previous.filter(|p| p.file != file) {
+            // Only remove the old file if no other entity has taken over that name.
+            if !index.pages.values().any(|p| p.file == previous.file) {
+                let _ = fs::remove_file(dir.join(&previous.file));
+            }
+        }
+    }
+    save_index(&dir, &index)?;
+    Ok(written)
+}
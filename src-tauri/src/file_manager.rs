@@ -1,16 +1,70 @@
 //! Markdown file manager: date-based directory layout with YAML frontmatter metadata.
+//!
+//! Frontmatter is round-tripped through a YAML mapping, so keys added by other tools
+//! (Obsidian properties, etc.) and their order are preserved when Kraph rewrites a file.
 
-use chrono::Utc;
+use crate::library_crypto::{decode_text_with, encode_text_with, LibraryKey};
+use chrono::{NaiveDateTime, Utc};
 use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+use std::collections::HashMap;
+use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::fs;
 use walkdir::WalkDir;
 
+/// Default layout: `YYYY/MM/YYYYMMDD_HHMMSS_<slug>.md`.
+pub const DEFAULT_PATH_TEMPLATE: &str = "{year}/{month}/{year}{month}{day}_{time}_{slug}.md";
+
+/// Slug length used by a bare `{slug}` placeholder.
+const DEFAULT_SLUG_CHARS: usize = 30;
+
+/// How many numbered variants of a file name are tried before giving up.
+const MAX_PATH_ATTEMPTS: usize = 1000;
+
+/// Frontmatter keys managed by Kraph, in the order they are written to new files.
+const KNOWN_KEYS: [&str; 8] = [
+    "id", "created", "updated", "source", "tags", "entities", "relations", "aliases",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MdEntity {
+    pub name: String,
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub entity_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attributes: Option<serde_json::Value>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MdRelation {
+    pub from: String,
+    pub to: String,
+    pub relation: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MdAlias {
+    pub primary: String,
+    pub alias: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct MdFrontmatter {
+    /// Database ID of the memory, once it has been saved.
+    pub id: Option<i64>,
     pub created: String,
+    /// Last time the memory was edited in Kraph (`None` until the first edit).
+    pub updated: Option<String>,
+    /// Where the memory came from (e.g. `kraph` for memories typed in the app).
+    pub source: Option<String>,
     pub tags: Option<Vec<String>>,
-    pub entities: Option<Vec<String>>,
+    pub entities: Option<Vec<MdEntity>>,
+    pub relations: Option<Vec<MdRelation>>,
+    pub aliases: Option<Vec<MdAlias>>,
+    /// The mapping as read from disk, so unknown keys and key order survive a rewrite.
+    #[serde(skip)]
+    pub(crate) raw: Mapping,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -20,15 +74,17 @@ pub struct MdRecord {
     pub file_path: String,
 }
 
-/// Derive a URL-safe slug from the content's first line (max 30 chars).
-fn slug_from_content(content: &str) -> String {
+fn now_timestamp() -> String {
+    Utc::now().format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+/// Derive a URL-safe slug from the content's first line (at most `max_chars` characters).
+fn slug_from_content(content: &str, max_chars: usize) -> String {
     let first_line = content.lines().next().unwrap_or("").trim();
     let title: String = if first_line.is_empty() {
         "untitled".to_string()
-    } else if first_line.len() > 30 {
-        first_line.chars().take(30).collect()
     } else {
-        first_line.to_string()
+        first_line.chars().take(max_chars).collect()
     };
     title
         .chars()
@@ -36,113 +92,513 @@ fn slug_from_content(content: &str) -> String {
         .collect::<String>()
         .trim()
         .replace(' ', "_")
+}
+
+/// Make a placeholder value safe to use as (part of) a single path component.
+fn sanitize_component(value: &str) -> String {
+    let cleaned: String = value
         .chars()
-        .take(40)
-        .collect::<String>()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    cleaned.trim().trim_matches('.').trim().to_string()
+}
+
+/// Values available to a path template for one memory.
+pub struct PathContext<'a> {
+    pub created: NaiveDateTime,
+    pub content: &'a str,
+    pub tags: &'a [String],
+    /// The memory's first non-Time entity, if any.
+    pub primary_entity: Option<&'a str>,
+}
+
+impl<'a> PathContext<'a> {
+    /// Build the context from a memory's frontmatter and body.
+    pub fn from_frontmatter(frontmatter: &'a MdFrontmatter, content: &'a str) -> Self {
+        PathContext {
+            created: parse_timestamp(&frontmatter.created)
+                .unwrap_or_else(|| Utc::now().naive_utc()),
+            content,
+            tags: frontmatter.tags.as_deref().unwrap_or(&[]),
+            primary_entity: frontmatter
+                .entities
+                .iter()
+                .flatten()
+                .find(|e| {
+                    !e.entity_type
+                        .as_deref()
+                        .is_some_and(|t| t.eq_ignore_ascii_case("Time"))
+                })
+                .map(|e| e.name.as_str()),
+        }
+    }
 }
 
-/// Build the file path for a new memory: `memories/YYYY/MM/YYYYMMDD_HHMMSS_<slug>.md`
-pub fn memory_file_path(memories_dir: &Path, content: &str) -> PathBuf {
-    let now = Utc::now();
-    let slug = slug_from_content(content);
-    let filename = format!(
-        "{}_{}_{}.md",
-        now.format("%Y%m%d"),
-        now.format("%H%M%S"),
-        if slug.is_empty() { "untitled" } else { slug.as_str() }
-    );
-    memories_dir
-        .join(now.format("%Y").to_string())
-        .join(now.format("%m").to_string())
-        .join(filename)
+/// Parse a `created` value as written by Kraph (`YYYY-MM-DD HH:MM:SS`), RFC 3339, or a bare date.
+fn parse_timestamp(value: &str) -> Option<NaiveDateTime> {
+    let value = value.trim();
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S")
+        .ok()
+        .or_else(|| {
+            chrono::DateTime::parse_from_rfc3339(value)
+                .ok()
+                .map(|d| d.naive_utc())
+        })
+        .or_else(|| {
+            chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .ok()
+                .and_then(|d| d.and_hms_opt(0, 0, 0))
+        })
 }
 
-/// Write a memory to a new Markdown file.
+/// Render a path template relative to the memories folder.
+///
+/// Placeholders: `{year}`, `{month}`, `{day}`, `{hour}`, `{minute}`, `{second}`,
+/// `{date}` (`YYYY-MM-DD`), `{time}` (`HHMMSS`), `{slug}` / `{slug:N}` (first line,
+/// 30 or N characters), `{tag}` (first tag), `{tags}` (all tags joined by `-`) and
+/// `{entity}` (primary entity). `/` separates folders; `.md` is appended when missing.
+pub fn render_path_template(template: &str, ctx: &PathContext) -> Result<PathBuf, String> {
+    let mut rendered = String::new();
+    let mut rest = template.trim();
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .map(|i| start + i)
+            .ok_or_else(|| format!("Unclosed placeholder in path template: {}", template))?;
+        let placeholder = &rest[start + 1..end];
+        let (name, arg) = match placeholder.split_once(':') {
+            Some((name, arg)) => (name, Some(arg)),
+            None => (placeholder, None),
+        };
+        let value = match (name, arg) {
+            ("year", None) => ctx.created.format("%Y").to_string(),
+            ("month", None) => ctx.created.format("%m").to_string(),
+            ("day", None) => ctx.created.format("%d").to_string(),
+            ("hour", None) => ctx.created.format("%H").to_string(),
+            ("minute", None) => ctx.created.format("%M").to_string(),
+            ("second", None) => ctx.created.format("%S").to_string(),
+            ("date", None) => ctx.created.format("%Y-%m-%d").to_string(),
+            ("time", None) => ctx.created.format("%H%M%S").to_string(),
+            ("slug", arg) => {
+                let max_chars = match arg {
+                    Some(n) => n
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|n| *n > 0)
+                        .ok_or_else(|| format!("Invalid slug length in path template: {}", n))?,
+                    None => DEFAULT_SLUG_CHARS,
+                };
+                slug_from_content(ctx.content, max_chars)
+            }
+            ("tag", None) => ctx
+                .tags
+                .first()
+                .cloned()
+                .unwrap_or_else(|| "untagged".to_string()),
+            ("tags", None) if ctx.tags.is_empty() => "untagged".to_string(),
+            ("tags", None) => ctx.tags.join("-"),
+            ("entity", None) => ctx.primary_entity.unwrap_or("unsorted").to_string(),
+            _ => return Err(format!("Unknown placeholder in path template: {{{}}}", placeholder)),
+        };
+        let value = sanitize_component(&value);
+        rendered.push_str(if value.is_empty() { "untitled" } else { &value });
+        rest = &rest[end + 1..];
+    }
+    rendered.push_str(rest);
+
+    let mut path = PathBuf::new();
+    for part in rendered.split(['/', '\\']) {
+        let part = part.trim();
+        if part.is_empty() || part == "." {
+            continue;
+        }
+        if part == ".." {
+            return Err("Path template must stay inside the memories folder".to_string());
+        }
+        path.push(part);
+    }
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .ok_or_else(|| "Path template does not produce a file name".to_string())?;
+    if !file_name.to_lowercase().ends_with(".md") {
+        path.set_file_name(format!("{}.md", file_name));
+    }
+    Ok(path)
+}
+
+/// Check that a template renders to a usable path.
+pub fn validate_path_template(template: &str) -> Result<(), String> {
+    let ctx = PathContext {
+        created: Utc::now().naive_utc(),
+        content: "Example",
+        tags: &[],
+        primary_entity: None,
+    };
+    render_path_template(template, &ctx).map(|_| ())
+}
+
+/// Build the file path for a memory from the library's path template
+/// (`DEFAULT_PATH_TEMPLATE` gives `memories/YYYY/MM/YYYYMMDD_HHMMSS_<slug>.md`).
+pub fn memory_file_path(
+    memories_dir: &Path,
+    template: &str,
+    ctx: &PathContext,
+) -> Result<PathBuf, String> {
+    Ok(memories_dir.join(render_path_template(template, ctx)?))
+}
+
+/// Claim a path that no other memory uses, starting from `base` and falling back to
+/// `<stem>_2.md`, `<stem>_3.md`, … The path is reserved with an empty placeholder file
+/// (`create_new`), so two saves in the same second can't both pick it.
+fn reserve_unique_path(base: &Path) -> Result<PathBuf, String> {
+    let stem = base
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "untitled".to_string());
+    for n in 1..=MAX_PATH_ATTEMPTS {
+        let candidate = if n == 1 {
+            base.to_path_buf()
+        } else {
+            base.with_file_name(format!("{}_{}.md", stem, n))
+        };
+        match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&candidate)
+        {
+            Ok(_) => return Ok(candidate),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(format!("Failed to create {:?}: {}", candidate, e)),
+        }
+    }
+    Err(format!("No free file name for {:?}", base))
+}
+
+/// Whether `path` is `target` or one of the numbered variants `reserve_unique_path` gives it.
+pub fn is_path_variant_of(path: &Path, target: &Path) -> bool {
+    if path == target {
+        return true;
+    }
+    let (Some(stem), Some(target_stem)) = (path.file_stem(), target.file_stem()) else {
+        return false;
+    };
+    path.parent() == target.parent()
+        && path.extension() == target.extension()
+        && stem
+            .to_string_lossy()
+            .strip_prefix(&format!("{}_", target_stem.to_string_lossy()))
+            .is_some_and(|n| n.parse::<usize>().is_ok())
+}
+
+/// Write a memory to a new Markdown file named by `template`. An empty `created` is filled
+/// with the current time. Never overwrites an existing file: a numeric suffix is added when
+/// the name is taken. `key` is the library's key when it is encrypted.
 pub fn write_memory(
     memories_dir: &Path,
+    template: &str,
     content: &str,
-    tags: Option<&[String]>,
-    entities: Option<&[String]>,
+    frontmatter: &MdFrontmatter,
+    key: Option<&LibraryKey>,
 ) -> Result<PathBuf, String> {
-    let path = memory_file_path(memories_dir, content);
+    let mut frontmatter = frontmatter.clone();
+    if frontmatter.created.trim().is_empty() {
+        frontmatter.created = now_timestamp();
+    }
+    let base = memory_file_path(
+        memories_dir,
+        template,
+        &PathContext::from_frontmatter(&frontmatter, content),
+    )?;
+    if let Some(parent) = base.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let rendered = render_memory(&frontmatter, content)?;
+    let path = reserve_unique_path(&base)?;
+    if let Err(e) = write_atomic(&path, &rendered, key) {
+        let _ = fs::remove_file(&path);
+        return Err(e);
+    }
+    Ok(path)
+}
+
+/// Move a memory file to `target`, adding a
+/// numeric suffix if another file already has that name. Returns the final path.
+/// The source folder is left in place so the move can still be undone; call
+/// [`prune_empty_dirs`] once it is final.
+pub fn move_memory_file(from: &Path, target: &Path) -> Result<PathBuf, String> {
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let to = reserve_unique_path(target)?;
+    if let Err(e) = fs::rename(from, &to) {
+        let _ = fs::remove_file(&to);
+        return Err(format!("Failed to move {:?} to {:?}: {}", from, to, e));
+    }
+    Ok(to)
+}
+
+/// Remove the folders above `path` that are now empty, stopping at `memories_dir`.
+pub fn prune_empty_dirs(memories_dir: &Path, path: &Path) {
+    let mut dir = path.parent();
+    while let Some(d) = dir {
+        if d == memories_dir || !d.starts_with(memories_dir) || fs::remove_dir(d).is_err() {
+            break;
+        }
+        dir = d.parent();
+    }
+}
+
+/// Re-read a memory file, let `update` change its frontmatter, and write it back with the same body.
+pub fn update_frontmatter<F>(path: &Path, key: Option<&LibraryKey>, update: F) -> Result<(), String>
+where
+    F: FnOnce(&mut MdFrontmatter),
+{
+    let mut record = read_memory(path, key)?;
+    update(&mut record.frontmatter);
+    write_atomic(
+        path,
+        &render_memory(&record.frontmatter, &record.content)?,
+        key,
+    )
+}
+
+/// Rewrite an existing memory file in place with a new body. The file keeps its path and
+/// `created` timestamp, `updated` is set to now, and `update` can adjust the rest of the
+/// frontmatter. A file that has gone missing is recreated at the same path.
+pub fn rewrite_memory<F>(
+    path: &Path,
+    content: &str,
+    key: Option<&LibraryKey>,
+    update: F,
+) -> Result<(), String>
+where
+    F: FnOnce(&mut MdFrontmatter),
+{
+    let mut frontmatter = if path.exists() {
+        read_memory(path, key)?.frontmatter
+    } else {
+        MdFrontmatter::default()
+    };
+    let created = std::mem::take(&mut frontmatter.created);
+    update(&mut frontmatter);
+    frontmatter.created = if created.trim().is_empty() {
+        now_timestamp()
+    } else {
+        created
+    };
+    frontmatter.updated = Some(now_timestamp());
     if let Some(parent) = path.parent() {
         fs::create_dir_all(parent).map_err(|e| e.to_string())?;
     }
-    let created = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
-    let tags_str = tags.map(|t| serde_json::to_string(t).unwrap_or_default());
-    let entities_str = entities.map(|e| serde_json::to_string(e).unwrap_or_default());
+    write_atomic(path, &render_memory(&frontmatter, content)?, key)
+}
 
-    let mut front = String::from("---\n");
-    front.push_str(&format!("created: {}\n", created));
-    if let Some(ref t) = tags_str {
-        front.push_str(&format!("tags: {}\n", t));
+/// Read a file written by this module, decrypting it with `key` if it is encrypted.
+pub fn read_file_text(path: &Path, key: Option<&LibraryKey>) -> Result<String, String> {
+    decode_text_with(key, fs::read_to_string(path).map_err(|e| e.to_string())?)
+}
+
+/// Atomically replace a file's contents, e.g. to restore it after a failed save.
+pub fn replace_file_contents(
+    path: &Path,
+    contents: &str,
+    key: Option<&LibraryKey>,
+) -> Result<(), String> {
+    write_atomic(path, contents, key)
+}
+
+/// Replace `path` atomically: write and fsync a sibling temp file, then rename it over the
+/// target, so readers (and the file watcher) never see a half-written file. Contents are
+/// encrypted when a `key` is given.
+fn write_atomic(path: &Path, contents: &str, key: Option<&LibraryKey>) -> Result<(), String> {
+    let contents = encode_text_with(key, contents)?;
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| format!("Invalid memory file path: {:?}", path))?;
+    let tmp = path.with_file_name(format!(".{}.tmp", file_name.to_string_lossy()));
+    let written = fs::File::create(&tmp).and_then(|mut file| {
+        file.write_all(contents.as_bytes())?;
+        file.sync_all()
+    });
+    if let Err(e) = written {
+        let _ = fs::remove_file(&tmp);
+        return Err(format!("Failed to write {:?}: {}", tmp, e));
     }
-    if let Some(ref e) = entities_str {
-        front.push_str(&format!("entities: {}\n", e));
+    fs::rename(&tmp, path).map_err(|e| {
+        let _ = fs::remove_file(&tmp);
+        format!("Failed to replace {:?}: {}", path, e)
+    })?;
+    // Persist the rename itself. Directories can't be opened on every platform, so this is best effort.
+    if let Some(dir) = path.parent().and_then(|p| fs::File::open(p).ok()) {
+        let _ = dir.sync_all();
     }
-    front.push_str("---\n\n");
-    let body = content.trim();
-    let full = format!("{}{}", front, body);
+    Ok(())
+}
 
-    fs::write(&path, full).map_err(|e| e.to_string())?;
-    Ok(path)
+/// Serialize frontmatter and body into the on-disk Markdown representation.
+fn render_memory(frontmatter: &MdFrontmatter, content: &str) -> Result<String, String> {
+    let yaml = serde_yaml::to_string(&to_mapping(frontmatter)?)
+        .map_err(|e| format!("Failed to serialize frontmatter: {e}"))?;
+    Ok(format!("---\n{}---\n\n{}", yaml, content.trim()))
+}
+
+fn to_yaml<T: Serialize>(value: &T) -> Result<Value, String> {
+    serde_yaml::to_value(value).map_err(|e| format!("Failed to serialize frontmatter: {e}"))
 }
 
-/// Parse YAML frontmatter using a simple line-by-line parser (no external YAML crate needed).
-fn parse_frontmatter(s: &str) -> Option<MdFrontmatter> {
-    let s = s.trim();
-    if !s.starts_with("---") {
-        return None;
-    }
-    let rest = s.strip_prefix("---")?.trim();
-    let end = rest.find("\n---")?;
-    let block = rest.get(..end)?;
-    let mut created = String::new();
-    let mut tags: Option<Vec<String>> = None;
-    let mut entities: Option<Vec<String>> = None;
-    for line in block.lines() {
-        let line = line.trim();
-        if let Some(stripped) = line.strip_prefix("created:") {
-            created = stripped.trim().to_string();
-        } else if let Some(stripped) = line.strip_prefix("tags:") {
-            let t = stripped.trim();
-            if let Ok(v) = serde_json::from_str(t) {
-                tags = Some(v);
+/// Merge the managed fields back into the original mapping. Existing keys keep their
+/// position, new keys are appended, and managed keys that are now `None` are removed.
+fn to_mapping(fm: &MdFrontmatter) -> Result<Mapping, String> {
+    let mut managed: HashMap<&str, Option<Value>> = HashMap::from([
+        ("id", fm.id.map(Value::from)),
+        ("created", Some(Value::String(fm.created.clone()))),
+        ("updated", fm.updated.clone().map(Value::String)),
+        ("source", fm.source.clone().map(Value::String)),
+        ("tags", fm.tags.as_ref().map(to_yaml).transpose()?),
+        ("entities", fm.entities.as_ref().map(to_yaml).transpose()?),
+        ("relations", fm.relations.as_ref().map(to_yaml).transpose()?),
+        ("aliases", fm.aliases.as_ref().map(to_yaml).transpose()?),
+    ]);
+
+    let mut map = Mapping::new();
+    // The ID is assigned after the file is first written; keep it at the top when it appears.
+    if !fm.raw.contains_key("id") {
+        if let Some(Some(id)) = managed.remove("id") {
+            map.insert(Value::from("id"), id);
+        }
+    }
+    for (key, value) in fm.raw.iter() {
+        match key.as_str().and_then(|k| managed.remove(k)) {
+            Some(Some(v)) => {
+                map.insert(key.clone(), v);
             }
-        } else if let Some(stripped) = line.strip_prefix("entities:") {
-            let e = stripped.trim();
-            if let Ok(v) = serde_json::from_str(e) {
-                entities = Some(v);
+            Some(None) => {}
+            None => {
+                map.insert(key.clone(), value.clone());
             }
         }
     }
-    if created.is_empty() {
-        created = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    for key in KNOWN_KEYS {
+        if let Some(Some(v)) = managed.remove(key) {
+            map.insert(Value::from(key), v);
+        }
+    }
+    Ok(map)
+}
+
+fn yaml_scalar_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.trim().to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
     }
-    Some(MdFrontmatter {
+    .filter(|s| !s.is_empty())
+}
+
+/// Accept both YAML lists and comma-separated strings (`tags: a, b`).
+fn yaml_string_list(value: &Value) -> Option<Vec<String>> {
+    match value {
+        Value::Sequence(items) => Some(items.iter().filter_map(yaml_scalar_string).collect()),
+        Value::String(s) => Some(
+            s.split(',')
+                .map(|x| x.trim().to_string())
+                .filter(|x| !x.is_empty())
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+/// Entities may be plain names (older files) or `{name, type, attributes}` mappings.
+fn yaml_entity_list(value: &Value) -> Option<Vec<MdEntity>> {
+    let items = value.as_sequence()?;
+    Some(
+        items
+            .iter()
+            .filter_map(|item| match item {
+                Value::Mapping(_) => serde_yaml::from_value::<MdEntity>(item.clone()).ok(),
+                other => yaml_scalar_string(other).map(|name| MdEntity {
+                    name,
+                    entity_type: None,
+                    attributes: None,
+                }),
+            })
+            .collect(),
+    )
+}
+
+fn yaml_list<T: serde::de::DeserializeOwned>(value: &Value) -> Option<Vec<T>> {
+    let items = value.as_sequence()?;
+    Some(
+        items
+            .iter()
+            .filter_map(|item| serde_yaml::from_value::<T>(item.clone()).ok())
+            .collect(),
+    )
+}
+
+/// Parse a YAML frontmatter block (without the `---` fences). Keys Kraph doesn't manage
+/// are kept verbatim for the next write.
+pub fn parse_frontmatter(block: &str) -> Result<MdFrontmatter, String> {
+    let raw: Mapping = if block.trim().is_empty() {
+        Mapping::new()
+    } else {
+        serde_yaml::from_str(block).map_err(|e| format!("Invalid YAML frontmatter: {e}"))?
+    };
+    let created = raw
+        .get("created")
+        .and_then(yaml_scalar_string)
+        .unwrap_or_else(now_timestamp);
+    Ok(MdFrontmatter {
+        id: raw.get("id").and_then(|v| v.as_i64()),
         created,
-        tags,
-        entities,
+        updated: raw.get("updated").and_then(yaml_scalar_string),
+        source: raw.get("source").and_then(yaml_scalar_string),
+        tags: raw.get("tags").and_then(yaml_string_list),
+        entities: raw.get("entities").and_then(yaml_entity_list),
+        relations: raw.get("relations").and_then(yaml_list),
+        aliases: raw.get("aliases").and_then(yaml_list),
+        raw,
     })
 }
 
+/// Split a Markdown document into its frontmatter block and body.
+/// The block is delimited by `---` lines at the very start of the file.
+fn split_frontmatter(raw: &str) -> Option<(&str, &str)> {
+    let rest = raw.trim_start_matches('\u{feff}');
+    let rest = rest
+        .strip_prefix("---\r\n")
+        .or_else(|| rest.strip_prefix("---\n"))?;
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        let trimmed = line.trim_end();
+        if trimmed == "---" || trimmed == "..." {
+            return Some((&rest[..offset], &rest[offset + line.len()..]));
+        }
+        offset += line.len();
+    }
+    None
+}
+
 /// Read and parse a Markdown memory file.
-pub fn read_memory(path: &Path) -> Result<MdRecord, String> {
-    let raw = fs::read_to_string(path).map_err(|e| e.to_string())?;
-    let (frontmatter, content) = if raw.trim_start().starts_with("---") {
-        let rest = raw.trim_start().strip_prefix("---").ok_or("invalid frontmatter")?;
-        let end = rest.find("\n---").ok_or("invalid frontmatter")?;
-        let block = rest.get(..end).ok_or("invalid frontmatter")?;
-        let fm = parse_frontmatter(&format!("---\n{}\n---", block)).ok_or("parse frontmatter")?;
-        let body = rest.get((end + 4)..).unwrap_or("").trim();
-        (fm, body.to_string())
-    } else {
-        let fm = MdFrontmatter {
-            created: Utc::now().format("%Y-%m-%d %H:%M:%S").to_string(),
-            tags: None,
-            entities: None,
-        };
-        (fm, raw)
+pub fn read_memory(path: &Path, key: Option<&LibraryKey>) -> Result<MdRecord, String> {
+    let raw = read_file_text(path, key)?;
+    let (frontmatter, content) = match split_frontmatter(&raw) {
+        Some((block, body)) => (parse_frontmatter(block)?, body.trim().to_string()),
+        None => (
+            MdFrontmatter {
+                created: now_timestamp(),
+                ..Default::default()
+            },
+            raw.trim().to_string(),
+        ),
     };
     Ok(MdRecord {
         frontmatter,
@@ -0,0 +1,612 @@
+//! Graph export for external tools: GraphML and GEXF (Gephi, yEd), CSV node/edge lists, Cypher
+//! scripts (Neo4j), and Turtle / JSON-LD via `rdf_export`.
+//!
+//! Every format carries the same columns: entity name, type, aliases, one column per attribute
+//! key and, optionally, the IDs of the memories that mention the entity. Relation strength is the
+//! edge weight. An edge's supporting memories are those that mention both of its entities, since
+//! relations are not linked to memories directly.
+
+use crate::database::{get_graph_data, list_entity_aliases, list_memory_entity_links, GraphData};
+use crate::rdf_export::render_rdf;
+use rusqlite::Connection;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Column names used by the fixed fields; attribute keys that clash get an `attr_` prefix.
+const RESERVED_COLUMNS: [&str; 6] = ["id", "kraph_id", "name", "type", "aliases", "memory_ids"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphExportFormat {
+    GraphMl,
+    Gexf,
+    Csv,
+    Cypher,
+    Turtle,
+    JsonLd,
+}
+
+impl GraphExportFormat {
+    pub fn parse(format: &str) -> Result<Self, String> {
+        match format.trim().to_lowercase().as_str() {
+            "graphml" => Ok(Self::GraphMl),
+            "gexf" => Ok(Self::Gexf),
+            "csv" => Ok(Self::Csv),
+            "cypher" => Ok(Self::Cypher),
+            "turtle" | "ttl" => Ok(Self::Turtle),
+            "jsonld" | "json-ld" => Ok(Self::JsonLd),
+            other => Err(format!(
+                "Unknown export format '{}'. Use graphml, gexf, csv, cypher, turtle or jsonld.",
+                other
+            )),
+        }
+    }
+}
+
+struct ExportNode {
+    id: String,
+    name: String,
+    node_type: String,
+    aliases: Vec<String>,
+    /// Attribute values by column name.
+    attributes: BTreeMap<String, Value>,
+    memory_ids: Vec<i64>,
+}
+
+struct ExportEdge {
+    source: String,
+    target: String,
+    relation: String,
+    strength: i32,
+    memory_ids: Vec<i64>,
+}
+
+struct ExportGraph {
+    nodes: Vec<ExportNode>,
+    edges: Vec<ExportEdge>,
+    /// Attribute columns in use, sorted.
+    attribute_columns: Vec<String>,
+    include_memory_ids: bool,
+}
+
+fn attribute_column(key: &str) -> String {
+    if RESERVED_COLUMNS.contains(&key.to_lowercase().as_str()) {
+        format!("attr_{}", key)
+    } else {
+        key.to_string()
+    }
+}
+
+fn build_export_graph(
+    data: GraphData,
+    aliases: Vec<(i64, String)>,
+    memory_links: Vec<(i64, i64)>,
+    include_memory_ids: bool,
+) -> ExportGraph {
+    let mut aliases_by_entity: HashMap<String, Vec<String>> = HashMap::new();
+    for (entity_id, alias) in aliases {
+        aliases_by_entity
+            .entry(entity_id.to_string())
+            .or_default()
+            .push(alias);
+    }
+    let mut memories_by_entity: HashMap<String, BTreeSet<i64>> = HashMap::new();
+    for (memory_id, entity_id) in memory_links {
+        memories_by_entity
+            .entry(entity_id.to_string())
+            .or_default()
+            .insert(memory_id);
+    }
+
+    let mut columns = BTreeSet::new();
+    let nodes: Vec<ExportNode> = data
+        .nodes
+        .into_iter()
+        .map(|n| {
+            let attributes: BTreeMap<String, Value> = n
+                .attributes
+                .as_deref()
+                .and_then(|a| serde_json::from_str::<Value>(a).ok())
+                .and_then(|v| v.as_object().cloned())
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|(_, v)| !v.is_null())
+                .map(|(k, v)| (attribute_column(&k), v))
+                .collect();
+            columns.extend(attributes.keys().cloned());
+            let mut aliases = aliases_by_entity.remove(&n.id).unwrap_or_default();
+            aliases.sort();
+            ExportNode {
+                memory_ids: memories_by_entity
+                    .get(&n.id)
+                    .map(|ids| ids.iter().copied().collect())
+                    .unwrap_or_default(),
+                id: n.id,
+                name: n.name,
+                node_type: n.node_type,
+                aliases,
+                attributes,
+            }
+        })
+        .collect();
+
+    let edges = data
+        .links
+        .into_iter()
+        .map(|l| {
+            let memory_ids = match (
+                memories_by_entity.get(&l.source),
+                memories_by_entity.get(&l.target),
+            ) {
+                (Some(a), Some(b)) => a.intersection(b).copied().collect(),
+                _ => Vec::new(),
+            };
+            ExportEdge {
+                source: l.source,
+                target: l.target,
+                relation: l.relation,
+                strength: l.strength,
+                memory_ids,
+            }
+        })
+        .collect();
+
+    ExportGraph {
+        nodes,
+        edges,
+        attribute_columns: columns.into_iter().collect(),
+        include_memory_ids,
+    }
+}
+
+/// Attribute value as text; strings are used as-is, other JSON values are serialized.
+pub(crate) fn value_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn join_ids(ids: &[i64]) -> String {
+    ids.iter()
+        .map(|id| id.to_string())
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+fn xml_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            // Control characters other than tab/newline are not allowed in XML 1.0.
+            c if c.is_control() && c != '\t' && c != '\n' && c != '\r' => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn render_graphml(graph: &ExportGraph) -> String {
+    let mut out = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n",
+    );
+    let mut node_keys: Vec<(String, &str, &str)> = vec![
+        ("name".to_string(), "label", "string"),
+        ("type".to_string(), "type", "string"),
+        ("aliases".to_string(), "aliases", "string"),
+    ];
+    if graph.include_memory_ids {
+        node_keys.push(("memory_ids".to_string(), "memory_ids", "string"));
+    }
+    for (key, name, ty) in &node_keys {
+        out.push_str(&format!(
+            "  <key id=\"n_{}\" for=\"node\" attr.name=\"{}\" attr.type=\"{}\"/>\n",
+            key, name, ty
+        ));
+    }
+    for (i, column) in graph.attribute_columns.iter().enumerate() {
+        out.push_str(&format!(
+            "  <key id=\"a{}\" for=\"node\" attr.name=\"{}\" attr.type=\"string\"/>\n",
+            i,
+            xml_escape(column)
+        ));
+    }
+    out.push_str(
+        "  <key id=\"e_relation\" for=\"edge\" attr.name=\"label\" attr.type=\"string\"/>\n",
+    );
+    out.push_str(
+        "  <key id=\"e_strength\" for=\"edge\" attr.name=\"weight\" attr.type=\"int\"/>\n",
+    );
+    if graph.include_memory_ids {
+        out.push_str(
+            "  <key id=\"e_memory_ids\" for=\"edge\" attr.name=\"memory_ids\" attr.type=\"string\"/>\n",
+        );
+    }
+    out.push_str("  <graph id=\"kraph\" edgedefault=\"directed\">\n");
+
+    for node in &graph.nodes {
+        out.push_str(&format!("    <node id=\"n{}\">\n", xml_escape(&node.id)));
+        let mut data = vec![
+            ("n_name".to_string(), node.name.clone()),
+            ("n_type".to_string(), node.node_type.clone()),
+        ];
+        if !node.aliases.is_empty() {
+            data.push(("n_aliases".to_string(), node.aliases.join(";")));
+        }
+        if graph.include_memory_ids && !node.memory_ids.is_empty() {
+            data.push(("n_memory_ids".to_string(), join_ids(&node.memory_ids)));
+        }
+        for (i, column) in graph.attribute_columns.iter().enumerate() {
+            if let Some(value) = node.attributes.get(column) {
+                data.push((format!("a{}", i), value_text(value)));
+            }
+        }
+        for (key, value) in data {
+            out.push_str(&format!(
+                "      <data key=\"{}\">{}</data>\n",
+                key,
+                xml_escape(&value)
+            ));
+        }
+        out.push_str("    </node>\n");
+    }
+    for (i, edge) in graph.edges.iter().enumerate() {
+        out.push_str(&format!(
+            "    <edge id=\"e{}\" source=\"n{}\" target=\"n{}\">\n",
+            i,
+            xml_escape(&edge.source),
+            xml_escape(&edge.target)
+        ));
+        out.push_str(&format!(
+            "      <data key=\"e_relation\">{}</data>\n",
+            xml_escape(&edge.relation)
+        ));
+        out.push_str(&format!(
+            "      <data key=\"e_strength\">{}</data>\n",
+            edge.strength
+        ));
+        if graph.include_memory_ids && !edge.memory_ids.is_empty() {
+            out.push_str(&format!(
+                "      <data key=\"e_memory_ids\">{}</data>\n",
+                join_ids(&edge.memory_ids)
+            ));
+        }
+        out.push_str("    </edge>\n");
+    }
+    out.push_str("  </graph>\n</graphml>\n");
+    out
+}
+
+fn render_gexf(graph: &ExportGraph) -> String {
+    let mut out = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <gexf xmlns=\"http://gexf.net/1.3\" version=\"1.3\">\n\
+         \x20 <meta lastmodifieddate=\"{}\">\n\
+         \x20   <creator>Kraph</creator>\n\
+         \x20 </meta>\n\
+         \x20 <graph defaultedgetype=\"directed\" mode=\"static\">\n",
+        chrono::Local::now().format("%Y-%m-%d")
+    );
+    let mut node_columns = vec!["type".to_string(), "aliases".to_string()];
+    if graph.include_memory_ids {
+        node_columns.push("memory_ids".to_string());
+    }
+    node_columns.extend(graph.attribute_columns.iter().cloned());
+    out.push_str("    <attributes class=\"node\">\n");
+    for (i, column) in node_columns.iter().enumerate() {
+        out.push_str(&format!(
+            "      <attribute id=\"{}\" title=\"{}\" type=\"string\"/>\n",
+            i,
+            xml_escape(column)
+        ));
+    }
+    out.push_str("    </attributes>\n");
+    if graph.include_memory_ids {
+        out.push_str(
+            "    <attributes class=\"edge\">\n\
+             \x20     <attribute id=\"0\" title=\"memory_ids\" type=\"string\"/>\n\
+             \x20   </attributes>\n",
+        );
+    }
+
+    out.push_str("    <nodes>\n");
+    for node in &graph.nodes {
+        let mut values: Vec<(usize, String)> = vec![(0, node.node_type.clone())];
+        if !node.aliases.is_empty() {
+            values.push((1, node.aliases.join(";")));
+        }
+        if graph.include_memory_ids && !node.memory_ids.is_empty() {
+            values.push((2, join_ids(&node.memory_ids)));
+        }
+        let offset = if graph.include_memory_ids { 3 } else { 2 };
+        for (i, column) in graph.attribute_columns.iter().enumerate() {
+            if let Some(value) = node.attributes.get(column) {
+                values.push((offset + i, value_text(value)));
+            }
+        }
+        out.push_str(&format!(
+            "      <node id=\"{}\" label=\"{}\">\n        <attvalues>\n",
+            xml_escape(&node.id),
+            xml_escape(&node.name)
+        ));
+        for (i, value) in values {
+            out.push_str(&format!(
+                "          <attvalue for=\"{}\" value=\"{}\"/>\n",
+                i,
+                xml_escape(&value)
+            ));
+        }
+        out.push_str("        </attvalues>\n      </node>\n");
+    }
+    out.push_str("    </nodes>\n    <edges>\n");
+    for (i, edge) in graph.edges.iter().enumerate() {
+        out.push_str(&format!(
+            "      <edge id=\"{}\" source=\"{}\" target=\"{}\" label=\"{}\" weight=\"{}\"",
+            i,
+            xml_escape(&edge.source),
+            xml_escape(&edge.target),
+            xml_escape(&edge.relation),
+            edge.strength
+        ));
+        if graph.include_memory_ids && !edge.memory_ids.is_empty() {
+            out.push_str(&format!(
+                ">\n        <attvalues>\n          <attvalue for=\"0\" value=\"{}\"/>\n        \
+                 </attvalues>\n      </edge>\n",
+                join_ids(&edge.memory_ids)
+            ));
+        } else {
+            out.push_str("/>\n");
+        }
+    }
+    out.push_str("    </edges>\n  </graph>\n</gexf>\n");
+    out
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+fn csv_row(fields: &[String]) -> String {
+    let mut row = fields
+        .iter()
+        .map(|f| csv_field(f))
+        .collect::<Vec<_>>()
+        .join(",");
+    row.push_str("\r\n");
+    row
+}
+
+/// Node and edge lists with Gephi's column names (`Id`, `Label`, `Source`, `Target`, `Weight`).
+fn render_csv(graph: &ExportGraph) -> (String, String) {
+    let mut header: Vec<String> = ["Id", "Label", "type", "aliases"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    if graph.include_memory_ids {
+        header.push("memory_ids".to_string());
+    }
+    header.extend(graph.attribute_columns.iter().cloned());
+    let mut nodes = csv_row(&header);
+    for node in &graph.nodes {
+        let mut row = vec![
+            node.id.clone(),
+            node.name.clone(),
+            node.node_type.clone(),
+            node.aliases.join(";"),
+        ];
+        if graph.include_memory_ids {
+            row.push(join_ids(&node.memory_ids));
+        }
+        for column in &graph.attribute_columns {
+            row.push(
+                node.attributes
+                    .get(column)
+                    .map(value_text)
+                    .unwrap_or_default(),
+            );
+        }
+        nodes.push_str(&csv_row(&row));
+    }
+
+    let mut header: Vec<String> = ["Source", "Target", "Type", "Label", "Weight"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    if graph.include_memory_ids {
+        header.push("memory_ids".to_string());
+    }
+    let mut edges = csv_row(&header);
+    for edge in &graph.edges {
+        let mut row = vec![
+            edge.source.clone(),
+            edge.target.clone(),
+            "Directed".to_string(),
+            edge.relation.clone(),
+            edge.strength.to_string(),
+        ];
+        if graph.include_memory_ids {
+            row.push(join_ids(&edge.memory_ids));
+        }
+        edges.push_str(&csv_row(&row));
+    }
+    (nodes, edges)
+}
+
+fn cypher_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('\'');
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\'' => out.push_str("\\'"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c => out.push(c),
+        }
+    }
+    out.push('\'');
+    out
+}
+
+/// Backtick-quoted identifier, so labels and relationship types can contain any character.
+fn cypher_identifier(s: &str) -> String {
+    let s = if s.trim().is_empty() { "UNKNOWN" } else { s };
+    format!("`{}`", s.replace('`', "``"))
+}
+
+fn cypher_value(value: &Value) -> String {
+    match value {
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => cypher_string(s),
+        other => cypher_string(&other.to_string()),
+    }
+}
+
+fn cypher_list<T>(items: &[T], render: impl Fn(&T) -> String) -> String {
+    format!(
+        "[{}]",
+        items.iter().map(render).collect::<Vec<_>>().join(", ")
+    )
+}
+
+/// A Neo4j script: one `CREATE` per entity (labelled `Entity` plus its type), then one
+/// `MATCH … CREATE` per relation, keyed by the `kraph_id` property.
+fn render_cypher(graph: &ExportGraph) -> String {
+    let mut out = String::from(
+        "// Exported from Kraph\n\
+         CREATE INDEX entity_kraph_id IF NOT EXISTS FOR (n:Entity) ON (n.kraph_id);\n\n",
+    );
+    for node in &graph.nodes {
+        let mut props = vec![
+            format!("kraph_id: {}", node.id),
+            format!("name: {}", cypher_string(&node.name)),
+            format!("type: {}", cypher_string(&node.node_type)),
+        ];
+        if !node.aliases.is_empty() {
+            props.push(format!(
+                "aliases: {}",
+                cypher_list(&node.aliases, |a| cypher_string(a))
+            ));
+        }
+        if graph.include_memory_ids {
+            props.push(format!(
+                "memory_ids: {}",
+                cypher_list(&node.memory_ids, |id| id.to_string())
+            ));
+        }
+        for (column, value) in &node.attributes {
+            props.push(format!(
+                "{}: {}",
+                cypher_identifier(column),
+                cypher_value(value)
+            ));
+        }
+        out.push_str(&format!(
+            "CREATE (:Entity:{} {{{}}});\n",
+            cypher_identifier(&node.node_type),
+            props.join(", ")
+        ));
+    }
+    out.push('\n');
+    for edge in &graph.edges {
+        let mut props = vec![format!("strength: {}", edge.strength)];
+        if graph.include_memory_ids {
+            props.push(format!(
+                "memory_ids: {}",
+                cypher_list(&edge.memory_ids, |id| id.to_string())
+            ));
+        }
+        out.push_str(&format!(
+            "MATCH (a:Entity {{kraph_id: {}}}), (b:Entity {{kraph_id: {}}}) \
+             CREATE (a)-[:{} {{{}}}]->(b);\n",
+            edge.source,
+            edge.target,
+            cypher_identifier(&edge.relation),
+            props.join(", ")
+        ));
+    }
+    out
+}
+
+/// `graph.csv` becomes `graph.nodes.csv` and `graph.edges.csv`.
+fn csv_paths(path: &Path) -> (PathBuf, PathBuf) {
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "graph".to_string());
+    (
+        path.with_file_name(format!("{}.nodes.csv", stem)),
+        path.with_file_name(format!("{}.edges.csv", stem)),
+    )
+}
+
+fn load_export_graph(conn: &Connection, include_memory_ids: bool) -> Result<ExportGraph, String> {
+    let data = get_graph_data(conn).map_err(|e| e.to_string())?;
+    let aliases = list_entity_aliases(conn).map_err(|e| e.to_string())?;
+    let memory_links = if include_memory_ids {
+        list_memory_entity_links(conn).map_err(|e| e.to_string())?
+    } else {
+        Vec::new()
+    };
+    Ok(build_export_graph(
+        data,
+        aliases,
+        memory_links,
+        include_memory_ids,
+    ))
+}
+
+/// Write the library's graph to `path` in `format`. CSV produces two files next to `path`.
+/// `base_iri` is only used by the RDF formats, which always include memories.
+/// Returns the files written.
+pub fn export_graph(
+    conn: &Connection,
+    format: GraphExportFormat,
+    path: &Path,
+    include_memory_ids: bool,
+    base_iri: &str,
+) -> Result<Vec<PathBuf>, String> {
+    let single = |content: String| vec![(path.to_path_buf(), content)];
+    let files = match format {
+        GraphExportFormat::GraphMl => single(render_graphml(&load_export_graph(
+            conn,
+            include_memory_ids,
+        )?)),
+        GraphExportFormat::Gexf => {
+            single(render_gexf(&load_export_graph(conn, include_memory_ids)?))
+        }
+        GraphExportFormat::Cypher => {
+            single(render_cypher(&load_export_graph(conn, include_memory_ids)?))
+        }
+        GraphExportFormat::Csv => {
+            let (nodes, edges) = render_csv(&load_export_graph(conn, include_memory_ids)?);
+            let (nodes_path, edges_path) = csv_paths(path);
+            vec![(nodes_path, nodes), (edges_path, edges)]
+        }
+        GraphExportFormat::Turtle => single(render_rdf(conn, base_iri, false)?),
+        GraphExportFormat::JsonLd => single(render_rdf(conn, base_iri, true)?),
+    };
+
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create {:?}: {}", parent, e))?;
+    }
+    let mut written = Vec::new();
+    for (file, content) in files {
+        fs::write(&file, content).map_err(|e| format!("Failed to write {:?}: {}", file, e))?;
+        written.push(file);
+    }
+    Ok(written)
+}
@@ -0,0 +1,635 @@
+//! Bulk import of entities and relations from CSV or JSON node and edge lists.
+//!
+//! Columns are mapped onto entity name, type, aliases and attributes, and onto relation
+//! endpoints, type and strength. Column names that are not mapped explicitly are guessed from
+//! common headers, including the ones `graph_export` writes. Rows are written through the usual
+//! upserts inside one transaction; a dry run performs the same work and rolls it back, so its
+//! report is exact. Imported rows are tagged with their source, which also keeps imported
+//! entities from being pruned when no memory mentions them.
+
+use crate::database::{
+    add_entity_alias, find_entity_id_by_name_or_alias, get_entity_by_id, get_relation_strength,
+    restore_relation, set_entity_source, set_relation_source, upsert_entity,
+};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::Path;
+
+const NAME_COLUMNS: [&str; 4] = ["name", "label", "entity", "title"];
+const TYPE_COLUMNS: [&str; 3] = ["type", "entity_type", "category"];
+const ID_COLUMNS: [&str; 2] = ["id", "kraph_id"];
+const ALIAS_COLUMNS: [&str; 2] = ["aliases", "alias"];
+const SOURCE_COLUMNS: [&str; 3] = ["source", "from", "from_entity"];
+const TARGET_COLUMNS: [&str; 3] = ["target", "to", "to_entity"];
+const RELATION_COLUMNS: [&str; 3] = ["relation", "relation_type", "label"];
+const STRENGTH_COLUMNS: [&str; 2] = ["strength", "weight"];
+/// Columns never turned into attributes (export bookkeeping).
+const IGNORED_COLUMNS: [&str; 1] = ["memory_ids"];
+
+const DEFAULT_ENTITY_TYPE: &str = "Concept";
+const DEFAULT_RELATION: &str = "related_to";
+const DEFAULT_ALIAS_SEPARATOR: &str = ";";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NodeColumnMapping {
+    /// Column that edge lists use to refer to nodes; defaults to the name.
+    pub id: Option<String>,
+    pub name: Option<String>,
+    #[serde(rename = "type")]
+    pub entity_type: Option<String>,
+    /// Type for rows without one.
+    pub default_type: Option<String>,
+    pub aliases: Option<String>,
+    pub alias_separator: Option<String>,
+    /// Columns kept as attributes; `None` keeps every column that is not mapped otherwise.
+    pub attributes: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EdgeColumnMapping {
+    pub source: Option<String>,
+    pub target: Option<String>,
+    pub relation: Option<String>,
+    /// Relation type for rows without one.
+    pub default_relation: Option<String>,
+    pub strength: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GraphImportRequest {
+    /// `.csv` or `.json` node list.
+    pub nodes_path: Option<String>,
+    /// `.csv` or `.json` edge list; may be the same JSON file as `nodes_path`.
+    pub edges_path: Option<String>,
+    pub nodes: NodeColumnMapping,
+    pub edges: EdgeColumnMapping,
+    /// Tag stored on imported rows; defaults to `import:<file name>`.
+    pub source: Option<String>,
+    /// Replace differing attribute values instead of reporting them as conflicts.
+    pub overwrite_attributes: bool,
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphImportRow {
+    /// `entity` or `relation`.
+    pub kind: String,
+    /// 1-based data row in its file.
+    pub row: usize,
+    pub label: String,
+    /// `new`, `updated`, `unchanged`, `conflict` or `error`.
+    pub status: String,
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GraphImportReport {
+    pub dry_run: bool,
+    pub source: String,
+    pub entities_new: usize,
+    pub entities_updated: usize,
+    pub entities_unchanged: usize,
+    pub entities_conflicting: usize,
+    pub relations_new: usize,
+    pub relations_updated: usize,
+    pub relations_unchanged: usize,
+    pub errors: usize,
+    pub rows: Vec<GraphImportRow>,
+    /// Entities written (or that would be), for refreshing derived views.
+    #[serde(skip)]
+    pub touched_entity_ids: Vec<i64>,
+}
+
+impl GraphImportReport {
+    fn push(&mut self, kind: &str, row: usize, label: &str, status: &str, message: Option<String>) {
+        match (kind, status) {
+            ("entity", "new") => self.entities_new += 1,
+            ("entity", "updated") => self.entities_updated += 1,
+            ("entity", "unchanged") => self.entities_unchanged += 1,
+            ("entity", "conflict") => self.entities_conflicting += 1,
+            ("relation", "new") => self.relations_new += 1,
+            ("relation", "updated") => self.relations_updated += 1,
+            ("relation", "unchanged") => self.relations_unchanged += 1,
+            _ => self.errors += 1,
+        }
+        self.rows.push(GraphImportRow {
+            kind: kind.to_string(),
+            row,
+            label: label.to_string(),
+            status: status.to_string(),
+            message,
+        });
+    }
+}
+
+type Row = Map<String, Value>;
+
+/// Minimal RFC 4180 reader: quoted fields, doubled quotes, embedded newlines, CRLF.
+pub fn parse_csv(text: &str) -> Vec<Vec<String>> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    chars.next();
+                }
+                '"' => in_quotes = false,
+                c => field.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' if field.is_empty() => in_quotes = true,
+            ',' => row.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' | '\r' => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            c => field.push(c),
+        }
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    rows.retain(|r| r.iter().any(|f| !f.trim().is_empty()));
+    rows
+}
+
+/// Read a node or edge list. JSON files hold an array of objects, or an object with a
+/// `nodes` / `edges` (or `links`) array so one file can carry both lists.
+fn read_rows(path: &Path, json_keys: &[&str]) -> Result<Vec<Row>, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+    let extension = path
+        .extension()
+        .and_then(|x| x.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+    match extension.as_str() {
+        "csv" => {
+            let mut rows = parse_csv(&text).into_iter();
+            let header: Vec<String> = rows
+                .next()
+                .ok_or_else(|| format!("{:?} is empty", path))?
+                .into_iter()
+                .map(|h| h.trim().to_string())
+                .collect();
+            Ok(rows
+                .map(|fields| {
+                    header
+                        .iter()
+                        .cloned()
+                        .zip(fields.into_iter().map(Value::String))
+                        .collect()
+                })
+                .collect())
+        }
+        "json" => {
+            let value: Value = serde_json::from_str(&text)
+                .map_err(|e| format!("Failed to parse {:?}: {}", path, e))?;
+            let items = match value {
+                Value::Array(items) => items,
+                Value::Object(mut obj) => json_keys
+                    .iter()
+                    .find_map(|k| obj.remove(*k))
+                    .and_then(|v| match v {
+                        Value::Array(items) => Some(items),
+                        _ => None,
+                    })
+                    .ok_or_else(|| {
+                        format!("{:?} has no '{}' array", path, json_keys.join("' or '"))
+                    })?,
+                _ => return Err(format!("{:?} must contain a JSON array or object", path)),
+            };
+            items
+                .into_iter()
+                .enumerate()
+                .map(|(i, item)| match item {
+                    Value::Object(row) => Ok(row),
+                    _ => Err(format!("{:?}: item {} is not an object", path, i + 1)),
+                })
+                .collect()
+        }
+        _ => Err(format!(
+            "{:?}: only .csv and .json files can be imported",
+            path
+        )),
+    }
+}
+
+/// Column named by the mapping, or the first header matching `candidates` (case-insensitive).
+fn resolve_column(rows: &[Row], mapped: Option<&str>, candidates: &[&str]) -> Option<String> {
+    if let Some(mapped) = mapped.map(str::trim).filter(|m| !m.is_empty()) {
+        return Some(mapped.to_string());
+    }
+    let columns: BTreeSet<&String> = rows.iter().flat_map(|r| r.keys()).collect();
+    candidates.iter().find_map(|candidate| {
+        columns
+            .iter()
+            .find(|c| c.eq_ignore_ascii_case(candidate))
+            .map(|c| c.to_string())
+    })
+}
+
+fn cell_text(value: &Value) -> Option<String> {
+    let text = match value {
+        Value::Null => return None,
+        Value::String(s) => s.trim().to_string(),
+        other => other.to_string(),
+    };
+    (!text.is_empty()).then_some(text)
+}
+
+fn cell(row: &Row, column: Option<&String>) -> Option<String> {
+    column.and_then(|c| row.get(c)).and_then(cell_text)
+}
+
+/// An entity's stored attribute JSON as a map; missing or malformed JSON gives an empty one.
+pub(crate) fn parse_attributes(attributes: Option<&str>) -> Map<String, Value> {
+    attributes
+        .and_then(|a| serde_json::from_str::<Value>(a).ok())
+        .and_then(|v| v.as_object().cloned())
+        .unwrap_or_default()
+}
+
+struct NodeColumns {
+    id: Option<String>,
+    name: String,
+    entity_type: Option<String>,
+    aliases: Option<String>,
+    attributes: Vec<String>,
+}
+
+fn node_columns(rows: &[Row], mapping: &NodeColumnMapping) -> Result<NodeColumns, String> {
+    let name = resolve_column(rows, mapping.name.as_deref(), &NAME_COLUMNS)
+        .ok_or("The node list has no name column; map one explicitly.")?;
+    let id = resolve_column(rows, mapping.id.as_deref(), &ID_COLUMNS);
+    let entity_type = resolve_column(rows, mapping.entity_type.as_deref(), &TYPE_COLUMNS);
+    let aliases = resolve_column(rows, mapping.aliases.as_deref(), &ALIAS_COLUMNS);
+    let attributes = match &mapping.attributes {
+        Some(columns) => columns.clone(),
+        None => {
+            let mapped: Vec<&String> = [Some(&name), id.as_ref(), entity_type.as_ref()]
+                .into_iter()
+                .chain([aliases.as_ref()])
+                .flatten()
+                .collect();
+            let mut columns: BTreeSet<String> = BTreeSet::new();
+            for row in rows {
+                for key in row.keys() {
+                    if !mapped.contains(&key)
+                        && !IGNORED_COLUMNS.iter().any(|c| key.eq_ignore_ascii_case(c))
+                    {
+                        columns.insert(key.clone());
+                    }
+                }
+            }
+            columns.into_iter().collect()
+        }
+    };
+    Ok(NodeColumns {
+        id,
+        name,
+        entity_type,
+        aliases,
+        attributes,
+    })
+}
+
+struct NodeOutcome {
+    label: String,
+    status: &'static str,
+    message: Option<String>,
+    /// Entity the row refers to, for resolving edges; set for conflicts with an existing entity.
+    entity_id: Option<i64>,
+}
+
+/// Import one node row.
+fn import_node(
+    conn: &Connection,
+    request: &GraphImportRequest,
+    columns: &NodeColumns,
+    row: &Row,
+    source: &str,
+) -> Result<NodeOutcome, String> {
+    let Some(name) = cell(row, Some(&columns.name)) else {
+        return Ok(NodeOutcome {
+            label: String::new(),
+            status: "error",
+            message: Some("Missing name".to_string()),
+            entity_id: None,
+        });
+    };
+    let entity_type = cell(row, columns.entity_type.as_ref())
+        .or_else(|| request.nodes.default_type.clone())
+        .unwrap_or_else(|| DEFAULT_ENTITY_TYPE.to_string());
+    let separator = request
+        .nodes
+        .alias_separator
+        .as_deref()
+        .filter(|s| !s.is_empty())
+        .unwrap_or(DEFAULT_ALIAS_SEPARATOR);
+    let mut aliases: Vec<String> = cell(row, columns.aliases.as_ref())
+        .map(|a| {
+            a.split(separator)
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty() && *s != name)
+                .collect()
+        })
+        .unwrap_or_default();
+    let imported: Map<String, Value> = columns
+        .attributes
+        .iter()
+        .filter_map(|c| {
+            let value = row.get(c)?;
+            cell_text(value)?;
+            Some((c.clone(), value.clone()))
+        })
+        .collect();
+
+    let existing = match find_entity_id_by_name_or_alias(conn, &name).map_err(|e| e.to_string())? {
+        Some(id) => Some(get_entity_by_id(conn, id).map_err(|e| e.to_string())?),
+        None => None,
+    };
+    let mut conflicts = Vec::new();
+    if let Some(existing) = &existing {
+        if !existing.entity_type.eq_ignore_ascii_case(&entity_type) {
+            conflicts.push(format!(
+                "'{}' already exists as {}",
+                existing.name, existing.entity_type
+            ));
+        }
+        if existing.name != name {
+            // Matched through an alias: keep the row's name as an alias of that entity.
+            aliases.push(name.clone());
+        }
+    }
+    for alias in &aliases {
+        let owner = find_entity_id_by_name_or_alias(conn, alias).map_err(|e| e.to_string())?;
+        if let Some(owner) = owner.filter(|o| Some(*o) != existing.as_ref().map(|e| e.id)) {
+            let owner = get_entity_by_id(conn, owner).map_err(|e| e.to_string())?;
+            conflicts.push(format!("alias '{}' belongs to '{}'", alias, owner.name));
+        }
+    }
+
+    let mut attributes = parse_attributes(existing.as_ref().and_then(|e| e.attributes.as_deref()));
+    let mut changes = Vec::new();
+    for (key, value) in imported {
+        let new_text = cell_text(&value).unwrap_or_default();
+        match attributes.get(&key).and_then(cell_text) {
+            Some(old) if old == new_text => continue,
+            Some(old) if !request.overwrite_attributes => {
+                conflicts.push(format!("{}: '{}' vs '{}'", key, old, new_text));
+            }
+            Some(old) => changes.push(format!("{}: '{}' → '{}'", key, old, new_text)),
+            None => changes.push(format!("{}: '{}'", key, new_text)),
+        }
+        attributes.insert(key, value);
+    }
+    if !conflicts.is_empty() {
+        return Ok(NodeOutcome {
+            label: name,
+            status: "conflict",
+            message: Some(conflicts.join("; ")),
+            entity_id: existing.map(|e| e.id),
+        });
+    }
+
+    let new_aliases: Vec<&String> = match &existing {
+        Some(existing) => {
+            let mut out = Vec::new();
+            for alias in &aliases {
+                let owner =
+                    find_entity_id_by_name_or_alias(conn, alias).map_err(|e| e.to_string())?;
+                if owner != Some(existing.id) {
+                    out.push(alias);
+                }
+            }
+            out
+        }
+        None => aliases.iter().collect(),
+    };
+    let (canonical_type, canonical_name) = match &existing {
+        Some(e) => (e.entity_type.clone(), e.name.clone()),
+        None => (entity_type, name.clone()),
+    };
+    let attributes_json = if attributes.is_empty() {
+        None
+    } else {
+        Some(Value::Object(attributes).to_string())
+    };
+    let status = match &existing {
+        None => "new",
+        Some(_) if !changes.is_empty() || !new_aliases.is_empty() => "updated",
+        Some(_) => "unchanged",
+    };
+    if !new_aliases.is_empty() {
+        changes.push(format!(
+            "aliases: {}",
+            new_aliases
+                .iter()
+                .map(|a| a.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+    let id = upsert_entity(
+        conn,
+        &canonical_type,
+        &canonical_name,
+        attributes_json.as_deref(),
+    )
+    .map_err(|e| e.to_string())?;
+    for alias in new_aliases {
+        add_entity_alias(conn, id, alias).map_err(|e| e.to_string())?;
+    }
+    set_entity_source(conn, id, source).map_err(|e| e.to_string())?;
+    let message = (status == "updated").then(|| changes.join("; "));
+    Ok(NodeOutcome {
+        label: name,
+        status,
+        message,
+        entity_id: Some(id),
+    })
+}
+
+/// The source tag of an import: the requested one, or `import:<file name>` of `path`.
+pub(crate) fn import_source(requested: Option<&str>, path: Option<&str>) -> String {
+    if let Some(source) = requested.map(str::trim).filter(|s| !s.is_empty()) {
+        return source.to_string();
+    }
+    let file = path
+        .and_then(|p| Path::new(p).file_name())
+        .map(|f| f.to_string_lossy().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    format!("import:{}", file)
+}
+
+/// Import node and edge lists in one transaction (rolled back for a dry run).
+pub fn import_graph(
+    conn: &mut Connection,
+    request: &GraphImportRequest,
+) -> Result<GraphImportReport, String> {
+    if request.nodes_path.is_none() && request.edges_path.is_none() {
+        return Err("Choose a node list, an edge list, or both.".to_string());
+    }
+    let source = import_source(
+        request.source.as_deref(),
+        request
+            .nodes_path
+            .as_deref()
+            .or(request.edges_path.as_deref()),
+    );
+    let node_rows = match request.nodes_path.as_deref() {
+        Some(path) => read_rows(Path::new(path), &["nodes"])?,
+        None => Vec::new(),
+    };
+    let edge_rows = match request.edges_path.as_deref() {
+        Some(path) => read_rows(Path::new(path), &["edges", "links"])?,
+        None => Vec::new(),
+    };
+
+    let mut report = GraphImportReport {
+        dry_run: request.dry_run,
+        source: source.clone(),
+        ..Default::default()
+    };
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+
+    // Node-list IDs (or names) → entity IDs, for resolving edge endpoints.
+    let mut node_ids: HashMap<String, i64> = HashMap::new();
+    if !node_rows.is_empty() {
+        let columns = node_columns(&node_rows, &request.nodes)?;
+        for (i, row) in node_rows.iter().enumerate() {
+            let outcome = import_node(&tx, request, &columns, row, &source)?;
+            if let Some(id) = outcome.entity_id {
+                if outcome.status != "conflict" {
+                    report.touched_entity_ids.push(id);
+                }
+                node_ids.insert(outcome.label.clone(), id);
+                if let Some(key) = cell(row, columns.id.as_ref()) {
+                    node_ids.insert(key, id);
+                }
+            }
+            report.push(
+                "entity",
+                i + 1,
+                &outcome.label,
+                outcome.status,
+                outcome.message,
+            );
+        }
+    }
+
+    if !edge_rows.is_empty() {
+        let from_column =
+            resolve_column(&edge_rows, request.edges.source.as_deref(), &SOURCE_COLUMNS)
+                .ok_or("The edge list has no source column; map one explicitly.")?;
+        let to_column =
+            resolve_column(&edge_rows, request.edges.target.as_deref(), &TARGET_COLUMNS)
+                .ok_or("The edge list has no target column; map one explicitly.")?;
+        let relation_column = resolve_column(
+            &edge_rows,
+            request.edges.relation.as_deref(),
+            &RELATION_COLUMNS,
+        );
+        let strength_column = resolve_column(
+            &edge_rows,
+            request.edges.strength.as_deref(),
+            &STRENGTH_COLUMNS,
+        );
+        let resolve = |key: &str| -> Result<Option<i64>, String> {
+            match node_ids.get(key) {
+                Some(id) => Ok(Some(*id)),
+                None => find_entity_id_by_name_or_alias(&tx, key).map_err(|e| e.to_string()),
+            }
+        };
+
+        for (i, row) in edge_rows.iter().enumerate() {
+            let from = cell(row, Some(&from_column)).unwrap_or_default();
+            let to = cell(row, Some(&to_column)).unwrap_or_default();
+            let relation = cell(row, relation_column.as_ref())
+                .or_else(|| request.edges.default_relation.clone())
+                .unwrap_or_else(|| DEFAULT_RELATION.to_string());
+            let label = format!("{} → {} ({})", from, to, relation);
+            let (Some(from_id), Some(to_id)) = (resolve(&from)?, resolve(&to)?) else {
+                let missing = if resolve(&from)?.is_none() {
+                    &from
+                } else {
+                    &to
+                };
+                report.push(
+                    "relation",
+                    i + 1,
+                    &label,
+                    "error",
+                    Some(format!("Unknown entity '{}'", missing)),
+                );
+                continue;
+            };
+            let strength = match cell(row, strength_column.as_ref()) {
+                Some(s) => match s.parse::<f64>() {
+                    Ok(v) => Some(v.round().max(1.0) as i32),
+                    Err(_) => {
+                        report.push(
+                            "relation",
+                            i + 1,
+                            &label,
+                            "error",
+                            Some(format!("Invalid strength '{}'", s)),
+                        );
+                        continue;
+                    }
+                },
+                None => None,
+            };
+
+            let before =
+                get_relation_strength(&tx, from_id, to_id, &relation).map_err(|e| e.to_string())?;
+            // Without a strength column an existing relation is left alone, so re-importing
+            // the same file doesn't keep raising its strength.
+            match (strength, before) {
+                (Some(strength), _) => restore_relation(&tx, from_id, to_id, &relation, strength),
+                (None, None) => restore_relation(&tx, from_id, to_id, &relation, 1),
+                (None, Some(_)) => Ok(()),
+            }
+            .map_err(|e| e.to_string())?;
+            let after =
+                get_relation_strength(&tx, from_id, to_id, &relation).map_err(|e| e.to_string())?;
+            set_relation_source(&tx, from_id, to_id, &relation, &source)
+                .map_err(|e| e.to_string())?;
+            report.touched_entity_ids.extend([from_id, to_id]);
+            let (status, message) = match before {
+                None => ("new", None),
+                Some(b) if Some(b) != after => (
+                    "updated",
+                    Some(format!("strength {} → {}", b, after.unwrap_or(b))),
+                ),
+                Some(_) => ("unchanged", None),
+            };
+            report.push("relation", i + 1, &label, status, message);
+        }
+    }
+
+    report.touched_entity_ids.sort_unstable();
+    report.touched_entity_ids.dedup();
+    if request.dry_run {
+        tx.rollback().map_err(|e| e.to_string())?;
+    } else {
+        tx.commit().map_err(|e| e.to_string())?;
+    }
+    Ok(report)
+}
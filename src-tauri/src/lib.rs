@@ -1,26 +1,85 @@
+mod calendar_import;
+mod chat_import;
 mod database;
+mod document_import;
+mod email_import;
+mod entity_pages;
 mod file_manager;
+mod graph_export;
+mod graph_import;
+mod library_archive;
+mod library_crypto;
+mod llm_provider;
+mod memory_history;
+mod memory_watcher;
 mod model_client;
 mod model_config;
 mod ollama;
 mod ollama_installer;
+mod output_schema;
+mod rdf_export;
+mod timeline;
+mod vcard_import;
 mod whisper;
 
+use calendar_import::{
+    expand_occurrences, imported_event_keys, read_calendar, CalendarImportError,
+    CalendarImportReport, CalendarImportRequest, DEFAULT_EXPANSION_DAYS,
+};
+use chat_import::{
+    group_conversations, read_chat_log, ChatFormat, ChatImportError, ChatImportReport,
+    ChatImportRequest, ChatSplit,
+};
+use document_import::{
+    chunk_content, chunk_document, document_frontmatter, document_id_of, document_parts,
+    outline_content, read_document, reconcile_extractions, DocumentChunkError,
+    DocumentImportReport, DocumentImportRequest, DocumentPart, DEFAULT_CHUNK_TOKENS,
+};
+use email_import::{
+    group_messages, imported_message_ids, precreate_correspondent, read_messages, EmailImportError,
+    EmailImportReport, EmailImportRequest,
+};
 use chrono::{Datelike, Duration as ChronoDuration, Local, NaiveDate, Utc};
 use database::{
-    add_entity_alias, cleanup_database, clear_all_data, clear_memory_entities, delete_memory,
-    find_entity_id_by_name_or_alias, get_entity_by_id, get_entity_by_name, get_graph_data,
-    get_memories_for_entity, get_memory_by_id, init_db, insert_memory, link_memory_entity,
-    list_memories, list_relations, merge_entities, prune_orphan_entities_and_relations,
-    update_memory, upsert_entity, upsert_relation, DbState, Entity, GraphData, Memory,
+    add_entity_alias, checkpoint, cleanup_database, clear_all_data, clear_memory_entities, delete_memory,
+    export_encrypted_copy, find_entity_id_by_name_or_alias, get_entity_by_id, get_entity_by_name,
+    get_entity_ids_for_memory, get_graph_data, get_memories_for_entity, get_memory_by_id,
+    get_memory_by_md_path, init_db, insert_memory, insert_memory_at, link_memory_entity,
+    list_entities, list_entity_aliases, list_entity_sources, list_memories, list_relation_sources,
+    list_relations, merge_entities, open_db, prune_orphan_entities_and_relations, restore_relation,
+    schema_version, set_entity_source, set_relation_source, update_memory, update_memory_file_path,
+    upsert_entity, upsert_relation, DbState, Entity, GraphData, Memory,
+};
+use entity_pages::{entity_pages_dir, update_entity_pages};
+use file_manager::{
+    is_path_variant_of, list_memory_files, memory_file_path, move_memory_file, prune_empty_dirs,
+    read_file_text, read_memory, replace_file_contents, rewrite_memory, update_frontmatter,
+    validate_path_template, write_memory, MdAlias, MdEntity, MdFrontmatter, MdRecord, MdRelation,
+    PathContext, DEFAULT_PATH_TEMPLATE,
+};
+use library_crypto::{
+    change_passphrase, create_key_file, decode_text_with, encode_text_with, is_encrypted,
+    remove_key_file, unlock, LibraryKey,
+};
+use graph_export::GraphExportFormat;
+use graph_import::{GraphImportReport, GraphImportRequest};
+use llm_provider::{
+    check_local_server_status, list_local_server_models, provider_for, Capabilities, LlmProvider,
+    ModelRole,
+};
+use memory_history::{
+    commit_changes, file_at, file_history, open_or_init, MemoryHistoryEntry,
+};
+use memory_watcher::{MemoryFileChange, MemoryWatcher, MemoryWatcherState};
+use model_client::{
+    call_model_extract, call_model_fusion, call_model_simple, call_model_simple_stream,
+    ensure_model_ready,
 };
-use file_manager::{list_memory_files, read_memory, write_memory, MdRecord};
-use model_client::{call_model_extract, call_model_fusion, call_model_simple};
 use model_config::{ModelConfig, ModelProvider};
 use ollama::{
     call_ollama_extract_blocking, check_ollama_status, ensure_model_available,
-    ensure_ollama_running, ExtractedData, ExtractedEntity, ExtractedRelation,
-    ENTITY_EXTRACT_PROMPT, KNOWLEDGE_FUSION_PROMPT,
+    ensure_ollama_running, EntityAlias, ExtractedData, ExtractedEntity, ExtractedRelation,
+    FusedKnowledge, ENTITY_EXTRACT_PROMPT, KNOWLEDGE_FUSION_PROMPT,
 };
 use ollama_installer::download_and_open_ollama_installer;
 use serde::{Deserialize, Serialize};
@@ -29,12 +88,17 @@ use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 use std::time::Instant;
 use tauri::{Emitter, Manager, State};
+use rdf_export::default_base_iri;
+use timeline::{build_timeline, TimelineEntry};
+use vcard_import::{VcardImportReport, VcardImportRequest};
 use whisper::{setup_whisper as setup_whisper_runtime, transcribe_audio_with_whisper};
 
 pub struct AppRootDir(pub PathBuf);
 pub struct AppDataDir(pub Mutex<PathBuf>);
 pub struct CurrentLibraryId(pub Mutex<String>);
 pub struct ModelConfigState(pub Mutex<ModelConfig>);
+/// Data key of the current library while it is unlocked; `None` for plaintext libraries.
+pub struct LibraryKeyState(pub Mutex<Option<LibraryKey>>);
 
 #[derive(Debug, Clone, Deserialize)]
 struct StoryGenerationRequest {
@@ -88,6 +152,20 @@ struct MemoryLibraryInfo {
     is_current: bool,
     #[serde(default)]
     enable_time_normalization: bool,
+    /// Layout of new memory files, relative to `memories/` (see `render_path_template`).
+    path_template: String,
+    /// Whether `entities/` pages are kept up to date (see `entity_pages`).
+    #[serde(default)]
+    enable_entity_pages: bool,
+    /// Whether every change to `memories/` is committed to a git repository in that folder.
+    #[serde(default)]
+    enable_git_history: bool,
+    /// Whether the library is protected by a passphrase (see `library_crypto`).
+    #[serde(default)]
+    is_encrypted: bool,
+    /// Encrypted and not unlocked in this session.
+    #[serde(default)]
+    is_locked: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -96,6 +174,12 @@ struct LibraryMeta {
     created_at: String,
     #[serde(default)]
     enable_time_normalization: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    path_template: Option<String>,
+    #[serde(default)]
+    enable_entity_pages: bool,
+    #[serde(default)]
+    enable_git_history: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -242,6 +326,23 @@ struct StoryRewriteChapterRequest {
     language: Option<String>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RebuildFileError {
+    path: String,
+    error: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+struct RebuildIndexReport {
+    total: usize,
+    rebuilt: usize,
+    /// Files whose frontmatter lacked entity data and were re-extracted by the model.
+    extracted: usize,
+    errors: Vec<RebuildFileError>,
+    /// Where the previous database was moved, if one existed.
+    backup_path: Option<String>,
+}
+
 #[tauri::command]
 fn greet(name: &str) -> String {
     format!("Hello, {}! You've been greeted from Rust!", name)
@@ -255,6 +356,19 @@ fn get_current_data_dir(data_dir: &State<AppDataDir>) -> Result<PathBuf, String>
         .map_err(|e| e.to_string())
 }
 
+fn get_library_key(key_state: &State<LibraryKeyState>) -> Result<Option<LibraryKey>, String> {
+    key_state
+        .0
+        .lock()
+        .map(|key| key.clone())
+        .map_err(|e| e.to_string())
+}
+
+/// Key of the current library, for code that only has the app handle.
+fn library_key(app: &tauri::AppHandle) -> Result<Option<LibraryKey>, String> {
+    get_library_key(&app.state::<LibraryKeyState>())
+}
+
 fn get_current_library_id(current_library: &State<CurrentLibraryId>) -> Result<String, String> {
     current_library
         .0
@@ -510,6 +624,35 @@ fn load_library_time_normalization(library_dir: &Path) -> bool {
         .unwrap_or(false)
 }
 
+fn load_library_path_template(library_dir: &Path) -> String {
+    load_library_meta(library_dir)
+        .and_then(|meta| meta.path_template)
+        .filter(|t| !t.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_PATH_TEMPLATE.to_string())
+}
+
+fn load_library_entity_pages(library_dir: &Path) -> bool {
+    load_library_meta(library_dir)
+        .map(|meta| meta.enable_entity_pages)
+        .unwrap_or(false)
+}
+
+fn load_library_git_history(library_dir: &Path) -> bool {
+    load_library_meta(library_dir)
+        .map(|meta| meta.enable_git_history)
+        .unwrap_or(false)
+}
+
+fn save_library_path_template(library_dir: &Path, template: Option<&str>) -> Result<(), String> {
+    let mut meta = load_library_meta(library_dir).ok_or("Library metadata not found")?;
+    meta.path_template = template
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty() && t != DEFAULT_PATH_TEMPLATE);
+    let content = serde_json::to_string_pretty(&meta).map_err(|e| e.to_string())?;
+    fs::write(library_meta_path(library_dir), content)
+        .map_err(|e| format!("Failed to save library metadata: {e}"))
+}
+
 fn save_library_meta(
     library_dir: &Path,
     name: &str,
@@ -526,6 +669,9 @@ fn save_library_meta(
         enable_time_normalization: enable_time_normalization
             .or_else(|| existing.as_ref().map(|x| x.enable_time_normalization))
             .unwrap_or(false),
+        enable_entity_pages: existing.as_ref().is_some_and(|x| x.enable_entity_pages),
+        enable_git_history: existing.as_ref().is_some_and(|x| x.enable_git_history),
+        path_template: existing.and_then(|x| x.path_template),
     };
     let content = serde_json::to_string_pretty(&meta).map_err(|e| e.to_string())?;
     fs::write(library_meta_path(library_dir), content)
@@ -549,17 +695,33 @@ fn persist_current_library_id(app_root: &Path, library_id: &str) -> Result<(), S
         .map_err(|e| format!("Failed to persist current library: {e}"))
 }
 
-fn build_library_info(library_dir: &Path, library_id: &str, current_id: &str) -> MemoryLibraryInfo {
+/// `current_unlocked` says whether the current library's key is loaded; other encrypted
+/// libraries always count as locked.
+fn build_library_info(
+    library_dir: &Path,
+    library_id: &str,
+    current_id: &str,
+    current_unlocked: bool,
+) -> MemoryLibraryInfo {
     MemoryLibraryInfo {
         id: library_id.to_string(),
         name: load_library_name(library_dir, library_id),
         path: library_dir.to_string_lossy().to_string(),
         is_current: library_id == current_id,
         enable_time_normalization: load_library_time_normalization(library_dir),
+        path_template: load_library_path_template(library_dir),
+        enable_entity_pages: load_library_entity_pages(library_dir),
+        enable_git_history: load_library_git_history(library_dir),
+        is_encrypted: is_encrypted(library_dir),
+        is_locked: is_encrypted(library_dir) && !(library_id == current_id && current_unlocked),
     }
 }
 
-fn list_library_infos(app_root: &Path, current_id: &str) -> Result<Vec<MemoryLibraryInfo>, String> {
+fn list_library_infos(
+    app_root: &Path,
+    current_id: &str,
+    current_unlocked: bool,
+) -> Result<Vec<MemoryLibraryInfo>, String> {
     let root = libraries_root(app_root);
     fs::create_dir_all(&root).map_err(|e| format!("Failed to create libraries root: {e}"))?;
 
@@ -571,16 +733,17 @@ fn list_library_infos(app_root: &Path, current_id: &str) -> Result<Vec<MemoryLib
             continue;
         }
         let id = entry.file_name().to_string_lossy().to_string();
-        libraries.push(build_library_info(&path, &id, current_id));
+        libraries.push(build_library_info(&path, &id, current_id, current_unlocked));
     }
     libraries.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
     Ok(libraries)
 }
 
 fn switch_to_library_internal(
+    app: &tauri::AppHandle,
     library_id: String,
+    passphrase: Option<&str>,
     app_root: &Path,
-    db: &State<DbState>,
     data_dir: &State<AppDataDir>,
     current_library: &State<CurrentLibraryId>,
     config_state: &State<ModelConfigState>,
@@ -592,16 +755,35 @@ fn switch_to_library_internal(
     }
     ensure_library_structure(&library_dir)?;
 
+    let key = if is_encrypted(&library_dir) {
+        let passphrase =
+            passphrase.ok_or("This library is encrypted. Enter its passphrase to open it.")?;
+        Some(unlock(&library_dir, passphrase)?)
+    } else {
+        None
+    };
     let db_path = library_dir.join("database").join("kraph.db");
-    let conn = init_db(&db_path).map_err(|e| e.to_string())?;
+    let db_key = key.as_ref().map(LibraryKey::sqlcipher_key);
+    let conn = init_db(&db_path, db_key.as_deref()).map_err(|e| e.to_string())?;
+    // Encrypted imports can only be relinked once unlocked; this also covers moved libraries.
+    let memories_dir = library_dir.join("memories");
+    let rebased = library_archive::rebase_memory_paths(&conn, &memories_dir, &memories_dir)?;
+    if rebased > 0 {
+        println!("📦 [library] Relinked {} memory files to {:?}", rebased, library_dir);
+    }
 
     {
-        let mut db_guard = (&**db)
-            .0
-            .lock()
-            .map_err(|e: std::sync::PoisonError<_>| e.to_string())?;
+        let db = app.state::<DbState>();
+        let mut db_guard =
+            db.0.lock()
+                .map_err(|e: std::sync::PoisonError<_>| e.to_string())?;
         *db_guard = Some(conn);
     }
+    {
+        let key_state = app.state::<LibraryKeyState>();
+        let mut key_guard = key_state.0.lock().map_err(|e| e.to_string())?;
+        *key_guard = key.clone();
+    }
     {
         let mut path_guard = data_dir.0.lock().map_err(|e| e.to_string())?;
         *path_guard = library_dir.clone();
@@ -614,13 +796,22 @@ fn switch_to_library_internal(
 
     // Load model config from the active library, defaulting when absent.
     let config_path = library_model_config_path(&library_dir);
-    let model_config = ModelConfig::load_from_file(&config_path).unwrap_or_default();
+    let model_config = ModelConfig::load_from_file(&config_path, key.as_ref()).unwrap_or_default();
     {
         let mut guard = config_state.0.lock().map_err(|e| e.to_string())?;
         *guard = model_config;
     }
 
-    Ok(build_library_info(&library_dir, &library_id, &library_id))
+    if let Err(e) = start_memory_watcher(app) {
+        println!("⚠️ [memory_watcher] Failed to watch library '{}': {}", library_id, e);
+    }
+
+    Ok(build_library_info(
+        &library_dir,
+        &library_id,
+        &library_id,
+        key.is_some(),
+    ))
 }
 
 #[tauri::command]
@@ -638,28 +829,38 @@ fn list_memories_dir(data_dir: State<AppDataDir>) -> Result<Vec<String>, String>
 fn list_memory_libraries(
     app_root: State<AppRootDir>,
     current_library: State<CurrentLibraryId>,
+    key_state: State<LibraryKeyState>,
 ) -> Result<Vec<MemoryLibraryInfo>, String> {
     let current_id = get_current_library_id(&current_library)?;
-    list_library_infos(&app_root.0, &current_id)
+    let unlocked = get_library_key(&key_state)?.is_some();
+    list_library_infos(&app_root.0, &current_id, unlocked)
 }
 
 #[tauri::command]
 fn get_current_memory_library(
     app_root: State<AppRootDir>,
     current_library: State<CurrentLibraryId>,
+    key_state: State<LibraryKeyState>,
 ) -> Result<MemoryLibraryInfo, String> {
     let current_id = get_current_library_id(&current_library)?;
     let library_dir = libraries_root(&app_root.0).join(&current_id);
     if !library_dir.exists() {
         return Err("Current library not found".to_string());
     }
-    Ok(build_library_info(&library_dir, &current_id, &current_id))
+    let unlocked = get_library_key(&key_state)?.is_some();
+    Ok(build_library_info(
+        &library_dir,
+        &current_id,
+        &current_id,
+        unlocked,
+    ))
 }
 
 #[tauri::command]
 fn create_memory_library(
     name: String,
     enable_time_normalization: Option<bool>,
+    passphrase: Option<String>,
     app_root: State<AppRootDir>,
     current_library: State<CurrentLibraryId>,
     config_state: State<ModelConfigState>,
@@ -676,6 +877,16 @@ fn create_memory_library(
     let library_id = unique_library_id(&root, &base_id);
     let library_dir = root.join(&library_id);
     ensure_library_structure(&library_dir)?;
+    let key = match passphrase.as_deref() {
+        Some(passphrase) => match create_key_file(&library_dir, passphrase) {
+            Ok(key) => Some(key),
+            Err(e) => {
+                let _ = fs::remove_dir_all(&library_dir);
+                return Err(e);
+            }
+        },
+        None => None,
+    };
     save_library_meta(
         &library_dir,
         trimmed,
@@ -684,17 +895,23 @@ fn create_memory_library(
 
     // Initialize with current model config so the new library is ready immediately.
     let current_config = config_state.0.lock().map_err(|e| e.to_string())?.clone();
-    current_config.save_to_file(&library_model_config_path(&library_dir))?;
+    current_config.save_to_file(&library_model_config_path(&library_dir), key.as_ref())?;
 
     let current_id = get_current_library_id(&current_library)?;
-    Ok(build_library_info(&library_dir, &library_id, &current_id))
+    Ok(build_library_info(
+        &library_dir,
+        &library_id,
+        &current_id,
+        false,
+    ))
 }
 
 #[tauri::command]
 fn switch_memory_library(
+    app: tauri::AppHandle,
     library_id: String,
+    passphrase: Option<String>,
     app_root: State<AppRootDir>,
-    db: State<DbState>,
     data_dir: State<AppDataDir>,
     current_library: State<CurrentLibraryId>,
     config_state: State<ModelConfigState>,
@@ -704,9 +921,10 @@ fn switch_memory_library(
         return Err("Library id cannot be empty.".to_string());
     }
     switch_to_library_internal(
+        &app,
         library_id.to_string(),
+        passphrase.as_deref(),
         &app_root.0,
-        &db,
         &data_dir,
         &current_library,
         &config_state,
@@ -719,6 +937,7 @@ fn rename_memory_library(
     name: String,
     app_root: State<AppRootDir>,
     current_library: State<CurrentLibraryId>,
+    key_state: State<LibraryKeyState>,
 ) -> Result<MemoryLibraryInfo, String> {
     let library_id = library_id.trim();
     let name = name.trim();
@@ -736,14 +955,328 @@ fn rename_memory_library(
     save_library_meta(&dir, name, None)?;
 
     let current_id = get_current_library_id(&current_library)?;
-    Ok(build_library_info(&dir, library_id, &current_id))
+    let unlocked = get_library_key(&key_state)?.is_some();
+    Ok(build_library_info(&dir, library_id, &current_id, unlocked))
 }
 
 #[tauri::command]
-fn delete_memory_library(
+fn set_memory_library_path_template(
+    library_id: String,
+    path_template: Option<String>,
+    app_root: State<AppRootDir>,
+    current_library: State<CurrentLibraryId>,
+    key_state: State<LibraryKeyState>,
+) -> Result<MemoryLibraryInfo, String> {
+    let library_id = library_id.trim();
+    if library_id.is_empty() {
+        return Err("Library id cannot be empty.".to_string());
+    }
+    let dir = libraries_root(&app_root.0).join(library_id);
+    if !dir.exists() || !dir.is_dir() {
+        return Err(format!("Library '{}' does not exist.", library_id));
+    }
+    if let Some(template) = path_template.as_deref() {
+        validate_path_template(template)?;
+    }
+    save_library_path_template(&dir, path_template.as_deref())?;
+
+    let current_id = get_current_library_id(&current_library)?;
+    let unlocked = get_library_key(&key_state)?.is_some();
+    Ok(build_library_info(&dir, library_id, &current_id, unlocked))
+}
+
+#[tauri::command]
+fn set_memory_library_entity_pages(
     library_id: String,
+    enabled: bool,
     app_root: State<AppRootDir>,
     db: State<DbState>,
+    current_library: State<CurrentLibraryId>,
+    key_state: State<LibraryKeyState>,
+) -> Result<MemoryLibraryInfo, String> {
+    let library_id = library_id.trim();
+    if library_id.is_empty() {
+        return Err("Library id cannot be empty.".to_string());
+    }
+    let dir = libraries_root(&app_root.0).join(library_id);
+    let mut meta = load_library_meta(&dir)
+        .ok_or_else(|| format!("Library '{}' does not exist.", library_id))?;
+    if enabled && is_encrypted(&dir) {
+        return Err(
+            "Entity pages would be written unencrypted, so encrypted libraries can't use them."
+                .to_string(),
+        );
+    }
+    meta.enable_entity_pages = enabled;
+    let content = serde_json::to_string_pretty(&meta).map_err(|e| e.to_string())?;
+    fs::write(library_meta_path(&dir), content)
+        .map_err(|e| format!("Failed to save library metadata: {e}"))?;
+
+    let current_id = get_current_library_id(&current_library)?;
+    if enabled && current_id == library_id {
+        let mut guard =
+            db.0.lock()
+                .map_err(|e: std::sync::PoisonError<_>| e.to_string())?;
+        let conn = guard.as_mut().ok_or("database not initialized")?;
+        let written = update_entity_pages(conn, &dir, None)?;
+        println!("📇 [entity_pages] Generated {} pages", written);
+    }
+    let unlocked = get_library_key(&key_state)?.is_some();
+    Ok(build_library_info(&dir, library_id, &current_id, unlocked))
+}
+
+#[tauri::command]
+fn set_memory_library_git_history(
+    library_id: String,
+    enabled: bool,
+    app_root: State<AppRootDir>,
+    current_library: State<CurrentLibraryId>,
+    key_state: State<LibraryKeyState>,
+) -> Result<MemoryLibraryInfo, String> {
+    let library_id = library_id.trim();
+    if library_id.is_empty() {
+        return Err("Library id cannot be empty.".to_string());
+    }
+    let dir = libraries_root(&app_root.0).join(library_id);
+    let mut meta = load_library_meta(&dir)
+        .ok_or_else(|| format!("Library '{}' does not exist.", library_id))?;
+    if enabled {
+        // Creates the repository with a snapshot of the current files on first use.
+        open_or_init(&dir.join("memories"))?;
+    }
+    meta.enable_git_history = enabled;
+    let content = serde_json::to_string_pretty(&meta).map_err(|e| e.to_string())?;
+    fs::write(library_meta_path(&dir), content)
+        .map_err(|e| format!("Failed to save library metadata: {e}"))?;
+
+    let current_id = get_current_library_id(&current_library)?;
+    let unlocked = get_library_key(&key_state)?.is_some();
+    Ok(build_library_info(&dir, library_id, &current_id, unlocked))
+}
+
+/// Encrypt every `kraph.db.bak-*` left by rebuild_index under `db_key`. A backup that can't
+/// be encrypted (e.g. a corrupt one) is left as it is, since it may be the only copy of the
+/// index. Returns the backups that are still in plaintext.
+fn encrypt_database_backups(db_dir: &Path, db_key: &str) -> Vec<PathBuf> {
+    let mut plaintext = Vec::new();
+    let Ok(entries) = fs::read_dir(db_dir) else {
+        return plaintext;
+    };
+    for path in entries.flatten().map(|e| e.path()) {
+        let is_backup = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|n| n.starts_with("kraph.db.bak-"));
+        if !is_backup || !path.is_file() {
+            continue;
+        }
+        let encrypted_path = path.with_extension("encrypting");
+        let _ = fs::remove_file(&encrypted_path);
+        let result = open_db(&path, None)
+            .and_then(|conn| export_encrypted_copy(&conn, &encrypted_path, db_key))
+            .map_err(|e| e.to_string())
+            .and_then(|_| fs::rename(&encrypted_path, &path).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            println!("❌ [library] Failed to encrypt backup {:?}: {}", path, e);
+            let _ = fs::remove_file(&encrypted_path);
+            plaintext.push(path);
+        }
+    }
+    plaintext
+}
+
+/// Blocking core logic for encrypt_memory_library: convert the active library in place.
+///
+/// The database is exported to an encrypted copy and swapped in first; once that succeeds the
+/// library counts as encrypted, and Markdown files, story projects and the model config are
+/// rewritten under the new key. Database backups from rebuild_index are encrypted too; any
+/// that cannot be are kept and reported.
+fn do_encrypt_memory_library(
+    app: tauri::AppHandle,
+    library_dir: PathBuf,
+    passphrase: String,
+) -> Result<(), String> {
+    if is_encrypted(&library_dir) {
+        return Err("This library is already encrypted.".to_string());
+    }
+    let memories_dir = library_dir.join("memories");
+    if memories_dir.join(".git").exists() {
+        return Err(
+            "The git history in the memories folder holds unencrypted copies of every \
+             file. Delete memories/.git before encrypting this library."
+                .to_string(),
+        );
+    }
+
+    let db_dir = library_dir.join("database");
+    let db_path = db_dir.join("kraph.db");
+    let encrypted_path = db_dir.join("kraph.encrypted.db");
+    let db = app.state::<DbState>();
+    let mut guard =
+        db.0.lock()
+            .map_err(|e: std::sync::PoisonError<_>| e.to_string())?;
+    let conn = guard.as_mut().ok_or("database not initialized")?;
+
+    let key = create_key_file(&library_dir, &passphrase)?;
+    let db_key = key.sqlcipher_key();
+    let _ = fs::remove_file(&encrypted_path);
+    if let Err(e) = export_encrypted_copy(conn, &encrypted_path, &db_key) {
+        let _ = fs::remove_file(&encrypted_path);
+        let _ = remove_key_file(&library_dir);
+        return Err(format!("Failed to encrypt the database: {e}"));
+    }
+    // Close the plaintext connection before replacing its file.
+    *guard = None;
+    if let Err(e) = fs::rename(&encrypted_path, &db_path) {
+        let _ = fs::remove_file(&encrypted_path);
+        let _ = remove_key_file(&library_dir);
+        *guard = Some(init_db(&db_path, None).map_err(|e| e.to_string())?);
+        return Err(format!("Failed to install the encrypted database: {e}"));
+    }
+    *guard = Some(init_db(&db_path, Some(&db_key)).map_err(|e| e.to_string())?);
+    drop(guard);
+    {
+        let key_state = app.state::<LibraryKeyState>();
+        let mut key_guard = key_state.0.lock().map_err(|e| e.to_string())?;
+        *key_guard = Some(key.clone());
+    }
+    println!("🔒 [library] Encrypted database of {:?}", library_dir);
+
+    let plaintext_backups = encrypt_database_backups(&db_dir, &db_key);
+
+    let mut failed = 0;
+    for path in list_memory_files(&memories_dir)? {
+        let result = read_file_text(&path, Some(&key)).and_then(|contents| {
+            ignore_memory_watcher_echo(&app, &path);
+            replace_file_contents(&path, &contents, Some(&key))
+        });
+        if let Err(e) = result {
+            println!("❌ [library] Failed to encrypt {:?}: {}", path, e);
+            failed += 1;
+        }
+    }
+    if let Ok(entries) = fs::read_dir(story_projects_root(&library_dir)) {
+        for path in entries.flatten().map(|e| e.path()) {
+            if path.extension().and_then(|x| x.to_str()) != Some("json") {
+                continue;
+            }
+            let result = read_file_text(&path, Some(&key))
+                .and_then(|contents| encode_text_with(Some(&key), &contents))
+                .and_then(|encoded| fs::write(&path, encoded).map_err(|e| e.to_string()));
+            if let Err(e) = result {
+                println!("❌ [library] Failed to encrypt {:?}: {}", path, e);
+                failed += 1;
+            }
+        }
+    }
+    let config = app
+        .state::<ModelConfigState>()
+        .0
+        .lock()
+        .map_err(|e| e.to_string())?
+        .clone();
+    config.save_to_file(&library_model_config_path(&library_dir), Some(&key))?;
+
+    // Entity pages can't be encrypted; remove them rather than leave a plaintext copy behind.
+    if let Some(mut meta) = load_library_meta(&library_dir) {
+        if meta.enable_entity_pages {
+            meta.enable_entity_pages = false;
+            let content = serde_json::to_string_pretty(&meta).map_err(|e| e.to_string())?;
+            fs::write(library_meta_path(&library_dir), content)
+                .map_err(|e| format!("Failed to save library metadata: {e}"))?;
+        }
+    }
+    let pages_dir = entity_pages_dir(&library_dir);
+    if pages_dir.exists() {
+        fs::remove_dir_all(&pages_dir)
+            .map_err(|e| format!("Failed to delete entities folder: {e}"))?;
+    }
+
+    if failed > 0 {
+        return Err(format!(
+            "The library is now encrypted, but {} file(s) could not be rewritten and are \
+             still unencrypted. Saving them again will encrypt them.",
+            failed
+        ));
+    }
+    if !plaintext_backups.is_empty() {
+        return Err(format!(
+            "The library is now encrypted, but these database backups could not be encrypted \
+             and are still unencrypted. Delete them by hand once you no longer need them: {}",
+            plaintext_backups
+                .iter()
+                .map(|p| p.to_string_lossy().to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+    Ok(())
+}
+
+/// Protect the active library with a passphrase. Everything in it is encrypted in place.
+#[tauri::command]
+async fn encrypt_memory_library(
+    app: tauri::AppHandle,
+    library_id: String,
+    passphrase: String,
+    app_root: State<'_, AppRootDir>,
+    current_library: State<'_, CurrentLibraryId>,
+    key_state: State<'_, LibraryKeyState>,
+) -> Result<MemoryLibraryInfo, String> {
+    let library_id = library_id.trim().to_string();
+    let current_id = get_current_library_id(&current_library)?;
+    if library_id != current_id {
+        return Err("Switch to the library before encrypting it.".to_string());
+    }
+    let library_dir = libraries_root(&app_root.0).join(&library_id);
+    let dir = library_dir.clone();
+    tokio::task::spawn_blocking(move || do_encrypt_memory_library(app, dir, passphrase))
+        .await
+        .map_err(|e| e.to_string())??;
+    let unlocked = get_library_key(&key_state)?.is_some();
+    Ok(build_library_info(
+        &library_dir,
+        &library_id,
+        &current_id,
+        unlocked,
+    ))
+}
+
+/// Change an encrypted library's passphrase. Only the wrapped key is rewritten.
+#[tauri::command]
+async fn change_library_passphrase(
+    library_id: String,
+    old_passphrase: String,
+    new_passphrase: String,
+    app_root: State<'_, AppRootDir>,
+    current_library: State<'_, CurrentLibraryId>,
+    key_state: State<'_, LibraryKeyState>,
+) -> Result<MemoryLibraryInfo, String> {
+    let library_id = library_id.trim().to_string();
+    let library_dir = libraries_root(&app_root.0).join(&library_id);
+    if !is_encrypted(&library_dir) {
+        return Err(format!("Library '{}' is not encrypted.", library_id));
+    }
+    let dir = library_dir.clone();
+    tokio::task::spawn_blocking(move || change_passphrase(&dir, &old_passphrase, &new_passphrase))
+        .await
+        .map_err(|e| e.to_string())??;
+
+    let current_id = get_current_library_id(&current_library)?;
+    let unlocked = get_library_key(&key_state)?.is_some();
+    Ok(build_library_info(
+        &library_dir,
+        &library_id,
+        &current_id,
+        unlocked,
+    ))
+}
+
+#[tauri::command]
+fn delete_memory_library(
+    app: tauri::AppHandle,
+    library_id: String,
+    app_root: State<AppRootDir>,
     data_dir: State<AppDataDir>,
     current_library: State<CurrentLibraryId>,
     config_state: State<ModelConfigState>,
@@ -760,21 +1293,24 @@ fn delete_memory_library(
     }
 
     let current_id = get_current_library_id(&current_library)?;
-    let libraries = list_library_infos(&app_root.0, &current_id)?;
+    let unlocked = get_library_key(&app.state::<LibraryKeyState>())?.is_some();
+    let libraries = list_library_infos(&app_root.0, &current_id, unlocked)?;
     if libraries.len() <= 1 {
         return Err("Cannot delete the last remaining library.".to_string());
     }
 
     if library_id == current_id {
+        // Encrypted libraries can't be opened without their passphrase.
         let fallback = libraries
             .iter()
-            .find(|x| x.id != library_id)
-            .ok_or("No fallback library available.")?;
+            .find(|x| x.id != library_id && !x.is_encrypted)
+            .ok_or("Switch to another library before deleting this one.")?;
 
         switch_to_library_internal(
+            &app,
             fallback.id.clone(),
+            None,
             &app_root.0,
-            &db,
             &data_dir,
             &current_library,
             &config_state,
@@ -785,12 +1321,167 @@ fn delete_memory_library(
     Ok(library_id)
 }
 
+/// Pack a library (the current one by default) into a single `.kraph` archive at `path`.
+/// See `library_archive` for what is included.
+#[tauri::command]
+async fn export_library(
+    app: tauri::AppHandle,
+    library_id: Option<String>,
+    path: String,
+) -> Result<String, String> {
+    tokio::task::spawn_blocking(move || do_export_library(app, library_id, path))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+fn do_export_library(
+    app: tauri::AppHandle,
+    library_id: Option<String>,
+    path: String,
+) -> Result<String, String> {
+    let app_root = app.state::<AppRootDir>().0.clone();
+    let current_id = get_current_library_id(&app.state::<CurrentLibraryId>())?;
+    let library_id = library_id
+        .map(|id| id.trim().to_string())
+        .filter(|id| !id.is_empty())
+        .unwrap_or_else(|| current_id.clone());
+    let library_dir = libraries_root(&app_root).join(&library_id);
+    if !library_dir.is_dir() {
+        return Err(format!("Library '{}' does not exist.", library_id));
+    }
+    let encrypted = is_encrypted(&library_dir);
+
+    // The active library's connection stays locked while its file is copied.
+    let db = app.state::<DbState>();
+    let mut guard = None;
+    let schema = if library_id == current_id {
+        let locked = db
+            .0
+            .lock()
+            .map_err(|e: std::sync::PoisonError<_>| e.to_string())?;
+        let conn = locked.as_ref().ok_or("database not initialized")?;
+        checkpoint(conn).map_err(|e| e.to_string())?;
+        let version = schema_version(conn).map_err(|e| e.to_string())?;
+        guard = Some(locked);
+        version
+    } else if encrypted {
+        return Err("Switch to and unlock an encrypted library before exporting it.".to_string());
+    } else {
+        let db_path = library_dir.join("database").join("kraph.db");
+        if !db_path.is_file() {
+            return Err(format!("Library '{}' has no database.", library_id));
+        }
+        // Not init_db: exporting must not migrate or otherwise rewrite another library.
+        let conn = open_db(&db_path, None).map_err(|e| e.to_string())?;
+        checkpoint(&conn).map_err(|e| e.to_string())?;
+        schema_version(&conn).map_err(|e| e.to_string())?
+    };
+    let manifest = library_archive::write_archive(
+        &library_dir,
+        Path::new(&path),
+        &library_id,
+        &load_library_name(&library_dir, &library_id),
+        encrypted,
+        schema,
+    )?;
+    drop(guard);
+    println!(
+        "📦 [export_library] Wrote {} ({} files)",
+        path,
+        manifest.files.len()
+    );
+    Ok(path)
+}
+
+/// Unpack a `.kraph` archive as a new library. The ID comes from `name` (default: the
+/// archived library's name) via `unique_library_id`, so existing libraries are never
+/// overwritten. Plain databases are migrated to the current schema and their memory file paths
+/// rebased onto the new folder right away; encrypted ones are handled when first unlocked.
+#[tauri::command]
+async fn import_library(
+    app: tauri::AppHandle,
+    path: String,
+    name: Option<String>,
+) -> Result<MemoryLibraryInfo, String> {
+    tokio::task::spawn_blocking(move || do_import_library(app, path, name))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+fn do_import_library(
+    app: tauri::AppHandle,
+    path: String,
+    name: Option<String>,
+) -> Result<MemoryLibraryInfo, String> {
+    let app_root = app.state::<AppRootDir>().0.clone();
+    let archive_path = Path::new(&path);
+    let manifest = library_archive::read_manifest(archive_path)?;
+    let name = name
+        .map(|n| n.trim().to_string())
+        .filter(|n| !n.is_empty())
+        .unwrap_or_else(|| manifest.name.clone());
+
+    let root = libraries_root(&app_root);
+    fs::create_dir_all(&root).map_err(|e| format!("Failed to create libraries root: {e}"))?;
+    let library_id = unique_library_id(&root, &normalize_library_id(&name));
+    // Unpack next to, not inside, `libraries/` so a half-imported folder is never listed.
+    let staging_dir = app_root.join(format!(".import-{}", library_id));
+    let _ = fs::remove_dir_all(&staging_dir);
+    library_archive::extract_archive(archive_path, &staging_dir)?;
+
+    let finish = || -> Result<(), String> {
+        ensure_library_structure(&staging_dir)?;
+        save_library_meta(&staging_dir, &name, None)?;
+        if !manifest.encrypted {
+            let db_path = staging_dir.join("database").join("kraph.db");
+            let conn = init_db(&db_path, None)
+                .map_err(|e| format!("Failed to migrate database: {e}"))?;
+            // Point file paths at the new library's final location, not the staging folder.
+            library_archive::rebase_memory_paths(
+                &conn,
+                &staging_dir.join("memories"),
+                &root.join(&library_id).join("memories"),
+            )?;
+            let current_config = app
+                .state::<ModelConfigState>()
+                .0
+                .lock()
+                .map_err(|e| e.to_string())?
+                .clone();
+            current_config.save_to_file(&library_model_config_path(&staging_dir), None)?;
+        }
+        fs::rename(&staging_dir, root.join(&library_id))
+            .map_err(|e| format!("Failed to move imported library into place: {e}"))
+    };
+    if let Err(e) = finish() {
+        let _ = fs::remove_dir_all(&staging_dir);
+        return Err(e);
+    }
+
+    println!(
+        "📦 [import_library] Imported '{}' as {} (schema {} → {})",
+        name,
+        library_id,
+        manifest.schema_version,
+        database::SCHEMA_VERSION
+    );
+    let current_id = get_current_library_id(&app.state::<CurrentLibraryId>())?;
+    Ok(build_library_info(
+        &root.join(&library_id),
+        &library_id,
+        &current_id,
+        false,
+    ))
+}
+
 #[tauri::command]
 fn save_story_project(
     request: StoryProjectSaveRequest,
     data_dir: State<AppDataDir>,
+    key_state: State<LibraryKeyState>,
 ) -> Result<StoryProjectSummary, String> {
     let data_dir = get_current_data_dir(&data_dir)?;
+    let key = get_library_key(&key_state)?;
     let root = story_projects_root(&data_dir);
     fs::create_dir_all(&root).map_err(|e| format!("Failed to create novels directory: {e}"))?;
 
@@ -820,7 +1511,7 @@ fn save_story_project(
     let path = story_project_file_path(&data_dir, &project_id);
     let now = Utc::now().to_rfc3339();
     let created_at = if path.exists() {
-        fs::read_to_string(&path)
+        read_file_text(&path, key.as_ref())
             .ok()
             .and_then(|s| serde_json::from_str::<StoryProjectData>(&s).ok())
             .map(|p| p.created_at)
@@ -854,7 +1545,8 @@ fn save_story_project(
     };
 
     let content = serde_json::to_string_pretty(&payload).map_err(|e| e.to_string())?;
-    fs::write(&path, content).map_err(|e| format!("Failed to save story project: {e}"))?;
+    fs::write(&path, encode_text_with(key.as_ref(), &content)?)
+        .map_err(|e| format!("Failed to save story project: {e}"))?;
 
     Ok(StoryProjectSummary {
         id: project_id,
@@ -864,8 +1556,12 @@ fn save_story_project(
 }
 
 #[tauri::command]
-fn list_story_projects(data_dir: State<AppDataDir>) -> Result<Vec<StoryProjectSummary>, String> {
+fn list_story_projects(
+    data_dir: State<AppDataDir>,
+    key_state: State<LibraryKeyState>,
+) -> Result<Vec<StoryProjectSummary>, String> {
     let data_dir = get_current_data_dir(&data_dir)?;
+    let key = get_library_key(&key_state)?;
     let root = story_projects_root(&data_dir);
     fs::create_dir_all(&root).map_err(|e| format!("Failed to create novels directory: {e}"))?;
 
@@ -879,7 +1575,7 @@ fn list_story_projects(data_dir: State<AppDataDir>) -> Result<Vec<StoryProjectSu
         if path.extension().and_then(|x| x.to_str()) != Some("json") {
             continue;
         }
-        if let Ok(content) = fs::read_to_string(&path) {
+        if let Ok(content) = read_file_text(&path, key.as_ref()) {
             if let Ok(project) = serde_json::from_str::<StoryProjectData>(&content) {
                 items.push(StoryProjectSummary {
                     id: project.project_id,
@@ -898,15 +1594,17 @@ fn list_story_projects(data_dir: State<AppDataDir>) -> Result<Vec<StoryProjectSu
 fn load_story_project(
     project_id: String,
     data_dir: State<AppDataDir>,
+    key_state: State<LibraryKeyState>,
 ) -> Result<StoryProjectData, String> {
     let project_id = project_id.trim();
     if project_id.is_empty() {
         return Err("Project id cannot be empty.".to_string());
     }
     let data_dir = get_current_data_dir(&data_dir)?;
+    let key = get_library_key(&key_state)?;
     let path = story_project_file_path(&data_dir, project_id);
-    let content =
-        fs::read_to_string(&path).map_err(|e| format!("Failed to read story project: {e}"))?;
+    let content = read_file_text(&path, key.as_ref())
+        .map_err(|e| format!("Failed to read story project: {e}"))?;
     let project = serde_json::from_str::<StoryProjectData>(&content)
         .map_err(|e| format!("Failed to parse story project: {e}"))?;
     Ok(project)
@@ -1049,8 +1747,12 @@ fn get_memories_folder_path(data_dir: State<AppDataDir>) -> Result<String, Strin
 }
 
 #[tauri::command]
-fn read_memory_file(path: String, _data_dir: State<AppDataDir>) -> Result<MdRecord, String> {
-    read_memory(&PathBuf::from(path))
+fn read_memory_file(
+    path: String,
+    _data_dir: State<AppDataDir>,
+    key_state: State<LibraryKeyState>,
+) -> Result<MdRecord, String> {
+    read_memory(&PathBuf::from(path), get_library_key(&key_state)?.as_ref())
 }
 
 #[tauri::command]
@@ -1065,48 +1767,184 @@ fn extract_entities(text: String) -> Result<ExtractedData, String> {
     )
 }
 
-/// Blocking core logic for save_memory, executed inside spawn_blocking to ensure real-time event delivery.
-fn do_save_memory(
-    app: tauri::AppHandle,
-    content: String,
-    tags: Option<Vec<String>>,
-    config: ModelConfig,
-    memories_dir: std::path::PathBuf,
-) -> Result<Memory, String> {
-    // Emit current model info
-    match &config.provider {
-        ModelProvider::Ollama {
-            model_name,
-            extract_model_name,
-            ..
-        } => {
-            emit_save_progress(
-                &app,
-                "saveProgress.modelInfo.ollama",
-                "info",
-                serde_json::json!({ "model": extract_model_name }),
-            );
-            println!("📝 [save_memory] Using Ollama model: {}", model_name);
+/// Frontmatter fields describing the extracted graph of a memory (empty lists are omitted).
+fn memory_graph_frontmatter(
+    entities: &[ExtractedEntity],
+    relations: &[ExtractedRelation],
+    aliases: &[EntityAlias],
+) -> MdFrontmatter {
+    MdFrontmatter {
+        entities: (!entities.is_empty()).then(|| {
+            entities
+                .iter()
+                .map(|e| MdEntity {
+                    name: e.name.clone(),
+                    entity_type: Some(e.entity_type.clone()),
+                    attributes: e.attributes.clone().filter(|a| !a.is_null()),
+                })
+                .collect()
+        }),
+        relations: (!relations.is_empty()).then(|| {
+            relations
+                .iter()
+                .map(|r| MdRelation {
+                    from: r.from.clone(),
+                    to: r.to.clone(),
+                    relation: r.relation.clone(),
+                })
+                .collect()
+        }),
+        aliases: (!aliases.is_empty()).then(|| {
+            aliases
+                .iter()
+                .map(|a| MdAlias {
+                    primary: a.primary.clone(),
+                    alias: a.alias.clone(),
+                })
+                .collect()
+        }),
+        ..Default::default()
+    }
+}
+
+/// Link a memory to its extracted entities, then apply aliases and relations.
+/// Intended to run inside the caller's transaction; any failure aborts the whole save.
+fn link_extracted_graph(
+    conn: &rusqlite::Connection,
+    memory_id: i64,
+    entities: &[ExtractedEntity],
+    relations: &[ExtractedRelation],
+    aliases: &[EntityAlias],
+) -> Result<(), String> {
+    let mut name_to_id: std::collections::HashMap<String, i64> = std::collections::HashMap::new();
+    for e in entities {
+        let attrs = e.attributes.as_ref().map(|a| a.to_string());
+        let entity_id =
+            match find_entity_id_by_name_or_alias(conn, &e.name).map_err(|e| e.to_string())? {
+                Some(id) => id,
+                None => upsert_entity(conn, &e.entity_type, &e.name, attrs.as_deref())
+                    .map_err(|e| e.to_string())?,
+            };
+        link_memory_entity(conn, memory_id, entity_id).map_err(|e| e.to_string())?;
+        name_to_id.insert(e.name.clone(), entity_id);
+    }
+    for alias_info in aliases {
+        let primary_id = name_to_id.get(&alias_info.primary);
+        let alias_id = name_to_id.get(&alias_info.alias);
+        match (primary_id, alias_id) {
+            (Some(&pid), Some(&aid)) if pid != aid => {
+                merge_entities(conn, aid, pid).map_err(|e| e.to_string())?;
+                name_to_id.insert(alias_info.alias.clone(), pid);
+            }
+            (Some(&pid), None) => {
+                add_entity_alias(conn, pid, &alias_info.alias).map_err(|e| e.to_string())?;
+            }
+            _ => {}
         }
-        ModelProvider::DeepSeek { model_name, .. } => {
-            emit_save_progress(
-                &app,
-                "saveProgress.modelInfo.deepseek",
-                "info",
-                serde_json::json!({ "model": model_name }),
-            );
-            println!("📝 [save_memory] Using DeepSeek API: {}", model_name);
+    }
+    for r in relations {
+        if let (Some(&from_id), Some(&to_id)) = (name_to_id.get(&r.from), name_to_id.get(&r.to)) {
+            upsert_relation(conn, from_id, to_id, &r.relation).map_err(|e| {
+                format!(
+                    "Failed to save relation {} -[{}]-> {}: {}",
+                    r.from, r.relation, r.to, e
+                )
+            })?;
         }
-        ModelProvider::OpenAI { model_name, .. } => {
-            emit_save_progress(
-                &app,
-                "saveProgress.modelInfo.openai",
-                "info",
-                serde_json::json!({ "model": model_name }),
-            );
-            println!("📝 [save_memory] Using OpenAI API: {}", model_name);
+    }
+    Ok(())
+}
+
+/// Undo a Markdown change whose database transaction did not commit: restore the file's
+/// previous contents, or delete it if it was created for this save.
+fn roll_back_memory_file(app: &tauri::AppHandle, path: &Path, original: Option<&str>) {
+    ignore_memory_watcher_echo(app, path);
+    let result = match original {
+        Some(contents) => {
+            library_key(app).and_then(|key| replace_file_contents(path, contents, key.as_ref()))
         }
+        None => fs::remove_file(path).map_err(|e| e.to_string()),
+    };
+    if let Err(e) = result {
+        println!("⚠️ Failed to roll back {:?}: {}", path, e);
+    }
+}
+
+/// Save a new memory and its graph in one transaction. The memory ID is written into the
+/// file's frontmatter before committing, so a failure at any step leaves nothing committed.
+fn commit_new_memory(
+    conn: &mut rusqlite::Connection,
+    path: &Path,
+    content: &str,
+    tags: Option<&[String]>,
+    knowledge: &FusedKnowledge,
+    key: Option<&LibraryKey>,
+) -> Result<Memory, String> {
+    let FusedKnowledge {
+        entities,
+        aliases,
+        relations,
+    } = knowledge;
+    let path_str = path.to_string_lossy().to_string();
+    let tags_str = tags.map(|t| t.join(","));
+    // The file's `created` is the memory's timestamp (it predates the save for imports).
+    let created = read_memory(path, key)?.frontmatter.created;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let memory_id = if created.trim().is_empty() {
+        insert_memory(&tx, content, Some(&path_str), tags_str.as_deref())
+    } else {
+        insert_memory_at(&tx, content, Some(&path_str), tags_str.as_deref(), &created)
     }
+    .map_err(|e| e.to_string())?;
+    link_extracted_graph(&tx, memory_id, entities, relations, aliases)?;
+    let memory = get_memory_by_id(&tx, memory_id).map_err(|e| e.to_string())?;
+
+    // Record the database ID (and, for files adopted from the watcher, the extracted graph)
+    // in the frontmatter; other keys the file already had are left untouched.
+    let graph = memory_graph_frontmatter(entities, relations, aliases);
+    update_frontmatter(path, key, |fm| {
+        fm.id = Some(memory_id);
+        fm.entities = graph.entities;
+        fm.relations = graph.relations;
+        fm.aliases = graph.aliases;
+    })?;
+
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(memory)
+}
+
+/// Inputs for saves that don't come straight from the editor.
+#[derive(Debug, Clone, Default)]
+struct SaveMemoryOptions {
+    /// Adopt a Markdown file that is already on disk instead of writing a new one.
+    existing_file: Option<PathBuf>,
+    /// When the memory happened (`YYYY-MM-DD HH:MM:SS`, UTC); defaults to now.
+    created: Option<String>,
+    /// Entities known up front (e.g. chat participants), added to whatever is extracted.
+    seed_entities: Vec<ExtractedEntity>,
+    /// Frontmatter `source` of new files; defaults to `kraph`.
+    source: Option<String>,
+    /// Extra frontmatter keys for new files (e.g. email headers).
+    frontmatter_extra: serde_yaml::Mapping,
+}
+
+/// Blocking core logic for save_memory, executed inside spawn_blocking to ensure real-time event delivery.
+fn do_save_memory(
+    app: tauri::AppHandle,
+    content: String,
+    tags: Option<Vec<String>>,
+    config: ModelConfig,
+    memories_dir: std::path::PathBuf,
+    options: SaveMemoryOptions,
+) -> Result<Memory, String> {
+    // Emit current model info
+    let provider = provider_for(&config)?;
+    emit_model_info(&app, provider.as_ref());
+    println!(
+        "📝 [save_memory] Using {} model: {}",
+        provider.label(),
+        provider.model(ModelRole::Chat)
+    );
 
     // Step 1: Quick entity extraction to find related entities for history lookup
     emit_save_progress(
@@ -1118,7 +1956,7 @@ fn do_save_memory(
     println!("🔍 [Step 1] Starting entity extraction...");
     let quick_extracted: Option<ExtractedData> = if !content.trim().is_empty() {
         match call_model_extract(&config, ENTITY_EXTRACT_PROMPT, &content) {
-            Ok(extracted) if extracted.entities.is_empty() => {
+            Ok(extracted) if extracted.entities.is_empty() && options.seed_entities.is_empty() => {
                 println!("❌ [Step 1] Extraction returned 0 entities, aborting save");
                 return Err(
                     serde_json::json!({ "code": "saveProgress.errors.noEntities" }).to_string(),
@@ -1287,6 +2125,14 @@ fn do_save_memory(
         println!("⚠️ No entities extracted");
         (Vec::new(), Vec::new(), Vec::new())
     };
+    for seed in &options.seed_entities {
+        if !entities
+            .iter()
+            .any(|e| e.name.eq_ignore_ascii_case(&seed.name))
+        {
+            entities.push(seed.clone());
+        }
+    }
 
     if is_time_normalization_enabled_for_active_library(&app) {
         let normalize_started = Instant::now();
@@ -1294,12 +2140,12 @@ fn do_save_memory(
             .iter()
             .filter(|x| x.entity_type.eq_ignore_ascii_case("Time"))
             .count();
-        normalize_time_entities_in_place(
-            &mut entities,
-            &mut relations,
-            &config,
-            Local::now().date_naive(),
-        );
+        let reference = options
+            .created
+            .as_deref()
+            .and_then(parse_iso_date_from_string)
+            .unwrap_or_else(|| Local::now().date_naive());
+        normalize_time_entities_in_place(&mut entities, &mut relations, &config, reference);
         println!(
             "⏱️ [Step 3.5] Time normalization took {} ms (time_entities_before={}, total_entities={})",
             normalize_started.elapsed().as_millis(),
@@ -1308,77 +2154,108 @@ fn do_save_memory(
         );
     }
 
-    let entity_names: Vec<String> = entities.iter().map(|x| x.name.clone()).collect();
+    persist_memory(
+        &app,
+        content,
+        tags,
+        &memories_dir,
+        FusedKnowledge {
+            entities,
+            aliases,
+            relations,
+        },
+        options,
+    )
+}
+
+/// Step 4 of a save: write the memory file (or adopt `options.existing_file`) and commit it
+/// together with its graph. The file is rolled back if the transaction fails.
+fn persist_memory(
+    app: &tauri::AppHandle,
+    content: String,
+    tags: Option<Vec<String>>,
+    memories_dir: &Path,
+    knowledge: FusedKnowledge,
+    options: SaveMemoryOptions,
+) -> Result<Memory, String> {
+    let graph_frontmatter = memory_graph_frontmatter(
+        &knowledge.entities,
+        &knowledge.relations,
+        &knowledge.aliases,
+    );
+    let key = library_key(app)?;
 
     // Step 4: Persist to database
     emit_save_progress(
-        &app,
+        app,
         "saveProgress.step4.saving",
         "running",
         serde_json::json!({}),
     );
     println!("💾 [Step 4] Saving to database...");
-    let path = write_memory(
-        &memories_dir,
-        &content,
-        tags.as_deref(),
-        if entity_names.is_empty() {
-            None
-        } else {
-            Some(&entity_names)
-        },
-    )?;
-    let path_str = path.to_string_lossy().to_string();
+    // Adopted files are restored to these contents if the save fails; new files are deleted.
+    let original = match &options.existing_file {
+        Some(path) => Some(read_file_text(path, key.as_ref())?),
+        None => None,
+    };
+    let path = match options.existing_file {
+        Some(path) => path,
+        None => {
+            let frontmatter = MdFrontmatter {
+                source: Some(options.source.unwrap_or_else(|| "kraph".to_string())),
+                created: options.created.unwrap_or_default(),
+                tags: tags.clone(),
+                raw: options.frontmatter_extra,
+                ..graph_frontmatter
+            };
+            let template = path_template_for_active_library(app);
+            write_memory(
+                memories_dir,
+                &template,
+                &content,
+                &frontmatter,
+                key.as_ref(),
+            )?
+        }
+    };
+    ignore_memory_watcher_echo(app, &path);
 
-    let saved_memory = {
+    let committed = {
         let db = app.state::<DbState>();
         let mut guard =
             db.0.lock()
                 .map_err(|e: std::sync::PoisonError<_>| e.to_string())?;
-        let conn = guard.as_mut().ok_or("database not initialized")?;
-
-        let tags_str = tags.as_ref().map(|t| t.join(","));
-        let memory_id = insert_memory(conn, &content, Some(&path_str), tags_str.as_deref())
-            .map_err(|e| e.to_string())?;
-
-        let mut name_to_id: std::collections::HashMap<String, i64> =
-            std::collections::HashMap::new();
-        for e in &entities {
-            let attrs = e.attributes.as_ref().map(|a| a.to_string());
-            let entity_id =
-                match find_entity_id_by_name_or_alias(conn, &e.name).map_err(|e| e.to_string())? {
-                    Some(id) => id,
-                    None => upsert_entity(conn, &e.entity_type, &e.name, attrs.as_deref())
-                        .map_err(|e| e.to_string())?,
-                };
-            link_memory_entity(conn, memory_id, entity_id).map_err(|e| e.to_string())?;
-            name_to_id.insert(e.name.clone(), entity_id);
-        }
-        for alias_info in &aliases {
-            let primary_id = name_to_id.get(&alias_info.primary);
-            let alias_id = name_to_id.get(&alias_info.alias);
-            match (primary_id, alias_id) {
-                (Some(&pid), Some(&aid)) if pid != aid => {
-                    merge_entities(conn, aid, pid).map_err(|e| e.to_string())?;
-                    name_to_id.insert(alias_info.alias.clone(), pid);
-                }
-                (Some(&pid), None) => {
-                    add_entity_alias(conn, pid, &alias_info.alias).map_err(|e| e.to_string())?;
-                }
-                _ => {}
-            }
+        match guard.as_mut() {
+            Some(conn) => commit_new_memory(
+                conn,
+                &path,
+                &content,
+                tags.as_deref(),
+                &knowledge,
+                key.as_ref(),
+            ),
+            None => Err("database not initialized".to_string()),
         }
-        for r in &relations {
-            if let (Some(&from_id), Some(&to_id)) = (name_to_id.get(&r.from), name_to_id.get(&r.to))
-            {
-                let _ = upsert_relation(conn, from_id, to_id, &r.relation);
-            }
+    };
+    let saved_memory = match committed {
+        Ok(memory) => memory,
+        Err(e) => {
+            roll_back_memory_file(app, &path, original.as_deref());
+            return Err(e);
         }
-
-        get_memory_by_id(conn, memory_id).map_err(|e| e.to_string())?
     };
+    ignore_memory_watcher_echo(app, &path);
+    refresh_entity_pages(app, Some(&linked_entity_ids(app, saved_memory.id)));
+    let mut message = format!("Add memory #{}: {}", saved_memory.id, memory_title(&content));
+    if !knowledge.aliases.is_empty() {
+        message.push_str("\n\nMerged aliases:\n");
+        for a in &knowledge.aliases {
+            message.push_str(&format!("- {} → {}\n", a.alias, a.primary));
+        }
+    }
+    record_memory_history(app, &message);
 
-    emit_save_progress(&app, "saveProgress.done", "done", serde_json::json!({}));
+    emit_save_progress(app, "saveProgress.done", "done", serde_json::json!({}));
     println!("✅ Memory saved successfully!");
     Ok(saved_memory)
 }
@@ -1393,418 +2270,2018 @@ async fn save_memory(
 ) -> Result<Memory, String> {
     let config = config_state.0.lock().map_err(|e| e.to_string())?.clone();
     let memories_dir = get_current_data_dir(&data_dir)?.join("memories");
-    tokio::task::spawn_blocking(move || do_save_memory(app, content, tags, config, memories_dir))
+    tokio::task::spawn_blocking(move || {
+        do_save_memory(
+            app,
+            content,
+            tags,
+            config,
+            memories_dir,
+            SaveMemoryOptions::default(),
+        )
+    })
         .await
         .map_err(|e| e.to_string())?
 }
 
-#[tauri::command]
-fn get_memories_list(db: State<DbState>) -> Result<Vec<Memory>, String> {
-    let mut guard = (&*db)
-        .0
-        .lock()
-        .map_err(|e: std::sync::PoisonError<_>| e.to_string())?;
-    let conn = guard.as_mut().ok_or("database not initialized")?;
-    list_memories(conn).map_err(|e| e.to_string())
+/// Where a batched import reports to: its progress event, the payload key holding an item's
+/// label, and the log prefix.
+struct ImportChannel {
+    event: &'static str,
+    label_key: &'static str,
+    log_tag: &'static str,
 }
 
-#[tauri::command]
-fn get_graph(db: State<DbState>) -> Result<GraphData, String> {
-    let mut guard = (&*db)
-        .0
-        .lock()
-        .map_err(|e: std::sync::PoisonError<_>| e.to_string())?;
-    let conn = guard.as_mut().ok_or("database not initialized")?;
-    get_graph_data(conn).map_err(|e| e.to_string())
+/// Helper: emit a batched-import progress event to the frontend.
+fn emit_import_progress(
+    app: &tauri::AppHandle,
+    channel: &ImportChannel,
+    current: usize,
+    total: usize,
+    batch: usize,
+    label: &str,
+    status: &str,
+) {
+    let mut payload = serde_json::json!({
+        "current": current,
+        "total": total,
+        "batch": batch,
+        "status": status,
+    });
+    payload[channel.label_key] = serde_json::Value::String(label.to_string());
+    let _ = app.emit(channel.event, payload);
 }
 
-#[tauri::command]
-fn query_entity(name: String, db: State<DbState>) -> Result<Option<Entity>, String> {
-    let mut guard = (&*db)
-        .0
-        .lock()
-        .map_err(|e: std::sync::PoisonError<_>| e.to_string())?;
-    let conn = guard.as_mut().ok_or("database not initialized")?;
-    get_entity_by_name(conn, &name).map_err(|e| e.to_string())
+/// Outcome of `run_batched_import`: how many items were saved, and `(label, error)` per failure.
+#[derive(Debug, Default)]
+struct BatchedImportOutcome {
+    saved: usize,
+    failures: Vec<(String, String)>,
 }
 
-#[tauri::command]
-fn search_memories_by_entity(entity_id: i64, db: State<DbState>) -> Result<Vec<Memory>, String> {
-    let mut guard = (&*db)
-        .0
-        .lock()
-        .map_err(|e: std::sync::PoisonError<_>| e.to_string())?;
-    let conn = guard.as_mut().ok_or("database not initialized")?;
-    get_memories_for_entity(conn, entity_id).map_err(|e| e.to_string())
+/// Helper: save `items` one by one in batches of `batch_size`. The channel gets a `running`
+/// event per item and a `batch` (or, at the end, `done`) event per finished batch; `label`
+/// names an item in those events and in the failure list.
+fn run_batched_import<T>(
+    app: &tauri::AppHandle,
+    channel: &ImportChannel,
+    items: &[T],
+    batch_size: usize,
+    label: impl Fn(&T) -> &str,
+    mut save: impl FnMut(&T) -> Result<(), String>,
+) -> BatchedImportOutcome {
+    let mut outcome = BatchedImportOutcome::default();
+    let batch_size = batch_size.max(1);
+    let total = items.len();
+    if total == 0 {
+        emit_import_progress(app, channel, 0, 0, 0, "", "done");
+    }
+    for (batch_index, batch) in items.chunks(batch_size).enumerate() {
+        for (offset, item) in batch.iter().enumerate() {
+            let current = batch_index * batch_size + offset + 1;
+            let name = label(item);
+            emit_import_progress(app, channel, current, total, batch_index + 1, name, "running");
+            match save(item) {
+                Ok(()) => outcome.saved += 1,
+                Err(e) => {
+                    println!("⚠️ [{}] Failed to save {}: {}", channel.log_tag, name, e);
+                    outcome.failures.push((name.to_string(), e));
+                }
+            }
+        }
+        let done = (batch_index * batch_size + batch.len()).min(total);
+        let status = if done == total { "done" } else { "batch" };
+        emit_import_progress(app, channel, done, total, batch_index + 1, "", status);
+    }
+    outcome
 }
 
-#[tauri::command]
-fn get_character_profile(entity_id: i64, db: State<DbState>) -> Result<serde_json::Value, String> {
-    let mut guard = (&*db)
-        .0
-        .lock()
-        .map_err(|e: std::sync::PoisonError<_>| e.to_string())?;
+/// Match every distinct address to a Person entity, creating it with the address when missing,
+/// before anything is extracted, so every memory links to the same entity however the model
+/// spells the name. Returns lowercase address → entity name.
+fn precreate_people<'a>(
+    app: &tauri::AppHandle,
+    mailboxes: impl IntoIterator<Item = &'a email_import::Mailbox>,
+) -> Result<std::collections::HashMap<String, String>, String> {
+    let mut person_names = std::collections::HashMap::new();
+    let db = app.state::<DbState>();
+    let mut guard =
+        db.0.lock()
+            .map_err(|e: std::sync::PoisonError<_>| e.to_string())?;
     let conn = guard.as_mut().ok_or("database not initialized")?;
-    let entity = get_entity_by_id(conn, entity_id).map_err(|e| e.to_string())?;
-    let memories = get_memories_for_entity(conn, entity_id).map_err(|e| e.to_string())?;
-    let relations = list_relations(conn).map_err(|e| e.to_string())?;
-    let entity_relations: Vec<_> = relations
-        .into_iter()
-        .filter(|r| r.from_entity_id == entity_id || r.to_entity_id == entity_id)
-        .collect();
-
-    // Collect all entity IDs needed for name resolution
-    let mut id_set: std::collections::HashSet<i64> = std::collections::HashSet::new();
-    for r in &entity_relations {
-        id_set.insert(r.from_entity_id);
-        id_set.insert(r.to_entity_id);
-    }
-    let mut id_to_name: std::collections::HashMap<i64, String> = std::collections::HashMap::new();
-    for id in id_set {
-        if let Ok(e) = get_entity_by_id(conn, id) {
-            id_to_name.insert(id, e.name);
+    for mailbox in mailboxes {
+        if let std::collections::hash_map::Entry::Vacant(entry) =
+            person_names.entry(mailbox.address.to_lowercase())
+        {
+            entry.insert(precreate_correspondent(conn, mailbox)?);
         }
     }
+    Ok(person_names)
+}
 
-    // Build enriched relation list with entity names
-    let enriched_relations: Vec<serde_json::Value> = entity_relations
-        .iter()
-        .map(|r| {
-            serde_json::json!({
-                "from_entity_id": r.from_entity_id,
-                "from_name": id_to_name.get(&r.from_entity_id).cloned().unwrap_or_default(),
-                "to_entity_id": r.to_entity_id,
-                "to_name": id_to_name.get(&r.to_entity_id).cloned().unwrap_or_default(),
-                "relation_type": r.relation_type,
-                "strength": r.strength,
-            })
-        })
-        .collect();
+/// Name of the Person entity `precreate_people` chose for `mailbox`.
+fn person_name(
+    person_names: &std::collections::HashMap<String, String>,
+    mailbox: &email_import::Mailbox,
+) -> String {
+    person_names
+        .get(&mailbox.address.to_lowercase())
+        .cloned()
+        .unwrap_or_else(|| mailbox.label().to_string())
+}
 
-    Ok(serde_json::json!({
-        "entity": entity,
-        "memories": memories,
-        "relations": enriched_relations
-    }))
+fn do_import_chat_log(
+    app: tauri::AppHandle,
+    request: ChatImportRequest,
+    config: ModelConfig,
+    memories_dir: PathBuf,
+) -> Result<ChatImportReport, String> {
+    let path = Path::new(&request.path);
+    let format = match request.format.as_deref().filter(|f| !f.trim().is_empty()) {
+        Some(format) => ChatFormat::parse(format)?,
+        None => ChatFormat::detect(path)?,
+    };
+    let split = ChatSplit::parse(request.split_by.as_deref().unwrap_or_default())?;
+    let messages = read_chat_log(path, format)?;
+    let conversations = group_conversations(&messages, format, split);
+    let mut report = ChatImportReport {
+        format: format.label().to_string(),
+        dry_run: request.dry_run,
+        messages: messages.len(),
+        conversations: conversations.iter().map(|c| c.summary()).collect(),
+        ..Default::default()
+    };
+    println!(
+        "💬 [import_chat_log] {} messages in {} conversation(s) from {}",
+        report.messages,
+        conversations.len(),
+        request.path
+    );
+    if request.dry_run {
+        return Ok(report);
+    }
+
+    let total = conversations.len();
+    let outcome = run_batched_import(
+        &app,
+        &CHAT_IMPORT_CHANNEL,
+        &conversations,
+        request.batch_size.unwrap_or(CHAT_IMPORT_BATCH_SIZE),
+        |conversation| conversation.title.as_str(),
+        |conversation| {
+            let options = SaveMemoryOptions {
+                created: Some(conversation.created.clone()),
+                seed_entities: conversation
+                    .seed_participants()
+                    .map(|name| ExtractedEntity {
+                        entity_type: "Person".to_string(),
+                        name: name.clone(),
+                        attributes: None,
+                    })
+                    .collect(),
+                source: Some(format!("chat:{}", format.tag())),
+                ..Default::default()
+            };
+            let tags = vec!["chat".to_string(), format.tag().to_string()];
+            do_save_memory(
+                app.clone(),
+                conversation.content.clone(),
+                Some(tags),
+                config.clone(),
+                memories_dir.clone(),
+                options,
+            )
+            .map(|_| ())
+        },
+    );
+    report.saved = outcome.saved;
+    report.failed = outcome.failures.len();
+    report.errors = outcome
+        .failures
+        .into_iter()
+        .map(|(title, error)| ChatImportError { title, error })
+        .collect();
+    println!(
+        "✅ [import_chat_log] Saved {}/{} conversation(s), {} failed",
+        report.saved, total, report.failed
+    );
+    Ok(report)
 }
 
+/// Import a Telegram JSON, WhatsApp TXT or WeChat CSV/HTML chat export. Conversations (per day
+/// or per chat) go through the regular save pipeline with their participants seeded as Person
+/// entities; `chat-import-progress` events report each conversation and each finished batch.
 #[tauri::command]
-fn get_timeline(db: State<DbState>) -> Result<Vec<Memory>, String> {
-    let mut guard = (&*db)
-        .0
-        .lock()
-        .map_err(|e: std::sync::PoisonError<_>| e.to_string())?;
-    let conn = guard.as_mut().ok_or("database not initialized")?;
-    list_memories(conn).map_err(|e| e.to_string())
+async fn import_chat_log(
+    app: tauri::AppHandle,
+    request: ChatImportRequest,
+    config_state: State<'_, ModelConfigState>,
+    data_dir: State<'_, AppDataDir>,
+) -> Result<ChatImportReport, String> {
+    let config = config_state.0.lock().map_err(|e| e.to_string())?.clone();
+    let memories_dir = get_current_data_dir(&data_dir)?.join("memories");
+    tokio::task::spawn_blocking(move || do_import_chat_log(app, request, config, memories_dir))
+        .await
+        .map_err(|e| e.to_string())?
 }
 
-/// Blocking core logic for update_memory_content, executed inside spawn_blocking to ensure real-time event delivery.
-fn do_update_memory(
+fn do_import_email(
     app: tauri::AppHandle,
-    memory_id: i64,
-    content: String,
-    tags_str: Option<String>,
+    request: EmailImportRequest,
     config: ModelConfig,
-) -> Result<Memory, String> {
-    println!("📝 [update_memory ID:{}]", memory_id);
-    match &config.provider {
-        ModelProvider::Ollama {
-            extract_model_name, ..
-        } => {
-            emit_save_progress(
-                &app,
-                "saveProgress.modelInfo.ollama",
-                "info",
-                serde_json::json!({ "model": extract_model_name }),
-            );
-        }
-        ModelProvider::DeepSeek { model_name, .. } => {
-            emit_save_progress(
-                &app,
-                "saveProgress.modelInfo.deepseek",
-                "info",
-                serde_json::json!({ "model": model_name }),
-            );
-        }
-        ModelProvider::OpenAI { model_name, .. } => {
-            emit_save_progress(
-                &app,
-                "saveProgress.modelInfo.openai",
-                "info",
-                serde_json::json!({ "model": model_name }),
-            );
-        }
+    memories_dir: PathBuf,
+) -> Result<EmailImportReport, String> {
+    let by_thread = match request.split_by.as_deref().unwrap_or_default().trim() {
+        "" | "message" => false,
+        "thread" => true,
+        other => return Err(format!("Unknown split '{}'; use message or thread.", other)),
+    };
+    let messages = read_messages(Path::new(&request.path))?;
+    let mut seen = imported_message_ids(&memories_dir, library_key(&app)?.as_ref())?;
+    let total_messages = messages.len();
+    let fresh: Vec<_> = messages
+        .into_iter()
+        .filter(|m| seen.insert(m.message_id.clone()))
+        .collect();
+    let memories = group_messages(&fresh, by_thread);
+    let mut report = EmailImportReport {
+        dry_run: request.dry_run,
+        messages: total_messages,
+        duplicates: total_messages - fresh.len(),
+        memories: memories.iter().map(|m| m.summary()).collect(),
+        ..Default::default()
+    };
+    println!(
+        "📧 [import_email] {} messages ({} already imported) in {} memories from {}",
+        report.messages,
+        report.duplicates,
+        memories.len(),
+        request.path
+    );
+    if request.dry_run {
+        return Ok(report);
     }
 
-    // Step 1: Quick entity extraction
-    emit_save_progress(
-        &app,
-        "saveProgress.step1.extracting",
-        "running",
-        serde_json::json!({}),
+    let person_names = precreate_people(&app, memories.iter().flat_map(|m| m.correspondents()))?;
+    println!(
+        "👥 [import_email] {} correspondent(s) ready",
+        person_names.len()
     );
-    println!("🔍 Starting entity extraction...");
-    let quick_extracted = if !content.trim().is_empty() {
-        match call_model_extract(&config, ENTITY_EXTRACT_PROMPT, &content) {
-            Ok(extracted) if extracted.entities.is_empty() => {
-                println!("❌ [Step 1] Extraction returned 0 entities, aborting update");
-                return Err(
-                    serde_json::json!({ "code": "saveProgress.errors.noEntities" }).to_string(),
-                );
-            }
-            Ok(extracted) => {
-                emit_save_progress(
-                    &app,
-                    "saveProgress.step1.extracted",
-                    "success",
-                    serde_json::json!({ "count": extracted.entities.len() }),
-                );
-                println!("✅ Extracted {} entities", extracted.entities.len());
-                Some(extracted)
-            }
-            Err(e) => {
-                println!(
-                    "❌ [Step 1] Entity extraction failed, aborting update: {}",
-                    e
-                );
-                return Err(
-                    serde_json::json!({ "code": "saveProgress.errors.extractFailed", "reason": e })
-                        .to_string(),
-                );
-            }
-        }
-    } else {
-        None
-    };
 
-    // Step 2: Fetch related historical memories for knowledge fusion
-    emit_save_progress(
+    let total = memories.len();
+    let outcome = run_batched_import(
         &app,
-        "saveProgress.step2.lookingUp",
-        "running",
-        serde_json::json!({}),
+        &EMAIL_IMPORT_CHANNEL,
+        &memories,
+        request.batch_size.unwrap_or(EMAIL_IMPORT_BATCH_SIZE),
+        |memory| memory.subject.as_str(),
+        |memory| {
+            let options = SaveMemoryOptions {
+                created: memory.created.clone(),
+                seed_entities: memory
+                    .correspondents()
+                    .into_iter()
+                    .map(|mailbox| ExtractedEntity {
+                        entity_type: "Person".to_string(),
+                        name: person_name(&person_names, mailbox),
+                        attributes: Some(serde_json::json!({ "email": mailbox.address })),
+                    })
+                    .collect(),
+                source: Some("email".to_string()),
+                frontmatter_extra: memory.frontmatter(),
+                ..Default::default()
+            };
+            do_save_memory(
+                app.clone(),
+                memory.content.clone(),
+                Some(vec!["email".to_string()]),
+                config.clone(),
+                memories_dir.clone(),
+                options,
+            )
+            .map(|_| ())
+        },
     );
-    let historical_memories = if let Some(ref ex) = quick_extracted {
-        let selected = {
-            let db = app.state::<DbState>();
-            let mut guard =
-                db.0.lock()
-                    .map_err(|e: std::sync::PoisonError<_>| e.to_string())?;
-            let conn = guard.as_mut().ok_or("database not initialized")?;
-            collect_relevant_historical_memories(conn, ex, &content, Some(memory_id))?
-        };
-        if selected.is_empty() {
-            emit_save_progress(
-                &app,
-                "saveProgress.step2.noHistory",
-                "success",
-                serde_json::json!({}),
-            );
-        } else {
-            emit_save_progress(
-                &app,
-                "saveProgress.step2.found",
-                "success",
-                serde_json::json!({ "count": selected.len() }),
-            );
-        }
-        selected
-    } else {
-        emit_save_progress(
-            &app,
-            "saveProgress.step2.noHistory",
-            "success",
-            serde_json::json!({}),
-        );
-        Vec::new()
-    };
+    report.saved = outcome.saved;
+    report.failed = outcome.failures.len();
+    report.errors = outcome
+        .failures
+        .into_iter()
+        .map(|(subject, error)| EmailImportError { subject, error })
+        .collect();
+    println!(
+        "✅ [import_email] Saved {}/{} memories, {} failed",
+        report.saved, total, report.failed
+    );
+    Ok(report)
+}
 
-    // Step 3: Knowledge fusion
-    let step3_started = Instant::now();
-    let fused = if !historical_memories.is_empty() && content.trim().len() > 5 {
-        emit_save_progress(
-            &app,
-            "saveProgress.step3.fusing",
-            "running",
-            serde_json::json!({}),
-        );
-        println!(
-            "🧠 [Step 3][update_memory:{}] Starting knowledge fusion (historical_memories={}, new_content_chars={})",
-            memory_id,
-            historical_memories.len(),
-            content.chars().count()
-        );
-        let fusion_call_started = Instant::now();
-        let fusion_result = call_model_fusion(
-            &config,
-            KNOWLEDGE_FUSION_PROMPT,
-            &historical_memories,
-            &content,
-        );
-        println!(
-            "⏱️ [Step 3][update_memory:{}] call_model_fusion returned in {} ms",
-            memory_id,
-            fusion_call_started.elapsed().as_millis()
-        );
-        fusion_result.ok()
-    } else {
-        emit_save_progress(
-            &app,
-            "saveProgress.step3.skipped",
-            "skipped",
-            serde_json::json!({}),
-        );
-        None
+/// Import an mbox file or `.eml` messages, one memory per message or per thread. Sender,
+/// recipients, subject and Message-ID go into the frontmatter, correspondents are created as
+/// Person entities with their address, and already-imported Message-IDs are skipped.
+#[tauri::command]
+async fn import_email(
+    app: tauri::AppHandle,
+    request: EmailImportRequest,
+    config_state: State<'_, ModelConfigState>,
+    data_dir: State<'_, AppDataDir>,
+) -> Result<EmailImportReport, String> {
+    let config = config_state.0.lock().map_err(|e| e.to_string())?.clone();
+    let memories_dir = get_current_data_dir(&data_dir)?.join("memories");
+    tokio::task::spawn_blocking(move || do_import_email(app, request, config, memories_dir))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Helper: emit a document-import progress event to the frontend.
+fn emit_document_import_progress(
+    app: &tauri::AppHandle,
+    current: usize,
+    total: usize,
+    section: Option<&str>,
+    status: &str,
+) {
+    let _ = app.emit(
+        "document-import-progress",
+        serde_json::json!({
+            "current": current,
+            "total": total,
+            "section": section,
+            "status": status,
+        }),
+    );
+}
+
+fn do_import_document(
+    app: tauri::AppHandle,
+    request: DocumentImportRequest,
+    config: ModelConfig,
+    memories_dir: PathBuf,
+) -> Result<DocumentImportReport, String> {
+    let document = read_document(Path::new(&request.path))?;
+    let budget = request.max_chunk_tokens.unwrap_or(DEFAULT_CHUNK_TOKENS);
+    let chunks = chunk_document(&document.text, budget);
+    let total = chunks.len();
+    let mut report = DocumentImportReport {
+        document_id: document.id.clone(),
+        title: document.title.clone(),
+        format: document.format.label().to_string(),
+        dry_run: request.dry_run,
+        chunks: chunks.iter().map(|c| c.summary()).collect(),
+        ..Default::default()
     };
     println!(
-        "⏱️ [Step 3][update_memory:{}] Total stage time: {} ms",
-        memory_id,
-        step3_started.elapsed().as_millis()
+        "📄 [import_document] '{}' split into {} chunk(s) of ≤{} tokens",
+        document.title, total, budget
     );
+    if request.dry_run {
+        return Ok(report);
+    }
+    let key = library_key(&app)?;
+    if let Some(part) = document_parts(&memories_dir, &document.id, key.as_ref())?.first() {
+        return Err(format!(
+            "'{}' was already imported (memory #{}).",
+            document.title,
+            part.memory_id.unwrap_or_default()
+        ));
+    }
 
-    let (mut entities, mut relations, aliases) = if let Some(fused_data) = fused {
-        emit_save_progress(
-            &app,
-            "saveProgress.step3.fusionDone",
-            "success",
-            serde_json::json!({ "entities": fused_data.entities.len(), "relations": fused_data.relations.len() }),
-        );
-        println!("✅ Knowledge fusion complete");
-        (
-            fused_data.entities,
-            fused_data.relations,
-            fused_data.aliases,
-        )
-    } else if let Some(ex) = quick_extracted {
-        emit_save_progress(
+    // Step 1: extract each chunk on its own so every prompt fits the model's context.
+    let mut extractions = Vec::with_capacity(total);
+    for chunk in &chunks {
+        emit_document_import_progress(
             &app,
-            "saveProgress.step3.extractionDone",
-            "success",
-            serde_json::json!({ "entities": ex.entities.len(), "relations": ex.relations.len() }),
+            chunk.index,
+            total,
+            chunk.section.as_deref(),
+            "extracting",
         );
-        println!("✅ Using quick extraction results");
-        (ex.entities, ex.relations, Vec::new())
-    } else {
-        emit_save_progress(
-            &app,
-            "saveProgress.step3.noEntities",
-            "warning",
-            serde_json::json!({}),
+        let content = chunk_content(&document, chunk, total);
+        match call_model_extract(&config, ENTITY_EXTRACT_PROMPT, &content) {
+            Ok(extracted) => extractions.push(Some(extracted)),
+            Err(e) => {
+                println!(
+                    "⚠️ [import_document] Extraction failed for chunk {}: {}",
+                    chunk.index, e
+                );
+                report.errors.push(DocumentChunkError {
+                    index: chunk.index,
+                    error: e,
+                });
+                extractions.push(None);
+            }
+        }
+    }
+    if extractions.iter().all(Option::is_none) {
+        let reason = &report.errors[0].error;
+        return Err(
+            serde_json::json!({ "code": "saveProgress.errors.extractFailed", "reason": reason })
+                .to_string(),
         );
-        (Vec::new(), Vec::new(), Vec::new())
-    };
+    }
 
+    // Step 2: reconcile the chunks into one graph update.
+    let mut graph = reconcile_extractions(&extractions);
     if is_time_normalization_enabled_for_active_library(&app) {
-        let normalize_started = Instant::now();
-        let time_entities_before = entities
-            .iter()
-            .filter(|x| x.entity_type.eq_ignore_ascii_case("Time"))
-            .count();
+        let reference = Local::now().date_naive();
         normalize_time_entities_in_place(
-            &mut entities,
-            &mut relations,
+            &mut graph.entities,
+            &mut graph.relations,
             &config,
-            Local::now().date_naive(),
-        );
-        println!(
-            "⏱️ [Step 3.5][update_memory:{}] Time normalization took {} ms (time_entities_before={}, total_entities={})",
-            memory_id,
-            normalize_started.elapsed().as_millis(),
-            time_entities_before,
-            entities.len()
+            reference,
         );
     }
+    // Normalization may rename Time entities; chunks only link to names that survived.
+    let known: std::collections::HashSet<String> = graph
+        .entities
+        .iter()
+        .map(|e| e.name.to_lowercase())
+        .collect();
+    for mentioned in &mut graph.chunk_entities {
+        mentioned.retain(|e| known.contains(&e.name.to_lowercase()));
+    }
+    report.entities = graph.entities.len();
+    report.relations = graph.relations.len();
+    println!(
+        "🧩 [import_document] Reconciled {} entities and {} relations",
+        report.entities, report.relations
+    );
 
-    // Step 4: Persist to database
-    emit_save_progress(
+    // Step 3: write the parent and chunk files, then index them in one transaction.
+    emit_document_import_progress(&app, total, total, None, "saving");
+    let mut tags = vec!["document".to_string()];
+    for tag in request.tags.unwrap_or_default() {
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    let template = path_template_for_active_library(&app);
+    let mut files: Vec<(PathBuf, String)> = Vec::with_capacity(total + 1);
+    let written = (|| {
+        let outline = outline_content(&document, &chunks);
+        let graph_frontmatter = memory_graph_frontmatter(&graph.entities, &graph.relations, &[]);
+        let frontmatter = MdFrontmatter {
+            source: Some(format!("document:{}", document.format.label())),
+            tags: Some(tags.clone()),
+            raw: document_frontmatter(&document, None, total),
+            ..graph_frontmatter
+        };
+        let path = write_memory(
+            &memories_dir,
+            &template,
+            &outline,
+            &frontmatter,
+            key.as_ref(),
+        )?;
+        ignore_memory_watcher_echo(&app, &path);
+        files.push((path, outline));
+        for (chunk, mentioned) in chunks.iter().zip(&graph.chunk_entities) {
+            let content = chunk_content(&document, chunk, total);
+            let frontmatter = MdFrontmatter {
+                source: Some(format!("document:{}", document.format.label())),
+                tags: Some(tags.clone()),
+                raw: document_frontmatter(&document, Some(chunk), total),
+                ..memory_graph_frontmatter(mentioned, &[], &[])
+            };
+            let path = write_memory(
+                &memories_dir,
+                &template,
+                &content,
+                &frontmatter,
+                key.as_ref(),
+            )?;
+            ignore_memory_watcher_echo(&app, &path);
+            files.push((path, content));
+        }
+        Ok::<_, String>(())
+    })();
+    let committed = written.and_then(|_| {
+        let db = app.state::<DbState>();
+        let mut guard =
+            db.0.lock()
+                .map_err(|e: std::sync::PoisonError<_>| e.to_string())?;
+        let conn = guard.as_mut().ok_or("database not initialized")?;
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        let mut ids = Vec::with_capacity(files.len());
+        for (i, (path, content)) in files.iter().enumerate() {
+            let path_str = path.to_string_lossy().to_string();
+            let memory_id = insert_memory(&tx, content, Some(&path_str), Some(&tags.join(",")))
+                .map_err(|e| e.to_string())?;
+            if i == 0 {
+                link_extracted_graph(&tx, memory_id, &graph.entities, &graph.relations, &[])?;
+            } else {
+                link_extracted_graph(&tx, memory_id, &graph.chunk_entities[i - 1], &[], &[])?;
+            }
+            ignore_memory_watcher_echo(&app, path);
+            update_frontmatter(path, key.as_ref(), |fm| fm.id = Some(memory_id))?;
+            ids.push(memory_id);
+        }
+        tx.commit().map_err(|e| e.to_string())?;
+        Ok(ids)
+    });
+    let ids = match committed {
+        Ok(ids) => ids,
+        Err(e) => {
+            for (path, _) in &files {
+                roll_back_memory_file(&app, path, None);
+            }
+            return Err(e);
+        }
+    };
+    for (path, _) in &files {
+        ignore_memory_watcher_echo(&app, path);
+    }
+    report.memory_id = ids.first().copied();
+    report.chunk_memory_ids = ids[1..].to_vec();
+    refresh_entity_pages(&app, Some(&linked_entity_ids(&app, ids[0])));
+    record_memory_history(
         &app,
-        "saveProgress.step4.saving",
-        "running",
-        serde_json::json!({}),
+        &format!(
+            "Import document #{}: {} ({} parts)",
+            ids[0], document.title, total
+        ),
     );
-    let updated_memory = {
+    emit_document_import_progress(&app, total, total, None, "done");
+    println!(
+        "✅ [import_document] Saved '{}' as memory #{} with {} chunk(s)",
+        document.title, ids[0], total
+    );
+    Ok(report)
+}
+
+/// Import a long .txt, .md, .html or .epub document. It is split into chunks within a token
+/// budget, each chunk is extracted separately, and the results are reconciled into one graph
+/// update on a parent memory; chunk memories link back to it through `document_id`.
+/// `document-import-progress` events report each chunk.
+#[tauri::command]
+async fn import_document(
+    app: tauri::AppHandle,
+    request: DocumentImportRequest,
+    config_state: State<'_, ModelConfigState>,
+    data_dir: State<'_, AppDataDir>,
+) -> Result<DocumentImportReport, String> {
+    let config = config_state.0.lock().map_err(|e| e.to_string())?.clone();
+    let memories_dir = get_current_data_dir(&data_dir)?.join("memories");
+    tokio::task::spawn_blocking(move || do_import_document(app, request, config, memories_dir))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// The memories of the document a memory belongs to: the parent first, then the chunks in
+/// order. Empty when the memory is not part of an imported document.
+#[tauri::command]
+fn get_document_parts(
+    memory_id: i64,
+    db: State<DbState>,
+    data_dir: State<AppDataDir>,
+    key_state: State<LibraryKeyState>,
+) -> Result<Vec<DocumentPart>, String> {
+    let key = get_library_key(&key_state)?;
+    let md_path = {
+        let mut guard =
+            db.0.lock()
+                .map_err(|e: std::sync::PoisonError<_>| e.to_string())?;
+        let conn = guard.as_mut().ok_or("database not initialized")?;
+        get_memory_by_id(conn, memory_id)
+            .map_err(|e| e.to_string())?
+            .md_file_path
+    };
+    let Some(document_id) = md_path
+        .as_deref()
+        .and_then(|path| document_id_of(Path::new(path), key.as_ref()))
+    else {
+        return Ok(Vec::new());
+    };
+    document_parts(
+        &get_current_data_dir(&data_dir)?.join("memories"),
+        &document_id,
+        key.as_ref(),
+    )
+}
+
+fn do_import_calendar(
+    app: tauri::AppHandle,
+    request: CalendarImportRequest,
+    memories_dir: PathBuf,
+) -> Result<CalendarImportReport, String> {
+    let cutoff = match request.expand_until.as_deref().map(str::trim) {
+        Some(date) if !date.is_empty() => NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .map_err(|_| format!("Invalid cutoff date '{}'; use YYYY-MM-DD.", date))?,
+        _ => Local::now().date_naive() + ChronoDuration::days(DEFAULT_EXPANSION_DAYS),
+    };
+    let events = read_calendar(Path::new(&request.path))?;
+    let occurrences = expand_occurrences(&events, cutoff);
+    let known = imported_event_keys(&memories_dir, library_key(&app)?.as_ref())?;
+    let (fresh, duplicates): (Vec<_>, Vec<_>) = occurrences
+        .into_iter()
+        .partition(|o| !known.contains(&o.key()));
+    let mut report = CalendarImportReport {
+        dry_run: request.dry_run,
+        events: events.len(),
+        occurrences: fresh.len() + duplicates.len(),
+        duplicates: duplicates.len(),
+        imported: fresh.iter().map(|o| o.summary()).collect(),
+        ..Default::default()
+    };
+    println!(
+        "📅 [import_calendar] {} events, {} occurrences until {} ({} already imported)",
+        report.events, report.occurrences, cutoff, report.duplicates
+    );
+    if request.dry_run {
+        return Ok(report);
+    }
+
+    // People with an address are matched to (or created as) Person entities up front, the
+    // same way email correspondents are.
+    let person_names = precreate_people(
+        &app,
+        fresh
+            .iter()
+            .flat_map(|o| o.people())
+            .filter(|mailbox| mailbox.address.contains('@')),
+    )?;
+
+    // A calendar is saved as a single batch; no model is called, so there is nothing to pace.
+    let total = fresh.len();
+    let outcome = run_batched_import(
+        &app,
+        &CALENDAR_IMPORT_CHANNEL,
+        &fresh,
+        total,
+        |occurrence| occurrence.event.summary.as_str(),
+        |occurrence| {
+            let (entities, relations) =
+                occurrence.graph(|mailbox| person_name(&person_names, mailbox));
+            let options = SaveMemoryOptions {
+                created: Some(occurrence.created()),
+                source: Some("calendar".to_string()),
+                frontmatter_extra: occurrence.frontmatter(),
+                ..Default::default()
+            };
+            let knowledge = FusedKnowledge {
+                entities,
+                aliases: Vec::new(),
+                relations,
+            };
+            persist_memory(
+                &app,
+                occurrence.content(),
+                Some(vec!["calendar".to_string()]),
+                &memories_dir,
+                knowledge,
+                options,
+            )
+            .map(|_| ())
+        },
+    );
+    report.saved = outcome.saved;
+    report.failed = outcome.failures.len();
+    report.errors = outcome
+        .failures
+        .into_iter()
+        .map(|(title, error)| CalendarImportError { title, error })
+        .collect();
+    println!(
+        "✅ [import_calendar] Saved {}/{} occurrences, {} failed",
+        report.saved, total, report.failed
+    );
+    Ok(report)
+}
+
+/// Import an `.ics` calendar. Each event occurrence becomes a memory with an Event entity,
+/// its normalized Time, Location and Person attendees; no model is called. Recurring events
+/// are expanded up to `expand_until`, and UIDs already imported are skipped.
+#[tauri::command]
+async fn import_calendar(
+    app: tauri::AppHandle,
+    request: CalendarImportRequest,
+    data_dir: State<'_, AppDataDir>,
+) -> Result<CalendarImportReport, String> {
+    let memories_dir = get_current_data_dir(&data_dir)?.join("memories");
+    tokio::task::spawn_blocking(move || do_import_calendar(app, request, memories_dir))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+fn get_memories_list(db: State<DbState>) -> Result<Vec<Memory>, String> {
+    let mut guard = (&*db)
+        .0
+        .lock()
+        .map_err(|e: std::sync::PoisonError<_>| e.to_string())?;
+    let conn = guard.as_mut().ok_or("database not initialized")?;
+    list_memories(conn).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_graph(db: State<DbState>) -> Result<GraphData, String> {
+    let mut guard = (&*db)
+        .0
+        .lock()
+        .map_err(|e: std::sync::PoisonError<_>| e.to_string())?;
+    let conn = guard.as_mut().ok_or("database not initialized")?;
+    get_graph_data(conn).map_err(|e| e.to_string())
+}
+
+/// Write the graph to `path` as `graphml`, `gexf`, `csv` (two files), `cypher`, `turtle` or
+/// `jsonld`. RDF IRIs are minted under `base_iri`, by default one derived from the library ID.
+/// Returns the paths of the files written.
+#[tauri::command]
+fn export_graph(
+    format: String,
+    path: String,
+    include_memory_ids: Option<bool>,
+    base_iri: Option<String>,
+    db: State<DbState>,
+    current_library: State<CurrentLibraryId>,
+) -> Result<Vec<String>, String> {
+    let format = GraphExportFormat::parse(&format)?;
+    let base_iri = match base_iri.filter(|b| !b.trim().is_empty()) {
+        Some(base_iri) => base_iri,
+        None => default_base_iri(&get_current_library_id(&current_library)?),
+    };
+    let guard = db
+        .0
+        .lock()
+        .map_err(|e: std::sync::PoisonError<_>| e.to_string())?;
+    let conn = guard.as_ref().ok_or("database not initialized")?;
+    let written = graph_export::export_graph(
+        conn,
+        format,
+        Path::new(&path),
+        include_memory_ids.unwrap_or(false),
+        &base_iri,
+    )?;
+    println!("📤 [export_graph] Wrote {:?}", written);
+    Ok(written
+        .into_iter()
+        .map(|p| p.to_string_lossy().to_string())
+        .collect())
+}
+
+/// Import entities and relations from CSV/JSON node and edge lists. With `dry_run` the
+/// import is rolled back and only the report (new / updated / conflicting rows) is returned.
+#[tauri::command]
+async fn import_graph(
+    app: tauri::AppHandle,
+    request: GraphImportRequest,
+) -> Result<GraphImportReport, String> {
+    tokio::task::spawn_blocking(move || do_import_graph(app, request))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+fn do_import_graph(
+    app: tauri::AppHandle,
+    request: GraphImportRequest,
+) -> Result<GraphImportReport, String> {
+    let report = {
+        let db = app.state::<DbState>();
+        let mut guard = db
+            .0
+            .lock()
+            .map_err(|e: std::sync::PoisonError<_>| e.to_string())?;
+        let conn = guard.as_mut().ok_or("database not initialized")?;
+        graph_import::import_graph(conn, &request)?
+    };
+    if !report.dry_run {
+        refresh_entity_pages(&app, Some(&report.touched_entity_ids));
+    }
+    println!(
+        "📥 [import_graph] {}{}: {} new / {} updated / {} conflicting entities, \
+         {} new / {} updated relations, {} errors",
+        report.source,
+        if report.dry_run { " (dry run)" } else { "" },
+        report.entities_new,
+        report.entities_updated,
+        report.entities_conflicting,
+        report.relations_new,
+        report.relations_updated,
+        report.errors
+    );
+    Ok(report)
+}
+
+/// Import contacts from a `.vcf` file as Person entities. With `dry_run` the import is rolled
+/// back and the report is the merge preview; cards listed in `skip` are left out.
+#[tauri::command]
+async fn import_contacts(
+    app: tauri::AppHandle,
+    request: VcardImportRequest,
+) -> Result<VcardImportReport, String> {
+    tokio::task::spawn_blocking(move || do_import_contacts(app, request))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+fn do_import_contacts(
+    app: tauri::AppHandle,
+    request: VcardImportRequest,
+) -> Result<VcardImportReport, String> {
+    let report = {
+        let db = app.state::<DbState>();
+        let mut guard = db
+            .0
+            .lock()
+            .map_err(|e: std::sync::PoisonError<_>| e.to_string())?;
+        let conn = guard.as_mut().ok_or("database not initialized")?;
+        vcard_import::import_vcards(conn, &request)?
+    };
+    if !report.dry_run {
+        refresh_entity_pages(&app, Some(&report.touched_entity_ids));
+    }
+    println!(
+        "📇 [import_contacts] {}{}: {} cards, {} new / {} merged / {} unchanged people, \
+         {} conflicts, {} skipped, {} new organizations, {} errors",
+        report.source,
+        if report.dry_run { " (dry run)" } else { "" },
+        report.cards,
+        report.people_new,
+        report.people_merged,
+        report.people_unchanged,
+        report.conflicts,
+        report.skipped,
+        report.organizations_new,
+        report.errors
+    );
+    Ok(report)
+}
+
+#[tauri::command]
+fn query_entity(name: String, db: State<DbState>) -> Result<Option<Entity>, String> {
+    let mut guard = (&*db)
+        .0
+        .lock()
+        .map_err(|e: std::sync::PoisonError<_>| e.to_string())?;
+    let conn = guard.as_mut().ok_or("database not initialized")?;
+    get_entity_by_name(conn, &name).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn search_memories_by_entity(entity_id: i64, db: State<DbState>) -> Result<Vec<Memory>, String> {
+    let mut guard = (&*db)
+        .0
+        .lock()
+        .map_err(|e: std::sync::PoisonError<_>| e.to_string())?;
+    let conn = guard.as_mut().ok_or("database not initialized")?;
+    get_memories_for_entity(conn, entity_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_character_profile(entity_id: i64, db: State<DbState>) -> Result<serde_json::Value, String> {
+    let mut guard = (&*db)
+        .0
+        .lock()
+        .map_err(|e: std::sync::PoisonError<_>| e.to_string())?;
+    let conn = guard.as_mut().ok_or("database not initialized")?;
+    let entity = get_entity_by_id(conn, entity_id).map_err(|e| e.to_string())?;
+    let memories = get_memories_for_entity(conn, entity_id).map_err(|e| e.to_string())?;
+    let relations = list_relations(conn).map_err(|e| e.to_string())?;
+    let entity_relations: Vec<_> = relations
+        .into_iter()
+        .filter(|r| r.from_entity_id == entity_id || r.to_entity_id == entity_id)
+        .collect();
+
+    // Collect all entity IDs needed for name resolution
+    let mut id_set: std::collections::HashSet<i64> = std::collections::HashSet::new();
+    for r in &entity_relations {
+        id_set.insert(r.from_entity_id);
+        id_set.insert(r.to_entity_id);
+    }
+    let mut id_to_name: std::collections::HashMap<i64, String> = std::collections::HashMap::new();
+    for id in id_set {
+        if let Ok(e) = get_entity_by_id(conn, id) {
+            id_to_name.insert(id, e.name);
+        }
+    }
+
+    // Build enriched relation list with entity names
+    let enriched_relations: Vec<serde_json::Value> = entity_relations
+        .iter()
+        .map(|r| {
+            serde_json::json!({
+                "from_entity_id": r.from_entity_id,
+                "from_name": id_to_name.get(&r.from_entity_id).cloned().unwrap_or_default(),
+                "to_entity_id": r.to_entity_id,
+                "to_name": id_to_name.get(&r.to_entity_id).cloned().unwrap_or_default(),
+                "relation_type": r.relation_type,
+                "strength": r.strength,
+            })
+        })
+        .collect();
+
+    Ok(serde_json::json!({
+        "entity": entity,
+        "memories": memories,
+        "relations": enriched_relations
+    }))
+}
+
+/// Chronological timeline of events and memories, ordered by their normalized Time entities.
+/// `participant_id` restricts it to one entity; undated entries are included unless disabled.
+#[tauri::command]
+fn get_timeline(
+    participant_id: Option<i64>,
+    include_undated: Option<bool>,
+    db: State<DbState>,
+) -> Result<Vec<TimelineEntry>, String> {
+    let mut guard = (&*db)
+        .0
+        .lock()
+        .map_err(|e: std::sync::PoisonError<_>| e.to_string())?;
+    let conn = guard.as_mut().ok_or("database not initialized")?;
+    build_timeline(conn, participant_id, include_undated.unwrap_or(true)).map_err(|e| e.to_string())
+}
+
+/// Blocking core logic for update_memory_content, executed inside spawn_blocking to ensure real-time event delivery.
+fn do_update_memory(
+    app: tauri::AppHandle,
+    memory_id: i64,
+    content: String,
+    tags_str: Option<String>,
+    config: ModelConfig,
+) -> Result<Memory, String> {
+    println!("📝 [update_memory ID:{}]", memory_id);
+    emit_model_info(&app, provider_for(&config)?.as_ref());
+
+    // Step 1: Quick entity extraction
+    emit_save_progress(
+        &app,
+        "saveProgress.step1.extracting",
+        "running",
+        serde_json::json!({}),
+    );
+    println!("🔍 Starting entity extraction...");
+    let quick_extracted = if !content.trim().is_empty() {
+        match call_model_extract(&config, ENTITY_EXTRACT_PROMPT, &content) {
+            Ok(extracted) if extracted.entities.is_empty() => {
+                println!("❌ [Step 1] Extraction returned 0 entities, aborting update");
+                return Err(
+                    serde_json::json!({ "code": "saveProgress.errors.noEntities" }).to_string(),
+                );
+            }
+            Ok(extracted) => {
+                emit_save_progress(
+                    &app,
+                    "saveProgress.step1.extracted",
+                    "success",
+                    serde_json::json!({ "count": extracted.entities.len() }),
+                );
+                println!("✅ Extracted {} entities", extracted.entities.len());
+                Some(extracted)
+            }
+            Err(e) => {
+                println!(
+                    "❌ [Step 1] Entity extraction failed, aborting update: {}",
+                    e
+                );
+                return Err(
+                    serde_json::json!({ "code": "saveProgress.errors.extractFailed", "reason": e })
+                        .to_string(),
+                );
+            }
+        }
+    } else {
+        None
+    };
+
+    // Step 2: Fetch related historical memories for knowledge fusion
+    emit_save_progress(
+        &app,
+        "saveProgress.step2.lookingUp",
+        "running",
+        serde_json::json!({}),
+    );
+    let historical_memories = if let Some(ref ex) = quick_extracted {
+        let selected = {
+            let db = app.state::<DbState>();
+            let mut guard =
+                db.0.lock()
+                    .map_err(|e: std::sync::PoisonError<_>| e.to_string())?;
+            let conn = guard.as_mut().ok_or("database not initialized")?;
+            collect_relevant_historical_memories(conn, ex, &content, Some(memory_id))?
+        };
+        if selected.is_empty() {
+            emit_save_progress(
+                &app,
+                "saveProgress.step2.noHistory",
+                "success",
+                serde_json::json!({}),
+            );
+        } else {
+            emit_save_progress(
+                &app,
+                "saveProgress.step2.found",
+                "success",
+                serde_json::json!({ "count": selected.len() }),
+            );
+        }
+        selected
+    } else {
+        emit_save_progress(
+            &app,
+            "saveProgress.step2.noHistory",
+            "success",
+            serde_json::json!({}),
+        );
+        Vec::new()
+    };
+
+    // Step 3: Knowledge fusion
+    let step3_started = Instant::now();
+    let fused = if !historical_memories.is_empty() && content.trim().len() > 5 {
+        emit_save_progress(
+            &app,
+            "saveProgress.step3.fusing",
+            "running",
+            serde_json::json!({}),
+        );
+        println!(
+            "🧠 [Step 3][update_memory:{}] Starting knowledge fusion (historical_memories={}, new_content_chars={})",
+            memory_id,
+            historical_memories.len(),
+            content.chars().count()
+        );
+        let fusion_call_started = Instant::now();
+        let fusion_result = call_model_fusion(
+            &config,
+            KNOWLEDGE_FUSION_PROMPT,
+            &historical_memories,
+            &content,
+        );
+        println!(
+            "⏱️ [Step 3][update_memory:{}] call_model_fusion returned in {} ms",
+            memory_id,
+            fusion_call_started.elapsed().as_millis()
+        );
+        fusion_result.ok()
+    } else {
+        emit_save_progress(
+            &app,
+            "saveProgress.step3.skipped",
+            "skipped",
+            serde_json::json!({}),
+        );
+        None
+    };
+    println!(
+        "⏱️ [Step 3][update_memory:{}] Total stage time: {} ms",
+        memory_id,
+        step3_started.elapsed().as_millis()
+    );
+
+    let (mut entities, mut relations, aliases) = if let Some(fused_data) = fused {
+        emit_save_progress(
+            &app,
+            "saveProgress.step3.fusionDone",
+            "success",
+            serde_json::json!({ "entities": fused_data.entities.len(), "relations": fused_data.relations.len() }),
+        );
+        println!("✅ Knowledge fusion complete");
+        (
+            fused_data.entities,
+            fused_data.relations,
+            fused_data.aliases,
+        )
+    } else if let Some(ex) = quick_extracted {
+        emit_save_progress(
+            &app,
+            "saveProgress.step3.extractionDone",
+            "success",
+            serde_json::json!({ "entities": ex.entities.len(), "relations": ex.relations.len() }),
+        );
+        println!("✅ Using quick extraction results");
+        (ex.entities, ex.relations, Vec::new())
+    } else {
+        emit_save_progress(
+            &app,
+            "saveProgress.step3.noEntities",
+            "warning",
+            serde_json::json!({}),
+        );
+        (Vec::new(), Vec::new(), Vec::new())
+    };
+
+    if is_time_normalization_enabled_for_active_library(&app) {
+        let normalize_started = Instant::now();
+        let time_entities_before = entities
+            .iter()
+            .filter(|x| x.entity_type.eq_ignore_ascii_case("Time"))
+            .count();
+        normalize_time_entities_in_place(
+            &mut entities,
+            &mut relations,
+            &config,
+            Local::now().date_naive(),
+        );
+        println!(
+            "⏱️ [Step 3.5][update_memory:{}] Time normalization took {} ms (time_entities_before={}, total_entities={})",
+            memory_id,
+            normalize_started.elapsed().as_millis(),
+            time_entities_before,
+            entities.len()
+        );
+    }
+
+    // Step 4: Persist to database
+    emit_save_progress(
+        &app,
+        "saveProgress.step4.saving",
+        "running",
+        serde_json::json!({}),
+    );
+    let previous_entity_ids: Vec<i64>;
+    let key = library_key(&app)?;
+    let updated_memory = {
+        let db = app.state::<DbState>();
+        let mut guard =
+            db.0.lock()
+                .map_err(|e: std::sync::PoisonError<_>| e.to_string())?;
+        let conn = guard.as_mut().ok_or("database not initialized")?;
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+
+        let md_path = get_memory_by_id(&tx, memory_id)
+            .map_err(|e| e.to_string())?
+            .md_file_path
+            .map(PathBuf::from);
+        previous_entity_ids =
+            get_entity_ids_for_memory(&tx, memory_id).map_err(|e| e.to_string())?;
+        update_memory(&tx, memory_id, &content, tags_str.as_deref()).map_err(|e| e.to_string())?;
+        clear_memory_entities(&tx, memory_id).map_err(|e| e.to_string())?;
+        link_extracted_graph(&tx, memory_id, &entities, &relations, &aliases)?;
+        prune_orphan_entities_and_relations(&tx).map_err(|e| e.to_string())?;
+
+        // Rewrite the Markdown file before committing; if the commit fails it is restored.
+        let original = match &md_path {
+            Some(path) => {
+                let original = read_file_text(path, key.as_ref()).ok();
+                let graph = memory_graph_frontmatter(&entities, &relations, &aliases);
+                let tags = tags_str.as_deref().map(|t| {
+                    t.split(',')
+                        .map(|x| x.trim().to_string())
+                        .filter(|x| !x.is_empty())
+                        .collect::<Vec<_>>()
+                });
+                ignore_memory_watcher_echo(&app, path);
+                rewrite_memory(path, &content, key.as_ref(), |fm| {
+                    fm.id = Some(memory_id);
+                    fm.tags = tags.filter(|t| !t.is_empty());
+                    fm.entities = graph.entities;
+                    fm.relations = graph.relations;
+                    fm.aliases = graph.aliases;
+                })?;
+                original
+            }
+            None => {
+                println!("⚠️ Memory {} has no Markdown file to update", memory_id);
+                None
+            }
+        };
+        if let Err(e) = tx.commit() {
+            if let Some(path) = &md_path {
+                roll_back_memory_file(&app, path, original.as_deref());
+            }
+            return Err(e.to_string());
+        }
+        if let Some(path) = &md_path {
+            ignore_memory_watcher_echo(&app, path);
+        }
+        get_memory_by_id(conn, memory_id).map_err(|e| e.to_string())?
+    };
+
+    let mut changed = previous_entity_ids;
+    changed.extend(linked_entity_ids(&app, memory_id));
+    refresh_entity_pages(&app, Some(&changed));
+    record_memory_history(
+        &app,
+        &format!("Update memory #{}: {}", memory_id, memory_title(&content)),
+    );
+
+    emit_save_progress(
+        &app,
+        "saveProgress.updateDone",
+        "done",
+        serde_json::json!({}),
+    );
+    println!("✅ Memory updated successfully!");
+    Ok(updated_memory)
+}
+
+#[tauri::command]
+async fn update_memory_content(
+    app: tauri::AppHandle,
+    memory_id: i64,
+    content: String,
+    tags: Option<Vec<String>>,
+    config_state: State<'_, ModelConfigState>,
+) -> Result<Memory, String> {
+    let config = config_state.0.lock().map_err(|e| e.to_string())?.clone();
+    let tags_str = tags.map(|t| t.join(","));
+    tokio::task::spawn_blocking(move || do_update_memory(app, memory_id, content, tags_str, config))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+fn delete_memory_by_id(app: tauri::AppHandle, memory_id: i64) -> Result<(), String> {
+    remove_memory(&app, memory_id, true)
+}
+
+/// Helper: emit an index rebuild progress event to the frontend.
+fn emit_rebuild_progress(
+    app: &tauri::AppHandle,
+    current: usize,
+    total: usize,
+    path: &Path,
+    status: &str,
+) {
+    let _ = app.emit(
+        "index-rebuild-progress",
+        serde_json::json!({
+            "current": current,
+            "total": total,
+            "path": path.to_string_lossy(),
+            "status": status,
+        }),
+    );
+}
+
+/// Look up an entity by name or alias in the previous database, if it is still readable.
+fn lookup_previous_entity(conn: &rusqlite::Connection, name: &str) -> Option<ExtractedEntity> {
+    let id = find_entity_id_by_name_or_alias(conn, name).ok()??;
+    let entity = get_entity_by_id(conn, id).ok()?;
+    Some(ExtractedEntity {
+        entity_type: entity.entity_type,
+        name: name.to_string(),
+        attributes: entity
+            .attributes
+            .as_deref()
+            .and_then(|a| serde_json::from_str(a).ok()),
+    })
+}
+
+/// Recreate the `memories` and `memory_entities` rows for one Markdown file.
+/// Returns whether the model had to be called because the frontmatter lacked entity data.
+fn rebuild_memory_from_file(
+    conn: &mut rusqlite::Connection,
+    previous: Option<&rusqlite::Connection>,
+    config: &ModelConfig,
+    normalize_times: bool,
+    path: &Path,
+    key: Option<&LibraryKey>,
+) -> Result<bool, String> {
+    let record = read_memory(path, key)?;
+    let body = record.content.trim();
+    if body.is_empty() {
+        return Err("Memory file has no content".to_string());
+    }
+
+    // Typed frontmatter entities are used as-is. Untyped ones (older files) are looked up in
+    // the previous database when it is still readable; otherwise the body is extracted again.
+    let fm = &record.frontmatter;
+    let listed = fm.entities.clone().unwrap_or_default();
+    let mut entities: Vec<ExtractedEntity> = listed
+        .iter()
+        .filter_map(|e| match &e.entity_type {
+            Some(entity_type) => Some(ExtractedEntity {
+                entity_type: entity_type.clone(),
+                name: e.name.clone(),
+                attributes: e.attributes.clone(),
+            }),
+            None => previous.and_then(|c| lookup_previous_entity(c, &e.name)),
+        })
+        .collect();
+    let mut relations: Vec<ExtractedRelation> = fm
+        .relations
+        .iter()
+        .flatten()
+        .map(|r| ExtractedRelation {
+            from: r.from.clone(),
+            to: r.to.clone(),
+            relation: r.relation.clone(),
+        })
+        .collect();
+    let needs_extraction = listed.is_empty() || entities.len() < listed.len();
+    if needs_extraction {
+        let extracted = call_model_extract(config, ENTITY_EXTRACT_PROMPT, body)?;
+        entities = extracted.entities;
+        relations = extracted.relations;
+        if normalize_times {
+            let reference = parse_iso_date_from_string(&fm.created)
+                .unwrap_or_else(|| Local::now().date_naive());
+            normalize_time_entities_in_place(&mut entities, &mut relations, config, reference);
+        }
+    }
+
+    let path_str = path.to_string_lossy().to_string();
+    let tags_str = record.frontmatter.tags.as_ref().map(|t| t.join(","));
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let memory_id = insert_memory_at(
+        &tx,
+        body,
+        Some(&path_str),
+        tags_str.as_deref(),
+        &fm.created,
+    )
+    .map_err(|e| e.to_string())?;
+    let mut name_to_id: std::collections::HashMap<String, i64> = std::collections::HashMap::new();
+    for e in &entities {
+        let attrs = e.attributes.as_ref().map(|a| a.to_string());
+        let entity_id =
+            match find_entity_id_by_name_or_alias(&tx, &e.name).map_err(|e| e.to_string())? {
+                Some(id) => id,
+                None => upsert_entity(&tx, &e.entity_type, &e.name, attrs.as_deref())
+                    .map_err(|e| e.to_string())?,
+            };
+        link_memory_entity(&tx, memory_id, entity_id).map_err(|e| e.to_string())?;
+        name_to_id.insert(e.name.clone(), entity_id);
+    }
+    for r in &relations {
+        if let (Some(&from_id), Some(&to_id)) = (name_to_id.get(&r.from), name_to_id.get(&r.to)) {
+            upsert_relation(&tx, from_id, to_id, &r.relation).map_err(|e| e.to_string())?;
+        }
+    }
+    for a in fm.aliases.iter().flatten() {
+        if let Some(&primary_id) = name_to_id.get(&a.primary) {
+            add_entity_alias(&tx, primary_id, &a.alias).map_err(|e| e.to_string())?;
+        }
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(needs_extraction)
+}
+
+/// Copy relations and aliases from the previous database onto entities that were rebuilt.
+/// Imported entities are recreated, since no memory file mentions them.
+fn carry_over_graph_edges(
+    conn: &rusqlite::Connection,
+    previous: &rusqlite::Connection,
+) -> Result<(), String> {
+    let previous_entities = list_entities(previous).map_err(|e| e.to_string())?;
+    let entity_sources: std::collections::HashMap<i64, String> = list_entity_sources(previous)
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .collect();
+    for e in &previous_entities {
+        let Some(source) = entity_sources.get(&e.id) else {
+            continue;
+        };
+        let id = match find_entity_id_by_name_or_alias(conn, &e.name).map_err(|e| e.to_string())? {
+            Some(id) => id,
+            None => upsert_entity(conn, &e.entity_type, &e.name, e.attributes.as_deref())
+                .map_err(|e| e.to_string())?,
+        };
+        set_entity_source(conn, id, source).map_err(|e| e.to_string())?;
+    }
+    let previous_names: std::collections::HashMap<i64, String> = previous_entities
+        .into_iter()
+        .map(|e| (e.id, e.name))
+        .collect();
+    let relation_sources: std::collections::HashMap<i64, String> = list_relation_sources(previous)
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .collect();
+    let resolve = |old_id: i64| -> Result<Option<i64>, String> {
+        match previous_names.get(&old_id) {
+            Some(name) => find_entity_id_by_name_or_alias(conn, name).map_err(|e| e.to_string()),
+            None => Ok(None),
+        }
+    };
+
+    for r in list_relations(previous).map_err(|e| e.to_string())? {
+        if let (Some(from_id), Some(to_id)) = (resolve(r.from_entity_id)?, resolve(r.to_entity_id)?)
+        {
+            restore_relation(conn, from_id, to_id, &r.relation_type, r.strength)
+                .map_err(|e| e.to_string())?;
+            if let Some(source) = relation_sources.get(&r.id) {
+                set_relation_source(conn, from_id, to_id, &r.relation_type, source)
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+    }
+    for (old_id, alias) in list_entity_aliases(previous).map_err(|e| e.to_string())? {
+        if let Some(entity_id) = resolve(old_id)? {
+            add_entity_alias(conn, entity_id, &alias).map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+/// Move the live database aside as `kraph.db.bak-<timestamp>` and put the rebuilt one in its place.
+/// If the rebuilt file cannot be moved in, the backup is put back.
+fn swap_in_rebuilt_database(db_path: &Path, rebuild_path: &Path) -> Result<Option<PathBuf>, String> {
+    let backup = if db_path.exists() {
+        let backup = db_path.with_file_name(format!(
+            "kraph.db.bak-{}",
+            Utc::now().format("%Y%m%d%H%M%S")
+        ));
+        fs::rename(db_path, &backup)
+            .map_err(|e| format!("Failed to back up the old database: {e}"))?;
+        Some(backup)
+    } else {
+        None
+    };
+    if let Err(e) = fs::rename(rebuild_path, db_path) {
+        let error = format!("Failed to install the rebuilt database: {e}");
+        if let Some(backup) = &backup {
+            restore_database_backup(db_path, backup).map_err(|r| format!("{error}; {r}"))?;
+        }
+        return Err(error);
+    }
+    Ok(backup)
+}
+
+/// Put a `kraph.db.bak-*` file back as the live database, replacing whatever is there.
+fn restore_database_backup(db_path: &Path, backup: &Path) -> Result<(), String> {
+    if db_path.exists() {
+        fs::remove_file(db_path)
+            .map_err(|e| format!("Failed to remove the rebuilt database: {e}"))?;
+    }
+    fs::rename(backup, db_path).map_err(|e| {
+        format!(
+            "Failed to restore the previous database from {}: {e}",
+            backup.display()
+        )
+    })
+}
+
+/// Swap the rebuilt database in and open it. On any failure the previous database is put back
+/// and reopened, so the library is never left on an empty or missing file.
+fn install_rebuilt_database(
+    guard: &mut Option<rusqlite::Connection>,
+    db_path: &Path,
+    rebuild_path: &Path,
+    db_key: Option<&str>,
+) -> Result<Option<PathBuf>, String> {
+    // Close the live connection before moving its file.
+    *guard = None;
+    let installed = swap_in_rebuilt_database(db_path, rebuild_path).and_then(|backup| {
+        match init_db(db_path, db_key) {
+            Ok(conn) => Ok((conn, backup)),
+            Err(e) => {
+                let error = format!("Failed to open the rebuilt database: {e}");
+                if let Some(backup) = &backup {
+                    restore_database_backup(db_path, backup)
+                        .map_err(|r| format!("{error}; {r}"))?;
+                }
+                Err(error)
+            }
+        }
+    });
+    match installed {
+        Ok((conn, backup)) => {
+            *guard = Some(conn);
+            Ok(backup)
+        }
+        Err(error) => {
+            let _ = fs::remove_file(rebuild_path);
+            // A missing file means the restore failed; don't let init_db create an empty one.
+            if db_path.exists() {
+                *guard = Some(init_db(db_path, db_key).map_err(|e| {
+                    format!("{error}; failed to reopen the previous database: {e}")
+                })?);
+            }
+            Err(error)
+        }
+    }
+}
+
+/// Blocking core logic for rebuild_index: recreate the library database from its Markdown files.
+///
+/// The new database is built next to the live one and swapped in at the end; the previous
+/// file is kept as `kraph.db.bak-<timestamp>`.
+fn do_rebuild_index(
+    app: tauri::AppHandle,
+    config: ModelConfig,
+    data_dir: PathBuf,
+) -> Result<RebuildIndexReport, String> {
+    let memories_dir = data_dir.join("memories");
+    let db_dir = data_dir.join("database");
+    let db_path = db_dir.join("kraph.db");
+    let rebuild_path = db_dir.join("kraph.rebuild.db");
+    if rebuild_path.exists() {
+        fs::remove_file(&rebuild_path)
+            .map_err(|e| format!("Failed to remove stale rebuild database: {e}"))?;
+    }
+
+    let key = library_key(&app)?;
+    let db_key = key.as_ref().map(LibraryKey::sqlcipher_key);
+    // The previous database is only a hint for entity types; a corrupted file is ignored.
+    let previous = if db_path.exists() {
+        open_db(&db_path, db_key.as_deref())
+            .ok()
+            .filter(|c| list_entities(c).is_ok())
+    } else {
+        None
+    };
+    let mut conn = init_db(&rebuild_path, db_key.as_deref()).map_err(|e| e.to_string())?;
+    let normalize_times = load_library_time_normalization(&data_dir);
+
+    // Insert oldest first so rebuilt memory IDs follow creation order.
+    let mut files = list_memory_files(&memories_dir)?;
+    files.reverse();
+
+    let mut report = RebuildIndexReport {
+        total: files.len(),
+        ..Default::default()
+    };
+    println!("🧱 [rebuild_index] Rebuilding {} memory files", files.len());
+    for (idx, path) in files.iter().enumerate() {
+        emit_rebuild_progress(&app, idx + 1, files.len(), path, "running");
+        match rebuild_memory_from_file(
+            &mut conn,
+            previous.as_ref(),
+            &config,
+            normalize_times,
+            path,
+            key.as_ref(),
+        ) {
+            Ok(extracted) => {
+                report.rebuilt += 1;
+                if extracted {
+                    report.extracted += 1;
+                }
+                emit_rebuild_progress(&app, idx + 1, files.len(), path, "success");
+            }
+            Err(e) => {
+                println!("❌ [rebuild_index] {:?}: {}", path, e);
+                emit_rebuild_progress(&app, idx + 1, files.len(), path, "error");
+                report.errors.push(RebuildFileError {
+                    path: path.to_string_lossy().to_string(),
+                    error: e,
+                });
+            }
+        }
+    }
+
+    if let Some(previous) = previous.as_ref() {
+        carry_over_graph_edges(&conn, previous)?;
+    }
+    drop(previous);
+    drop(conn);
+
+    let db = app.state::<DbState>();
+    let mut guard = db
+        .0
+        .lock()
+        .map_err(|e: std::sync::PoisonError<_>| e.to_string())?;
+    if get_current_data_dir(&app.state::<AppDataDir>())? != data_dir {
+        let _ = fs::remove_file(&rebuild_path);
+        return Err("The active library changed during the rebuild.".to_string());
+    }
+    let backup = install_rebuilt_database(&mut guard, &db_path, &rebuild_path, db_key.as_deref())?;
+    report.backup_path = backup.map(|p| p.to_string_lossy().to_string());
+    drop(guard);
+    refresh_entity_pages(&app, None);
+
+    println!(
+        "✅ [rebuild_index] Rebuilt {}/{} files ({} re-extracted, {} errors)",
+        report.rebuilt,
+        report.total,
+        report.extracted,
+        report.errors.len()
+    );
+    Ok(report)
+}
+
+/// Rebuild the active library's database from its Markdown files.
+/// Progress is delivered via the "index-rebuild-progress" event.
+#[tauri::command]
+async fn rebuild_index(
+    app: tauri::AppHandle,
+    config_state: State<'_, ModelConfigState>,
+    data_dir: State<'_, AppDataDir>,
+) -> Result<RebuildIndexReport, String> {
+    let config = config_state.0.lock().map_err(|e| e.to_string())?.clone();
+    let data_dir = get_current_data_dir(&data_dir)?;
+    tokio::task::spawn_blocking(move || do_rebuild_index(app, config, data_dir))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ReorganizeFilesReport {
+    total: usize,
+    moved: usize,
+    unchanged: usize,
+    errors: Vec<RebuildFileError>,
+}
+
+/// Move one memory's file to where `template` puts it and point `md_file_path` at it.
+/// Returns whether the file moved.
+fn reorganize_memory_file(
+    app: &tauri::AppHandle,
+    memories_dir: &Path,
+    template: &str,
+    memory: &Memory,
+    key: Option<&LibraryKey>,
+) -> Result<bool, String> {
+    let from = memory
+        .md_file_path
+        .as_deref()
+        .map(PathBuf::from)
+        .ok_or("Memory has no Markdown file")?;
+    let record = read_memory(&from, key)?;
+    let ctx = PathContext::from_frontmatter(&record.frontmatter, &record.content);
+    let target = memory_file_path(memories_dir, template, &ctx)?;
+    if is_path_variant_of(&from, &target) {
+        return Ok(false);
+    }
+
+    ignore_memory_watcher_echo(app, &from);
+    ignore_memory_watcher_echo(app, &target);
+    let to = move_memory_file(&from, &target)?;
+    ignore_memory_watcher_echo(app, &to);
+    if let Err(e) = relink_memory_file(app, memory.id, &to) {
+        // Put the file back so the database keeps pointing at it.
+        if let Err(r) = fs::rename(&to, &from) {
+            return Err(format!("{e}; failed to move {:?} back to {:?}: {r}", to, from));
+        }
+        prune_empty_dirs(memories_dir, &to);
+        return Err(e);
+    }
+    prune_empty_dirs(memories_dir, &from);
+    Ok(true)
+}
+
+/// Blocking core logic for reorganize_memory_files.
+fn do_reorganize_memory_files(
+    app: tauri::AppHandle,
+    data_dir: PathBuf,
+    path_template: Option<String>,
+) -> Result<ReorganizeFilesReport, String> {
+    if let Some(template) = path_template.as_deref() {
+        validate_path_template(template)?;
+        save_library_path_template(&data_dir, Some(template))?;
+    }
+    let template = load_library_path_template(&data_dir);
+    let memories_dir = data_dir.join("memories");
+
+    let memories = {
+        let db = app.state::<DbState>();
+        let mut guard =
+            db.0.lock()
+                .map_err(|e: std::sync::PoisonError<_>| e.to_string())?;
+        let conn = guard.as_mut().ok_or("database not initialized")?;
+        list_memories(conn).map_err(|e| e.to_string())?
+    };
+    let memories: Vec<Memory> = memories
+        .into_iter()
+        .filter(|m| m.md_file_path.is_some())
+        .collect();
+
+    println!(
+        "🗂️ [reorganize] {} memory files, template '{}'",
+        memories.len(),
+        template
+    );
+    let mut report = ReorganizeFilesReport {
+        total: memories.len(),
+        moved: 0,
+        unchanged: 0,
+        errors: Vec::new(),
+    };
+    let key = library_key(&app)?;
+    for (idx, memory) in memories.iter().enumerate() {
+        let path = PathBuf::from(memory.md_file_path.as_deref().unwrap_or_default());
+        match reorganize_memory_file(&app, &memories_dir, &template, memory, key.as_ref()) {
+            Ok(true) => report.moved += 1,
+            Ok(false) => report.unchanged += 1,
+            Err(e) => {
+                println!("❌ [reorganize] {:?}: {}", path, e);
+                report.errors.push(RebuildFileError {
+                    path: path.to_string_lossy().to_string(),
+                    error: e,
+                });
+            }
+        }
+        let _ = app.emit(
+            "memory-files-reorganize-progress",
+            serde_json::json!({ "current": idx + 1, "total": memories.len() }),
+        );
+    }
+    if report.moved > 0 {
+        record_memory_history(&app, &format!("Reorganize memory files into {}", template));
+    }
+    println!(
+        "✅ [reorganize] moved {}, unchanged {}, errors {}",
+        report.moved,
+        report.unchanged,
+        report.errors.len()
+    );
+    Ok(report)
+}
+
+/// Move the active library's memory files to match its path template (or `path_template`,
+/// which is saved as the library's new template first).
+#[tauri::command]
+async fn reorganize_memory_files(
+    app: tauri::AppHandle,
+    path_template: Option<String>,
+    data_dir: State<'_, AppDataDir>,
+) -> Result<ReorganizeFilesReport, String> {
+    let data_dir = get_current_data_dir(&data_dir)?;
+    tokio::task::spawn_blocking(move || do_reorganize_memory_files(app, data_dir, path_template))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Commits that changed a memory file, newest first.
+#[tauri::command]
+fn get_memory_file_history(
+    path: String,
+    data_dir: State<AppDataDir>,
+) -> Result<Vec<MemoryHistoryEntry>, String> {
+    let memories_dir = get_current_data_dir(&data_dir)?.join("memories");
+    if !memories_dir.join(".git").exists() {
+        return Ok(Vec::new());
+    }
+    file_history(&memories_dir, Path::new(&path))
+}
+
+/// Contents of a memory file as of an earlier commit.
+#[tauri::command]
+fn read_memory_file_at(
+    path: String,
+    commit: String,
+    data_dir: State<AppDataDir>,
+    key_state: State<LibraryKeyState>,
+) -> Result<String, String> {
+    let memories_dir = get_current_data_dir(&data_dir)?.join("memories");
+    decode_text_with(
+        get_library_key(&key_state)?.as_ref(),
+        file_at(&memories_dir, Path::new(&path), &commit)?,
+    )
+}
+
+/// Blocking core logic for restore_memory_file: put the old contents back, commit that,
+/// then sync the database exactly as if the file had been edited outside the app.
+fn do_restore_memory_file(
+    app: tauri::AppHandle,
+    memories_dir: PathBuf,
+    path: PathBuf,
+    commit: String,
+) -> Result<(), String> {
+    let key = library_key(&app)?;
+    let contents = decode_text_with(key.as_ref(), file_at(&memories_dir, &path, &commit)?)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    ignore_memory_watcher_echo(&app, &path);
+    replace_file_contents(&path, &contents, key.as_ref())?;
+    let rel = path.strip_prefix(&memories_dir).unwrap_or(&path);
+    record_memory_history(
+        &app,
+        &format!("Restore {} from {}", rel.to_string_lossy(), &commit[..commit.len().min(7)]),
+    );
+    sync_memory_file_changes(&app, &memories_dir, vec![MemoryFileChange::Upserted(path)]);
+    Ok(())
+}
+
+/// Restore a memory file from an earlier commit and re-index it.
+#[tauri::command]
+async fn restore_memory_file(
+    app: tauri::AppHandle,
+    path: String,
+    commit: String,
+    data_dir: State<'_, AppDataDir>,
+) -> Result<(), String> {
+    let memories_dir = get_current_data_dir(&data_dir)?.join("memories");
+    let path = PathBuf::from(path);
+    tokio::task::spawn_blocking(move || do_restore_memory_file(app, memories_dir, path, commit))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Tell the memories watcher that the app itself just wrote `path`.
+fn ignore_memory_watcher_echo(app: &tauri::AppHandle, path: &Path) {
+    let state = app.state::<MemoryWatcherState>();
+    if let Ok(guard) = state.0.lock() {
+        if let Some(watcher) = guard.as_ref() {
+            watcher.ignore_echo(path);
+        }
+    };
+}
+
+/// (Re)start the watcher on the active library's `memories/` folder.
+fn start_memory_watcher(app: &tauri::AppHandle) -> Result<(), String> {
+    let memories_dir = get_current_data_dir(&app.state::<AppDataDir>())?.join("memories");
+    let state = app.state::<MemoryWatcherState>();
+    let mut guard = state.0.lock().map_err(|e| e.to_string())?;
+    // Drop the previous watcher first so its pending batch can't reach the new library.
+    *guard = None;
+    let handle = app.clone();
+    let root = memories_dir.clone();
+    let watcher = MemoryWatcher::start(&memories_dir, move |changes| {
+        sync_memory_file_changes(&handle, &root, changes)
+    })?;
+    *guard = Some(watcher);
+    println!("👀 [memory_watcher] Watching {:?}", memories_dir);
+    Ok(())
+}
+
+fn find_memory_by_file(app: &tauri::AppHandle, path: &Path) -> Result<Option<Memory>, String> {
+    let db = app.state::<DbState>();
+    let mut guard = db
+        .0
+        .lock()
+        .map_err(|e: std::sync::PoisonError<_>| e.to_string())?;
+    let conn = guard.as_mut().ok_or("database not initialized")?;
+    get_memory_by_md_path(conn, &path.to_string_lossy()).map_err(|e| e.to_string())
+}
+
+fn relink_memory_file(app: &tauri::AppHandle, memory_id: i64, path: &Path) -> Result<(), String> {
+    let db = app.state::<DbState>();
+    let mut guard = db
+        .0
+        .lock()
+        .map_err(|e: std::sync::PoisonError<_>| e.to_string())?;
+    let conn = guard.as_mut().ok_or("database not initialized")?;
+    update_memory_file_path(conn, memory_id, &path.to_string_lossy()).map_err(|e| e.to_string())
+}
+
+/// Delete a memory row, and its Markdown file when `delete_file` is set
+/// (the watcher passes `false` because the file is already gone).
+fn remove_memory(app: &tauri::AppHandle, memory_id: i64, delete_file: bool) -> Result<(), String> {
+    let (memory, entity_ids) = {
         let db = app.state::<DbState>();
-        let mut guard =
-            db.0.lock()
-                .map_err(|e: std::sync::PoisonError<_>| e.to_string())?;
+        let mut guard = db
+            .0
+            .lock()
+            .map_err(|e: std::sync::PoisonError<_>| e.to_string())?;
         let conn = guard.as_mut().ok_or("database not initialized")?;
-        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        let memory = get_memory_by_id(conn, memory_id).map_err(|e| e.to_string())?;
+        let entity_ids = get_entity_ids_for_memory(conn, memory_id).map_err(|e| e.to_string())?;
+        delete_memory(conn, memory_id).map_err(|e| e.to_string())?;
+        (memory, entity_ids)
+    };
+    if delete_file {
+        if let Some(path) = memory.md_file_path.as_deref().map(Path::new) {
+            if path.exists() {
+                ignore_memory_watcher_echo(app, path);
+                if let Err(e) = fs::remove_file(path) {
+                    println!("⚠️ Failed to delete {:?}: {}", path, e);
+                }
+            }
+        }
+    }
+    refresh_entity_pages(app, Some(&entity_ids));
+    record_memory_history(
+        app,
+        &format!("Delete memory #{}: {}", memory_id, memory_title(&memory.content)),
+    );
+    Ok(())
+}
 
-        update_memory(&tx, memory_id, &content, tags_str.as_deref()).map_err(|e| e.to_string())?;
-        clear_memory_entities(&tx, memory_id).map_err(|e| e.to_string())?;
+/// IDs of the entities linked to a memory (empty if it can't be read).
+fn linked_entity_ids(app: &tauri::AppHandle, memory_id: i64) -> Vec<i64> {
+    let db = app.state::<DbState>();
+    let Ok(mut guard) = db.0.lock() else {
+        return Vec::new();
+    };
+    guard
+        .as_mut()
+        .and_then(|conn| get_entity_ids_for_memory(conn, memory_id).ok())
+        .unwrap_or_default()
+}
 
-        let mut name_to_id: std::collections::HashMap<String, i64> =
-            std::collections::HashMap::new();
-        for e in &entities {
-            let attrs = e.attributes.as_ref().map(|a| a.to_string());
-            let entity_id =
-                match find_entity_id_by_name_or_alias(&tx, &e.name).map_err(|e| e.to_string())? {
-                    Some(id) => id,
-                    None => upsert_entity(&tx, &e.entity_type, &e.name, attrs.as_deref())
-                        .map_err(|e| e.to_string())?,
-                };
-            link_memory_entity(&tx, memory_id, entity_id).map_err(|e| e.to_string())?;
-            name_to_id.insert(e.name.clone(), entity_id);
-        }
-        for alias_info in &aliases {
-            let primary_id = name_to_id.get(&alias_info.primary);
-            let alias_id = name_to_id.get(&alias_info.alias);
-            match (primary_id, alias_id) {
-                (Some(&pid), Some(&aid)) if pid != aid => {
-                    merge_entities(&tx, aid, pid).map_err(|e| e.to_string())?;
-                    name_to_id.insert(alias_info.alias.clone(), pid);
-                }
-                (Some(&pid), None) => {
-                    add_entity_alias(&tx, pid, &alias_info.alias).map_err(|e| e.to_string())?;
+/// Bring the database in line with Markdown files created, edited, moved or deleted
+/// outside the app. Runs on the watcher thread; model calls go through the same
+/// pipeline as `save_memory` / `update_memory_content`.
+fn sync_memory_file_changes(app: &tauri::AppHandle, root: &Path, changes: Vec<MemoryFileChange>) {
+    let active_root = match get_current_data_dir(&app.state::<AppDataDir>()) {
+        Ok(dir) => dir.join("memories"),
+        Err(_) => return,
+    };
+    if active_root != root {
+        // The library was switched while this batch was pending.
+        return;
+    }
+    let config = match app.state::<ModelConfigState>().0.lock() {
+        Ok(guard) => guard.clone(),
+        Err(_) => return,
+    };
+    let Ok(key) = library_key(app) else {
+        return;
+    };
+
+    let mut created = 0usize;
+    let mut updated = 0usize;
+    let mut moved = 0usize;
+    let mut deleted = 0usize;
+    let mut errors: Vec<serde_json::Value> = Vec::new();
+    let mut record_error = |path: &Path, e: String| {
+        println!("❌ [memory_watcher] {:?}: {}", path, e);
+        errors.push(serde_json::json!({ "path": path.to_string_lossy(), "error": e }));
+    };
+
+    // Removals are resolved last so that a delete + create pair with the same body
+    // (an external move the OS didn't report as a rename) keeps its memory ID.
+    let mut removed: Vec<(PathBuf, Memory)> = Vec::new();
+    let mut upserted: Vec<PathBuf> = Vec::new();
+    for change in changes {
+        match change {
+            MemoryFileChange::Removed(path) => match find_memory_by_file(app, &path) {
+                Ok(Some(memory)) => removed.push((path, memory)),
+                Ok(None) => {}
+                Err(e) => record_error(&path, e),
+            },
+            MemoryFileChange::Upserted(path) => upserted.push(path),
+            MemoryFileChange::Moved { from, to } => {
+                match find_memory_by_file(app, &from) {
+                    Ok(Some(memory)) => match relink_memory_file(app, memory.id, &to) {
+                        Ok(()) => moved += 1,
+                        Err(e) => record_error(&to, e),
+                    },
+                    Ok(None) => {}
+                    Err(e) => record_error(&from, e),
                 }
-                _ => {}
+                // Picks up edits made together with the move, or adopts an untracked file.
+                upserted.push(to);
             }
         }
-        for r in &relations {
-            if let (Some(&from_id), Some(&to_id)) = (name_to_id.get(&r.from), name_to_id.get(&r.to))
-            {
-                let _ = upsert_relation(&tx, from_id, to_id, &r.relation);
+    }
+
+    for path in upserted {
+        let record = match read_memory(&path, key.as_ref()) {
+            Ok(record) => record,
+            Err(e) => {
+                record_error(&path, e);
+                continue;
             }
+        };
+        let body = record.content.trim().to_string();
+        if body.is_empty() {
+            continue;
         }
+        let tags = record.frontmatter.tags;
 
-        prune_orphan_entities_and_relations(&tx).map_err(|e| e.to_string())?;
-        tx.commit().map_err(|e| e.to_string())?;
-        get_memory_by_id(conn, memory_id).map_err(|e| e.to_string())?
-    };
+        let existing = match find_memory_by_file(app, &path) {
+            Ok(existing) => existing,
+            Err(e) => {
+                record_error(&path, e);
+                continue;
+            }
+        };
+        if existing.is_none() {
+            if let Some(pos) = removed.iter().position(|(_, m)| m.content.trim() == body) {
+                let (_, memory) = removed.remove(pos);
+                match relink_memory_file(app, memory.id, &path) {
+                    Ok(()) => moved += 1,
+                    Err(e) => record_error(&path, e),
+                }
+                continue;
+            }
+        }
 
-    emit_save_progress(
-        &app,
-        "saveProgress.updateDone",
-        "done",
-        serde_json::json!({}),
-    );
-    println!("✅ Memory updated successfully!");
-    Ok(updated_memory)
-}
+        let result = match existing {
+            Some(memory) if memory.content.trim() == body => continue,
+            Some(memory) => do_update_memory(
+                app.clone(),
+                memory.id,
+                body,
+                tags.map(|t| t.join(",")),
+                config.clone(),
+            )
+            .map(|_| updated += 1),
+            None => do_save_memory(
+                app.clone(),
+                body,
+                tags,
+                config.clone(),
+                root.to_path_buf(),
+                SaveMemoryOptions {
+                    existing_file: Some(path.clone()),
+                    ..Default::default()
+                },
+            )
+            .map(|_| created += 1),
+        };
+        if let Err(e) = result {
+            record_error(&path, e);
+        }
+    }
 
-#[tauri::command]
-async fn update_memory_content(
-    app: tauri::AppHandle,
-    memory_id: i64,
-    content: String,
-    tags: Option<Vec<String>>,
-    config_state: State<'_, ModelConfigState>,
-) -> Result<Memory, String> {
-    let config = config_state.0.lock().map_err(|e| e.to_string())?.clone();
-    let tags_str = tags.map(|t| t.join(","));
-    tokio::task::spawn_blocking(move || do_update_memory(app, memory_id, content, tags_str, config))
-        .await
-        .map_err(|e| e.to_string())?
-}
+    for (path, memory) in removed {
+        match remove_memory(app, memory.id, false) {
+            Ok(()) => deleted += 1,
+            Err(e) => record_error(&path, e),
+        }
+    }
 
-#[tauri::command]
-fn delete_memory_by_id(memory_id: i64, db: State<DbState>) -> Result<(), String> {
-    let mut guard = (&*db)
-        .0
-        .lock()
-        .map_err(|e: std::sync::PoisonError<_>| e.to_string())?;
-    let conn = guard.as_mut().ok_or("database not initialized")?;
-    delete_memory(conn, memory_id).map_err(|e| e.to_string())
+    if moved > 0 {
+        record_memory_history(app, &format!("Move {} memory file(s)", moved));
+    }
+
+    let _ = app.emit(
+        "memory-files-synced",
+        serde_json::json!({
+            "created": created,
+            "updated": updated,
+            "moved": moved,
+            "deleted": deleted,
+            "errors": errors,
+        }),
+    );
 }
 
 #[tauri::command]
@@ -1820,7 +4297,11 @@ fn cleanup_db(db: State<DbState>) -> Result<String, String> {
 
 /// Clear all data (destructive — use with caution).
 #[tauri::command]
-fn clear_all_data_cmd(db: State<DbState>, data_dir: State<AppDataDir>) -> Result<String, String> {
+fn clear_all_data_cmd(
+    app: tauri::AppHandle,
+    db: State<DbState>,
+    data_dir: State<AppDataDir>,
+) -> Result<String, String> {
     let mut guard = (&*db)
         .0
         .lock()
@@ -1837,6 +4318,13 @@ fn clear_all_data_cmd(db: State<DbState>, data_dir: State<AppDataDir>) -> Result
             .map_err(|e| format!("Failed to delete memories folder: {}", e))?;
         std::fs::create_dir_all(&memories_dir)
             .map_err(|e| format!("Failed to recreate memories folder: {}", e))?;
+        // The recursive watch was attached to the removed folder.
+        start_memory_watcher(&app)?;
+    }
+    let pages_dir = entity_pages_dir(&get_current_data_dir(&data_dir)?);
+    if pages_dir.exists() {
+        std::fs::remove_dir_all(&pages_dir)
+            .map_err(|e| format!("Failed to delete entities folder: {}", e))?;
     }
 
     Ok("All data has been cleared".to_string())
@@ -1867,6 +4355,87 @@ const RAG_MAX_CONTEXT_CHARS: usize = 2800;
 const RAG_MAX_MEMORY_CHARS: usize = 520;
 const RAG_MIN_RELEVANCE_SCORE: f32 = 0.12;
 const TIME_NORMALIZE_AI_MAX_CALLS: usize = 4;
+/// Conversations saved between `chat-import-progress` batch reports.
+const CHAT_IMPORT_BATCH_SIZE: usize = 10;
+const CHAT_IMPORT_CHANNEL: ImportChannel = ImportChannel {
+    event: "chat-import-progress",
+    label_key: "title",
+    log_tag: "import_chat_log",
+};
+/// Default number of email memories saved between `email-import-progress` batch events.
+const EMAIL_IMPORT_BATCH_SIZE: usize = 10;
+const EMAIL_IMPORT_CHANNEL: ImportChannel = ImportChannel {
+    event: "email-import-progress",
+    label_key: "subject",
+    log_tag: "import_email",
+};
+const CALENDAR_IMPORT_CHANNEL: ImportChannel = ImportChannel {
+    event: "calendar-import-progress",
+    label_key: "title",
+    log_tag: "import_calendar",
+};
+
+/// Commit the active library's `memories/` folder, if it keeps git history.
+/// History is best effort: a failed commit never fails the change that triggered it.
+fn record_memory_history(app: &tauri::AppHandle, message: &str) {
+    let Ok(library_dir) = get_current_data_dir(&app.state::<AppDataDir>()) else {
+        return;
+    };
+    if !load_library_git_history(&library_dir) {
+        return;
+    }
+    match commit_changes(&library_dir.join("memories"), message) {
+        Ok(Some(commit)) => println!(
+            "🕘 [history] {} ({})",
+            message.lines().next().unwrap_or(""),
+            &commit[..7]
+        ),
+        Ok(None) => {}
+        Err(e) => println!("⚠️ [history] Failed to commit: {}", e),
+    }
+}
+
+/// First non-empty line of a memory, shortened for commit messages.
+fn memory_title(content: &str) -> String {
+    let line = content
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty())
+        .unwrap_or("untitled");
+    if line.chars().count() > 60 {
+        format!("{}…", line.chars().take(60).collect::<String>())
+    } else {
+        line.to_string()
+    }
+}
+
+/// Regenerate entity pages after a change, if the active library keeps them.
+/// `changed` lists the entities involved; `None` regenerates every page.
+fn refresh_entity_pages(app: &tauri::AppHandle, changed: Option<&[i64]>) {
+    let Ok(library_dir) = get_current_data_dir(&app.state::<AppDataDir>()) else {
+        return;
+    };
+    if !load_library_entity_pages(&library_dir) || is_encrypted(&library_dir) {
+        return;
+    }
+    let db = app.state::<DbState>();
+    let Ok(mut guard) = db.0.lock() else {
+        return;
+    };
+    let Some(conn) = guard.as_mut() else {
+        return;
+    };
+    if let Err(e) = update_entity_pages(conn, &library_dir, changed) {
+        println!("⚠️ [entity_pages] Failed to update pages: {}", e);
+    }
+}
+
+fn path_template_for_active_library(app: &tauri::AppHandle) -> String {
+    match app.state::<AppDataDir>().0.lock() {
+        Ok(guard) => load_library_path_template(&guard),
+        Err(_) => DEFAULT_PATH_TEMPLATE.to_string(),
+    }
+}
 
 fn is_time_normalization_enabled_for_active_library(app: &tauri::AppHandle) -> bool {
     let data_dir_state = app.state::<AppDataDir>();
@@ -2485,28 +5054,19 @@ fn collect_relevant_historical_memories(
     Ok(selected)
 }
 
-/// Entity-aware memory retrieval and intelligent Q&A.
-fn do_answer_question(
-    question: String,
-    config: ModelConfig,
-    app: tauri::AppHandle,
-) -> Result<String, String> {
-    let question = question.trim().to_string();
-    if question.is_empty() {
-        return Ok(String::new());
-    }
-    if let ModelProvider::Ollama {
-        base_url,
-        model_name,
-        ..
-    } = &config.provider
-    {
-        ensure_ollama_running(base_url)?;
-        ensure_model_available(base_url, model_name)?;
-    }
+const NO_MEMORIES_ANSWER: &str = "No relevant memories found. Please record some content first.";
+
+/// Entity-aware memory retrieval for Q&A: returns the answer prompt, or `None` when there are
+/// no memories to answer from.
+fn prepare_answer_prompt(
+    question: &str,
+    config: &ModelConfig,
+    app: &tauri::AppHandle,
+) -> Result<Option<String>, String> {
+    ensure_model_ready(config)?;
 
     let entity_name = call_model_simple(
-        &config,
+        config,
         &format!("{}{}", ollama::EXTRACT_ENTITY_PROMPT, question),
     )
     .ok()
@@ -2548,7 +5108,7 @@ fn do_answer_question(
     };
 
     if memories.is_empty() {
-        return Ok("No relevant memories found. Please record some content first.".to_string());
+        return Ok(None);
     }
 
     // Keep memory ordering explicit for conflict resolution:
@@ -2574,8 +5134,23 @@ fn do_answer_question(
         ollama::ANSWER_PROMPT_SUFFIX,
         question
     );
+    Ok(Some(prompt))
+}
 
-    call_model_simple(&config, &prompt)
+/// Entity-aware memory retrieval and intelligent Q&A.
+fn do_answer_question(
+    question: String,
+    config: ModelConfig,
+    app: tauri::AppHandle,
+) -> Result<String, String> {
+    let question = question.trim().to_string();
+    if question.is_empty() {
+        return Ok(String::new());
+    }
+    match prepare_answer_prompt(&question, &config, &app)? {
+        Some(prompt) => call_model_simple(&config, &prompt),
+        None => Ok(NO_MEMORIES_ANSWER.to_string()),
+    }
 }
 
 #[tauri::command]
@@ -2590,6 +5165,157 @@ async fn answer_question(
         .map_err(|e| e.to_string())?
 }
 
+/// In-flight streamed answers by request ID; aborting a task drops its HTTP request.
+pub struct AnswerStreamState(
+    pub Mutex<std::collections::HashMap<String, tauri::async_runtime::JoinHandle<()>>>,
+);
+
+/// Payload of the `answer-stream` event.
+#[derive(Debug, Clone, Serialize)]
+struct AnswerStreamEvent {
+    request_id: String,
+    /// `delta`, `done`, `error` or `cancelled`.
+    status: &'static str,
+    /// New text since the previous event (`delta` only).
+    delta: String,
+    /// The complete answer (`done` only).
+    text: Option<String>,
+    truncated: bool,
+    error: Option<String>,
+}
+
+fn emit_answer_stream(app: &tauri::AppHandle, event: AnswerStreamEvent) {
+    let _ = app.emit("answer-stream", event);
+}
+
+async fn stream_answer(
+    app: &tauri::AppHandle,
+    request_id: &str,
+    question: String,
+    config: ModelConfig,
+) -> Result<(String, bool), String> {
+    let prompt = {
+        let app = app.clone();
+        let config = config.clone();
+        tokio::task::spawn_blocking(move || prepare_answer_prompt(&question, &config, &app))
+            .await
+            .map_err(|e| e.to_string())??
+    };
+    let Some(prompt) = prompt else {
+        return Ok((NO_MEMORIES_ANSWER.to_string(), false));
+    };
+    call_model_simple_stream(&config, &prompt, |delta| {
+        emit_answer_stream(
+            app,
+            AnswerStreamEvent {
+                request_id: request_id.to_string(),
+                status: "delta",
+                delta: delta.to_string(),
+                text: None,
+                truncated: false,
+                error: None,
+            },
+        );
+    })
+    .await
+}
+
+/// Streaming variant of `answer_question`: returns immediately and emits `answer-stream`
+/// events for `request_id` (text deltas, then `done` or `error`). Cancel with `cancel_answer`.
+#[tauri::command]
+fn answer_question_stream(
+    app: tauri::AppHandle,
+    request_id: String,
+    question: String,
+    config_state: State<'_, ModelConfigState>,
+    streams: State<'_, AnswerStreamState>,
+) -> Result<(), String> {
+    let question = question.trim().to_string();
+    let config = config_state.0.lock().map_err(|e| e.to_string())?.clone();
+    // Hold the lock while spawning so the task cannot deregister before it is registered.
+    let mut tasks = streams.0.lock().map_err(|e| e.to_string())?;
+    if tasks.contains_key(&request_id) {
+        return Err(format!("Answer request {} is already running", request_id));
+    }
+    let task_app = app.clone();
+    let task_id = request_id.clone();
+    let task = tauri::async_runtime::spawn(async move {
+        let started = Instant::now();
+        let result = if question.is_empty() {
+            Ok((String::new(), false))
+        } else {
+            stream_answer(&task_app, &task_id, question, config).await
+        };
+        if let Ok(mut tasks) = task_app.state::<AnswerStreamState>().0.lock() {
+            tasks.remove(&task_id);
+        }
+        let event = match result {
+            Ok((text, truncated)) => {
+                println!(
+                    "💬 [answer_stream] {} done in {} ms ({} chars{})",
+                    task_id,
+                    started.elapsed().as_millis(),
+                    text.chars().count(),
+                    if truncated { ", truncated" } else { "" }
+                );
+                AnswerStreamEvent {
+                    request_id: task_id,
+                    status: "done",
+                    delta: String::new(),
+                    text: Some(text),
+                    truncated,
+                    error: None,
+                }
+            }
+            Err(e) => {
+                println!("❌ [answer_stream] {} failed: {}", task_id, e);
+                AnswerStreamEvent {
+                    request_id: task_id,
+                    status: "error",
+                    delta: String::new(),
+                    text: None,
+                    truncated: false,
+                    error: Some(e),
+                }
+            }
+        };
+        emit_answer_stream(&task_app, event);
+    });
+    tasks.insert(request_id, task);
+    Ok(())
+}
+
+/// Abort a streamed answer. Returns `false` when the request already finished.
+#[tauri::command]
+fn cancel_answer(
+    app: tauri::AppHandle,
+    request_id: String,
+    streams: State<'_, AnswerStreamState>,
+) -> Result<bool, String> {
+    let task = streams
+        .0
+        .lock()
+        .map_err(|e| e.to_string())?
+        .remove(&request_id);
+    let Some(task) = task else {
+        return Ok(false);
+    };
+    task.abort();
+    println!("🛑 [answer_stream] {} cancelled", request_id);
+    emit_answer_stream(
+        &app,
+        AnswerStreamEvent {
+            request_id,
+            status: "cancelled",
+            delta: String::new(),
+            text: None,
+            truncated: false,
+            error: None,
+        },
+    );
+    Ok(true)
+}
+
 fn truncate_for_prompt(input: &str, max_chars: usize) -> String {
     if input.chars().count() <= max_chars {
         return input.to_string();
@@ -3276,15 +6002,7 @@ fn generate_story_from_events(
 
     let config = config_state.0.lock().map_err(|e| e.to_string())?.clone();
 
-    if let ModelProvider::Ollama {
-        base_url,
-        model_name,
-        ..
-    } = &config.provider
-    {
-        ensure_ollama_running(base_url)?;
-        ensure_model_available(base_url, model_name)?;
-    }
+    ensure_model_ready(&config)?;
 
     let context = collect_story_prompt_context(&db)?;
     if request.key_events.is_empty() {
@@ -3352,15 +6070,7 @@ fn continue_story_chapter(
     config_state: State<ModelConfigState>,
 ) -> Result<StoryContinuationResult, String> {
     let config = config_state.0.lock().map_err(|e| e.to_string())?.clone();
-    if let ModelProvider::Ollama {
-        base_url,
-        model_name,
-        ..
-    } = &config.provider
-    {
-        ensure_ollama_running(base_url)?;
-        ensure_model_available(base_url, model_name)?;
-    }
+    ensure_model_ready(&config)?;
 
     let next_chapter = request
         .target_chapter
@@ -3432,15 +6142,7 @@ fn rewrite_story_chapter(
         })?;
 
     let config = config_state.0.lock().map_err(|e| e.to_string())?.clone();
-    if let ModelProvider::Ollama {
-        base_url,
-        model_name,
-        ..
-    } = &config.provider
-    {
-        ensure_ollama_running(base_url)?;
-        ensure_model_available(base_url, model_name)?;
-    }
+    ensure_model_ready(&config)?;
 
     let prompt = build_story_rewrite_prompt(&request, &target);
     let response = call_model_simple(&config, &prompt)?;
@@ -3513,16 +6215,54 @@ fn update_model_config(
     new_config: ModelConfig,
     config_state: State<ModelConfigState>,
     data_dir: State<AppDataDir>,
+    key_state: State<LibraryKeyState>,
 ) -> Result<(), String> {
     let mut guard = config_state.0.lock().map_err(|e| e.to_string())?;
     *guard = new_config.clone();
 
     let config_path = get_current_data_dir(&data_dir)?.join("model_config.json");
-    new_config.save_to_file(&config_path)?;
+    new_config.save_to_file(&config_path, get_library_key(&key_state)?.as_ref())?;
 
     Ok(())
 }
 
+/// What the given model configuration's backend supports (streaming, JSON mode, ...).
+#[tauri::command]
+fn get_model_capabilities(config: ModelConfig) -> Result<Capabilities, String> {
+    Ok(provider_for(&config)?.capabilities())
+}
+
+/// Embed `texts` with the current model, one vector per input.
+#[tauri::command]
+async fn embed_texts(
+    texts: Vec<String>,
+    config_state: State<'_, ModelConfigState>,
+) -> Result<Vec<Vec<f32>>, String> {
+    let config = config_state.0.lock().map_err(|e| e.to_string())?.clone();
+    tokio::task::spawn_blocking(move || {
+        let provider = provider_for(&config)?;
+        if !provider.capabilities().embeddings {
+            return Err(format!("{} does not support embeddings", provider.label()));
+        }
+        provider.embed(&texts)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Count the tokens of `text` for the current model: exact where the provider has a tokenizer
+/// endpoint (`token_counting`), estimated otherwise.
+#[tauri::command]
+async fn count_tokens(
+    text: String,
+    config_state: State<'_, ModelConfigState>,
+) -> Result<usize, String> {
+    let config = config_state.0.lock().map_err(|e| e.to_string())?.clone();
+    tokio::task::spawn_blocking(move || provider_for(&config)?.count_tokens(&text))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
 /// Test whether the current model configuration is reachable.
 #[tauri::command]
 fn test_model_config(config: ModelConfig) -> Result<String, String> {
@@ -3534,7 +6274,18 @@ fn test_model_config(config: ModelConfig) -> Result<String, String> {
             }
             call_model_simple(&config, "Hello, please reply: model is working correctly.")
         }
-        ModelProvider::DeepSeek { .. } | ModelProvider::OpenAI { .. } => {
+        ModelProvider::LocalServer {
+            base_url, api_key, ..
+        } => {
+            let (is_running, msg) = check_local_server_status(base_url, api_key);
+            if !is_running {
+                return Err(msg);
+            }
+            call_model_simple(&config, "Hello, please reply: model is working correctly.")
+        }
+        ModelProvider::DeepSeek { .. }
+        | ModelProvider::OpenAI { .. }
+        | ModelProvider::Anthropic { .. } => {
             call_model_simple(&config, "Hello, please reply: model is working correctly.")
         }
     }
@@ -3546,6 +6297,32 @@ fn check_ollama() -> Result<(bool, String), String> {
     Ok(check_ollama_status(OLLAMA_URL))
 }
 
+/// Check whether a llama.cpp / LM Studio server is up and has a model loaded.
+#[tauri::command]
+async fn check_local_server(
+    base_url: String,
+    api_key: Option<String>,
+) -> Result<(bool, String), String> {
+    tokio::task::spawn_blocking(move || {
+        check_local_server_status(&base_url, api_key.as_deref().unwrap_or_default())
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Models offered by a llama.cpp / LM Studio server (`/v1/models`).
+#[tauri::command]
+async fn list_local_models(
+    base_url: String,
+    api_key: Option<String>,
+) -> Result<Vec<String>, String> {
+    tokio::task::spawn_blocking(move || {
+        list_local_server_models(&base_url, api_key.as_deref().unwrap_or_default())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
 /// Helper: emit a setup log event to the frontend.
 fn emit_setup_log(app: &tauri::AppHandle, msg: &str, status: &str) {
     let _ = app.emit(
@@ -3564,6 +6341,16 @@ fn emit_save_progress(app: &tauri::AppHandle, code: &str, status: &str, params:
     );
 }
 
+/// Announce which backend and model the following save steps use (`saveProgress.modelInfo.*`).
+fn emit_model_info(app: &tauri::AppHandle, provider: &dyn LlmProvider) {
+    emit_save_progress(
+        app,
+        &format!("saveProgress.modelInfo.{}", provider.id()),
+        "info",
+        serde_json::json!({ "model": provider.model(ModelRole::Extract) }),
+    );
+}
+
 /// Helper: emit a setup-done event to the frontend.
 fn emit_setup_done(app: &tauri::AppHandle, success: bool) {
     let _ = app.emit(
@@ -3765,18 +6552,41 @@ pub fn run() {
             ensure_library_structure(&current_library_dir)?;
             let _ = persist_current_library_id(&app_data_dir, &current_library_id);
 
-            let db_path = current_library_dir.join("database").join("kraph.db");
-            let conn = init_db(&db_path).map_err(|e| e.to_string())?;
-            app.manage(DbState(Mutex::new(Some(conn))));
+            // An encrypted library stays locked (no database) until it is opened with
+            // its passphrase via switch_memory_library.
+            let locked = is_encrypted(&current_library_dir);
+            let conn = if locked {
+                println!("🔒 [library] '{}' is encrypted and locked", current_library_id);
+                None
+            } else {
+                let db_path = current_library_dir.join("database").join("kraph.db");
+                Some(init_db(&db_path, None).map_err(|e| e.to_string())?)
+            };
+            app.manage(DbState(Mutex::new(conn)));
             app.manage(AppRootDir(app_data_dir.clone()));
             app.manage(AppDataDir(Mutex::new(current_library_dir.clone())));
             app.manage(CurrentLibraryId(Mutex::new(current_library_id)));
+            app.manage(LibraryKeyState(Mutex::new(None)));
 
             // Load model configuration from the current library (or use defaults)
             let config_path = library_model_config_path(&current_library_dir);
-            let model_config = ModelConfig::load_from_file(&config_path).unwrap_or_default();
+            let model_config = if locked {
+                ModelConfig::default()
+            } else {
+                ModelConfig::load_from_file(&config_path, None).unwrap_or_default()
+            };
             app.manage(ModelConfigState(Mutex::new(model_config)));
 
+            app.manage(MemoryWatcherState(Mutex::new(None)));
+            app.manage(AnswerStreamState(Mutex::new(
+                std::collections::HashMap::new(),
+            )));
+            if !locked {
+                if let Err(e) = start_memory_watcher(app.handle()) {
+                    println!("⚠️ [memory_watcher] Failed to start: {}", e);
+                }
+            }
+
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
@@ -3786,7 +6596,14 @@ pub fn run() {
             create_memory_library,
             switch_memory_library,
             rename_memory_library,
+            set_memory_library_path_template,
+            set_memory_library_entity_pages,
+            set_memory_library_git_history,
+            encrypt_memory_library,
+            change_library_passphrase,
             delete_memory_library,
+            export_library,
+            import_library,
             save_story_project,
             list_story_projects,
             load_story_project,
@@ -3803,6 +6620,14 @@ pub fn run() {
             save_memory,
             get_memories_list,
             get_graph,
+            export_graph,
+            import_graph,
+            import_chat_log,
+            import_email,
+            import_document,
+            import_calendar,
+            import_contacts,
+            get_document_parts,
             query_entity,
             search_memories_by_entity,
             get_character_profile,
@@ -3811,18 +6636,30 @@ pub fn run() {
             delete_memory_by_id,
             cleanup_db,
             clear_all_data_cmd,
+            rebuild_index,
+            reorganize_memory_files,
+            get_memory_file_history,
+            read_memory_file_at,
+            restore_memory_file,
             setup_whisper,
             transcribe_audio,
             answer_question,
+            answer_question_stream,
+            cancel_answer,
             generate_story_from_events,
             continue_story_chapter,
             rewrite_story_chapter,
             download_ollama_installer,
             check_ollama,
+            check_local_server,
+            list_local_models,
             run_ollama_setup,
             get_model_config,
             update_model_config,
             test_model_config,
+            get_model_capabilities,
+            embed_texts,
+            count_tokens,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
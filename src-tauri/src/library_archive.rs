@@ -0,0 +1,290 @@
+//! Portable `.kraph` library archives.
+//!
+//! An archive is a zip of one `libraries/<id>/` folder (database, memories, entity pages,
+//! story projects, `library.json` and, for encrypted libraries, `crypto.json`) plus a
+//! `manifest.json` with the schema version and a SHA-256 checksum per file. The model config
+//! is left out because it can hold API keys, and so are git histories and database scratch
+//! files. Encrypted libraries are archived as-is, so they stay encrypted in transit.
+
+use crate::database::{list_memories, update_memory_file_path, SCHEMA_VERSION};
+use chrono::Utc;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+use walkdir::WalkDir;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+pub const ARCHIVE_FORMAT: &str = "kraph-library";
+pub const ARCHIVE_FORMAT_VERSION: u32 = 1;
+const MANIFEST_NAME: &str = "manifest.json";
+const DATABASE_FILE: &str = "database/kraph.db";
+/// Upper bound on the buffer reserved up front for one entry; the manifest's `size` is untrusted.
+const MAX_PREALLOCATED_BYTES: u64 = 8 * 1024 * 1024;
+/// Top-level entries that never leave the machine.
+const EXCLUDED_ROOT_FILES: [&str; 1] = ["model_config.json"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveFile {
+    /// Path relative to the library folder, `/`-separated.
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveManifest {
+    pub format: String,
+    pub format_version: u32,
+    pub app_version: String,
+    /// `database::SCHEMA_VERSION` of the exported database.
+    pub schema_version: i64,
+    pub library_id: String,
+    pub name: String,
+    pub encrypted: bool,
+    pub exported_at: String,
+    pub files: Vec<ArchiveFile>,
+}
+
+fn archive_path_of(relative: &Path) -> Option<String> {
+    let parts: Vec<String> = relative
+        .components()
+        .map(|c| match c {
+            Component::Normal(part) => part.to_str().map(str::to_string),
+            _ => None,
+        })
+        .collect::<Option<_>>()?;
+    Some(parts.join("/"))
+}
+
+fn is_archived(path: &str) -> bool {
+    if EXCLUDED_ROOT_FILES.contains(&path) || path.split('/').any(|part| part == ".git") {
+        return false;
+    }
+    // Only the database itself; rebuild/encryption scratch files and journals stay behind.
+    !path.starts_with("database/") || path == DATABASE_FILE
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+/// Zip `library_dir` into `archive_path`. The caller must keep the database quiet (checkpointed
+/// and locked, or closed) while this runs.
+pub fn write_archive(
+    library_dir: &Path,
+    archive_path: &Path,
+    library_id: &str,
+    name: &str,
+    encrypted: bool,
+    schema_version: i64,
+) -> Result<ArchiveManifest, String> {
+    let mut files = Vec::new();
+    for entry in WalkDir::new(library_dir).sort_by_file_name() {
+        let entry = entry.map_err(|e| e.to_string())?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let relative = entry
+            .path()
+            .strip_prefix(library_dir)
+            .map_err(|e| e.to_string())?;
+        match archive_path_of(relative) {
+            Some(path) if is_archived(&path) => files.push((path, entry.path().to_path_buf())),
+            Some(_) => {}
+            None => println!(
+                "⚠️ [library_archive] Skipping {:?}: not a portable path",
+                relative
+            ),
+        }
+    }
+
+    if let Some(parent) = archive_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create {:?}: {}", parent, e))?;
+    }
+    let tmp_path = archive_path.with_extension("kraph.tmp");
+    let written = (|| {
+        let file = File::create(&tmp_path)
+            .map_err(|e| format!("Failed to create {:?}: {}", tmp_path, e))?;
+        let mut zip = ZipWriter::new(file);
+        let options = SimpleFileOptions::default()
+            .compression_method(CompressionMethod::Deflated)
+            .large_file(true);
+        let mut manifest = ArchiveManifest {
+            format: ARCHIVE_FORMAT.to_string(),
+            format_version: ARCHIVE_FORMAT_VERSION,
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            schema_version,
+            library_id: library_id.to_string(),
+            name: name.to_string(),
+            encrypted,
+            exported_at: Utc::now().to_rfc3339(),
+            files: Vec::new(),
+        };
+        for (path, source) in &files {
+            let bytes =
+                fs::read(source).map_err(|e| format!("Failed to read {:?}: {}", source, e))?;
+            zip.start_file(path.as_str(), options)
+                .map_err(|e| e.to_string())?;
+            zip.write_all(&bytes).map_err(|e| e.to_string())?;
+            manifest.files.push(ArchiveFile {
+                path: path.clone(),
+                size: bytes.len() as u64,
+                sha256: sha256_hex(&bytes),
+            });
+        }
+        let manifest_json = serde_json::to_string_pretty(&manifest).map_err(|e| e.to_string())?;
+        zip.start_file(MANIFEST_NAME, options)
+            .map_err(|e| e.to_string())?;
+        zip.write_all(manifest_json.as_bytes())
+            .map_err(|e| e.to_string())?;
+        zip.finish().map_err(|e| e.to_string())?;
+        Ok(manifest)
+    })();
+    match written {
+        Ok(manifest) => {
+            fs::rename(&tmp_path, archive_path)
+                .map_err(|e| format!("Failed to write {:?}: {}", archive_path, e))?;
+            Ok(manifest)
+        }
+        Err(e) => {
+            let _ = fs::remove_file(&tmp_path);
+            Err(e)
+        }
+    }
+}
+
+fn open_archive(archive_path: &Path) -> Result<ZipArchive<File>, String> {
+    let file = File::open(archive_path)
+        .map_err(|e| format!("Failed to open {:?}: {}", archive_path, e))?;
+    ZipArchive::new(file).map_err(|e| format!("{:?} is not a library archive: {}", archive_path, e))
+}
+
+fn manifest_of(archive: &mut ZipArchive<File>) -> Result<ArchiveManifest, String> {
+    let mut entry = archive
+        .by_name(MANIFEST_NAME)
+        .map_err(|_| "The archive has no manifest.json.".to_string())?;
+    let mut text = String::new();
+    entry
+        .read_to_string(&mut text)
+        .map_err(|e| format!("Failed to read manifest: {}", e))?;
+    let manifest: ArchiveManifest =
+        serde_json::from_str(&text).map_err(|e| format!("Invalid manifest: {}", e))?;
+    if manifest.format != ARCHIVE_FORMAT {
+        return Err(format!("Unknown archive format '{}'.", manifest.format));
+    }
+    if manifest.format_version > ARCHIVE_FORMAT_VERSION {
+        return Err(format!(
+            "The archive uses format version {}; this version of Kraph reads up to {}.",
+            manifest.format_version, ARCHIVE_FORMAT_VERSION
+        ));
+    }
+    if manifest.schema_version > SCHEMA_VERSION {
+        return Err(format!(
+            "The archive was exported with database schema {}; this version of Kraph supports \
+             up to {}. Update Kraph to import it.",
+            manifest.schema_version, SCHEMA_VERSION
+        ));
+    }
+    Ok(manifest)
+}
+
+/// Read and validate an archive's manifest without extracting anything.
+pub fn read_manifest(archive_path: &Path) -> Result<ArchiveManifest, String> {
+    manifest_of(&mut open_archive(archive_path)?)
+}
+
+/// Extract the files listed in the manifest into `target_dir`, verifying every checksum.
+/// Entries not listed in the manifest are ignored. On error `target_dir` is removed.
+pub fn extract_archive(archive_path: &Path, target_dir: &Path) -> Result<ArchiveManifest, String> {
+    let mut archive = open_archive(archive_path)?;
+    let manifest = manifest_of(&mut archive)?;
+    let extracted = (|| {
+        for file in &manifest.files {
+            let relative = PathBuf::from(&file.path);
+            if archive_path_of(&relative).as_deref() != Some(file.path.as_str())
+                || !is_archived(&file.path)
+            {
+                return Err(format!("The archive lists an unsafe path '{}'.", file.path));
+            }
+            let mut entry = archive
+                .by_name(&file.path)
+                .map_err(|_| format!("The archive is missing '{}'.", file.path))?;
+            let mut bytes = Vec::with_capacity(file.size.min(MAX_PREALLOCATED_BYTES) as usize);
+            (&mut entry)
+                .take(file.size)
+                .read_to_end(&mut bytes)
+                .map_err(|e| format!("Failed to read '{}': {}", file.path, e))?;
+            if sha256_hex(&bytes) != file.sha256 {
+                return Err(format!(
+                    "Checksum mismatch for '{}'; the archive is damaged.",
+                    file.path
+                ));
+            }
+            let target = target_dir.join(&relative);
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| format!("Failed to create {:?}: {}", parent, e))?;
+            }
+            fs::write(&target, bytes)
+                .map_err(|e| format!("Failed to write {:?}: {}", target, e))?;
+        }
+        Ok(())
+    })();
+    match extracted {
+        Ok(()) => Ok(manifest),
+        Err(e) => {
+            let _ = fs::remove_dir_all(target_dir);
+            Err(e)
+        }
+    }
+}
+
+/// Point `memories.md_file_path` at the matching file under `memories_dir`.
+///
+/// Paths in an imported database still lead into the exporting library's `memories/` folder,
+/// which is someone else's files on the same machine and nothing at all on another one. The
+/// part after a `memories` folder is kept, preferring a split where that file exists in
+/// `files_dir` (the folder the files are in right now, e.g. an import's staging copy).
+/// Paths already under `memories_dir` are left alone. Returns the number of rewritten paths.
+pub fn rebase_memory_paths(
+    conn: &Connection,
+    files_dir: &Path,
+    memories_dir: &Path,
+) -> Result<usize, String> {
+    let mut rebased = 0;
+    for memory in list_memories(conn).map_err(|e| e.to_string())? {
+        let Some(old) = memory.md_file_path.as_deref() else {
+            continue;
+        };
+        if Path::new(old).starts_with(memories_dir) {
+            continue;
+        }
+        // Split on both separators so archives made on another OS resolve too.
+        let parts: Vec<&str> = old.split(['/', '\\']).filter(|p| !p.is_empty()).collect();
+        let candidates: Vec<PathBuf> = parts
+            .iter()
+            .enumerate()
+            .filter(|(i, part)| **part == "memories" && i + 1 < parts.len())
+            .map(|(i, _)| parts[i + 1..].iter().collect::<PathBuf>())
+            .collect();
+        // Without a file to confirm it, the innermost `memories` folder is the library's own.
+        let Some(relative) = candidates
+            .iter()
+            .find(|relative| files_dir.join(relative).is_file())
+            .or(candidates.last())
+            .cloned()
+            .or_else(|| parts.last().map(PathBuf::from))
+        else {
+            continue;
+        };
+        let new = memories_dir.join(relative);
+        update_memory_file_path(conn, memory.id, &new.to_string_lossy())
+            .map_err(|e| e.to_string())?;
+        rebased += 1;
+    }
+    Ok(rebased)
+}
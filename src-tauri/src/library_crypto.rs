@@ -0,0 +1,229 @@
+//! Optional per-library encryption at rest.
+//!
+//! An encrypted library has a random 64-byte data key: the first half encrypts Markdown files and
+//! the model config with XChaCha20-Poly1305, the second half is the SQLCipher key for `kraph.db`.
+//! The data key is stored in `crypto.json`, wrapped with a key derived from the passphrase by
+//! Argon2id, so changing the passphrase only re-wraps it.
+//!
+//! File helpers (`encode_text_with` / `decode_text_with`) take the key of the library being read
+//! or written, which callers keep in managed state once it is unlocked; plaintext files are
+//! always readable so libraries can be converted in place.
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use zeroize::Zeroize;
+
+/// Header that marks an encrypted file; the base64 ciphertext follows it.
+const ENCRYPTED_HEADER: &str = "---\nencrypted: kraph-v1\n---\n\n";
+
+const NONCE_LEN: usize = 24;
+const SALT_LEN: usize = 16;
+const MIN_PASSPHRASE_CHARS: usize = 8;
+
+/// Argon2id cost for new key files: 64 MiB, 3 passes.
+const KDF_MEMORY_KIB: u32 = 64 * 1024;
+const KDF_ITERATIONS: u32 = 3;
+const KDF_PARALLELISM: u32 = 1;
+
+#[derive(Clone)]
+pub struct LibraryKey {
+    files: [u8; 32],
+    database: [u8; 32],
+}
+
+impl Drop for LibraryKey {
+    fn drop(&mut self) {
+        self.files.zeroize();
+        self.database.zeroize();
+    }
+}
+
+impl LibraryKey {
+    fn generate() -> Self {
+        let mut key = LibraryKey {
+            files: [0; 32],
+            database: [0; 32],
+        };
+        OsRng.fill_bytes(&mut key.files);
+        OsRng.fill_bytes(&mut key.database);
+        key
+    }
+
+    /// Value for `PRAGMA key` (a raw key, so SQLCipher skips its own KDF).
+    pub fn sqlcipher_key(&self) -> String {
+        let hex: String = self.database.iter().map(|b| format!("{:02X}", b)).collect();
+        format!("x'{}'", hex)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct KdfParams {
+    algorithm: String,
+    memory_kib: u32,
+    iterations: u32,
+    parallelism: u32,
+    salt: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct KeyFile {
+    version: u32,
+    kdf: KdfParams,
+    /// Data key encrypted with the passphrase-derived key (base64 of nonce + ciphertext).
+    wrapped_key: String,
+}
+
+fn key_file_path(library_dir: &Path) -> PathBuf {
+    library_dir.join("crypto.json")
+}
+
+pub fn is_encrypted(library_dir: &Path) -> bool {
+    key_file_path(library_dir).exists()
+}
+
+fn derive_wrapping_key(passphrase: &str, kdf: &KdfParams) -> Result<[u8; 32], String> {
+    if kdf.algorithm != "argon2id" {
+        return Err(format!("Unsupported key derivation: {}", kdf.algorithm));
+    }
+    let salt = BASE64.decode(&kdf.salt).map_err(|e| e.to_string())?;
+    let params = Params::new(kdf.memory_kib, kdf.iterations, kdf.parallelism, Some(32))
+        .map_err(|e| e.to_string())?;
+    let mut out = [0u8; 32];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), &salt, &mut out)
+        .map_err(|e| format!("Key derivation failed: {e}"))?;
+    Ok(out)
+}
+
+fn seal(key: &[u8; 32], plaintext: &[u8]) -> Result<Vec<u8>, String> {
+    let cipher = XChaCha20Poly1305::new(Key::from_slice(key));
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let mut out = nonce.to_vec();
+    out.extend(
+        cipher
+            .encrypt(&nonce, plaintext)
+            .map_err(|_| "Encryption failed".to_string())?,
+    );
+    Ok(out)
+}
+
+fn open(key: &[u8; 32], sealed: &[u8]) -> Option<Vec<u8>> {
+    if sealed.len() < NONCE_LEN {
+        return None;
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    XChaCha20Poly1305::new(Key::from_slice(key))
+        .decrypt(XNonce::from_slice(nonce), ciphertext)
+        .ok()
+}
+
+fn write_key_file(library_dir: &Path, key: &LibraryKey, passphrase: &str) -> Result<(), String> {
+    if passphrase.chars().count() < MIN_PASSPHRASE_CHARS {
+        return Err(format!(
+            "The passphrase must be at least {} characters long.",
+            MIN_PASSPHRASE_CHARS
+        ));
+    }
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let kdf = KdfParams {
+        algorithm: "argon2id".to_string(),
+        memory_kib: KDF_MEMORY_KIB,
+        iterations: KDF_ITERATIONS,
+        parallelism: KDF_PARALLELISM,
+        salt: BASE64.encode(salt),
+    };
+    let mut wrapping = derive_wrapping_key(passphrase, &kdf)?;
+    let mut raw = [key.files, key.database].concat();
+    let sealed = seal(&wrapping, &raw);
+    wrapping.zeroize();
+    raw.zeroize();
+    let file = KeyFile {
+        version: 1,
+        kdf,
+        wrapped_key: BASE64.encode(sealed?),
+    };
+    let content = serde_json::to_string_pretty(&file).map_err(|e| e.to_string())?;
+    let tmp = key_file_path(library_dir).with_extension("json.tmp");
+    fs::write(&tmp, content).map_err(|e| format!("Failed to save the library key: {e}"))?;
+    fs::rename(&tmp, key_file_path(library_dir))
+        .map_err(|e| format!("Failed to save the library key: {e}"))
+}
+
+/// Generate a data key for a library and store it wrapped with `passphrase`.
+/// The library only counts as encrypted once this returns.
+pub fn create_key_file(library_dir: &Path, passphrase: &str) -> Result<LibraryKey, String> {
+    let key = LibraryKey::generate();
+    write_key_file(library_dir, &key, passphrase)?;
+    Ok(key)
+}
+
+/// Remove the key file again, e.g. when converting a library to encryption failed.
+pub fn remove_key_file(library_dir: &Path) -> Result<(), String> {
+    fs::remove_file(key_file_path(library_dir)).map_err(|e| e.to_string())
+}
+
+/// Unwrap a library's data key with `passphrase`.
+pub fn unlock(library_dir: &Path, passphrase: &str) -> Result<LibraryKey, String> {
+    let content = fs::read_to_string(key_file_path(library_dir))
+        .map_err(|e| format!("Failed to read the library key: {e}"))?;
+    let file: KeyFile =
+        serde_json::from_str(&content).map_err(|e| format!("Invalid library key file: {e}"))?;
+    let mut wrapping = derive_wrapping_key(passphrase, &file.kdf)?;
+    let sealed = BASE64
+        .decode(&file.wrapped_key)
+        .map_err(|e| e.to_string())?;
+    let raw = open(&wrapping, &sealed);
+    wrapping.zeroize();
+    let mut raw = raw.ok_or("Wrong passphrase.")?;
+    if raw.len() != 64 {
+        raw.zeroize();
+        return Err("Invalid library key file.".to_string());
+    }
+    let mut key = LibraryKey {
+        files: [0; 32],
+        database: [0; 32],
+    };
+    key.files.copy_from_slice(&raw[..32]);
+    key.database.copy_from_slice(&raw[32..]);
+    raw.zeroize();
+    Ok(key)
+}
+
+/// Re-wrap the data key under a new passphrase. Encrypted data is untouched.
+pub fn change_passphrase(library_dir: &Path, old: &str, new: &str) -> Result<(), String> {
+    let key = unlock(library_dir, old)?;
+    write_key_file(library_dir, &key, new)
+}
+
+/// Encrypt `contents` with `key`, or return them unchanged when `key` is `None`.
+pub fn encode_text_with(key: Option<&LibraryKey>, contents: &str) -> Result<String, String> {
+    match key {
+        Some(key) => Ok(format!(
+            "{}{}\n",
+            ENCRYPTED_HEADER,
+            BASE64.encode(seal(&key.files, contents.as_bytes())?)
+        )),
+        None => Ok(contents.to_string()),
+    }
+}
+
+/// Decrypt `raw` with `key` if it is an encrypted file; plaintext is returned as-is.
+pub fn decode_text_with(key: Option<&LibraryKey>, raw: String) -> Result<String, String> {
+    let Some(payload) = raw.strip_prefix(ENCRYPTED_HEADER) else {
+        return Ok(raw);
+    };
+    let key = key.ok_or("This library is locked.")?;
+    let sealed = BASE64
+        .decode(payload.trim())
+        .map_err(|e| format!("Corrupted encrypted file: {e}"))?;
+    let plain = open(&key.files, &sealed).ok_or("Failed to decrypt file.")?;
+    String::from_utf8(plain).map_err(|e| e.to_string())
+}
@@ -0,0 +1,1276 @@
+//! LLM providers behind one trait.
+//!
+//! `model_client` builds prompts and parses results; everything that differs between backends
+//! (endpoints, request bodies, streaming framing, truncation signals) lives in an
+//! [`LlmProvider`] implementation. [`provider_for`] picks the implementation for a
+//! [`ModelConfig`] from the registry, so adding a backend means adding a `ModelProvider`
+//! variant, an implementation and one registry entry.
+
+use crate::model_config::{ModelConfig, ModelProvider};
+use crate::ollama::{ensure_model_available, ensure_ollama_running};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Timeout for a whole completion request (local models can be slow).
+const REQUEST_TIMEOUT_SECS: u64 = 300;
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// What a provider supports beyond plain blocking chat.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct Capabilities {
+    pub chat: bool,
+    pub streaming: bool,
+    /// The backend can be asked to return a JSON object only.
+    pub json_mode: bool,
+    /// JSON output can be constrained to a schema (`ResponseFormat::Schema`).
+    pub structured_output: bool,
+    pub embeddings: bool,
+    /// `count_tokens` uses the model's tokenizer instead of the heuristic estimate.
+    pub token_counting: bool,
+}
+
+/// Which configured model a request is for (Ollama can use a separate extraction model).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModelRole {
+    Chat,
+    Extract,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseFormat<'a> {
+    Text,
+    /// A JSON object matching `schema`. Backends without structured output receive the
+    /// schema as an instruction instead.
+    Schema {
+        name: &'static str,
+        schema: &'a Value,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct CompletionRequest<'a> {
+    /// Instructions sent separately from the prompt where the backend supports it.
+    pub system: Option<&'a str>,
+    pub prompt: &'a str,
+    pub role: ModelRole,
+    pub max_tokens: i32,
+    pub temperature: f32,
+    pub format: ResponseFormat<'a>,
+}
+
+impl<'a> CompletionRequest<'a> {
+    /// A plain-text chat request using the configured temperature and token budget.
+    pub fn new(config: &ModelConfig, prompt: &'a str) -> Self {
+        Self {
+            system: None,
+            prompt,
+            role: ModelRole::Chat,
+            max_tokens: config.max_tokens,
+            temperature: config.temperature,
+            format: ResponseFormat::Text,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Completion {
+    /// The response text, trimmed.
+    pub text: String,
+    /// The output hit `max_tokens`.
+    pub truncated: bool,
+}
+
+pub trait LlmProvider: Send + Sync {
+    /// Stable identifier, also used for i18n keys (`ollama`, `deepseek`, `openai`).
+    fn id(&self) -> &'static str;
+
+    /// Name used in logs.
+    fn label(&self) -> &'static str;
+
+    fn model(&self, role: ModelRole) -> &str;
+
+    fn capabilities(&self) -> Capabilities;
+
+    /// Make sure the backend can serve requests (start a local service, pull the model).
+    fn ensure_ready(&self) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Blocking single-turn completion.
+    fn complete(&self, request: &CompletionRequest) -> Result<Completion, String>;
+
+    /// Streaming completion: `on_delta` receives each text fragment as it arrives. Dropping
+    /// the future aborts the HTTP request.
+    fn complete_stream<'a>(
+        &'a self,
+        request: &'a CompletionRequest<'a>,
+        on_delta: &'a mut (dyn FnMut(&str) + Send),
+    ) -> BoxFuture<'a, Result<Completion, String>>;
+
+    fn embed(&self, _texts: &[String]) -> Result<Vec<Vec<f32>>, String> {
+        Err(format!("{} does not support embeddings", self.label()))
+    }
+
+    /// Tokens in `text`; providers without a tokenizer endpoint return an estimate.
+    fn count_tokens(&self, text: &str) -> Result<usize, String> {
+        Ok(estimate_tokens(text))
+    }
+}
+
+/// Rough token count: one per CJK character, one per four other characters.
+pub fn estimate_tokens(text: &str) -> usize {
+    let (cjk, other) = text.chars().fold((0usize, 0usize), |(cjk, other), c| {
+        if matches!(c as u32, 0x3040..=0x30FF | 0x3400..=0x9FFF | 0xAC00..=0xD7AF | 0xF900..=0xFAFF)
+        {
+            (cjk + 1, other)
+        } else {
+            (cjk, other + 1)
+        }
+    });
+    cjk + other.div_ceil(4)
+}
+
+type ProviderFactory = fn(&ModelConfig) -> Option<Box<dyn LlmProvider>>;
+
+/// Every known provider; the first factory that accepts the config wins.
+const REGISTRY: &[ProviderFactory] = &[
+    OllamaProvider::from_config,
+    OpenAiProvider::from_config,
+    AnthropicProvider::from_config,
+    LocalServerProvider::from_config,
+];
+
+/// The provider serving `config`.
+pub fn provider_for(config: &ModelConfig) -> Result<Box<dyn LlmProvider>, String> {
+    REGISTRY
+        .iter()
+        .find_map(|factory| factory(config))
+        .ok_or_else(|| "No model provider available for the current configuration".to_string())
+}
+
+// ---------------------------------------------------------------------------
+// Shared HTTP helpers
+// ---------------------------------------------------------------------------
+
+fn blocking_client() -> Result<reqwest::blocking::Client, String> {
+    reqwest::blocking::Client::builder()
+        .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+fn async_client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+/// Feed each line of a streamed response body to `on_line` until it returns `false` or the
+/// body ends. Lines are split on raw bytes so multi-byte characters never straddle chunks.
+async fn for_each_line<F>(mut res: reqwest::Response, mut on_line: F) -> Result<(), String>
+where
+    F: FnMut(&str) -> Result<bool, String>,
+{
+    let mut buffer: Vec<u8> = Vec::new();
+    while let Some(chunk) = res
+        .chunk()
+        .await
+        .map_err(|e| format!("Stream interrupted: {}", e))?
+    {
+        buffer.extend_from_slice(&chunk);
+        while let Some(pos) = buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=pos).collect();
+            if !on_line(String::from_utf8_lossy(&line).trim())? {
+                return Ok(());
+            }
+        }
+    }
+    if !buffer.is_empty() {
+        on_line(String::from_utf8_lossy(&buffer).trim())?;
+    }
+    Ok(())
+}
+
+/// Server-sent events: call `on_data` with the payload of every `data:` line until it returns
+/// `false` or the stream sends `[DONE]`. Blank lines and `:` keep-alive comments are skipped.
+async fn for_each_sse_data<F>(res: reqwest::Response, mut on_data: F) -> Result<(), String>
+where
+    F: FnMut(&Value) -> Result<bool, String>,
+{
+    for_each_line(res, |line| {
+        let Some(data) = line.strip_prefix("data:").map(str::trim) else {
+            return Ok(true);
+        };
+        if data == "[DONE]" {
+            return Ok(false);
+        }
+        let json: Value =
+            serde_json::from_str(data).map_err(|e| format!("Failed to parse API stream: {}", e))?;
+        on_data(&json)
+    })
+    .await
+}
+
+// ---------------------------------------------------------------------------
+// Ollama
+// ---------------------------------------------------------------------------
+
+/// Ollama always runs at this temperature; `ModelConfig::temperature` applies to API backends.
+const OLLAMA_TEMPERATURE: f32 = 0.2;
+
+pub struct OllamaProvider {
+    base_url: String,
+    model_name: String,
+    extract_model_name: String,
+}
+
+impl OllamaProvider {
+    fn from_config(config: &ModelConfig) -> Option<Box<dyn LlmProvider>> {
+        match &config.provider {
+            ModelProvider::Ollama {
+                base_url,
+                model_name,
+                extract_model_name,
+            } => Some(Box::new(Self {
+                base_url: base_url.trim_end_matches('/').to_string(),
+                model_name: model_name.clone(),
+                extract_model_name: extract_model_name.clone(),
+            })),
+            _ => None,
+        }
+    }
+
+    /// `/api/generate` body; `stream` selects NDJSON streaming.
+    fn body(&self, request: &CompletionRequest, stream: bool) -> Value {
+        let mut body = json!({
+            "model": self.model(request.role),
+            "prompt": request.prompt,
+            "stream": stream,
+            "options": {
+                "temperature": OLLAMA_TEMPERATURE,
+                "num_predict": request.max_tokens
+            }
+        });
+        if let Some(system) = request.system {
+            body["system"] = json!(system);
+        }
+        match request.format {
+            ResponseFormat::Text => {}
+            ResponseFormat::Schema { schema, .. } => body["format"] = schema.clone(),
+        }
+        body
+    }
+}
+
+impl LlmProvider for OllamaProvider {
+    fn id(&self) -> &'static str {
+        "ollama"
+    }
+
+    fn label(&self) -> &'static str {
+        "Ollama"
+    }
+
+    fn model(&self, role: ModelRole) -> &str {
+        match role {
+            ModelRole::Chat => &self.model_name,
+            ModelRole::Extract => &self.extract_model_name,
+        }
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            chat: true,
+            streaming: true,
+            json_mode: true,
+            structured_output: true,
+            embeddings: true,
+            token_counting: false,
+        }
+    }
+
+    fn ensure_ready(&self) -> Result<(), String> {
+        ensure_ollama_running(&self.base_url)?;
+        ensure_model_available(&self.base_url, &self.model_name)
+    }
+
+    fn complete(&self, request: &CompletionRequest) -> Result<Completion, String> {
+        let res = blocking_client()?
+            .post(format!("{}/api/generate", self.base_url))
+            .json(&self.body(request, false))
+            .send()
+            .map_err(|e| format!("Ollama request failed: {}", e))?;
+
+        if !res.status().is_success() {
+            let status = res.status();
+            let err_body = res.text().unwrap_or_default();
+            return Err(format!("Ollama error {}: {}", status, err_body));
+        }
+
+        let json: Value = res
+            .json()
+            .map_err(|e| format!("Failed to parse Ollama response: {}", e))?;
+        let text = json
+            .get("response")
+            .and_then(|v| v.as_str())
+            .ok_or("Ollama response missing 'response' field")?
+            .trim()
+            .to_string();
+        let truncated = json.get("done_reason").and_then(|r| r.as_str()) == Some("length");
+        Ok(Completion { text, truncated })
+    }
+
+    fn complete_stream<'a>(
+        &'a self,
+        request: &'a CompletionRequest<'a>,
+        on_delta: &'a mut (dyn FnMut(&str) + Send),
+    ) -> BoxFuture<'a, Result<Completion, String>> {
+        Box::pin(async move {
+            let res = async_client()?
+                .post(format!("{}/api/generate", self.base_url))
+                .json(&self.body(request, true))
+                .send()
+                .await
+                .map_err(|e| format!("Ollama request failed: {}", e))?;
+
+            if !res.status().is_success() {
+                let status = res.status();
+                let err_body = res.text().await.unwrap_or_default();
+                return Err(format!("Ollama error {}: {}", status, err_body));
+            }
+
+            // One JSON object per line; the last one has `done: true`.
+            let mut completion = Completion::default();
+            for_each_line(res, |line| {
+                if line.is_empty() {
+                    return Ok(true);
+                }
+                let json: Value = serde_json::from_str(line)
+                    .map_err(|e| format!("Failed to parse Ollama stream: {}", e))?;
+                if let Some(error) = json.get("error").and_then(|v| v.as_str()) {
+                    return Err(format!("Ollama error: {}", error));
+                }
+                if let Some(delta) = json.get("response").and_then(|v| v.as_str()) {
+                    if !delta.is_empty() {
+                        completion.text.push_str(delta);
+                        on_delta(delta);
+                    }
+                }
+                let done = json.get("done").and_then(|v| v.as_bool()).unwrap_or(false);
+                if done {
+                    completion.truncated =
+                        json.get("done_reason").and_then(|r| r.as_str()) == Some("length");
+                }
+                Ok(!done)
+            })
+            .await?;
+            completion.text = completion.text.trim().to_string();
+            Ok(completion)
+        })
+    }
+
+    fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, String> {
+        let res = blocking_client()?
+            .post(format!("{}/api/embed", self.base_url))
+            .json(&json!({ "model": self.model_name, "input": texts }))
+            .send()
+            .map_err(|e| format!("Ollama request failed: {}", e))?;
+        if !res.status().is_success() {
+            let status = res.status();
+            let err_body = res.text().unwrap_or_default();
+            return Err(format!("Ollama error {}: {}", status, err_body));
+        }
+        #[derive(serde::Deserialize)]
+        struct EmbedResponse {
+            embeddings: Vec<Vec<f32>>,
+        }
+        let parsed: EmbedResponse = res
+            .json()
+            .map_err(|e| format!("Failed to parse Ollama embeddings: {}", e))?;
+        Ok(parsed.embeddings)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// OpenAI-compatible chat completions (OpenAI, DeepSeek, ...)
+// ---------------------------------------------------------------------------
+
+/// Appended to the instructions for JSON output: OpenAI-style servers reject `json_object`
+/// requests whose messages never mention JSON.
+const JSON_MODE_HINT: &str = "Respond with a single JSON object.";
+
+/// Instructions for backends that cannot enforce a schema themselves.
+fn schema_hint(schema: &Value) -> String {
+    format!(
+        "{} It must match this JSON schema: {}",
+        JSON_MODE_HINT, schema
+    )
+}
+
+/// How an OpenAI-compatible request asks for JSON, strongest first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum JsonMode {
+    /// `response_format: json_schema`.
+    Schema,
+    /// `response_format: json_object`; the schema goes into the instructions.
+    Object,
+    /// No `response_format`; the instructions alone ask for JSON.
+    Prompt,
+}
+
+impl JsonMode {
+    fn weaker(self) -> Option<Self> {
+        match self {
+            JsonMode::Schema => Some(JsonMode::Object),
+            JsonMode::Object => Some(JsonMode::Prompt),
+            JsonMode::Prompt => None,
+        }
+    }
+}
+
+/// The JSON mode a config's endpoint fell back to after rejecting a `response_format`, so
+/// later requests skip the failing round trip. It lives in the [`ModelConfig`] and is shared
+/// by its clones, so it is forgotten when the config is replaced or another library is opened.
+#[derive(Debug, Clone, Default)]
+pub struct JsonModeFallback(Arc<Mutex<Option<JsonMode>>>);
+
+/// A 4xx whose body names the response format: the endpoint does not support that mode.
+fn rejects_response_format(status: reqwest::StatusCode, body: &str) -> bool {
+    status.is_client_error() && (body.contains("response_format") || body.contains("json_schema"))
+}
+
+pub struct OpenAiProvider {
+    id: &'static str,
+    label: &'static str,
+    api_key: String,
+    base_url: String,
+    model_name: String,
+    /// The backend is expected to accept `response_format: json_schema`; otherwise
+    /// `json_object` is sent and the schema becomes part of the instructions. Endpoints that
+    /// reject either fall back to the next weaker `JsonMode`.
+    json_schema: bool,
+    fallback: JsonModeFallback,
+}
+
+impl OpenAiProvider {
+    fn from_config(config: &ModelConfig) -> Option<Box<dyn LlmProvider>> {
+        let (id, label, api_key, base_url, model_name) = match &config.provider {
+            ModelProvider::DeepSeek {
+                api_key,
+                base_url,
+                model_name,
+            } => ("deepseek", "DeepSeek", api_key, base_url, model_name),
+            ModelProvider::OpenAI {
+                api_key,
+                base_url,
+                model_name,
+            } => ("openai", "OpenAI-compatible", api_key, base_url, model_name),
+            _ => return None,
+        };
+        Some(Box::new(Self {
+            id,
+            label,
+            api_key: api_key.clone(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            model_name: model_name.clone(),
+            // DeepSeek only implements `json_object`.
+            json_schema: id != "deepseek",
+            fallback: config.json_mode_fallback.clone(),
+        }))
+    }
+
+    fn headers(&self) -> reqwest::header::HeaderMap {
+        bearer_headers(&self.api_key)
+    }
+
+    /// The strongest JSON mode this endpoint has not rejected.
+    fn json_mode(&self) -> JsonMode {
+        let known = self.fallback.0.lock().ok().and_then(|mode| *mode);
+        match known {
+            Some(mode) => mode,
+            None if self.json_schema => JsonMode::Schema,
+            None => JsonMode::Object,
+        }
+    }
+
+    /// After an error response, the weaker mode to retry a structured request with, if the
+    /// error was a rejected `response_format`. The choice is remembered for the config.
+    fn fallback_mode(
+        &self,
+        request: &CompletionRequest,
+        mode: JsonMode,
+        status: reqwest::StatusCode,
+        body: &str,
+    ) -> Option<JsonMode> {
+        if request.format == ResponseFormat::Text || !rejects_response_format(status, body) {
+            return None;
+        }
+        let weaker = mode.weaker()?;
+        println!(
+            "⚠️ [{}] endpoint rejected {:?} response format ({}), retrying with {:?}",
+            self.id, mode, status, weaker
+        );
+        if let Ok(mut known) = self.fallback.0.lock() {
+            *known = Some(weaker);
+        }
+        Some(weaker)
+    }
+
+    /// `/chat/completions` body; `stream` selects server-sent events and `mode` how a
+    /// structured request asks for JSON.
+    fn body(&self, request: &CompletionRequest, stream: bool, mode: JsonMode) -> Value {
+        let hint = match request.format {
+            ResponseFormat::Text => None,
+            ResponseFormat::Schema { schema, .. } if mode != JsonMode::Schema => {
+                Some(schema_hint(schema))
+            }
+            _ if request.system.is_some_and(|system| system.contains("JSON")) => None,
+            _ => Some(JSON_MODE_HINT.to_string()),
+        };
+        let system = match (request.system, hint) {
+            (Some(system), Some(hint)) => Some(format!("{}\n{}", system, hint)),
+            (system, hint) => hint.or(system.map(str::to_string)),
+        };
+        let mut messages = Vec::new();
+        if let Some(system) = system {
+            messages.push(json!({ "role": "system", "content": system }));
+        }
+        messages.push(json!({ "role": "user", "content": request.prompt }));
+        let mut body = json!({
+            "model": self.model_name,
+            "messages": messages,
+            "temperature": request.temperature,
+            "max_tokens": request.max_tokens
+        });
+        if stream {
+            body["stream"] = json!(true);
+        }
+        match (request.format, mode) {
+            (ResponseFormat::Text, _) | (_, JsonMode::Prompt) => {}
+            (ResponseFormat::Schema { name, schema }, JsonMode::Schema) => {
+                body["response_format"] = json!({
+                    "type": "json_schema",
+                    "json_schema": { "name": name, "schema": schema, "strict": false }
+                });
+            }
+            (ResponseFormat::Schema { .. }, JsonMode::Object) => {
+                body["response_format"] = json!({ "type": "json_object" });
+            }
+        }
+        body
+    }
+}
+
+/// `Authorization: Bearer` headers; keyless local servers get none.
+fn bearer_headers(api_key: &str) -> reqwest::header::HeaderMap {
+    let mut headers = reqwest::header::HeaderMap::new();
+    if !api_key.is_empty() {
+        if let Ok(value) = reqwest::header::HeaderValue::from_str(&format!("Bearer {}", api_key)) {
+            headers.insert(reqwest::header::AUTHORIZATION, value);
+        }
+    }
+    headers
+}
+
+/// `finish_reason == "length"` means the output hit `max_tokens`.
+fn finish_reason_is_length(choice: &Value) -> bool {
+    choice.get("finish_reason").and_then(|r| r.as_str()) == Some("length")
+}
+
+impl LlmProvider for OpenAiProvider {
+    fn id(&self) -> &'static str {
+        self.id
+    }
+
+    fn label(&self) -> &'static str {
+        self.label
+    }
+
+    fn model(&self, _role: ModelRole) -> &str {
+        &self.model_name
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            chat: true,
+            streaming: true,
+            json_mode: true,
+            structured_output: self.json_schema,
+            embeddings: false,
+            token_counting: false,
+        }
+    }
+
+    fn complete(&self, request: &CompletionRequest) -> Result<Completion, String> {
+        let client = blocking_client()?;
+        let mut mode = self.json_mode();
+        let res = loop {
+            let res = client
+                .post(format!("{}/chat/completions", self.base_url))
+                .headers(self.headers())
+                .json(&self.body(request, false, mode))
+                .send()
+                .map_err(|e| format!("API request failed: {}", e))?;
+            if res.status().is_success() {
+                break res;
+            }
+            let status = res.status();
+            let err_body = res.text().unwrap_or_default();
+            match self.fallback_mode(request, mode, status, &err_body) {
+                Some(weaker) => mode = weaker,
+                None => return Err(format!("API error {}: {}", status, err_body)),
+            }
+        };
+
+        let json: Value = res
+            .json()
+            .map_err(|e| format!("Failed to parse API response: {}", e))?;
+
+        let choice = json
+            .get("choices")
+            .and_then(|v| v.as_array())
+            .and_then(|arr| arr.first())
+            .ok_or("API response missing 'choices'")?;
+
+        let text = choice
+            .get("message")
+            .and_then(|msg| msg.get("content"))
+            .and_then(|content| content.as_str())
+            .ok_or("API response missing 'content'")?
+            .trim()
+            .to_string();
+
+        Ok(Completion {
+            text,
+            truncated: finish_reason_is_length(choice),
+        })
+    }
+
+    fn complete_stream<'a>(
+        &'a self,
+        request: &'a CompletionRequest<'a>,
+        on_delta: &'a mut (dyn FnMut(&str) + Send),
+    ) -> BoxFuture<'a, Result<Completion, String>> {
+        Box::pin(async move {
+            let client = async_client()?;
+            let mut mode = self.json_mode();
+            let res = loop {
+                let res = client
+                    .post(format!("{}/chat/completions", self.base_url))
+                    .headers(self.headers())
+                    .header("Accept", "text/event-stream")
+                    .json(&self.body(request, true, mode))
+                    .send()
+                    .await
+                    .map_err(|e| format!("API request failed: {}", e))?;
+                if res.status().is_success() {
+                    break res;
+                }
+                let status = res.status();
+                let err_body = res.text().await.unwrap_or_default();
+                match self.fallback_mode(request, mode, status, &err_body) {
+                    Some(weaker) => mode = weaker,
+                    None => return Err(format!("API error {}: {}", status, err_body)),
+                }
+            };
+
+            let mut completion = Completion::default();
+            for_each_sse_data(res, |json| {
+                if let Some(error) = json.get("error") {
+                    let message = error
+                        .get("message")
+                        .and_then(|m| m.as_str())
+                        .map(str::to_string)
+                        .unwrap_or_else(|| error.to_string());
+                    return Err(format!("API error: {}", message));
+                }
+                let Some(choice) = json
+                    .get("choices")
+                    .and_then(|v| v.as_array())
+                    .and_then(|arr| arr.first())
+                else {
+                    return Ok(true);
+                };
+                if let Some(delta) = choice
+                    .get("delta")
+                    .and_then(|d| d.get("content"))
+                    .and_then(|c| c.as_str())
+                {
+                    if !delta.is_empty() {
+                        completion.text.push_str(delta);
+                        on_delta(delta);
+                    }
+                }
+                completion.truncated |= finish_reason_is_length(choice);
+                Ok(true)
+            })
+            .await?;
+            completion.text = completion.text.trim().to_string();
+            Ok(completion)
+        })
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Anthropic Messages API
+// ---------------------------------------------------------------------------
+
+/// Version header required by the Messages API.
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+pub struct AnthropicProvider {
+    api_key: String,
+    base_url: String,
+    model_name: String,
+}
+
+impl AnthropicProvider {
+    fn from_config(config: &ModelConfig) -> Option<Box<dyn LlmProvider>> {
+        match &config.provider {
+            ModelProvider::Anthropic {
+                api_key,
+                base_url,
+                model_name,
+            } => Some(Box::new(Self {
+                api_key: api_key.clone(),
+                base_url: base_url.trim_end_matches('/').to_string(),
+                model_name: model_name.clone(),
+            })),
+            _ => None,
+        }
+    }
+
+    /// Endpoint URL; the configured base may or may not already end in `/v1`.
+    fn url(&self, path: &str) -> String {
+        let base = self.base_url.strip_suffix("/v1").unwrap_or(&self.base_url);
+        format!("{}/v1/{}", base, path)
+    }
+
+    fn headers(&self) -> reqwest::header::HeaderMap {
+        let mut headers = reqwest::header::HeaderMap::new();
+        if let Ok(key) = reqwest::header::HeaderValue::from_str(&self.api_key) {
+            headers.insert("x-api-key", key);
+        }
+        headers.insert(
+            "anthropic-version",
+            reqwest::header::HeaderValue::from_static(ANTHROPIC_VERSION),
+        );
+        headers
+    }
+
+    /// `/v1/messages` body; `stream` selects server-sent events.
+    fn body(&self, request: &CompletionRequest, stream: bool) -> Value {
+        let mut body = json!({
+            "model": self.model_name,
+            "max_tokens": request.max_tokens,
+            "temperature": request.temperature,
+            "messages": [
+                {
+                    "role": "user",
+                    "content": request.prompt
+                }
+            ]
+        });
+        let system = match (request.system, request.format) {
+            (Some(system), ResponseFormat::Schema { schema, .. }) => {
+                Some(format!("{}\n{}", system, schema_hint(schema)))
+            }
+            (None, ResponseFormat::Schema { schema, .. }) => Some(schema_hint(schema)),
+            (system, _) => system.map(str::to_string),
+        };
+        if let Some(system) = system {
+            body["system"] = json!(system);
+        }
+        if stream {
+            body["stream"] = json!(true);
+        }
+        body
+    }
+}
+
+/// `stop_reason == "max_tokens"` means the output hit `max_tokens`.
+fn stop_reason_is_max_tokens(value: &Value) -> bool {
+    value.get("stop_reason").and_then(|r| r.as_str()) == Some("max_tokens")
+}
+
+/// Message of an `{"type": "error", "error": {...}}` stream event.
+fn anthropic_error(json: &Value) -> Option<String> {
+    let error = json.get("error")?;
+    Some(
+        error
+            .get("message")
+            .and_then(|m| m.as_str())
+            .map(str::to_string)
+            .unwrap_or_else(|| error.to_string()),
+    )
+}
+
+impl LlmProvider for AnthropicProvider {
+    fn id(&self) -> &'static str {
+        "anthropic"
+    }
+
+    fn label(&self) -> &'static str {
+        "Anthropic"
+    }
+
+    fn model(&self, _role: ModelRole) -> &str {
+        &self.model_name
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            chat: true,
+            streaming: true,
+            json_mode: false,
+            structured_output: false,
+            embeddings: false,
+            token_counting: true,
+        }
+    }
+
+    fn complete(&self, request: &CompletionRequest) -> Result<Completion, String> {
+        let res = blocking_client()?
+            .post(self.url("messages"))
+            .headers(self.headers())
+            .json(&self.body(request, false))
+            .send()
+            .map_err(|e| format!("Anthropic request failed: {}", e))?;
+
+        if !res.status().is_success() {
+            let status = res.status();
+            let err_body = res.text().unwrap_or_default();
+            return Err(format!("Anthropic error {}: {}", status, err_body));
+        }
+
+        let json: Value = res
+            .json()
+            .map_err(|e| format!("Failed to parse Anthropic response: {}", e))?;
+        // The reply is a list of content blocks; only text blocks carry the answer.
+        let text: String = json
+            .get("content")
+            .and_then(|c| c.as_array())
+            .ok_or("Anthropic response missing 'content'")?
+            .iter()
+            .filter(|block| block.get("type").and_then(|t| t.as_str()) == Some("text"))
+            .filter_map(|block| block.get("text").and_then(|t| t.as_str()))
+            .collect();
+        Ok(Completion {
+            text: text.trim().to_string(),
+            truncated: stop_reason_is_max_tokens(&json),
+        })
+    }
+
+    fn complete_stream<'a>(
+        &'a self,
+        request: &'a CompletionRequest<'a>,
+        on_delta: &'a mut (dyn FnMut(&str) + Send),
+    ) -> BoxFuture<'a, Result<Completion, String>> {
+        Box::pin(async move {
+            let res = async_client()?
+                .post(self.url("messages"))
+                .headers(self.headers())
+                .header("Accept", "text/event-stream")
+                .json(&self.body(request, true))
+                .send()
+                .await
+                .map_err(|e| format!("Anthropic request failed: {}", e))?;
+
+            if !res.status().is_success() {
+                let status = res.status();
+                let err_body = res.text().await.unwrap_or_default();
+                return Err(format!("Anthropic error {}: {}", status, err_body));
+            }
+
+            // Every event repeats its name in the `type` field of its data, so the
+            // `event:` lines can be ignored.
+            let mut completion = Completion::default();
+            for_each_sse_data(res, |json| {
+                match json.get("type").and_then(|t| t.as_str()) {
+                    Some("content_block_delta") => {
+                        if let Some(delta) = json
+                            .get("delta")
+                            .filter(|d| {
+                                d.get("type").and_then(|t| t.as_str()) == Some("text_delta")
+                            })
+                            .and_then(|d| d.get("text"))
+                            .and_then(|t| t.as_str())
+                        {
+                            completion.text.push_str(delta);
+                            on_delta(delta);
+                        }
+                        Ok(true)
+                    }
+                    Some("message_delta") => {
+                        if json.get("delta").is_some_and(stop_reason_is_max_tokens) {
+                            completion.truncated = true;
+                        }
+                        Ok(true)
+                    }
+                    Some("message_stop") => Ok(false),
+                    Some("error") => Err(format!(
+                        "Anthropic error: {}",
+                        anthropic_error(json).unwrap_or_default()
+                    )),
+                    _ => Ok(true),
+                }
+            })
+            .await?;
+            completion.text = completion.text.trim().to_string();
+            Ok(completion)
+        })
+    }
+
+    fn count_tokens(&self, text: &str) -> Result<usize, String> {
+        let res = blocking_client()?
+            .post(self.url("messages/count_tokens"))
+            .headers(self.headers())
+            .json(&json!({
+                "model": self.model_name,
+                "messages": [{ "role": "user", "content": text }]
+            }))
+            .send()
+            .map_err(|e| format!("Anthropic request failed: {}", e))?;
+
+        if !res.status().is_success() {
+            let status = res.status();
+            let err_body = res.text().unwrap_or_default();
+            return Err(format!("Anthropic error {}: {}", status, err_body));
+        }
+
+        let json: Value = res
+            .json()
+            .map_err(|e| format!("Failed to parse Anthropic response: {}", e))?;
+        json.get("input_tokens")
+            .and_then(|n| n.as_u64())
+            .map(|n| n as usize)
+            .ok_or_else(|| "Anthropic response missing 'input_tokens'".to_string())
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Local OpenAI-compatible servers (llama.cpp `llama-server`, LM Studio)
+// ---------------------------------------------------------------------------
+
+/// Health and discovery requests should answer quickly.
+const LOCAL_SERVER_PROBE_TIMEOUT_SECS: u64 = 3;
+
+/// Server root without a trailing `/v1`, so both spellings of the base URL work.
+fn local_server_root(base_url: &str) -> &str {
+    let base = base_url.trim_end_matches('/');
+    base.strip_suffix("/v1").unwrap_or(base)
+}
+
+fn probe_client() -> Result<reqwest::blocking::Client, String> {
+    reqwest::blocking::Client::builder()
+        .timeout(Duration::from_secs(LOCAL_SERVER_PROBE_TIMEOUT_SECS))
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))
+}
+
+/// Model IDs served by a local server (`GET /v1/models`).
+pub fn list_local_server_models(base_url: &str, api_key: &str) -> Result<Vec<String>, String> {
+    let url = format!("{}/v1/models", local_server_root(base_url));
+    let res = probe_client()?
+        .get(&url)
+        .headers(bearer_headers(api_key))
+        .send()
+        .map_err(|e| format!("Cannot connect to local server: {}", e))?;
+    if !res.status().is_success() {
+        return Err(format!("Local server returned status: {}", res.status()));
+    }
+    let json: Value = res
+        .json()
+        .map_err(|e| format!("Failed to parse model list: {}", e))?;
+    Ok(json
+        .get("data")
+        .and_then(|d| d.as_array())
+        .map(|models| {
+            models
+                .iter()
+                .filter_map(|m| m.get("id").and_then(|id| id.as_str()))
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+/// Status check like `check_ollama_status`: returns `(is_running, human-readable message)`.
+/// llama-server answers `/health` (503 while the model loads); LM Studio has no health
+/// endpoint, so a working `/v1/models` counts as healthy there.
+pub fn check_local_server_status(base_url: &str, api_key: &str) -> (bool, String) {
+    let client = match probe_client() {
+        Ok(c) => c,
+        Err(e) => return (false, e),
+    };
+    let url = format!("{}/health", local_server_root(base_url));
+    match client.get(&url).headers(bearer_headers(api_key)).send() {
+        Ok(resp) if resp.status().is_success() => {
+            return (true, "Local server is running".to_string());
+        }
+        Ok(resp) if resp.status() == reqwest::StatusCode::SERVICE_UNAVAILABLE => {
+            return (false, "Local server is still loading the model".to_string());
+        }
+        Ok(_) => {}
+        Err(e) => return (false, format!("Cannot connect to local server: {}", e)),
+    }
+    match list_local_server_models(base_url, api_key) {
+        Ok(models) if models.is_empty() => (false, "Local server has no model loaded".to_string()),
+        Ok(models) => (
+            true,
+            format!("Local server is running ({} models)", models.len()),
+        ),
+        Err(e) => (false, e),
+    }
+}
+
+pub struct LocalServerProvider {
+    chat: OpenAiProvider,
+    base_url: String,
+}
+
+impl LocalServerProvider {
+    fn from_config(config: &ModelConfig) -> Option<Box<dyn LlmProvider>> {
+        let ModelProvider::LocalServer {
+            base_url,
+            api_key,
+            model_name,
+        } = &config.provider
+        else {
+            return None;
+        };
+        let root = local_server_root(base_url).to_string();
+        Some(Box::new(Self {
+            chat: OpenAiProvider {
+                id: "local_server",
+                label: "Local server",
+                api_key: api_key.clone(),
+                base_url: format!("{}/v1", root),
+                model_name: model_name.clone(),
+                // Both servers turn a JSON schema into a sampling grammar; LM Studio does
+                // not accept `json_object`.
+                json_schema: true,
+                fallback: config.json_mode_fallback.clone(),
+            },
+            base_url: root,
+        }))
+    }
+}
+
+impl LlmProvider for LocalServerProvider {
+    fn id(&self) -> &'static str {
+        self.chat.id
+    }
+
+    fn label(&self) -> &'static str {
+        self.chat.label
+    }
+
+    fn model(&self, role: ModelRole) -> &str {
+        self.chat.model(role)
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            chat: true,
+            streaming: true,
+            json_mode: true,
+            structured_output: true,
+            embeddings: false,
+            token_counting: false,
+        }
+    }
+
+    fn ensure_ready(&self) -> Result<(), String> {
+        let (running, message) = check_local_server_status(&self.base_url, &self.chat.api_key);
+        if !running {
+            return Err(message);
+        }
+        // llama-server serves whatever it was started with; LM Studio routes by model ID.
+        let model = &self.chat.model_name;
+        if model.is_empty() {
+            return Ok(());
+        }
+        let models = list_local_server_models(&self.base_url, &self.chat.api_key)?;
+        if models.is_empty() || models.iter().any(|m| m == model) {
+            Ok(())
+        } else {
+            Err(format!(
+                "Model '{}' is not available on the local server (available: {})",
+                model,
+                models.join(", ")
+            ))
+        }
+    }
+
+    fn complete(&self, request: &CompletionRequest) -> Result<Completion, String> {
+        self.chat.complete(request)
+    }
+
+    fn complete_stream<'a>(
+        &'a self,
+        request: &'a CompletionRequest<'a>,
+        on_delta: &'a mut (dyn FnMut(&str) + Send),
+    ) -> BoxFuture<'a, Result<Completion, String>> {
+        self.chat.complete_stream(request, on_delta)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread::JoinHandle;
+
+    /// Serve one canned HTTP response (`status` is e.g. `200 OK`) on a local port; the handle
+    /// yields the request line and body the provider sent.
+    fn mock_server(
+        status: &str,
+        content_type: &str,
+        body: &str,
+    ) -> (String, JoinHandle<(String, Value)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let response = format!(
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            content_type,
+            body.len(),
+            body
+        );
+        let handle = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut content_length = 0;
+            loop {
+                let mut header = String::new();
+                reader.read_line(&mut header).unwrap();
+                let header = header.trim();
+                if header.is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = header.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+            }
+            let mut request_body = vec![0; content_length];
+            reader.read_exact(&mut request_body).unwrap();
+            reader.get_mut().write_all(response.as_bytes()).unwrap();
+            (
+                request_line.trim().to_string(),
+                serde_json::from_slice(&request_body).unwrap(),
+            )
+        });
+        (base_url, handle)
+    }
+
+    fn anthropic(base_url: String) -> AnthropicProvider {
+        AnthropicProvider {
+            api_key: "test-key".to_string(),
+            base_url,
+            model_name: "claude-test".to_string(),
+        }
+    }
+
+    fn request() -> CompletionRequest<'static> {
+        CompletionRequest::new(&ModelConfig::default(), "Hello")
+    }
+
+    fn stream(provider: &AnthropicProvider) -> (Result<Completion, String>, Vec<String>) {
+        let mut deltas = Vec::new();
+        let request = request();
+        let result = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(
+                provider.complete_stream(&request, &mut |delta| deltas.push(delta.to_string())),
+            );
+        (result, deltas)
+    }
+
+    #[test]
+    fn anthropic_max_tokens_marks_completion_truncated() {
+        let (base_url, server) = mock_server(
+            "200 OK",
+            "application/json",
+            r#"{"content":[{"type":"text","text":"Partial "},{"type":"tool_use","id":"x"},{"type":"text","text":"answer"}],"stop_reason":"max_tokens"}"#,
+        );
+        let completion = anthropic(base_url).complete(&request()).unwrap();
+        assert_eq!(completion.text, "Partial answer");
+        assert!(completion.truncated);
+
+        let (request_line, body) = server.join().unwrap();
+        assert!(request_line.starts_with("POST /v1/messages "));
+        assert_eq!(body["model"], "claude-test");
+        assert!(body.get("stream").is_none());
+    }
+
+    #[test]
+    fn anthropic_stream_collects_content_block_deltas() {
+        let events = [
+            r#"{"type":"message_start","message":{"id":"msg"}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hel"}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"input_json_delta","partial_json":"{"}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"lo"}}"#,
+            r#"{"type":"message_delta","delta":{"stop_reason":"max_tokens"}}"#,
+            r#"{"type":"message_stop"}"#,
+        ];
+        let body: String = events
+            .iter()
+            .map(|data| format!("event: ignored\ndata: {}\n\n", data))
+            .collect();
+        let (base_url, server) = mock_server("200 OK", "text/event-stream", &body);
+        let (result, deltas) = stream(&anthropic(format!("{}/v1", base_url)));
+        let completion = result.unwrap();
+        assert_eq!(completion.text, "Hello");
+        assert!(completion.truncated);
+        assert_eq!(deltas, ["Hel", "lo"]);
+
+        let (request_line, body) = server.join().unwrap();
+        assert!(request_line.starts_with("POST /v1/messages "));
+        assert_eq!(body["stream"], true);
+    }
+
+    #[test]
+    fn anthropic_stream_error_event_fails_completion() {
+        let body = concat!(
+            "event: content_block_delta\n",
+            r#"data: {"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hi"}}"#,
+            "\n\nevent: error\n",
+            r#"data: {"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#,
+            "\n\n",
+        );
+        let (base_url, server) = mock_server("200 OK", "text/event-stream", body);
+        let (result, deltas) = stream(&anthropic(base_url));
+        assert_eq!(result.unwrap_err(), "Anthropic error: Overloaded");
+        assert_eq!(deltas, ["Hi"]);
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn anthropic_count_tokens_reports_http_errors() {
+        let error = r#"{"type":"error","error":{"type":"rate_limit_error","message":"Slow down"}}"#;
+        let (base_url, server) = mock_server("429 Too Many Requests", "application/json", error);
+        let result = anthropic(base_url).count_tokens("Hello");
+        assert_eq!(
+            result.unwrap_err(),
+            format!("Anthropic error 429 Too Many Requests: {}", error)
+        );
+
+        let (request_line, body) = server.join().unwrap();
+        assert!(request_line.starts_with("POST /v1/messages/count_tokens "));
+        assert_eq!(body["messages"][0]["content"], "Hello");
+    }
+}
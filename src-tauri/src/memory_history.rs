@@ -0,0 +1,145 @@
+//! Optional git history for a library's `memories/` folder, using libgit2 (no `git` binary needed).
+//!
+//! Every change is committed as a snapshot of the whole folder, so edits, moves and deletions made
+//! outside the app are picked up by the next commit too.
+
+use chrono::{TimeZone, Utc};
+use git2::{IndexAddOption, Oid, Repository, Signature};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Temp files from atomic writes must never end up in a commit.
+const GITIGNORE: &str = ".*.tmp\n";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryHistoryEntry {
+    pub commit: String,
+    pub message: String,
+    pub time: String,
+    /// `added`, `modified` or `deleted`.
+    pub change: String,
+}
+
+/// Open the repository in `memories_dir`, creating it (with an initial snapshot) if needed.
+pub fn open_or_init(memories_dir: &Path) -> Result<Repository, String> {
+    if memories_dir.join(".git").exists() {
+        return Repository::open(memories_dir).map_err(|e| e.to_string());
+    }
+    fs::create_dir_all(memories_dir).map_err(|e| e.to_string())?;
+    let repo = Repository::init(memories_dir)
+        .map_err(|e| format!("Failed to create history repository: {e}"))?;
+    let gitignore = memories_dir.join(".gitignore");
+    if !gitignore.exists() {
+        fs::write(&gitignore, GITIGNORE).map_err(|e| e.to_string())?;
+    }
+    commit_snapshot(&repo, "Start memory history")?;
+    Ok(repo)
+}
+
+fn signature(repo: &Repository) -> Result<Signature<'static>, String> {
+    repo.signature()
+        .map(|s| s.to_owned())
+        .or_else(|_| Signature::now("Kraph", "kraph@localhost"))
+        .map_err(|e| e.to_string())
+}
+
+/// Stage everything in the working tree (including deletions) and commit it.
+/// Returns `None` when nothing changed since the last commit.
+fn commit_snapshot(repo: &Repository, message: &str) -> Result<Option<String>, String> {
+    let mut index = repo.index().map_err(|e| e.to_string())?;
+    index
+        .add_all(["*"], IndexAddOption::DEFAULT, None)
+        .map_err(|e| e.to_string())?;
+    index.update_all(["*"], None).map_err(|e| e.to_string())?;
+    index.write().map_err(|e| e.to_string())?;
+    let tree_id = index.write_tree().map_err(|e| e.to_string())?;
+
+    let parent = match repo.head() {
+        Ok(head) => Some(head.peel_to_commit().map_err(|e| e.to_string())?),
+        Err(_) => None,
+    };
+    if parent.as_ref().is_some_and(|p| p.tree_id() == tree_id) {
+        return Ok(None);
+    }
+    let tree = repo.find_tree(tree_id).map_err(|e| e.to_string())?;
+    let sig = signature(repo)?;
+    let parents: Vec<&git2::Commit> = parent.iter().collect();
+    let oid = repo
+        .commit(Some("HEAD"), &sig, &sig, message, &tree, &parents)
+        .map_err(|e| format!("Failed to commit memory history: {e}"))?;
+    Ok(Some(oid.to_string()))
+}
+
+/// Commit the current state of `memories_dir` with `message`.
+pub fn commit_changes(memories_dir: &Path, message: &str) -> Result<Option<String>, String> {
+    let repo = open_or_init(memories_dir)?;
+    commit_snapshot(&repo, message)
+}
+
+fn relative_path(memories_dir: &Path, path: &Path) -> Result<PathBuf, String> {
+    path.strip_prefix(memories_dir)
+        .map(Path::to_path_buf)
+        .map_err(|_| format!("{:?} is not inside the memories folder", path))
+}
+
+fn blob_at(commit: &git2::Commit, rel: &Path) -> Option<Oid> {
+    commit
+        .tree()
+        .ok()?
+        .get_path(rel)
+        .ok()
+        .map(|entry| entry.id())
+}
+
+/// Commits that changed `path`, newest first. Renames are not followed.
+pub fn file_history(memories_dir: &Path, path: &Path) -> Result<Vec<MemoryHistoryEntry>, String> {
+    let rel = relative_path(memories_dir, path)?;
+    let repo = Repository::open(memories_dir).map_err(|e| e.to_string())?;
+    if repo.head().is_err() {
+        return Ok(Vec::new());
+    }
+    let mut walk = repo.revwalk().map_err(|e| e.to_string())?;
+    walk.push_head().map_err(|e| e.to_string())?;
+    walk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)
+        .map_err(|e| e.to_string())?;
+
+    let mut entries = Vec::new();
+    for oid in walk {
+        let commit = repo
+            .find_commit(oid.map_err(|e| e.to_string())?)
+            .map_err(|e| e.to_string())?;
+        let current = blob_at(&commit, &rel);
+        let previous = commit.parent(0).ok().and_then(|p| blob_at(&p, &rel));
+        let change = match (previous, current) {
+            (None, Some(_)) => "added",
+            (Some(_), None) => "deleted",
+            (Some(a), Some(b)) if a != b => "modified",
+            _ => continue,
+        };
+        let time = Utc
+            .timestamp_opt(commit.time().seconds(), 0)
+            .single()
+            .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_default();
+        entries.push(MemoryHistoryEntry {
+            commit: commit.id().to_string(),
+            message: commit.message().unwrap_or_default().trim().to_string(),
+            time,
+            change: change.to_string(),
+        });
+    }
+    Ok(entries)
+}
+
+/// Contents of `path` as of `commit`.
+pub fn file_at(memories_dir: &Path, path: &Path, commit: &str) -> Result<String, String> {
+    let rel = relative_path(memories_dir, path)?;
+    let repo = Repository::open(memories_dir).map_err(|e| e.to_string())?;
+    let oid = Oid::from_str(commit).map_err(|e| e.to_string())?;
+    let commit = repo.find_commit(oid).map_err(|e| e.to_string())?;
+    let blob_id = blob_at(&commit, &rel)
+        .ok_or_else(|| format!("{:?} does not exist in commit {}", rel, commit.id()))?;
+    let blob = repo.find_blob(blob_id).map_err(|e| e.to_string())?;
+    String::from_utf8(blob.content().to_vec()).map_err(|e| e.to_string())
+}
@@ -0,0 +1,177 @@
+//! Filesystem watcher for the active library's `memories/` folder.
+//!
+//! Raw notify events are collected until the folder has been quiet for `DEBOUNCE`, then folded
+//! into one `MemoryFileChange` per path. Paths the app wrote itself are ignored for `ECHO_WINDOW`
+//! so that saving a memory doesn't immediately trigger a second re-extraction.
+
+use notify::event::{ModifyKind, RenameMode};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Quiet period before a batch of raw events is handed to the sync handler.
+const DEBOUNCE: Duration = Duration::from_millis(800);
+
+/// How long a path written by the app itself is ignored by the watcher.
+const ECHO_WINDOW: Duration = Duration::from_secs(3);
+
+/// Watcher for the currently active library (`None` until started).
+pub struct MemoryWatcherState(pub Mutex<Option<MemoryWatcher>>);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MemoryFileChange {
+    /// The file was created or its content changed.
+    Upserted(PathBuf),
+    /// The file no longer exists.
+    Removed(PathBuf),
+    /// The file was renamed or moved inside the watched folder.
+    Moved { from: PathBuf, to: PathBuf },
+}
+
+pub struct MemoryWatcher {
+    own_writes: Arc<Mutex<HashMap<PathBuf, Instant>>>,
+    // Dropping the watcher closes the event channel, which stops the debounce thread.
+    _watcher: RecommendedWatcher,
+}
+
+impl MemoryWatcher {
+    /// Start watching `root` recursively. `on_changes` runs on a background thread
+    /// with each debounced batch.
+    pub fn start<F>(root: &Path, on_changes: F) -> Result<Self, String>
+    where
+        F: Fn(Vec<MemoryFileChange>) + Send + 'static,
+    {
+        std::fs::create_dir_all(root)
+            .map_err(|e| format!("Failed to create memories directory: {e}"))?;
+        let (tx, rx) = mpsc::channel::<notify::Result<Event>>();
+        let mut watcher = notify::recommended_watcher(tx)
+            .map_err(|e| format!("Failed to create file watcher: {e}"))?;
+        watcher
+            .watch(root, RecursiveMode::Recursive)
+            .map_err(|e| format!("Failed to watch {:?}: {}", root, e))?;
+
+        let own_writes: Arc<Mutex<HashMap<PathBuf, Instant>>> = Arc::default();
+        let worker_own_writes = Arc::clone(&own_writes);
+        thread::spawn(move || {
+            while let Ok(first) = rx.recv() {
+                let mut batch = PendingBatch::default();
+                batch.push(first);
+                let disconnected = loop {
+                    match rx.recv_timeout(DEBOUNCE) {
+                        Ok(event) => batch.push(event),
+                        Err(RecvTimeoutError::Timeout) => break false,
+                        Err(RecvTimeoutError::Disconnected) => break true,
+                    }
+                };
+                let changes = batch.into_changes(&worker_own_writes);
+                if !changes.is_empty() {
+                    on_changes(changes);
+                }
+                if disconnected {
+                    break;
+                }
+            }
+        });
+
+        Ok(Self {
+            own_writes,
+            _watcher: watcher,
+        })
+    }
+
+    /// Record that the app itself just wrote, moved or deleted `path`.
+    pub fn ignore_echo(&self, path: &Path) {
+        if let Ok(mut guard) = self.own_writes.lock() {
+            guard.insert(path.to_path_buf(), Instant::now());
+        }
+    }
+}
+
+fn is_markdown(path: &Path) -> bool {
+    path.extension().is_some_and(|x| x == "md")
+}
+
+/// Raw events of one debounce window, reduced to "which paths were touched" plus explicit renames.
+#[derive(Default)]
+struct PendingBatch {
+    touched: Vec<PathBuf>,
+    renames: Vec<(PathBuf, PathBuf)>,
+}
+
+impl PendingBatch {
+    fn touch(&mut self, path: &Path) {
+        if is_markdown(path) && !self.touched.iter().any(|p| p == path) {
+            self.touched.push(path.to_path_buf());
+        }
+    }
+
+    fn push(&mut self, event: notify::Result<Event>) {
+        let event = match event {
+            Ok(event) => event,
+            Err(e) => {
+                println!("⚠️ [memory_watcher] watch error: {}", e);
+                return;
+            }
+        };
+        match event.kind {
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if event.paths.len() == 2 => {
+                let (from, to) = (&event.paths[0], &event.paths[1]);
+                if is_markdown(from) && is_markdown(to) {
+                    self.renames.push((from.clone(), to.clone()));
+                } else {
+                    self.touch(from);
+                    self.touch(to);
+                }
+            }
+            EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_) => {
+                for path in &event.paths {
+                    self.touch(path);
+                }
+            }
+            EventKind::Access(_) | EventKind::Any | EventKind::Other => {}
+        }
+    }
+
+    /// Fold the batch into final per-path changes, judging each path by whether it still exists.
+    fn into_changes(self, own_writes: &Mutex<HashMap<PathBuf, Instant>>) -> Vec<MemoryFileChange> {
+        let is_echo = {
+            let mut guard = match own_writes.lock() {
+                Ok(guard) => guard,
+                Err(e) => e.into_inner(),
+            };
+            guard.retain(|_, at| at.elapsed() < ECHO_WINDOW);
+            let snapshot: Vec<PathBuf> = guard.keys().cloned().collect();
+            move |path: &Path| snapshot.iter().any(|p| p == path)
+        };
+
+        let mut changes = Vec::new();
+        let mut handled: Vec<PathBuf> = Vec::new();
+        for (from, to) in self.renames {
+            handled.push(from.clone());
+            handled.push(to.clone());
+            if is_echo(&from) || is_echo(&to) {
+                continue;
+            }
+            if !from.exists() && to.exists() {
+                changes.push(MemoryFileChange::Moved { from, to });
+            } else if to.exists() {
+                changes.push(MemoryFileChange::Upserted(to));
+            }
+        }
+        for path in self.touched {
+            if handled.contains(&path) || is_echo(&path) {
+                continue;
+            }
+            if path.is_file() {
+                changes.push(MemoryFileChange::Upserted(path));
+            } else {
+                changes.push(MemoryFileChange::Removed(path));
+            }
+        }
+        changes
+    }
+}
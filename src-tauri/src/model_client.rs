@@ -1,9 +1,15 @@
-//! Generic model client: supports Ollama, DeepSeek, OpenAI, and any OpenAI-compatible API.
-
-use crate::model_config::{ModelConfig, ModelProvider};
+//! Generic model client: prompt assembly and response parsing on top of an `LlmProvider`
+//! (Ollama, DeepSeek, OpenAI, and any OpenAI-compatible API). When `structured_output` is on,
+//! extraction and fusion request output constrained to the schemas in `output_schema`.
+
+use crate::llm_provider::{
+    estimate_tokens, provider_for, Completion, CompletionRequest, LlmProvider, ModelRole,
+    ResponseFormat,
+};
+use crate::model_config::ModelConfig;
 use crate::ollama::{ExtractedData, FusedKnowledge};
-use serde_json::json;
-use std::time::{Duration, Instant};
+use crate::output_schema::OutputSchema;
+use std::time::Instant;
 
 /// Minimum output token budget for entity extraction.
 /// Extraction produces large JSON objects; too small a budget causes truncated, unparseable output.
@@ -12,66 +18,111 @@ const EXTRACT_MIN_TOKENS: i32 = 8192;
 /// Minimum output token budget for knowledge fusion.
 const FUSION_MIN_TOKENS: i32 = 8192;
 
+/// How often a structured response that fails validation is re-requested.
+const SCHEMA_RETRIES: usize = 1;
+
+/// Run a blocking completion with the timing and truncation logging shared by every call.
+fn complete_logged(
+    provider: &dyn LlmProvider,
+    tag: &str,
+    request: &CompletionRequest,
+) -> Result<Completion, String> {
+    let api_started = Instant::now();
+    let completion = provider.complete(request)?;
+    println!(
+        "⏱️ [{}] {} api call took {} ms (model={}, response_chars={}, truncated={})",
+        tag,
+        provider.id(),
+        api_started.elapsed().as_millis(),
+        provider.model(request.role),
+        completion.text.chars().count(),
+        completion.truncated
+    );
+    if completion.truncated {
+        println!(
+            "⚠️ [{}] {} response truncated (max_tokens={} too low), attempting partial parse",
+            tag,
+            provider.label(),
+            request.max_tokens
+        );
+    }
+    Ok(completion)
+}
+
+/// Run a structured-output completion and validate it with `parse`. A response that fails
+/// validation is re-requested with the error and the rejected output appended, up to
+/// `SCHEMA_RETRIES` times; truncated responses are not re-asked since the budget is the cause.
+fn complete_validated<T>(
+    provider: &dyn LlmProvider,
+    tag: &str,
+    request: &CompletionRequest,
+    parse: fn(&str) -> Result<T, String>,
+) -> Result<T, String> {
+    let mut prompt = request.prompt.to_string();
+    let mut attempt = 0;
+    loop {
+        let completion = complete_logged(
+            provider,
+            tag,
+            &CompletionRequest {
+                prompt: &prompt,
+                ..request.clone()
+            },
+        )?;
+        let error = match parse(&completion.text) {
+            Ok(data) => return Ok(data),
+            Err(e) if attempt >= SCHEMA_RETRIES || completion.truncated => return Err(e),
+            Err(e) => e,
+        };
+        attempt += 1;
+        println!(
+            "🔁 [{}] response failed validation ({}), re-asking (attempt {}/{})",
+            tag, error, attempt, SCHEMA_RETRIES
+        );
+        prompt = format!(
+            "{}\n\nYour previous response could not be used: {}\nPrevious response:\n{}\n\n\
+            Reply again with only the corrected JSON object.",
+            request.prompt, error, completion.text
+        );
+    }
+}
+
+/// The response format for a structured call: the schema when the user enabled structured
+/// output, plain text (relying on the prompt's JSON instructions) otherwise.
+fn structured_format<'a>(
+    config: &ModelConfig,
+    name: &'static str,
+    schema: &'a serde_json::Value,
+) -> ResponseFormat<'a> {
+    if config.structured_output {
+        ResponseFormat::Schema { name, schema }
+    } else {
+        ResponseFormat::Text
+    }
+}
+
+/// Start the configured backend if it is local and make sure its model is available.
+pub fn ensure_model_ready(config: &ModelConfig) -> Result<(), String> {
+    provider_for(config)?.ensure_ready()
+}
+
 /// Call the configured model for entity extraction.
 pub fn call_model_extract(
     config: &ModelConfig,
     prompt: &str,
     text: &str,
 ) -> Result<ExtractedData, String> {
+    let provider = provider_for(config)?;
     let full_prompt = format!("{}{}", prompt, text);
-    // Extraction requires at least EXTRACT_MIN_TOKENS to avoid JSON truncation
-    let max_tokens = config.max_tokens.max(EXTRACT_MIN_TOKENS);
-
-    match &config.provider {
-        ModelProvider::Ollama {
-            base_url,
-            extract_model_name,
-            ..
-        } => {
-            call_ollama_api(base_url, extract_model_name, &full_prompt, max_tokens)
-                .and_then(|response| parse_extracted_data(&response))
-        }
-        ModelProvider::DeepSeek {
-            api_key,
-            base_url,
-            model_name,
-        } => {
-            call_openai_compatible_api(
-                base_url,
-                api_key,
-                model_name,
-                &full_prompt,
-                config.temperature,
-                max_tokens,
-            )
-            .and_then(|(response, truncated)| {
-                if truncated {
-                    println!("⚠️ [extract] DeepSeek response truncated (max_tokens={} too low), attempting partial parse", max_tokens);
-                }
-                parse_extracted_data(&response)
-            })
-        }
-        ModelProvider::OpenAI {
-            api_key,
-            base_url,
-            model_name,
-        } => {
-            call_openai_compatible_api(
-                base_url,
-                api_key,
-                model_name,
-                &full_prompt,
-                config.temperature,
-                max_tokens,
-            )
-            .and_then(|(response, truncated)| {
-                if truncated {
-                    println!("⚠️ [extract] API response truncated (max_tokens={} too low), attempting partial parse", max_tokens);
-                }
-                parse_extracted_data(&response)
-            })
-        }
-    }
+    let schema = ExtractedData::json_schema();
+    let request = CompletionRequest {
+        role: ModelRole::Extract,
+        format: structured_format(config, ExtractedData::NAME, &schema),
+        // Extraction requires at least EXTRACT_MIN_TOKENS to avoid JSON truncation
+        max_tokens: config.max_tokens.max(EXTRACT_MIN_TOKENS),
+        ..CompletionRequest::new(config, &full_prompt)
+    };
+    complete_validated(provider.as_ref(), "extract", &request, parse_extracted_data)
 }
 
 /// Call the configured model for knowledge fusion.
@@ -82,6 +133,7 @@ pub fn call_model_fusion(
     new_memory: &str,
 ) -> Result<FusedKnowledge, String> {
     let fusion_started = Instant::now();
+    let provider = provider_for(config)?;
     let historical_text = if historical_memories.is_empty() {
         "(no historical memories)".to_string()
     } else {
@@ -94,280 +146,63 @@ pub fn call_model_fusion(
     };
 
     let full_prompt = format!("{}{}\n\nNew memory:\n{}", prompt, historical_text, new_memory);
-    let max_tokens = config.max_tokens.max(FUSION_MIN_TOKENS);
-    let historical_chars: usize = historical_memories.iter().map(|x| x.chars().count()).sum();
-    let provider_name = match &config.provider {
-        ModelProvider::Ollama { .. } => "ollama",
-        ModelProvider::DeepSeek { .. } => "deepseek",
-        ModelProvider::OpenAI { .. } => "openai-compatible",
+    let schema = FusedKnowledge::json_schema();
+    let request = CompletionRequest {
+        format: structured_format(config, FusedKnowledge::NAME, &schema),
+        max_tokens: config.max_tokens.max(FUSION_MIN_TOKENS),
+        ..CompletionRequest::new(config, &full_prompt)
     };
+    let historical_chars: usize = historical_memories.iter().map(|x| x.chars().count()).sum();
     println!(
-        "🧠 [fusion] start provider={} memories={} historical_chars={} new_chars={} prompt_chars={} max_tokens={}",
-        provider_name,
+        "🧠 [fusion] start provider={} memories={} historical_chars={} new_chars={} prompt_chars={} prompt_tokens≈{} max_tokens={}",
+        provider.id(),
         historical_memories.len(),
         historical_chars,
         new_memory.chars().count(),
         full_prompt.chars().count(),
-        max_tokens
+        estimate_tokens(&full_prompt),
+        request.max_tokens
     );
 
-    match &config.provider {
-        ModelProvider::Ollama {
-            base_url,
-            model_name,
-            ..
-        } => {
-            let api_started = Instant::now();
-            let response = call_ollama_api(base_url, model_name, &full_prompt, max_tokens)?;
-            println!(
-                "⏱️ [fusion] ollama api call took {} ms (response_chars={})",
-                api_started.elapsed().as_millis(),
-                response.chars().count()
-            );
-
-            let parse_started = Instant::now();
-            let parsed = parse_fused_knowledge(&response)?;
-            println!(
-                "⏱️ [fusion] parse took {} ms (entities={}, relations={}, aliases={})",
-                parse_started.elapsed().as_millis(),
-                parsed.entities.len(),
-                parsed.relations.len(),
-                parsed.aliases.len()
-            );
-            println!(
-                "⏱️ [fusion] total took {} ms",
-                fusion_started.elapsed().as_millis()
-            );
-            Ok(parsed)
-        }
-        ModelProvider::DeepSeek {
-            api_key,
-            base_url,
-            model_name,
-        } => {
-            let api_started = Instant::now();
-            let (response, truncated) = call_openai_compatible_api(
-                base_url,
-                api_key,
-                model_name,
-                &full_prompt,
-                config.temperature,
-                max_tokens,
-            )?;
-            println!(
-                "⏱️ [fusion] deepseek api call took {} ms (response_chars={}, truncated={})",
-                api_started.elapsed().as_millis(),
-                response.chars().count(),
-                truncated
-            );
-            if truncated {
-                println!("⚠️ [fusion] DeepSeek response truncated, attempting partial parse");
-            }
-            let parse_started = Instant::now();
-            let parsed = parse_fused_knowledge(&response)?;
-            println!(
-                "⏱️ [fusion] parse took {} ms (entities={}, relations={}, aliases={})",
-                parse_started.elapsed().as_millis(),
-                parsed.entities.len(),
-                parsed.relations.len(),
-                parsed.aliases.len()
-            );
-            println!(
-                "⏱️ [fusion] total took {} ms",
-                fusion_started.elapsed().as_millis()
-            );
-            Ok(parsed)
-        }
-        ModelProvider::OpenAI {
-            api_key,
-            base_url,
-            model_name,
-        } => {
-            let api_started = Instant::now();
-            let (response, truncated) = call_openai_compatible_api(
-                base_url,
-                api_key,
-                model_name,
-                &full_prompt,
-                config.temperature,
-                max_tokens,
-            )?;
-            println!(
-                "⏱️ [fusion] openai-compatible api call took {} ms (response_chars={}, truncated={})",
-                api_started.elapsed().as_millis(),
-                response.chars().count(),
-                truncated
-            );
-            if truncated {
-                println!("⚠️ [fusion] API response truncated, attempting partial parse");
-            }
-            let parse_started = Instant::now();
-            let parsed = parse_fused_knowledge(&response)?;
-            println!(
-                "⏱️ [fusion] parse took {} ms (entities={}, relations={}, aliases={})",
-                parse_started.elapsed().as_millis(),
-                parsed.entities.len(),
-                parsed.relations.len(),
-                parsed.aliases.len()
-            );
-            println!(
-                "⏱️ [fusion] total took {} ms",
-                fusion_started.elapsed().as_millis()
-            );
-            Ok(parsed)
-        }
-    }
+    let parse_started = Instant::now();
+    let parsed = complete_validated(provider.as_ref(), "fusion", &request, parse_fused_knowledge)?;
+    println!(
+        "⏱️ [fusion] completion and parse took {} ms (entities={}, relations={}, aliases={})",
+        parse_started.elapsed().as_millis(),
+        parsed.entities.len(),
+        parsed.relations.len(),
+        parsed.aliases.len()
+    );
+    println!(
+        "⏱️ [fusion] total took {} ms",
+        fusion_started.elapsed().as_millis()
+    );
+    Ok(parsed)
 }
 
 /// Call the configured model for a simple single-turn prompt.
-pub fn call_model_simple(
-    config: &ModelConfig,
-    prompt: &str,
-) -> Result<String, String> {
-    match &config.provider {
-        ModelProvider::Ollama {
-            base_url,
-            model_name,
-            ..
-        } => call_ollama_api(base_url, model_name, prompt, config.max_tokens),
-        ModelProvider::DeepSeek {
-            api_key,
-            base_url,
-            model_name,
-        } => call_openai_compatible_api(
-            base_url,
-            api_key,
-            model_name,
-            prompt,
-            config.temperature,
-            config.max_tokens,
-        )
-        .map(|(response, _)| response),
-        ModelProvider::OpenAI {
-            api_key,
-            base_url,
-            model_name,
-        } => call_openai_compatible_api(
-            base_url,
-            api_key,
-            model_name,
-            prompt,
-            config.temperature,
-            config.max_tokens,
-        )
-        .map(|(response, _)| response),
-    }
+pub fn call_model_simple(config: &ModelConfig, prompt: &str) -> Result<String, String> {
+    let provider = provider_for(config)?;
+    provider
+        .complete(&CompletionRequest::new(config, prompt))
+        .map(|completion| completion.text)
 }
 
-/// Call the Ollama `/api/generate` endpoint (blocking).
-fn call_ollama_api(
-    base_url: &str,
-    model: &str,
-    prompt: &str,
-    max_tokens: i32,
-) -> Result<String, String> {
-    let url = format!("{}/api/generate", base_url.trim_end_matches('/'));
-    let body = json!({
-        "model": model,
-        "prompt": prompt,
-        "stream": false,
-        "options": { "temperature": 0.2, "num_predict": max_tokens }
-    });
-
-    let client = reqwest::blocking::Client::builder()
-        .timeout(Duration::from_secs(300))
-        .build()
-        .map_err(|e| e.to_string())?;
-
-    let res = client
-        .post(&url)
-        .json(&body)
-        .send()
-        .map_err(|e| format!("Ollama request failed: {}", e))?;
-
-    if !res.status().is_success() {
-        let status = res.status();
-        let err_body = res.text().unwrap_or_default();
-        return Err(format!("Ollama error {}: {}", status, err_body));
-    }
-
-    let json: serde_json::Value = res.json()
-        .map_err(|e| format!("Failed to parse Ollama response: {}", e))?;
-    let response_text = json
-        .get("response")
-        .and_then(|v| v.as_str())
-        .ok_or("Ollama response missing 'response' field")?
-        .trim()
-        .to_string();
-    Ok(response_text)
-}
-
-/// Call an OpenAI-compatible chat completions API (DeepSeek, OpenAI, etc.).
-/// Returns `(response_text, truncated)` where `truncated` is true when `finish_reason == "length"`.
-fn call_openai_compatible_api(
-    base_url: &str,
-    api_key: &str,
-    model: &str,
+/// Stream a single-turn prompt from the configured model. `on_delta` receives each text
+/// fragment as it arrives; the full (trimmed) text is returned together with the truncation
+/// flag. Dropping the future aborts the HTTP request.
+pub async fn call_model_simple_stream<F>(
+    config: &ModelConfig,
     prompt: &str,
-    temperature: f32,
-    max_tokens: i32,
-) -> Result<(String, bool), String> {
-    let url = format!("{}/chat/completions", base_url.trim_end_matches('/'));
-    let body = json!({
-        "model": model,
-        "messages": [
-            {
-                "role": "user",
-                "content": prompt
-            }
-        ],
-        "temperature": temperature,
-        "max_tokens": max_tokens
-    });
-
-    let client = reqwest::blocking::Client::builder()
-        .timeout(Duration::from_secs(300))
-        .build()
-        .map_err(|e| e.to_string())?;
-
-    let res = client
-        .post(&url)
-        .header("Authorization", format!("Bearer {}", api_key))
-        .header("Content-Type", "application/json")
-        .json(&body)
-        .send()
-        .map_err(|e| format!("API request failed: {}", e))?;
-
-    if !res.status().is_success() {
-        let status = res.status();
-        let err_body = res.text().unwrap_or_default();
-        return Err(format!("API error {}: {}", status, err_body));
-    }
-
-    let json: serde_json::Value = res.json()
-        .map_err(|e| format!("Failed to parse API response: {}", e))?;
-
-    let choice = json
-        .get("choices")
-        .and_then(|v| v.as_array())
-        .and_then(|arr| arr.first())
-        .ok_or("API response missing 'choices'")?;
-
-    // Detect truncation due to max_tokens limit
-    let truncated = choice
-        .get("finish_reason")
-        .and_then(|r| r.as_str())
-        .map_or(false, |r| r == "length");
-
-    let response_text = choice
-        .get("message")
-        .and_then(|msg| msg.get("content"))
-        .and_then(|content| content.as_str())
-        .ok_or("API response missing 'content'")?
-        .trim()
-        .to_string();
-
-    Ok((response_text, truncated))
+    mut on_delta: F,
+) -> Result<(String, bool), String>
+where
+    F: FnMut(&str) + Send,
+{
+    let provider = provider_for(config)?;
+    let request = CompletionRequest::new(config, prompt);
+    let completion = provider.complete_stream(&request, &mut on_delta).await?;
+    Ok((completion.text, completion.truncated))
 }
 
 /// Extract JSON from the model response and parse it as `ExtractedData`.
@@ -1,5 +1,8 @@
-//! Model configuration module: supports local Ollama and cloud APIs (DeepSeek, OpenAI, etc.)
+//! Model configuration module: supports local Ollama, llama.cpp / LM Studio servers and cloud
+//! APIs (DeepSeek, OpenAI, Anthropic, etc.)
 
+use crate::library_crypto::{decode_text_with, encode_text_with, LibraryKey};
+use crate::llm_provider::JsonModeFallback;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::Path;
@@ -25,6 +28,21 @@ pub enum ModelProvider {
         base_url: String,
         model_name: String,
     },
+    #[serde(rename = "anthropic")]
+    Anthropic {
+        api_key: String,
+        base_url: String,
+        model_name: String,
+    },
+    /// llama.cpp `llama-server`, LM Studio and other local OpenAI-compatible servers.
+    #[serde(rename = "local_server")]
+    LocalServer {
+        base_url: String,
+        #[serde(default)]
+        api_key: String,
+        #[serde(default)]
+        model_name: String,
+    },
 }
 
 impl Default for ModelProvider {
@@ -42,6 +60,18 @@ pub struct ModelConfig {
     pub provider: ModelProvider,
     pub temperature: f32,
     pub max_tokens: i32,
+    /// Ask the backend to constrain extraction and fusion output to a JSON schema. On by
+    /// default: endpoints that reject `response_format` fall back to JSON mode or to the
+    /// prompt's JSON instructions, so turning it off only skips that first attempt.
+    #[serde(default = "default_structured_output")]
+    pub structured_output: bool,
+    /// The `response_format` the endpoint fell back to; learned at runtime, never saved.
+    #[serde(skip)]
+    pub json_mode_fallback: JsonModeFallback,
+}
+
+fn default_structured_output() -> bool {
+    true
 }
 
 impl Default for ModelConfig {
@@ -50,29 +80,35 @@ impl Default for ModelConfig {
             provider: ModelProvider::default(),
             temperature: 0.2,
             max_tokens: 4096,
+            structured_output: default_structured_output(),
+            json_mode_fallback: JsonModeFallback::default(),
         }
     }
 }
 
 impl ModelConfig {
-    pub fn load_from_file(path: &Path) -> Result<Self, String> {
+    /// Load a library's config; `key` decrypts it when the library is encrypted.
+    pub fn load_from_file(path: &Path, key: Option<&LibraryKey>) -> Result<Self, String> {
         if !path.exists() {
             return Ok(Self::default());
         }
         let content = fs::read_to_string(path)
             .map_err(|e| format!("Failed to read config file: {}", e))?;
+        let content = decode_text_with(key, content)?;
         let config: ModelConfig = serde_json::from_str(&content)
             .map_err(|e| format!("Failed to parse config file: {}", e))?;
         Ok(config)
     }
 
-    pub fn save_to_file(&self, path: &Path) -> Result<(), String> {
+    /// Save a library's config; `key` encrypts it, API keys included, for encrypted libraries.
+    pub fn save_to_file(&self, path: &Path, key: Option<&LibraryKey>) -> Result<(), String> {
         if let Some(parent) = path.parent() {
             fs::create_dir_all(parent)
                 .map_err(|e| format!("Failed to create config directory: {}", e))?;
         }
         let content = serde_json::to_string_pretty(self)
             .map_err(|e| format!("Failed to serialize config: {}", e))?;
+        let content = encode_text_with(key, &content)?;
         fs::write(path, content)
             .map_err(|e| format!("Failed to write config file: {}", e))?;
         Ok(())
@@ -4,6 +4,7 @@
 //! but we use a small generative model via Ollama to avoid extra deployment requirements.
 //! If a dedicated NER model is integrated later, replace the calls here with local encoder inference.
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::process::Stdio;
 use std::time::Duration;
@@ -133,35 +134,36 @@ pub const KNOWLEDGE_FUSION_PROMPT: &str = r#"你是一个知识图谱管理专
 历史记忆：
 "#;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ExtractedEntity {
     #[serde(rename = "type")]
     pub entity_type: String,
     pub name: String,
     #[serde(default)]
+    #[schemars(with = "Option<std::collections::BTreeMap<String, String>>")]
     pub attributes: Option<serde_json::Value>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ExtractedRelation {
     pub from: String,
     pub to: String,
     pub relation: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ExtractedData {
     pub entities: Vec<ExtractedEntity>,
     pub relations: Vec<ExtractedRelation>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct EntityAlias {
     pub primary: String,
     pub alias: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct FusedKnowledge {
     pub entities: Vec<ExtractedEntity>,
     #[serde(default)]
@@ -0,0 +1,37 @@
+//! JSON schemas for the structures models are asked to return.
+//!
+//! Schemas are derived from the Rust types with `schemars`, which follows their serde
+//! attributes, so providers with structured-output support (Ollama `format`, OpenAI
+//! `response_format: json_schema`) constrain generation to something that deserializes.
+
+use crate::ollama::{ExtractedData, FusedKnowledge};
+use schemars::generate::SchemaSettings;
+use schemars::JsonSchema;
+use serde_json::Value;
+
+pub trait OutputSchema: JsonSchema {
+    /// Schema name sent to backends that require one (`[a-zA-Z0-9_-]`).
+    const NAME: &'static str;
+
+    /// The derived schema with nested types inlined (grammar-based backends do not resolve
+    /// `$ref`) and without the `$schema` and `title` keys.
+    fn json_schema() -> Value {
+        let mut schema = SchemaSettings::draft07()
+            .with(|settings| {
+                settings.inline_subschemas = true;
+                settings.meta_schema = None;
+            })
+            .into_generator()
+            .into_root_schema_for::<Self>();
+        schema.remove("title");
+        schema.to_value()
+    }
+}
+
+impl OutputSchema for ExtractedData {
+    const NAME: &'static str = "extracted_data";
+}
+
+impl OutputSchema for FusedKnowledge {
+    const NAME: &'static str = "fused_knowledge";
+}
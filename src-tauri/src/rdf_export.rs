@@ -0,0 +1,447 @@
+//! RDF export of a library as Turtle or JSON-LD.
+//!
+//! IRIs are minted under a base IRI: entities are `<base>entity/<id>` typed with
+//! `<base>type/<EntityType>`, relations become `<base>relation/<relation_type>` predicates and
+//! attributes `<base>attribute/<key>` properties. Names use `rdfs:label`, aliases `skos:altLabel`.
+//! Memories are exported as `prov:Entity` source documents (`<base>memory/<id>`) and linked from
+//! the entities they mention with `prov:wasDerivedFrom`.
+
+use crate::database::{
+    list_entities, list_entity_aliases, list_memories, list_memory_entity_links, list_relations,
+    Entity, Memory, Relation,
+};
+use chrono::NaiveDateTime;
+use rusqlite::Connection;
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+const PREFIXES: [(&str, &str); 6] = [
+    ("rdf", "http://www.w3.org/1999/02/22-rdf-syntax-ns#"),
+    ("rdfs", "http://www.w3.org/2000/01/rdf-schema#"),
+    ("skos", "http://www.w3.org/2004/02/skos/core#"),
+    ("prov", "http://www.w3.org/ns/prov#"),
+    ("dcterms", "http://purl.org/dc/terms/"),
+    ("xsd", "http://www.w3.org/2001/XMLSchema#"),
+];
+
+/// Namespaces minted under the base IRI: (prefix, path below the base).
+const LOCAL_NAMESPACES: [(&str, &str); 5] = [
+    ("entity", "entity/"),
+    ("memory", "memory/"),
+    ("type", "type/"),
+    ("rel", "relation/"),
+    ("attr", "attribute/"),
+];
+
+/// Characters of a memory's first line used as its `dcterms:title`.
+const MEMORY_TITLE_CHARS: usize = 80;
+
+/// Default base IRI for a library without a configured one.
+pub fn default_base_iri(library_id: &str) -> String {
+    format!("https://kraph.local/{}/", iri_segment(library_id))
+}
+
+/// Make `base` usable as a namespace: it must be absolute and end with `/` or `#`.
+pub fn normalize_base_iri(base: &str) -> Result<String, String> {
+    let base = base.trim();
+    let scheme_ok = base.split_once(':').is_some_and(|(scheme, rest)| {
+        !scheme.is_empty()
+            && !rest.is_empty()
+            && scheme
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+    });
+    if !scheme_ok
+        || base
+            .chars()
+            .any(|c| c.is_whitespace() || "<>\"{}|\\^`".contains(c))
+    {
+        return Err(format!("'{}' is not a valid base IRI.", base));
+    }
+    if base.ends_with('/') || base.ends_with('#') {
+        Ok(base.to_string())
+    } else {
+        Ok(format!("{}/", base))
+    }
+}
+
+/// Turn a name into one IRI path segment: whitespace becomes `_`, characters that are not
+/// allowed in IRIs (or would end the segment) are percent-encoded. Unicode letters are kept.
+fn iri_segment(name: &str) -> String {
+    let joined = name.split_whitespace().collect::<Vec<_>>().join("_");
+    let joined = if joined.is_empty() {
+        "_".to_string()
+    } else {
+        joined
+    };
+    let mut out = String::with_capacity(joined.len());
+    for c in joined.chars() {
+        if c.is_control() || "<>\"{}|\\^`/?#%[]@!$&'()*+,;=:".contains(c) {
+            let mut buf = [0u8; 4];
+            for b in c.encode_utf8(&mut buf).bytes() {
+                out.push_str(&format!("%{:02X}", b));
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// A term in one of the minted namespaces.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct Term {
+    prefix: &'static str,
+    local: String,
+}
+
+impl Term {
+    fn new(prefix: &'static str, name: &str) -> Self {
+        Term {
+            prefix,
+            local: iri_segment(name),
+        }
+    }
+
+    /// Turtle form: a prefixed name when the local part is simple, a full IRI otherwise.
+    fn turtle(&self, base: &str) -> String {
+        let simple = self
+            .local
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphanumeric() || c == '_')
+            && self
+                .local
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+            && !self.local.ends_with('-');
+        if simple {
+            format!("{}:{}", self.prefix, self.local)
+        } else {
+            format!("<{}>", self.iri(base))
+        }
+    }
+
+    fn compact(&self) -> String {
+        format!("{}:{}", self.prefix, self.local)
+    }
+
+    fn iri(&self, base: &str) -> String {
+        let path = LOCAL_NAMESPACES
+            .iter()
+            .find(|(p, _)| *p == self.prefix)
+            .map(|(_, path)| *path)
+            .unwrap_or_default();
+        format!("{}{}{}", base, path, self.local)
+    }
+}
+
+enum Literal {
+    Text(String),
+    Integer(String),
+    Decimal(String),
+    Boolean(bool),
+    DateTime(String),
+}
+
+impl Literal {
+    fn from_json(value: &Value) -> Option<Literal> {
+        match value {
+            Value::Null => None,
+            Value::Bool(b) => Some(Literal::Boolean(*b)),
+            Value::Number(n) if n.is_i64() || n.is_u64() => Some(Literal::Integer(n.to_string())),
+            Value::Number(n) => Some(Literal::Decimal(n.to_string())),
+            Value::String(s) => Some(Literal::Text(s.clone())),
+            other => Some(Literal::Text(other.to_string())),
+        }
+    }
+
+    fn turtle(&self) -> String {
+        match self {
+            Literal::Text(s) => turtle_string(s),
+            Literal::Integer(n) => n.clone(),
+            Literal::Decimal(n) => format!("{}^^xsd:decimal", turtle_string(n)),
+            Literal::Boolean(b) => b.to_string(),
+            Literal::DateTime(s) => format!("{}^^xsd:dateTime", turtle_string(s)),
+        }
+    }
+
+    fn json_ld(&self) -> Value {
+        match self {
+            Literal::Text(s) => Value::String(s.clone()),
+            Literal::Integer(n) => n
+                .parse::<i64>()
+                .map(Value::from)
+                .unwrap_or_else(|_| json!({ "@value": n, "@type": "xsd:integer" })),
+            Literal::Decimal(n) => json!({ "@value": n, "@type": "xsd:decimal" }),
+            Literal::Boolean(b) => Value::Bool(*b),
+            Literal::DateTime(s) => json!({ "@value": s, "@type": "xsd:dateTime" }),
+        }
+    }
+}
+
+fn turtle_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04X}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Stored timestamps are `YYYY-MM-DD HH:MM:SS`; `None` when unparseable.
+fn xsd_date_time(timestamp: &str) -> Option<String> {
+    NaiveDateTime::parse_from_str(timestamp.trim(), "%Y-%m-%d %H:%M:%S")
+        .ok()
+        .map(|t| t.format("%Y-%m-%dT%H:%M:%S").to_string())
+}
+
+enum Object {
+    Resource(Term),
+    Literal(Literal),
+}
+
+/// A term from one of `PREFIXES`.
+fn well_known(prefix: &'static str, local: &str) -> Term {
+    Term {
+        prefix,
+        local: local.to_string(),
+    }
+}
+
+struct Resource {
+    subject: Term,
+    types: Vec<Term>,
+    properties: BTreeMap<Term, Vec<Object>>,
+}
+
+impl Resource {
+    fn new(subject: Term) -> Self {
+        Resource {
+            subject,
+            types: Vec::new(),
+            properties: BTreeMap::new(),
+        }
+    }
+
+    fn add(&mut self, predicate: Term, object: Object) {
+        self.properties.entry(predicate).or_default().push(object);
+    }
+}
+
+fn memory_title(memory: &Memory) -> String {
+    memory
+        .content
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty())
+        .unwrap_or_default()
+        .chars()
+        .take(MEMORY_TITLE_CHARS)
+        .collect()
+}
+
+fn build_resources(
+    entities: &[Entity],
+    relations: &[Relation],
+    aliases: Vec<(i64, String)>,
+    memories: &[Memory],
+    links: Vec<(i64, i64)>,
+) -> Vec<Resource> {
+    let entity_ids: HashSet<i64> = entities.iter().map(|e| e.id).collect();
+    let mut aliases_by_entity: HashMap<i64, Vec<String>> = HashMap::new();
+    for (entity_id, alias) in aliases {
+        aliases_by_entity.entry(entity_id).or_default().push(alias);
+    }
+    let mut memories_by_entity: HashMap<i64, Vec<i64>> = HashMap::new();
+    for (memory_id, entity_id) in links {
+        memories_by_entity
+            .entry(entity_id)
+            .or_default()
+            .push(memory_id);
+    }
+    let mut relations_by_entity: HashMap<i64, Vec<&Relation>> = HashMap::new();
+    for r in relations {
+        if entity_ids.contains(&r.to_entity_id) {
+            relations_by_entity
+                .entry(r.from_entity_id)
+                .or_default()
+                .push(r);
+        }
+    }
+
+    let mut out = Vec::new();
+    for e in entities {
+        let mut res = Resource::new(Term::new("entity", &e.id.to_string()));
+        res.types.push(Term::new("type", &e.entity_type));
+        res.add(
+            well_known("rdfs", "label"),
+            Object::Literal(Literal::Text(e.name.clone())),
+        );
+        let mut entity_aliases = aliases_by_entity.remove(&e.id).unwrap_or_default();
+        entity_aliases.sort();
+        for alias in entity_aliases {
+            res.add(
+                well_known("skos", "altLabel"),
+                Object::Literal(Literal::Text(alias)),
+            );
+        }
+        let attributes = e
+            .attributes
+            .as_deref()
+            .and_then(|a| serde_json::from_str::<Value>(a).ok())
+            .and_then(|v| v.as_object().cloned())
+            .unwrap_or_default();
+        for (key, value) in &attributes {
+            let values = match value {
+                Value::Array(items) => items.iter().filter_map(Literal::from_json).collect(),
+                other => Literal::from_json(other).into_iter().collect::<Vec<_>>(),
+            };
+            for literal in values {
+                res.add(Term::new("attr", key), Object::Literal(literal));
+            }
+        }
+        for r in relations_by_entity.get(&e.id).into_iter().flatten() {
+            res.add(
+                Term::new("rel", &r.relation_type),
+                Object::Resource(Term::new("entity", &r.to_entity_id.to_string())),
+            );
+        }
+        let mut sources = memories_by_entity.remove(&e.id).unwrap_or_default();
+        sources.sort_unstable();
+        for memory_id in sources {
+            res.add(
+                well_known("prov", "wasDerivedFrom"),
+                Object::Resource(Term::new("memory", &memory_id.to_string())),
+            );
+        }
+        out.push(res);
+    }
+
+    for m in memories {
+        let mut res = Resource::new(Term::new("memory", &m.id.to_string()));
+        res.types.push(well_known("prov", "Entity"));
+        let title = memory_title(m);
+        if !title.is_empty() {
+            res.add(
+                well_known("dcterms", "title"),
+                Object::Literal(Literal::Text(title)),
+            );
+        }
+        res.add(
+            well_known("prov", "value"),
+            Object::Literal(Literal::Text(m.content.clone())),
+        );
+        if let Some(created) = xsd_date_time(&m.created_at) {
+            res.add(
+                well_known("prov", "generatedAtTime"),
+                Object::Literal(Literal::DateTime(created)),
+            );
+        }
+        let tags = m.tags.as_deref().unwrap_or_default().split(',');
+        for tag in tags.map(str::trim).filter(|t| !t.is_empty()) {
+            res.add(
+                well_known("dcterms", "subject"),
+                Object::Literal(Literal::Text(tag.to_string())),
+            );
+        }
+        out.push(res);
+    }
+    out
+}
+
+fn render_turtle(base: &str, resources: &[Resource]) -> String {
+    let mut out = String::new();
+    for (prefix, iri) in PREFIXES {
+        out.push_str(&format!("@prefix {}: <{}> .\n", prefix, iri));
+    }
+    for (prefix, path) in LOCAL_NAMESPACES {
+        out.push_str(&format!("@prefix {}: <{}{}> .\n", prefix, base, path));
+    }
+    for res in resources {
+        out.push('\n');
+        out.push_str(&res.subject.turtle(base));
+        let mut statements = Vec::new();
+        if !res.types.is_empty() {
+            let types: Vec<String> = res.types.iter().map(|t| t.turtle(base)).collect();
+            statements.push(format!("a {}", types.join(", ")));
+        }
+        for (predicate, objects) in &res.properties {
+            let objects: Vec<String> = objects
+                .iter()
+                .map(|o| match o {
+                    Object::Resource(t) => t.turtle(base),
+                    Object::Literal(l) => l.turtle(),
+                })
+                .collect();
+            statements.push(format!("{} {}", predicate.turtle(base), objects.join(", ")));
+        }
+        out.push_str("\n    ");
+        out.push_str(&statements.join(" ;\n    "));
+        out.push_str(" .\n");
+    }
+    out
+}
+
+fn render_json_ld(base: &str, resources: &[Resource]) -> Result<String, String> {
+    let mut context = Map::new();
+    for (prefix, iri) in PREFIXES {
+        context.insert(prefix.to_string(), Value::from(iri));
+    }
+    for (prefix, path) in LOCAL_NAMESPACES {
+        context.insert(prefix.to_string(), Value::from(format!("{}{}", base, path)));
+    }
+
+    let graph: Vec<Value> = resources
+        .iter()
+        .map(|res| {
+            let mut node = Map::new();
+            node.insert("@id".to_string(), Value::from(res.subject.compact()));
+            if !res.types.is_empty() {
+                node.insert(
+                    "@type".to_string(),
+                    Value::Array(res.types.iter().map(|t| Value::from(t.compact())).collect()),
+                );
+            }
+            for (predicate, objects) in &res.properties {
+                let values: Vec<Value> = objects
+                    .iter()
+                    .map(|o| match o {
+                        Object::Resource(t) => json!({ "@id": t.compact() }),
+                        Object::Literal(l) => l.json_ld(),
+                    })
+                    .collect();
+                node.insert(predicate.compact(), Value::Array(values));
+            }
+            Value::Object(node)
+        })
+        .collect();
+
+    let doc = json!({ "@context": context, "@graph": graph });
+    serde_json::to_string_pretty(&doc).map_err(|e| e.to_string())
+}
+
+/// Render the library as Turtle (`json_ld == false`) or JSON-LD under `base_iri`.
+pub fn render_rdf(conn: &Connection, base_iri: &str, json_ld: bool) -> Result<String, String> {
+    let base = normalize_base_iri(base_iri)?;
+    let entities = list_entities(conn).map_err(|e| e.to_string())?;
+    let relations = list_relations(conn).map_err(|e| e.to_string())?;
+    let aliases = list_entity_aliases(conn).map_err(|e| e.to_string())?;
+    let memories = list_memories(conn).map_err(|e| e.to_string())?;
+    let links = list_memory_entity_links(conn).map_err(|e| e.to_string())?;
+    let resources = build_resources(&entities, &relations, aliases, &memories, links);
+    if json_ld {
+        render_json_ld(&base, &resources)
+    } else {
+        Ok(render_turtle(&base, &resources))
+    }
+}
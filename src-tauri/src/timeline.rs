@@ -0,0 +1,298 @@
+//! Event-date timeline: orders Event entities and memories by the normalized dates of their linked Time entities.
+
+use crate::database::{
+    list_entities, list_memories, list_memory_entity_links, list_relations, Entity, Memory,
+};
+use chrono::NaiveDate;
+use rusqlite::{Connection, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Separators recognised between the two ends of a date range, e.g. `2024-03-01 ~ 2024-03-05`.
+const RANGE_SEPARATORS: [&str; 8] = [" ~ ", "~", "～", "至", " to ", "..", " - ", "—"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimelineParticipant {
+    pub id: i64,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub entity_type: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimelineEntry {
+    /// `event` or `memory`.
+    pub kind: String,
+    /// Inclusive `YYYY-MM-DD` bounds; both are `None` when no date could be resolved.
+    pub start_date: Option<String>,
+    pub end_date: Option<String>,
+    /// How the date was found: `relation` (Time entity related to the event),
+    /// `memory` (Time entity mentioned in the same memory), or `None` when undated.
+    pub date_source: Option<String>,
+    pub event: Option<Entity>,
+    pub memory: Option<Memory>,
+    pub participants: Vec<TimelineParticipant>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct DateSpan {
+    start: NaiveDate,
+    end: NaiveDate,
+}
+
+impl DateSpan {
+    fn union(self, other: DateSpan) -> DateSpan {
+        DateSpan {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+}
+
+fn union_all(spans: impl IntoIterator<Item = DateSpan>) -> Option<DateSpan> {
+    spans.into_iter().reduce(DateSpan::union)
+}
+
+fn last_day_of_month(year: i32, month: u32) -> Option<NaiveDate> {
+    let (next_year, next_month) = if month == 12 {
+        (year + 1, 1)
+    } else {
+        (year, month + 1)
+    };
+    NaiveDate::from_ymd_opt(next_year, next_month, 1)?.pred_opt()
+}
+
+/// Parse `YYYY-MM-DD`, `YYYY-MM` or `YYYY` (also with `/` or `.` separators) into a span
+/// covering the whole day, month or year.
+fn parse_partial_date(text: &str) -> Option<DateSpan> {
+    let normalized = text.trim().replace(['/', '.'], "-");
+    let date_part = normalized.split(['T', ' ']).next().unwrap_or("");
+    let parts: Vec<&str> = date_part.split('-').filter(|x| !x.is_empty()).collect();
+    let year = parts.first()?.parse::<i32>().ok()?;
+    if !(1..=9999).contains(&year) || parts.first()?.len() != 4 {
+        return None;
+    }
+    match parts.len() {
+        1 => Some(DateSpan {
+            start: NaiveDate::from_ymd_opt(year, 1, 1)?,
+            end: NaiveDate::from_ymd_opt(year, 12, 31)?,
+        }),
+        2 => {
+            let month = parts[1].parse::<u32>().ok()?;
+            Some(DateSpan {
+                start: NaiveDate::from_ymd_opt(year, month, 1)?,
+                end: last_day_of_month(year, month)?,
+            })
+        }
+        3 => {
+            let day = NaiveDate::from_ymd_opt(
+                year,
+                parts[1].parse::<u32>().ok()?,
+                parts[2].parse::<u32>().ok()?,
+            )?;
+            Some(DateSpan {
+                start: day,
+                end: day,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Parse a single date or a range such as `2024-03-01 ~ 2024-03-05` or `2024-03..2024-05`.
+fn parse_date_span(text: &str) -> Option<DateSpan> {
+    let trimmed = text.trim();
+    if trimmed.is_empty() {
+        return None;
+    }
+    // Ranges go first: `parse_partial_date` stops at the first space, so it would read
+    // `2024-03-01 ~ 2024-03-05` as a single day.
+    for sep in RANGE_SEPARATORS {
+        if let Some((left, right)) = trimmed.split_once(sep) {
+            let (Some(from), Some(to)) = (parse_partial_date(left), parse_partial_date(right))
+            else {
+                continue;
+            };
+            return Some(from.union(to));
+        }
+    }
+    parse_partial_date(trimmed)
+}
+
+/// Resolve the date span of a Time entity from its `normalized_date` attribute
+/// (optionally paired with `normalized_end_date`), falling back to its name.
+fn time_entity_span(entity: &Entity) -> Option<DateSpan> {
+    let attrs = entity
+        .attributes
+        .as_deref()
+        .and_then(|a| serde_json::from_str::<serde_json::Value>(a).ok());
+    let attr_str = |key: &str| {
+        attrs
+            .as_ref()
+            .and_then(|v| v.get(key))
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+    };
+
+    let from_attrs = attr_str("normalized_date").and_then(|d| parse_date_span(&d));
+    let span = from_attrs.or_else(|| parse_date_span(&entity.name))?;
+    match attr_str("normalized_end_date").and_then(|d| parse_date_span(&d)) {
+        Some(end) => Some(span.union(end)),
+        None => Some(span),
+    }
+}
+
+fn participant_from(entity: &Entity) -> TimelineParticipant {
+    TimelineParticipant {
+        id: entity.id,
+        name: entity.name.clone(),
+        entity_type: entity.entity_type.clone(),
+    }
+}
+
+fn is_type(entity: &Entity, entity_type: &str) -> bool {
+    entity.entity_type.eq_ignore_ascii_case(entity_type)
+}
+
+fn sort_key(entry: &TimelineEntry) -> (bool, String, String, u8, String) {
+    let created = entry
+        .memory
+        .as_ref()
+        .map(|m| m.created_at.clone())
+        .or_else(|| entry.event.as_ref().map(|e| e.created_at.clone()))
+        .unwrap_or_default();
+    (
+        entry.start_date.is_none(),
+        entry.start_date.clone().unwrap_or_default(),
+        entry.end_date.clone().unwrap_or_default(),
+        if entry.kind == "event" { 0 } else { 1 },
+        created,
+    )
+}
+
+/// Build a chronological timeline of Event entities and memories.
+///
+/// Events are dated by Time entities they are related to, falling back to Time entities
+/// mentioned in the same memories. Memories are dated by the Time entities they mention.
+/// When several dates apply, the entry spans from the earliest to the latest.
+/// Undated entries are placed after dated ones when `include_undated` is set.
+/// `participant_id` keeps only events related to that entity and memories that mention it.
+pub fn build_timeline(
+    conn: &Connection,
+    participant_id: Option<i64>,
+    include_undated: bool,
+) -> SqliteResult<Vec<TimelineEntry>> {
+    let entities = list_entities(conn)?;
+    let relations = list_relations(conn)?;
+    let memories = list_memories(conn)?;
+    let links = list_memory_entity_links(conn)?;
+
+    let by_id: HashMap<i64, &Entity> = entities.iter().map(|e| (e.id, e)).collect();
+    let time_spans: HashMap<i64, DateSpan> = entities
+        .iter()
+        .filter(|e| is_type(e, "Time"))
+        .filter_map(|e| time_entity_span(e).map(|span| (e.id, span)))
+        .collect();
+
+    let mut neighbours: HashMap<i64, HashSet<i64>> = HashMap::new();
+    for r in &relations {
+        neighbours
+            .entry(r.from_entity_id)
+            .or_default()
+            .insert(r.to_entity_id);
+        neighbours
+            .entry(r.to_entity_id)
+            .or_default()
+            .insert(r.from_entity_id);
+    }
+    let mut memory_to_entities: HashMap<i64, Vec<i64>> = HashMap::new();
+    let mut entity_to_memories: HashMap<i64, Vec<i64>> = HashMap::new();
+    for (memory_id, entity_id) in &links {
+        memory_to_entities
+            .entry(*memory_id)
+            .or_default()
+            .push(*entity_id);
+        entity_to_memories
+            .entry(*entity_id)
+            .or_default()
+            .push(*memory_id);
+    }
+
+    let span_of = |ids: &[i64]| union_all(ids.iter().filter_map(|id| time_spans.get(id).copied()));
+    let participants_of = |ids: &[i64]| -> Vec<TimelineParticipant> {
+        let mut out: Vec<TimelineParticipant> = ids
+            .iter()
+            .filter_map(|id| by_id.get(id))
+            .filter(|e| !is_type(e, "Time"))
+            .map(|e| participant_from(e))
+            .collect();
+        out.sort_by(|a, b| a.name.cmp(&b.name));
+        out.dedup_by_key(|p| p.id);
+        out
+    };
+
+    let mut entries = Vec::new();
+
+    for event in entities.iter().filter(|e| is_type(e, "Event")) {
+        let related: Vec<i64> = neighbours
+            .get(&event.id)
+            .map(|ids| ids.iter().copied().collect())
+            .unwrap_or_default();
+        if let Some(pid) = participant_id {
+            if pid != event.id && !related.contains(&pid) {
+                continue;
+            }
+        }
+
+        let mut span = span_of(&related).map(|s| (s, "relation"));
+        if span.is_none() {
+            let co_mentioned: Vec<i64> = entity_to_memories
+                .get(&event.id)
+                .into_iter()
+                .flatten()
+                .filter_map(|m| memory_to_entities.get(m))
+                .flatten()
+                .copied()
+                .collect();
+            span = span_of(&co_mentioned).map(|s| (s, "memory"));
+        }
+
+        entries.push(TimelineEntry {
+            kind: "event".to_string(),
+            start_date: span.map(|(s, _)| s.start.format("%Y-%m-%d").to_string()),
+            end_date: span.map(|(s, _)| s.end.format("%Y-%m-%d").to_string()),
+            date_source: span.map(|(_, source)| source.to_string()),
+            event: Some(event.clone()),
+            memory: None,
+            participants: participants_of(&related),
+        });
+    }
+
+    for memory in memories {
+        let linked = memory_to_entities
+            .get(&memory.id)
+            .cloned()
+            .unwrap_or_default();
+        if let Some(pid) = participant_id {
+            if !linked.contains(&pid) {
+                continue;
+            }
+        }
+        let span = span_of(&linked);
+        entries.push(TimelineEntry {
+            kind: "memory".to_string(),
+            start_date: span.map(|s| s.start.format("%Y-%m-%d").to_string()),
+            end_date: span.map(|s| s.end.format("%Y-%m-%d").to_string()),
+            date_source: span.map(|_| "memory".to_string()),
+            event: None,
+            memory: Some(memory),
+            participants: participants_of(&linked),
+        });
+    }
+
+    if !include_undated {
+        entries.retain(|e| e.start_date.is_some());
+    }
+    entries.sort_by_key(sort_key);
+    Ok(entries)
+}
@@ -0,0 +1,477 @@
+//! vCard (`.vcf`) contacts import.
+//!
+//! Each card becomes a Person entity with email, phone, organization, title and birthday
+//! attributes. Nicknames and email addresses become aliases, so later email and calendar
+//! imports find the same person. The organization gets its own entity and a `works_at`
+//! relation. Cards are matched against existing entities by name, email and nickname through
+//! `find_entity_id_by_name_or_alias`. A dry run imports the cards and then rolls the
+//! transaction back, so each card's preview (new, merge, conflict) is what a real import does.
+
+use crate::calendar_import::{parse_property, unescape, unfold, Property};
+use crate::database::{
+    add_entity_alias, find_entity_id_by_name_or_alias, get_entity_by_id, get_relation_strength,
+    set_entity_source, set_relation_source, upsert_entity, upsert_relation,
+};
+use crate::email_import::{decode_charset, decode_quoted_printable};
+use crate::graph_export::value_text;
+use crate::graph_import::{import_source, parse_attributes};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::path::Path;
+
+const WORKS_AT: &str = "works_at";
+/// Attributes holding several values; new values are added instead of conflicting.
+const LIST_ATTRIBUTES: [&str; 2] = ["email", "phone"];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VcardImportRequest {
+    pub path: String,
+    /// Source tag for the people, organizations and `works_at` relations the cards create;
+    /// defaults to `import:` plus the `.vcf` file name.
+    pub source: Option<String>,
+    /// Let a card's organization, department, title or birthday replace a different one on the
+    /// person instead of reporting a conflict. Emails and phones are always merged.
+    pub overwrite_attributes: bool,
+    /// 1-based card numbers from the preview to leave out.
+    pub skip: Vec<usize>,
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VcardPreview {
+    /// 1-based position of the card in the file.
+    pub card: usize,
+    pub name: String,
+    /// `new`, `merge`, `unchanged`, `conflict`, `skipped` or `error`.
+    pub status: String,
+    /// The entity the card matched, if any.
+    pub existing_id: Option<i64>,
+    pub existing_name: Option<String>,
+    pub organization: Option<String>,
+    pub changes: Vec<String>,
+    pub conflicts: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VcardImportReport {
+    pub dry_run: bool,
+    pub source: String,
+    pub cards: usize,
+    pub people_new: usize,
+    pub people_merged: usize,
+    pub people_unchanged: usize,
+    pub conflicts: usize,
+    pub skipped: usize,
+    pub errors: usize,
+    pub organizations_new: usize,
+    pub relations_new: usize,
+    pub previews: Vec<VcardPreview>,
+    /// People and organizations the cards created or changed, whose entity pages need
+    /// refreshing.
+    #[serde(skip)]
+    pub touched_entity_ids: Vec<i64>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Contact {
+    pub name: String,
+    pub nicknames: Vec<String>,
+    pub emails: Vec<String>,
+    pub phones: Vec<String>,
+    pub organization: Option<String>,
+    pub department: Option<String>,
+    pub title: Option<String>,
+    /// `YYYY-MM-DD`, or `--MM-DD` when the year is unknown.
+    pub birthday: Option<String>,
+}
+
+impl Contact {
+    fn attributes(&self) -> Map<String, Value> {
+        let mut attributes = Map::new();
+        let mut put = |key: &str, value: Option<String>| {
+            if let Some(value) = value.filter(|v| !v.is_empty()) {
+                attributes.insert(key.to_string(), Value::String(value));
+            }
+        };
+        put("email", Some(self.emails.join(", ")));
+        put("phone", Some(self.phones.join(", ")));
+        put("organization", self.organization.clone());
+        put("department", self.department.clone());
+        put("title", self.title.clone());
+        put("birthday", self.birthday.clone());
+        attributes
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Parsing
+// ---------------------------------------------------------------------------
+
+/// vCard 2.1 quoted-printable values continue on the next line after a trailing `=`.
+fn join_soft_breaks(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut lines = text.split('\n').peekable();
+    while let Some(line) = lines.next() {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        let quoted_printable = line
+            .split(':')
+            .next()
+            .is_some_and(|head| head.to_ascii_uppercase().contains("QUOTED-PRINTABLE"));
+        out.push_str(line);
+        let mut continued = line;
+        while quoted_printable && continued.ends_with('=') && lines.peek().is_some() {
+            out.pop();
+            let next = lines.next().unwrap_or_default();
+            continued = next.strip_suffix('\r').unwrap_or(next);
+            out.push_str(continued);
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Split a structured value on unescaped `separator`s and unescape the parts.
+fn components(value: &str, separator: char) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                current.push(c);
+                current.extend(chars.next());
+            }
+            c if c == separator => parts.push(unescape(&std::mem::take(&mut current))),
+            c => current.push(c),
+        }
+    }
+    parts.push(unescape(&current));
+    parts
+}
+
+/// Decoded text of a property (quoted-printable and legacy charsets of vCard 2.1).
+fn property_text(property: &Property) -> String {
+    let encoding = property.param("ENCODING").map(str::to_ascii_uppercase);
+    if encoding.as_deref() == Some("QUOTED-PRINTABLE") {
+        let bytes = decode_quoted_printable(property.value.as_bytes(), false);
+        decode_charset(&bytes, property.param("CHARSET"))
+    } else {
+        property.value.clone()
+    }
+}
+
+fn normalize_birthday(value: &str) -> Option<String> {
+    let value = value.trim();
+    let date = value.split('T').next().unwrap_or(value);
+    if let Some(rest) = date.strip_prefix("--") {
+        let digits: String = rest.chars().filter(char::is_ascii_digit).collect();
+        return (digits.len() == 4).then(|| format!("--{}-{}", &digits[..2], &digits[2..]));
+    }
+    let digits: String = date.chars().filter(char::is_ascii_digit).collect();
+    if digits.len() != 8 {
+        return (!value.is_empty()).then(|| value.to_string());
+    }
+    let parsed = chrono::NaiveDate::parse_from_str(&digits, "%Y%m%d").ok()?;
+    Some(parsed.format("%Y-%m-%d").to_string())
+}
+
+fn push_unique(list: &mut Vec<String>, value: String) {
+    let value = value.trim().to_string();
+    if !value.is_empty() && !list.iter().any(|v| v.eq_ignore_ascii_case(&value)) {
+        list.push(value);
+    }
+}
+
+fn parse_contact(properties: &[Property]) -> Contact {
+    let mut contact = Contact::default();
+    let mut structured_name = None;
+    for property in properties {
+        // Apple exports group properties as `item1.EMAIL`.
+        let name = property.name.rsplit('.').next().unwrap_or_default();
+        let text = property_text(property);
+        match name {
+            "FN" => contact.name = unescape(&text),
+            "N" => {
+                let parts = components(&text, ';');
+                let given = parts.get(1).map(String::as_str).unwrap_or_default();
+                let family = parts.first().map(String::as_str).unwrap_or_default();
+                structured_name = Some(format!("{} {}", given, family).trim().to_string());
+            }
+            "NICKNAME" => {
+                for nickname in components(&text, ',') {
+                    push_unique(&mut contact.nicknames, nickname);
+                }
+            }
+            "EMAIL" => push_unique(&mut contact.emails, unescape(&text)),
+            "TEL" => {
+                let number = unescape(&text);
+                let number = number.strip_prefix("tel:").unwrap_or(&number).to_string();
+                push_unique(&mut contact.phones, number);
+            }
+            "ORG" => {
+                let parts = components(&text, ';');
+                contact.organization = parts.first().cloned().filter(|o| !o.is_empty());
+                contact.department = parts.get(1).cloned().filter(|d| !d.is_empty());
+            }
+            "TITLE" => contact.title = Some(unescape(&text)).filter(|t| !t.is_empty()),
+            "BDAY" => contact.birthday = normalize_birthday(&text),
+            _ => {}
+        }
+    }
+    if contact.name.trim().is_empty() {
+        contact.name = structured_name.unwrap_or_default();
+    }
+    contact.name = contact.name.trim().to_string();
+    contact
+}
+
+/// Every card of a `.vcf` file, in file order.
+pub fn read_contacts(path: &Path) -> Result<Vec<Contact>, String> {
+    let bytes = std::fs::read(path).map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+    let text = String::from_utf8_lossy(&bytes);
+    let text = join_soft_breaks(text.trim_start_matches('\u{feff}'));
+    let mut contacts = Vec::new();
+    let mut current: Option<Vec<Property>> = None;
+    for line in unfold(&text) {
+        let Some(property) = parse_property(&line) else {
+            continue;
+        };
+        let value = property.value.trim().to_ascii_uppercase();
+        match (property.name.as_str(), current.as_mut()) {
+            ("BEGIN", None) if value == "VCARD" => current = Some(Vec::new()),
+            ("END", Some(_)) if value == "VCARD" => {
+                contacts.push(parse_contact(&current.take().unwrap_or_default()));
+            }
+            (_, Some(properties)) => properties.push(property),
+            _ => {}
+        }
+    }
+    if contacts.is_empty() {
+        return Err(format!("{:?} contains no vCards.", path));
+    }
+    Ok(contacts)
+}
+
+// ---------------------------------------------------------------------------
+// Import
+// ---------------------------------------------------------------------------
+
+/// Add the card's values of a list attribute to the existing ones.
+fn merge_list(old: &str, new: &str) -> String {
+    let mut values: Vec<String> = old.split(',').map(|v| v.trim().to_string()).collect();
+    for value in new.split(',') {
+        push_unique(&mut values, value.to_string());
+    }
+    values.retain(|v| !v.is_empty());
+    values.join(", ")
+}
+
+/// Find, merge or create the Person of one card, with its aliases and organization.
+fn import_contact(
+    conn: &Connection,
+    request: &VcardImportRequest,
+    contact: &Contact,
+    card: usize,
+    source: &str,
+    report: &mut VcardImportReport,
+) -> Result<VcardPreview, String> {
+    let mut preview = VcardPreview {
+        card,
+        name: contact.name.clone(),
+        status: String::new(),
+        existing_id: None,
+        existing_name: None,
+        organization: contact.organization.clone(),
+        changes: Vec::new(),
+        conflicts: Vec::new(),
+    };
+    if contact.name.is_empty() {
+        preview.status = "error".to_string();
+        preview
+            .conflicts
+            .push("The card has no name (FN or N)".to_string());
+        return Ok(preview);
+    }
+
+    let mut existing = None;
+    for key in std::iter::once(&contact.name)
+        .chain(&contact.emails)
+        .chain(&contact.nicknames)
+    {
+        if let Some(id) = find_entity_id_by_name_or_alias(conn, key).map_err(|e| e.to_string())? {
+            existing = Some(get_entity_by_id(conn, id).map_err(|e| e.to_string())?);
+            break;
+        }
+    }
+    preview.existing_id = existing.as_ref().map(|e| e.id);
+    preview.existing_name = existing.as_ref().map(|e| e.name.clone());
+    if let Some(existing) = &existing {
+        if !existing.entity_type.eq_ignore_ascii_case("Person") {
+            preview.conflicts.push(format!(
+                "'{}' already exists as {}",
+                existing.name, existing.entity_type
+            ));
+        }
+    }
+
+    let mut aliases: Vec<String> = Vec::new();
+    for alias in contact.nicknames.iter().chain(&contact.emails) {
+        push_unique(&mut aliases, alias.clone());
+    }
+    if existing.as_ref().is_some_and(|e| e.name != contact.name) {
+        // Matched through an alias: the card's name becomes another alias.
+        push_unique(&mut aliases, contact.name.clone());
+    }
+    let mut new_aliases = Vec::new();
+    for alias in aliases {
+        let owner = find_entity_id_by_name_or_alias(conn, &alias).map_err(|e| e.to_string())?;
+        match owner {
+            Some(owner) if Some(owner) == preview.existing_id => {}
+            Some(owner) => {
+                let owner = get_entity_by_id(conn, owner).map_err(|e| e.to_string())?;
+                preview
+                    .conflicts
+                    .push(format!("alias '{}' belongs to '{}'", alias, owner.name));
+            }
+            None => new_aliases.push(alias),
+        }
+    }
+
+    let mut attributes = parse_attributes(existing.as_ref().and_then(|e| e.attributes.as_deref()));
+    for (key, value) in contact.attributes() {
+        let new_text = value_text(&value);
+        let merged = match attributes.get(&key).map(value_text) {
+            Some(old) if old == new_text => continue,
+            Some(old) if LIST_ATTRIBUTES.contains(&key.as_str()) => {
+                let merged = merge_list(&old, &new_text);
+                if merged == old {
+                    continue;
+                }
+                preview
+                    .changes
+                    .push(format!("{}: '{}' → '{}'", key, old, merged));
+                merged
+            }
+            Some(old) if !request.overwrite_attributes => {
+                preview
+                    .conflicts
+                    .push(format!("{}: '{}' vs '{}'", key, old, new_text));
+                continue;
+            }
+            Some(old) => {
+                preview
+                    .changes
+                    .push(format!("{}: '{}' → '{}'", key, old, new_text));
+                new_text
+            }
+            None => {
+                preview.changes.push(format!("{}: '{}'", key, new_text));
+                new_text
+            }
+        };
+        attributes.insert(key, Value::String(merged));
+    }
+    if !new_aliases.is_empty() {
+        preview
+            .changes
+            .push(format!("aliases: {}", new_aliases.join(", ")));
+    }
+
+    if !preview.conflicts.is_empty() {
+        preview.status = "conflict".to_string();
+        return Ok(preview);
+    }
+    if request.skip.contains(&card) {
+        preview.status = "skipped".to_string();
+        return Ok(preview);
+    }
+
+    let (entity_type, name) = match &existing {
+        Some(e) => (e.entity_type.clone(), e.name.clone()),
+        None => ("Person".to_string(), contact.name.clone()),
+    };
+    let attributes_json = (!attributes.is_empty()).then(|| Value::Object(attributes).to_string());
+    let person_id = upsert_entity(conn, &entity_type, &name, attributes_json.as_deref())
+        .map_err(|e| e.to_string())?;
+    for alias in &new_aliases {
+        add_entity_alias(conn, person_id, alias).map_err(|e| e.to_string())?;
+    }
+    set_entity_source(conn, person_id, source).map_err(|e| e.to_string())?;
+    report.touched_entity_ids.push(person_id);
+
+    if let Some(organization) = &contact.organization {
+        let org_id =
+            match find_entity_id_by_name_or_alias(conn, organization).map_err(|e| e.to_string())? {
+                Some(id) => id,
+                None => {
+                    report.organizations_new += 1;
+                    preview
+                        .changes
+                        .push(format!("new organization '{}'", organization));
+                    let id = upsert_entity(conn, "Organization", organization, None)
+                        .map_err(|e| e.to_string())?;
+                    set_entity_source(conn, id, source).map_err(|e| e.to_string())?;
+                    id
+                }
+            };
+        report.touched_entity_ids.push(org_id);
+        let known =
+            get_relation_strength(conn, person_id, org_id, WORKS_AT).map_err(|e| e.to_string())?;
+        if known.is_none() {
+            upsert_relation(conn, person_id, org_id, WORKS_AT).map_err(|e| e.to_string())?;
+            set_relation_source(conn, person_id, org_id, WORKS_AT, source)
+                .map_err(|e| e.to_string())?;
+            report.relations_new += 1;
+            preview
+                .changes
+                .push(format!("{} → {}", WORKS_AT, organization));
+        }
+    }
+
+    preview.status = match &existing {
+        None => "new",
+        Some(_) if !preview.changes.is_empty() => "merge",
+        Some(_) => "unchanged",
+    }
+    .to_string();
+    Ok(preview)
+}
+
+/// Import the cards of a `.vcf` file, leaving out the card numbers in `request.skip`.
+pub fn import_vcards(
+    conn: &mut Connection,
+    request: &VcardImportRequest,
+) -> Result<VcardImportReport, String> {
+    let path = Path::new(&request.path);
+    let contacts = read_contacts(path)?;
+    let source = import_source(request.source.as_deref(), Some(&request.path));
+    let mut report = VcardImportReport {
+        dry_run: request.dry_run,
+        source: source.clone(),
+        cards: contacts.len(),
+        ..Default::default()
+    };
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    for (i, contact) in contacts.iter().enumerate() {
+        let preview = import_contact(&tx, request, contact, i + 1, &source, &mut report)?;
+        match preview.status.as_str() {
+            "new" => report.people_new += 1,
+            "merge" => report.people_merged += 1,
+            "unchanged" => report.people_unchanged += 1,
+            "conflict" => report.conflicts += 1,
+            "skipped" => report.skipped += 1,
+            _ => report.errors += 1,
+        }
+        report.previews.push(preview);
+    }
+    report.touched_entity_ids.sort_unstable();
+    report.touched_entity_ids.dedup();
+    if request.dry_run {
+        tx.rollback().map_err(|e| e.to_string())?;
+    } else {
+        tx.commit().map_err(|e| e.to_string())?;
+    }
+    Ok(report)
+}
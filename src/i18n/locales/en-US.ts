@@ -70,6 +70,9 @@ export default {
       switched: 'Library switched',
       renamed: 'Library renamed',
       deleted: 'Library deleted',
+      unlockTitle: 'Unlock Library',
+      unlockPrompt: 'This library is encrypted. Enter its passphrase',
+      unlockConfirm: 'Unlock',
       switchFailed: 'Failed to switch library: ',
       createFailed: 'Failed to create library: ',
       renameFailed: 'Failed to rename library: ',
@@ -89,6 +92,11 @@ export default {
     title: 'Memory List',
     loading: 'Loading…',
   },
+  timeline: {
+    title: 'Timeline',
+    loading: 'Loading…',
+    undated: 'Undated',
+  },
   inputPanel: {
     placeholder: 'Enter content to record, or use voice input…',
     voiceStartTitle: 'Start recording (Whisper)',
@@ -120,12 +128,14 @@ export default {
     placeholder: 'Enter your question…',
     thinking: 'Thinking…',
     ask: 'Ask',
+    cancel: 'Stop',
     progress: {
       connecting: 'Connecting to model',
       retrieving: 'Retrieving related memories',
       generating: 'Generating answer',
       done: 'Answer completed ({seconds}s)',
       failed: 'Request finished ({seconds}s)',
+      cancelled: 'Stopped ({seconds}s)',
     },
     ollamaError: {
       message: 'Ollama service error detected. Run one-click initialization?\n(Auto install, start service, and pull model)',
@@ -281,6 +291,7 @@ export default {
     loading: 'Loading...',
     notConfigured: 'Not configured',
     localOllama: 'Local Ollama',
+    localServer: 'Local server',
   },
   modelSettings: {
     title: 'Model Configuration',
@@ -288,6 +299,8 @@ export default {
       localOllama: 'Using: Local Ollama',
       deepseek: 'Using: DeepSeek API',
       openai: 'Using: OpenAI API',
+      anthropic: 'Using: Anthropic API',
+      localServer: 'Using: Local server (llama.cpp / LM Studio)',
       notConfigured: 'Not configured',
       unknown: 'Unknown provider',
     },
@@ -303,6 +316,13 @@ export default {
       localOllama: 'Local Ollama',
       deepseek: 'DeepSeek API',
       openai: 'OpenAI API',
+      anthropic: 'Anthropic API',
+      localServer: 'llama.cpp / LM Studio',
+      localServerConfig: 'Local Server Config',
+      localServerUrlTip: 'llama-server defaults to port 8080, LM Studio to 1234',
+      localServerApiKey: 'API Key (optional)',
+      discoverModels: 'Discover',
+      noLocalModels: 'No models found — is the server running?',
       ollamaConfig: 'Ollama Config',
       serviceUrl: 'Service URL',
       qaModel: 'Q&A Model',
@@ -318,9 +338,13 @@ export default {
       selectModel: 'Select Model',
       openaiConfig: 'OpenAI Config',
       openaiApiUrlTip: 'Can be configured with other OpenAI-compatible APIs',
+      anthropicConfig: 'Anthropic Config',
+      anthropicApiUrlTip: 'Change only for a proxy or a compatible gateway',
       generalParams: 'General Parameters',
       temperatureTip: 'Lower values produce more deterministic output; higher values produce more random output',
       maxTokens: 'Max Tokens',
+      structuredOutput: 'Structured Output',
+      structuredOutputTip: 'Constrain extraction and fusion output to a JSON schema. APIs that reject response_format fall back to plain JSON automatically',
       saveConfig: 'Save',
       testConnection: 'Test Connection',
       resetDefault: 'Reset',
@@ -334,6 +358,12 @@ export default {
         recommend: 'Recommended: qwen2.5:7b (fast), qwen2.5:14b (accurate)',
         install: 'Install: ',
       },
+      localServer: {
+        title: '🦙 llama.cpp / LM Studio',
+        servers: 'Runs GGUF models through a local OpenAI-compatible server; data stays local',
+        start: 'Start llama.cpp: ',
+        lmStudio: 'LM Studio: load a model and enable the local server (http://localhost:1234)',
+      },
       deepseek: {
         title: '🌐 DeepSeek API',
         accessible: 'Fast access, great for China users',
@@ -347,6 +377,12 @@ export default {
         price: 'Pricier: gpt-4 ~$30/1M tokens',
         compatible: 'Also works with OpenAI-compatible APIs (e.g. Azure OpenAI)',
       },
+      anthropic: {
+        title: '🧠 Anthropic API',
+        native: 'Native Messages API with streaming answers',
+        recommend: 'Recommended: claude-sonnet-4-5 (balanced), claude-haiku-4-5 (fast)',
+        register: 'Get an API key at: ',
+      },
       recommend: {
         title: '💡 Recommendations',
         beginner: 'Beginner/Testing',
@@ -372,6 +408,8 @@ export default {
       ollama: '📝 Using Ollama (extract model: {model})',
       deepseek: '📝 Using DeepSeek API ({model})',
       openai: '📝 Using OpenAI API ({model})',
+      anthropic: '📝 Using Anthropic API ({model})',
+      local_server: '📝 Using local server ({model})',
     },
     step1: {
       extracting: '🔍 Step 1/4: Extracting entities…',
@@ -70,6 +70,9 @@ export default {
       switched: '已切换记忆库',
       renamed: '记忆库已重命名',
       deleted: '记忆库已删除',
+      unlockTitle: '解锁记忆库',
+      unlockPrompt: '该记忆库已加密，请输入密码',
+      unlockConfirm: '解锁',
       switchFailed: '切换记忆库失败: ',
       createFailed: '创建记忆库失败: ',
       renameFailed: '重命名记忆库失败: ',
@@ -89,6 +92,11 @@ export default {
     title: '记忆列表',
     loading: '加载中…',
   },
+  timeline: {
+    title: '时间线',
+    loading: '加载中…',
+    undated: '未标注日期',
+  },
   inputPanel: {
     placeholder: '输入要记录的内容，或使用语音输入…',
     voiceStartTitle: '开始录音（Whisper）',
@@ -120,12 +128,14 @@ export default {
     placeholder: '输入问题…',
     thinking: '思考中…',
     ask: '提问',
+    cancel: '停止',
     progress: {
       connecting: '正在连接模型',
       retrieving: '正在检索相关记忆',
       generating: '正在生成回答',
       done: '回答完成（耗时 {seconds}s）',
       failed: '请求结束（耗时 {seconds}s）',
+      cancelled: '已停止（耗时 {seconds}s）',
     },
     ollamaError: {
       message: '检测到 Ollama 服务异常，是否立即运行一键初始化？\n（将自动完成安装检测、服务启动、模型下载）',
@@ -281,6 +291,7 @@ export default {
     loading: '加载中...',
     notConfigured: '未配置',
     localOllama: '本地 Ollama',
+    localServer: '本地服务',
   },
   modelSettings: {
     title: '模型配置',
@@ -288,6 +299,8 @@ export default {
       localOllama: '当前使用：本地 Ollama 模型',
       deepseek: '当前使用：DeepSeek API',
       openai: '当前使用：OpenAI API',
+      anthropic: '当前使用：Anthropic API',
+      localServer: '当前使用：本地服务（llama.cpp / LM Studio）',
       notConfigured: '未配置',
       unknown: '未知提供商',
     },
@@ -303,6 +316,13 @@ export default {
       localOllama: '本地 Ollama',
       deepseek: 'DeepSeek API',
       openai: 'OpenAI API',
+      anthropic: 'Anthropic API',
+      localServer: 'llama.cpp / LM Studio',
+      localServerConfig: '本地服务配置',
+      localServerUrlTip: 'llama-server 默认端口 8080，LM Studio 默认端口 1234',
+      localServerApiKey: 'API Key（可选）',
+      discoverModels: '发现模型',
+      noLocalModels: '未发现模型，请确认服务已启动',
       ollamaConfig: 'Ollama 配置',
       serviceUrl: '服务地址',
       qaModel: '问答模型',
@@ -318,9 +338,13 @@ export default {
       selectModel: '选择模型',
       openaiConfig: 'OpenAI 配置',
       openaiApiUrlTip: '可配置兼容 OpenAI 格式的其他 API',
+      anthropicConfig: 'Anthropic 配置',
+      anthropicApiUrlTip: '仅在使用代理或兼容网关时修改',
       generalParams: '通用参数',
       temperatureTip: '较低值使输出更确定，较高值使输出更随机',
       maxTokens: '最大 Tokens',
+      structuredOutput: '结构化输出',
+      structuredOutputTip: '将抽取与融合结果约束为 JSON Schema；不支持 response_format 的 API 会自动回退为普通 JSON',
       saveConfig: '保存',
       testConnection: '测试连接',
       resetDefault: '重置为默认',
@@ -334,6 +358,12 @@ export default {
         recommend: '推荐模型：qwen2.5:7b（快速）、qwen2.5:14b（准确）',
         install: '安装：',
       },
+      localServer: {
+        title: '🦙 llama.cpp / LM Studio',
+        servers: '通过本地 OpenAI 兼容服务运行 GGUF 模型，数据不出本地',
+        start: '启动 llama.cpp：',
+        lmStudio: 'LM Studio：加载模型并开启本地服务（http://localhost:1234）',
+      },
       deepseek: {
         title: '🌐 DeepSeek API',
         accessible: '国内可直接访问，速度快',
@@ -347,6 +377,12 @@ export default {
         price: '价格较高：gpt-4 约 $30/1M tokens',
         compatible: '也可配置兼容 OpenAI 格式的其他 API（如 Azure OpenAI）',
       },
+      anthropic: {
+        title: '🧠 Anthropic API',
+        native: '原生 Messages API，支持流式回答',
+        recommend: '推荐：claude-sonnet-4-5（均衡）、claude-haiku-4-5（快速）',
+        register: '获取 API Key：',
+      },
       recommend: {
         title: '💡 推荐配置',
         beginner: '新手/测试',
@@ -372,6 +408,8 @@ export default {
       ollama: '📝 使用 Ollama（提取模型: {model}）',
       deepseek: '📝 使用 DeepSeek API（{model}）',
       openai: '📝 使用 OpenAI API（{model}）',
+      anthropic: '📝 使用 Anthropic API（{model}）',
+      local_server: '📝 使用本地服务（{model}）',
     },
     step1: {
       extracting: '🔍 第 1/4 步：提取实体…',
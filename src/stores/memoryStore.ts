@@ -43,11 +43,11 @@ export const useMemoryStore = defineStore('memory', () => {
     }
   }
 
-  async function fetchTimeline() {
+  async function fetchTimeline(participantId?: number, includeUndated?: boolean) {
     loading.value = true
     error.value = null
     try {
-      return await getTimeline()
+      return await getTimeline(participantId, includeUndated)
     } catch (e) {
       error.value = e instanceof Error ? e.message : String(e)
       return []
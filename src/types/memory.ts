@@ -1,3 +1,5 @@
+import type { Entity } from './entity'
+
 export interface Memory {
   id: number
   content: string
@@ -6,11 +8,49 @@ export interface Memory {
   tags: string | null
 }
 
+export interface TimelineParticipant {
+  id: number
+  name: string
+  type: string
+}
+
+export interface TimelineEntry {
+  kind: 'event' | 'memory'
+  start_date: string | null
+  end_date: string | null
+  date_source: 'relation' | 'memory' | null
+  event: Entity | null
+  memory: Memory | null
+  participants: TimelineParticipant[]
+}
+
+export interface MdEntity {
+  name: string
+  type?: string
+  attributes?: Record<string, unknown>
+}
+
+export interface MdRelation {
+  from: string
+  to: string
+  relation: string
+}
+
+export interface MdAlias {
+  primary: string
+  alias: string
+}
+
 export interface MdRecord {
   frontmatter: {
+    id: number | null
     created: string
+    updated: string | null
+    source: string | null
     tags: string[] | null
-    entities: string[] | null
+    entities: MdEntity[] | null
+    relations: MdRelation[] | null
+    aliases: MdAlias[] | null
   }
   content: string
   file_path: string
@@ -1,6 +1,6 @@
 // Type definitions for model configuration
 
-export type ModelProviderType = 'ollama' | 'deepseek' | 'openai'
+export type ModelProviderType = 'ollama' | 'deepseek' | 'openai' | 'anthropic' | 'local_server'
 
 export interface OllamaProvider {
   type: 'ollama'
@@ -23,12 +23,33 @@ export interface OpenAIProvider {
   model_name: string
 }
 
-export type ModelProvider = OllamaProvider | DeepSeekProvider | OpenAIProvider
+export interface AnthropicProvider {
+  type: 'anthropic'
+  api_key: string
+  base_url: string
+  model_name: string
+}
+
+/** llama.cpp `llama-server`, LM Studio or another local OpenAI-compatible server. */
+export interface LocalServerProvider {
+  type: 'local_server'
+  base_url: string
+  api_key: string
+  model_name: string
+}
+
+export type ModelProvider =
+  | OllamaProvider
+  | DeepSeekProvider
+  | OpenAIProvider
+  | AnthropicProvider
+  | LocalServerProvider
 
 export interface ModelConfig {
   provider: ModelProvider
   temperature: number
   max_tokens: number
+  structured_output: boolean
 }
 
 export const DEFAULT_OLLAMA_CONFIG: OllamaProvider = {
@@ -51,3 +72,17 @@ export const DEFAULT_OPENAI_CONFIG: OpenAIProvider = {
   base_url: 'https://api.openai.com/v1',
   model_name: 'gpt-4',
 }
+
+export const DEFAULT_ANTHROPIC_CONFIG: AnthropicProvider = {
+  type: 'anthropic',
+  api_key: '',
+  base_url: 'https://api.anthropic.com',
+  model_name: 'claude-sonnet-4-5',
+}
+
+export const DEFAULT_LOCAL_SERVER_CONFIG: LocalServerProvider = {
+  type: 'local_server',
+  base_url: 'http://localhost:8080',
+  api_key: '',
+  model_name: '',
+}
@@ -1,5 +1,5 @@
 import { invoke } from '@tauri-apps/api/core'
-import type { Memory, MdRecord } from '../types/memory'
+import type { Memory, MdRecord, TimelineEntry } from '../types/memory'
 import type { Entity } from '../types/entity'
 import type { GraphData } from '../types/graph'
 import type { ModelConfig } from '../types/model-config'
@@ -15,6 +15,20 @@ export interface MemoryLibraryInfo {
   path: string
   is_current: boolean
   enable_time_normalization?: boolean
+  path_template: string
+  enable_entity_pages?: boolean
+  enable_git_history?: boolean
+  /** Protected by a passphrase. */
+  is_encrypted?: boolean
+  /** Encrypted and not yet unlocked in this session; switch to it with its passphrase. */
+  is_locked?: boolean
+}
+
+export interface MemoryHistoryEntry {
+  commit: string
+  message: string
+  time: string
+  change: 'added' | 'modified' | 'deleted'
 }
 
 export interface StoryGenerationRequest {
@@ -132,6 +146,21 @@ export interface ExternalPluginInfo {
   install_path: string
 }
 
+export interface RebuildIndexReport {
+  total: number
+  rebuilt: number
+  extracted: number
+  errors: { path: string; error: string }[]
+  backup_path: string | null
+}
+
+export interface ReorganizeFilesReport {
+  total: number
+  moved: number
+  unchanged: number
+  errors: { path: string; error: string }[]
+}
+
 export async function listMemoriesDir(): Promise<string[]> {
   return invoke('list_memories_dir')
 }
@@ -147,12 +176,17 @@ export async function getCurrentMemoryLibrary(): Promise<MemoryLibraryInfo> {
 export async function createMemoryLibrary(
   name: string,
   enableTimeNormalization?: boolean,
+  passphrase?: string,
 ): Promise<MemoryLibraryInfo> {
-  return invoke('create_memory_library', { name, enableTimeNormalization })
+  return invoke('create_memory_library', { name, enableTimeNormalization, passphrase })
 }
 
-export async function switchMemoryLibrary(libraryId: string): Promise<MemoryLibraryInfo> {
-  return invoke('switch_memory_library', { libraryId })
+/** `passphrase` is required to open an encrypted library. */
+export async function switchMemoryLibrary(
+  libraryId: string,
+  passphrase?: string
+): Promise<MemoryLibraryInfo> {
+  return invoke('switch_memory_library', { libraryId, passphrase })
 }
 
 export async function renameMemoryLibrary(
@@ -162,10 +196,72 @@ export async function renameMemoryLibrary(
   return invoke('rename_memory_library', { libraryId, name })
 }
 
+/**
+ * Set the layout used for new memory files, e.g. `journal/{date}.md` or `{entity}/{slug}.md`.
+ * Pass null to restore the default `{year}/{month}/{year}{month}{day}_{time}_{slug}.md`.
+ */
+export async function setMemoryLibraryPathTemplate(
+  libraryId: string,
+  pathTemplate: string | null
+): Promise<MemoryLibraryInfo> {
+  return invoke('set_memory_library_path_template', { libraryId, pathTemplate })
+}
+
+/**
+ * Turn generated entity pages (the library's `entities/` folder) on or off.
+ * Enabling them on the active library generates every page immediately.
+ */
+export async function setMemoryLibraryEntityPages(
+  libraryId: string,
+  enabled: boolean
+): Promise<MemoryLibraryInfo> {
+  return invoke('set_memory_library_entity_pages', { libraryId, enabled })
+}
+
+/**
+ * Turn git history for the library's `memories/` folder on or off.
+ * Enabling it creates the repository with a snapshot of the current files.
+ */
+export async function setMemoryLibraryGitHistory(
+  libraryId: string,
+  enabled: boolean
+): Promise<MemoryLibraryInfo> {
+  return invoke('set_memory_library_git_history', { libraryId, enabled })
+}
+
+/** Encrypt the current library in place with a passphrase (at least 8 characters). */
+export async function encryptMemoryLibrary(
+  libraryId: string,
+  passphrase: string
+): Promise<MemoryLibraryInfo> {
+  return invoke('encrypt_memory_library', { libraryId, passphrase })
+}
+
+export async function changeLibraryPassphrase(
+  libraryId: string,
+  oldPassphrase: string,
+  newPassphrase: string
+): Promise<MemoryLibraryInfo> {
+  return invoke('change_library_passphrase', { libraryId, oldPassphrase, newPassphrase })
+}
+
 export async function deleteMemoryLibrary(libraryId: string): Promise<string> {
   return invoke('delete_memory_library', { libraryId })
 }
 
+/**
+ * Pack a library (default: the current one) into a portable `.kraph` archive at `path`.
+ * The model config is not included. Returns the archive path.
+ */
+export async function exportLibrary(path: string, libraryId?: string): Promise<string> {
+  return invoke('export_library', { path, libraryId })
+}
+
+/** Unpack a `.kraph` archive as a new library, named `name` or the archived library's name. */
+export async function importLibrary(path: string, name?: string): Promise<MemoryLibraryInfo> {
+  return invoke('import_library', { path, name })
+}
+
 export async function openMemoriesFolder(): Promise<string> {
   return invoke('open_memories_folder')
 }
@@ -197,6 +293,315 @@ export async function getGraph(): Promise<GraphData> {
   return invoke('get_graph')
 }
 
+export type GraphExportFormat = 'graphml' | 'gexf' | 'csv' | 'cypher' | 'turtle' | 'jsonld'
+
+/**
+ * Write the graph to `path` for Gephi, Neo4j, semantic-web tools and the like.
+ * CSV writes `<name>.nodes.csv` and `<name>.edges.csv` next to `path`. Turtle and JSON-LD mint
+ * IRIs under `baseIri` (default `https://kraph.local/<library id>/`). Returns the files written.
+ */
+export async function exportGraph(
+  format: GraphExportFormat,
+  path: string,
+  includeMemoryIds?: boolean,
+  baseIri?: string
+): Promise<string[]> {
+  return invoke('export_graph', { format, path, includeMemoryIds, baseIri })
+}
+
+export interface NodeColumnMapping {
+  /** Column edge lists use to refer to nodes; defaults to the name. */
+  id?: string
+  name?: string
+  type?: string
+  default_type?: string
+  aliases?: string
+  /** Defaults to `;`. */
+  alias_separator?: string
+  /** Attribute columns; omitted keeps every column not mapped otherwise. */
+  attributes?: string[]
+}
+
+export interface EdgeColumnMapping {
+  source?: string
+  target?: string
+  relation?: string
+  default_relation?: string
+  strength?: string
+}
+
+export interface GraphImportRequest {
+  nodes_path?: string
+  edges_path?: string
+  nodes?: NodeColumnMapping
+  edges?: EdgeColumnMapping
+  /** Tag stored on imported rows; defaults to `import:<file name>`. */
+  source?: string
+  overwrite_attributes?: boolean
+  dry_run?: boolean
+}
+
+export interface GraphImportRow {
+  kind: 'entity' | 'relation'
+  row: number
+  label: string
+  status: 'new' | 'updated' | 'unchanged' | 'conflict' | 'error'
+  message: string | null
+}
+
+export interface GraphImportReport {
+  dry_run: boolean
+  source: string
+  entities_new: number
+  entities_updated: number
+  entities_unchanged: number
+  entities_conflicting: number
+  relations_new: number
+  relations_updated: number
+  relations_unchanged: number
+  errors: number
+  rows: GraphImportRow[]
+}
+
+/**
+ * Import entities and relations from CSV or JSON node/edge lists. Unmapped columns are guessed
+ * from common headers. With `dry_run` nothing is written and only the report is returned.
+ */
+export async function importGraph(request: GraphImportRequest): Promise<GraphImportReport> {
+  return invoke('import_graph', { request })
+}
+
+export type ChatExportFormat = 'telegram' | 'whatsapp' | 'wechat_csv' | 'wechat_html'
+
+export interface ChatImportRequest {
+  path: string
+  /** Guessed from the extension when omitted (.json, .txt, .csv, .html). */
+  format?: ChatExportFormat
+  /** One memory per chat per day (default) or per chat. */
+  split_by?: 'day' | 'thread'
+  batch_size?: number
+  /** Parse and group only; nothing is saved. */
+  dry_run?: boolean
+}
+
+export interface ChatImportReport {
+  format: string
+  dry_run: boolean
+  messages: number
+  conversations: { title: string; created: string; participants: string[]; message_count: number }[]
+  saved: number
+  failed: number
+  errors: { title: string; error: string }[]
+}
+
+/** Payload of the `chat-import-progress` event; `status` is `running`, `batch` or `done`. */
+export interface ChatImportProgress {
+  current: number
+  total: number
+  batch: number
+  title: string
+  status: 'running' | 'batch' | 'done'
+}
+
+/**
+ * Import a Telegram JSON, WhatsApp TXT or WeChat CSV/HTML chat export as memories, with the
+ * participants seeded as Person entities. Listen to `chat-import-progress` for progress.
+ */
+export async function importChatLog(request: ChatImportRequest): Promise<ChatImportReport> {
+  return invoke('import_chat_log', { request })
+}
+
+export interface EmailImportRequest {
+  /** An mbox file, a single .eml file or a folder of .eml files. */
+  path: string
+  /** One memory per message (default) or per thread. */
+  split_by?: 'message' | 'thread'
+  batch_size?: number
+  /** Parse and group only; nothing is saved. */
+  dry_run?: boolean
+}
+
+export interface EmailImportReport {
+  dry_run: boolean
+  messages: number
+  /** Messages skipped because their Message-ID was already imported. */
+  duplicates: number
+  memories: { subject: string; created: string | null; participants: string[]; message_count: number }[]
+  saved: number
+  failed: number
+  errors: { subject: string; error: string }[]
+}
+
+/** Payload of the `email-import-progress` event; `status` is `running`, `batch` or `done`. */
+export interface EmailImportProgress {
+  current: number
+  total: number
+  batch: number
+  subject: string
+  status: 'running' | 'batch' | 'done'
+}
+
+/**
+ * Import an mbox file or .eml messages as memories. Correspondents become Person entities with
+ * their address, and re-importing skips known Message-IDs. Listen to `email-import-progress`.
+ */
+export async function importEmail(request: EmailImportRequest): Promise<EmailImportReport> {
+  return invoke('import_email', { request })
+}
+
+export interface DocumentImportRequest {
+  /** A .txt, .md, .html or .epub file. */
+  path: string
+  /** Token budget per chunk (default 1500). */
+  max_chunk_tokens?: number
+  /** Added to every memory of the document, besides `document`. */
+  tags?: string[]
+  /** Read and chunk only; nothing is extracted or saved. */
+  dry_run?: boolean
+}
+
+export interface DocumentChunkSummary {
+  index: number
+  section: string | null
+  tokens: number
+  chars: number
+}
+
+export interface DocumentImportReport {
+  document_id: string
+  title: string
+  format: string
+  dry_run: boolean
+  chunks: DocumentChunkSummary[]
+  /** Chunks whose extraction failed; they are saved without entities. */
+  errors: { index: number; error: string }[]
+  entities: number
+  relations: number
+  /** The parent memory holding the outline and the reconciled graph. */
+  memory_id: number | null
+  chunk_memory_ids: number[]
+}
+
+/** Payload of the `document-import-progress` event. */
+export interface DocumentImportProgress {
+  current: number
+  total: number
+  section: string | null
+  status: 'extracting' | 'saving' | 'done'
+}
+
+export interface DocumentPart {
+  /** `null` for the parent memory, 1-based position for chunks. */
+  chunk: number | null
+  section: string | null
+  memory_id: number | null
+  path: string
+}
+
+/**
+ * Import a long document: it is split into chunks within a token budget, each chunk is
+ * extracted on its own and the results are merged into one graph update. Listen to
+ * `document-import-progress` for progress.
+ */
+export async function importDocument(request: DocumentImportRequest): Promise<DocumentImportReport> {
+  return invoke('import_document', { request })
+}
+
+/** The parent and chunk memories of the document `memoryId` belongs to (empty if none). */
+export async function getDocumentParts(memoryId: number): Promise<DocumentPart[]> {
+  return invoke('get_document_parts', { memoryId })
+}
+
+export interface CalendarImportRequest {
+  /** An .ics file. */
+  path: string
+  /** Last date (YYYY-MM-DD) recurring events are expanded to; defaults to a year from now. */
+  expand_until?: string
+  /** Parse and expand only; nothing is saved. */
+  dry_run?: boolean
+}
+
+export interface CalendarImportReport {
+  dry_run: boolean
+  events: number
+  occurrences: number
+  /** Occurrences skipped because their UID was already imported. */
+  duplicates: number
+  imported: {
+    title: string
+    start: string
+    location: string | null
+    attendees: string[]
+    recurring: boolean
+  }[]
+  saved: number
+  failed: number
+  errors: { title: string; error: string }[]
+}
+
+/** Payload of the `calendar-import-progress` event. */
+export interface CalendarImportProgress {
+  current: number
+  total: number
+  /** Always 1: a calendar is saved as a single batch. */
+  batch: number
+  title: string
+  status: 'running' | 'done'
+}
+
+/**
+ * Import an .ics calendar: each event occurrence becomes a memory with Event, Time, Location
+ * and Person entities. Re-importing skips known UIDs. Listen to `calendar-import-progress`.
+ */
+export async function importCalendar(request: CalendarImportRequest): Promise<CalendarImportReport> {
+  return invoke('import_calendar', { request })
+}
+
+export interface VcardImportRequest {
+  path: string
+  /** Tag stored on imported rows; defaults to `import:<file name>`. */
+  source?: string
+  overwrite_attributes?: boolean
+  /** 1-based card numbers from the preview to leave out. */
+  skip?: number[]
+  dry_run?: boolean
+}
+
+export interface VcardPreview {
+  card: number
+  name: string
+  status: 'new' | 'merge' | 'unchanged' | 'conflict' | 'skipped' | 'error'
+  existing_id: number | null
+  existing_name: string | null
+  organization: string | null
+  changes: string[]
+  conflicts: string[]
+}
+
+export interface VcardImportReport {
+  dry_run: boolean
+  source: string
+  cards: number
+  people_new: number
+  people_merged: number
+  people_unchanged: number
+  conflicts: number
+  skipped: number
+  errors: number
+  organizations_new: number
+  relations_new: number
+  previews: VcardPreview[]
+}
+
+/**
+ * Import a .vcf address book as Person entities (phone, email, organization, birthday), with
+ * nicknames as aliases and a `works_at` relation per organization. Run with `dry_run` first to
+ * get the merge preview, then again with the cards to leave out in `skip`.
+ */
+export async function importContacts(request: VcardImportRequest): Promise<VcardImportReport> {
+  return invoke('import_contacts', { request })
+}
+
 export async function queryEntity(name: string): Promise<Entity | null> {
   return invoke('query_entity', { name })
 }
@@ -222,8 +627,11 @@ export async function getCharacterProfile(entityId: number): Promise<{
   return invoke('get_character_profile', { entityId })
 }
 
-export async function getTimeline(): Promise<Memory[]> {
-  return invoke('get_timeline')
+export async function getTimeline(
+  participantId?: number,
+  includeUndated?: boolean
+): Promise<TimelineEntry[]> {
+  return invoke('get_timeline', { participantId, includeUndated })
 }
 
 export async function updateMemory(
@@ -246,6 +654,37 @@ export async function clearAllData(): Promise<string> {
   return invoke('clear_all_data_cmd')
 }
 
+/**
+ * Rebuild the active library's database from its Markdown files.
+ * Progress is delivered via the Tauri event "index-rebuild-progress".
+ */
+export async function rebuildIndex(): Promise<RebuildIndexReport> {
+  return invoke('rebuild_index')
+}
+
+/**
+ * Move the active library's memory files to match its path template (or the given one,
+ * which is saved first). Progress is delivered via "memory-files-reorganize-progress".
+ */
+export async function reorganizeMemoryFiles(
+  pathTemplate?: string
+): Promise<ReorganizeFilesReport> {
+  return invoke('reorganize_memory_files', { pathTemplate })
+}
+
+export async function getMemoryFileHistory(path: string): Promise<MemoryHistoryEntry[]> {
+  return invoke('get_memory_file_history', { path })
+}
+
+export async function readMemoryFileAt(path: string, commit: string): Promise<string> {
+  return invoke('read_memory_file_at', { path, commit })
+}
+
+/** Restore a memory file from an earlier commit; the memory is re-indexed afterwards. */
+export async function restoreMemoryFile(path: string, commit: string): Promise<void> {
+  return invoke('restore_memory_file', { path, commit })
+}
+
 export async function transcribeAudio(audioBase64: string): Promise<string> {
   return invoke('transcribe_audio', { audioBase64 })
 }
@@ -258,6 +697,31 @@ export async function answerQuestion(question: string): Promise<string> {
   return invoke('answer_question', { question })
 }
 
+/** Payload of the `answer-stream` event. */
+export interface AnswerStreamEvent {
+  request_id: string
+  status: 'delta' | 'done' | 'error' | 'cancelled'
+  /** New text since the previous event (`delta` only). */
+  delta: string
+  /** The complete answer (`done` only). */
+  text: string | null
+  truncated: boolean
+  error: string | null
+}
+
+/**
+ * Start a streamed answer. Returns immediately; listen to `answer-stream` and filter on
+ * `request_id` for the text deltas and the final `done` / `error` event.
+ */
+export async function answerQuestionStream(requestId: string, question: string): Promise<void> {
+  return invoke('answer_question_stream', { requestId, question })
+}
+
+/** Abort a streamed answer. Resolves to `false` when it already finished. */
+export async function cancelAnswer(requestId: string): Promise<boolean> {
+  return invoke('cancel_answer', { requestId })
+}
+
 export async function generateStoryFromEvents(
   request: StoryGenerationRequest
 ): Promise<StoryGenerationResult> {
@@ -320,6 +784,16 @@ export async function checkOllama(): Promise<[boolean, string]> {
   return invoke('check_ollama')
 }
 
+/** Health of a llama.cpp / LM Studio server: `[is_running, message]`. */
+export async function checkLocalServer(baseUrl: string, apiKey?: string): Promise<[boolean, string]> {
+  return invoke('check_local_server', { baseUrl, apiKey })
+}
+
+/** Model IDs a llama.cpp / LM Studio server offers (`/v1/models`). */
+export async function listLocalModels(baseUrl: string, apiKey?: string): Promise<string[]> {
+  return invoke('list_local_models', { baseUrl, apiKey })
+}
+
 /**
  * One-click Ollama setup: check install → start service → pull model.
  * Progress is delivered to the frontend via Tauri events "ollama-setup-log" / "ollama-setup-done".
@@ -342,3 +816,32 @@ export async function updateModelConfig(config: ModelConfig): Promise<void> {
 export async function testModelConfig(config: ModelConfig): Promise<string> {
   return invoke('test_model_config', { config })
 }
+
+/** What a model backend supports beyond plain chat. */
+export interface ModelCapabilities {
+  chat: boolean
+  streaming: boolean
+  json_mode: boolean
+  /** JSON output can be constrained to a schema. */
+  structured_output: boolean
+  embeddings: boolean
+  /** Token counts come from the model's tokenizer rather than an estimate. */
+  token_counting: boolean
+}
+
+export async function getModelCapabilities(config: ModelConfig): Promise<ModelCapabilities> {
+  return invoke('get_model_capabilities', { config })
+}
+
+/** Embed texts with the current model (one vector per text). */
+export async function embedTexts(texts: string[]): Promise<number[][]> {
+  return invoke('embed_texts', { texts })
+}
+
+/**
+ * Count the tokens of `text` for the current model. Exact when `token_counting` is set,
+ * an estimate otherwise.
+ */
+export async function countTokens(text: string): Promise<number> {
+  return invoke('count_tokens', { text })
+}